@@ -0,0 +1,48 @@
+// the translation units the parse benchmark times, shared with a test that
+// makes sure they still compile
+use std::fmt::Write;
+
+// a function that touches most of the grammar: declarators, every level of
+// binary operator, the statements, calls and postfix operators
+fn function(source: &mut String, index: usize) {
+    let _ = writeln!(source, "int f{index}(int a, int b) {{");
+    let _ = writeln!(
+        source,
+        "    int x = a * {index} + b - (a << 2) / 3, y[4], *p = &x;"
+    );
+    let _ = writeln!(source, "    int z = 0;");
+    let _ = writeln!(source, "    int (*g)(int, int);");
+    let _ = writeln!(source, "    for (int i = 0; i < 10; i++) {{");
+    let _ = writeln!(source, "        if (x > i && b != 0 || a == 1) {{");
+    let _ = writeln!(source, "            x += i ? a : b;");
+    let _ = writeln!(source, "        }} else {{");
+    let _ = writeln!(source, "            *p = x % 7 ^ (b | 3) & ~a;");
+    let _ = writeln!(source, "        }}");
+    let _ = writeln!(source, "    }}");
+    let _ = writeln!(source, "    while (x >= 100) {{");
+    let _ = writeln!(source, "        x = x >> 1, z--;");
+    let _ = writeln!(source, "    }}");
+    let _ = writeln!(source, "    switch (x & 3) {{");
+    let _ = writeln!(source, "    case 0:");
+    let _ = writeln!(source, "        z = z + x;");
+    let _ = writeln!(source, "        break;");
+    let _ = writeln!(source, "    default:");
+    let _ = writeln!(source, "        y[x & 3] = -x;");
+    let _ = writeln!(source, "        break;");
+    let _ = writeln!(source, "    }}");
+
+    if index > 0 {
+        let _ = writeln!(source, "    return x + f{}(b, a) <= !z;", index - 1);
+    } else {
+        let _ = writeln!(source, "    return x;");
+    }
+
+    let _ = writeln!(source, "}}");
+    let _ = writeln!(source);
+}
+
+pub fn translation_unit(functions: usize) -> String {
+    let mut source = String::new();
+    (0..functions).for_each(|index| function(&mut source, index));
+    source
+}
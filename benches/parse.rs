@@ -0,0 +1,79 @@
+// lexing, parsing and code generation time over generated translation units
+// of growing size, reported per thousand lines so that the sizes can be
+// compared. run with `cargo bench`
+mod generated;
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use c_riscv::{analyze, generate, parse, tokenize, Diagnostics, Options, WarningOptions};
+use generated::translation_unit;
+
+const SIZES: &[usize] = &[50, 200, 800];
+const ITERATIONS: u32 = 10;
+
+fn diagnostics() -> Diagnostics {
+    Diagnostics::new(WarningOptions::default())
+}
+
+// the fastest of a few runs, the others are mostly noise. only `run` is
+// timed, `setup` makes what it consumes
+fn fastest<T>(mut setup: impl FnMut() -> T, mut run: impl FnMut(T)) -> Duration {
+    (0..ITERATIONS)
+        .map(|_| {
+            let input = setup();
+            let start = Instant::now();
+            run(input);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn per_kloc(duration: Duration, lines: usize) -> f64 {
+    duration.as_secs_f64() * 1000.0 * 1000.0 / lines as f64
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>16} {:>16} {:>16}",
+        "lines", "lex ms/kloc", "parse ms/kloc", "codegen ms/kloc"
+    );
+
+    for &functions in SIZES {
+        let source = translation_unit(functions);
+        let lines = source.lines().count();
+
+        let tokens = tokenize(&source, &mut diagnostics()).expect("the generated source lexes");
+        let analyzed = || {
+            let program = parse(&tokens, &mut diagnostics()).expect("the generated source parses");
+            analyze(program, &mut diagnostics()).expect("the generated source is valid")
+        };
+        analyzed();
+
+        let lex = fastest(
+            || (),
+            |()| {
+                let _ = black_box(tokenize(black_box(&source), &mut diagnostics()));
+            },
+        );
+        let parse = fastest(
+            || (),
+            |()| {
+                let _ = black_box(parse(black_box(&tokens), &mut diagnostics()));
+            },
+        );
+        let codegen = fastest(analyzed, |program| {
+            let _ = black_box(generate(program, &Options::default(), &mut diagnostics()));
+        });
+
+        println!(
+            "{lines:>8} {:>16.3} {:>16.3} {:>16.3}",
+            per_kloc(lex, lines),
+            per_kloc(parse, lines),
+            per_kloc(codegen, lines)
+        );
+    }
+}
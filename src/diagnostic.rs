@@ -0,0 +1,274 @@
+use std::fmt::Display;
+
+use crate::types::span::Span;
+
+use warning::{Warning, WarningOptions};
+
+pub mod json;
+pub mod render;
+pub mod sarif;
+pub mod warning;
+
+// error codes, grouped by the stage that reports them
+pub mod code {
+    pub const SYNTAX: &str = "E0001";
+    pub const UNSUPPORTED: &str = "E0002";
+    pub const JUMP_OUTSIDE_LOOP: &str = "E0003";
+    pub const CASE_OUTSIDE_SWITCH: &str = "E0004";
+    pub const NOT_CONSTANT: &str = "E0005";
+    pub const DUPLICATE_PARAMETER: &str = "E0006";
+    pub const CONFLICTING_TYPES: &str = "E0007";
+
+    pub const UNDECLARED: &str = "E0100";
+    pub const INVALID_OPERANDS: &str = "E0101";
+    pub const INCOMPATIBLE_TYPES: &str = "E0102";
+    pub const NOT_ASSIGNABLE: &str = "E0103";
+    pub const NOT_ADDRESSABLE: &str = "E0104";
+    pub const ARGUMENT_COUNT: &str = "E0105";
+    pub const NOT_CALLABLE: &str = "E0106";
+    pub const NOT_SCALAR: &str = "E0107";
+    pub const REDEFINITION: &str = "E0108";
+    pub const DUPLICATE_CASE: &str = "E0109";
+    pub const INVALID_SIZE: &str = "E0110";
+
+    pub const INVALID_ATTRIBUTE: &str = "E0200";
+    pub const INVALID_CSR: &str = "E0201";
+    pub const INVALID_ASM: &str = "E0202";
+    pub const NAKED_FUNCTION: &str = "E0203";
+    pub const INTERRUPT_HANDLER: &str = "E0204";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+// how diagnostics are written out: text for people, json and sarif for tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Sarif,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "sarif" => Some(Format::Sarif),
+            _ => None,
+        }
+    }
+}
+
+// the diagnostics of one input, with the name and source needed to point
+// into it
+#[derive(Debug, Clone, Copy)]
+pub struct Report<'a> {
+    pub filename: &'a str,
+    pub source: &'a str,
+    pub diagnostics: &'a Diagnostics,
+}
+
+// a span of source code with an optional message next to its underline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub span: Span,
+    pub message: Option<String>,
+}
+
+// a fix-it: replacing the span with the replacement fixes the problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub warning: Option<Warning>,
+    pub message: String,
+    pub primary: Option<Annotation>,
+    pub secondary: Vec<Annotation>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity,
+            code: None,
+            warning: None,
+            message: message.into(),
+            primary: None,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    // every warning has a flag to turn it off with
+    pub fn warning(warning: Warning, message: impl Into<String>) -> Self {
+        Diagnostic {
+            warning: Some(warning),
+            ..Diagnostic::new(Severity::Warning, message)
+        }
+    }
+
+    pub fn with_code(self, code: &'static str) -> Self {
+        Diagnostic {
+            code: Some(code),
+            ..self
+        }
+    }
+
+    pub fn at(self, span: Span) -> Self {
+        Diagnostic {
+            primary: Some(Annotation {
+                span,
+                message: None,
+            }),
+            ..self
+        }
+    }
+
+    pub fn with_label(self, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            primary: Some(Annotation {
+                span,
+                message: Some(message.into()),
+            }),
+            ..self
+        }
+    }
+
+    // diagnostics point at the innermost node that has a span, so outer
+    // nodes only fill it in when nothing did before
+    pub fn or_at(self, span: Span) -> Self {
+        if self.primary.is_some() {
+            self
+        } else {
+            self.at(span)
+        }
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Annotation {
+            span,
+            message: Some(message.into()),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        span: Span,
+        replacement: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span,
+            replacement: replacement.into(),
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+// proof that an error has been reported, stages return it instead of a
+// value so that callers do not need to report anything themselves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorReported;
+
+// everything reported while compiling a file, in the order it was found
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+    options: WarningOptions,
+}
+
+impl Diagnostics {
+    pub fn new(options: WarningOptions) -> Self {
+        Diagnostics {
+            diagnostics: Vec::new(),
+            options,
+        }
+    }
+
+    pub fn options(&self) -> &WarningOptions {
+        &self.options
+    }
+
+    // warnings that are turned off are dropped here, and with -Werror the
+    // rest become errors
+    pub fn report(&mut self, mut diagnostic: Diagnostic) {
+        if let Some(warning) = diagnostic.warning {
+            if !self.options.is_enabled(warning) {
+                return;
+            }
+
+            if self.options.is_error() {
+                diagnostic.severity = Severity::Error;
+            }
+        }
+
+        // the parser backtracks, so it can run into the same problem twice
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    pub fn extend(&mut self, other: Diagnostics) {
+        for diagnostic in other.diagnostics {
+            self.report(diagnostic);
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    // the value, unless an error was reported while computing it
+    pub fn check<T>(&self, value: T) -> Result<T, ErrorReported> {
+        if self.has_errors() {
+            Err(ErrorReported)
+        } else {
+            Ok(value)
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    json::Json,
+    types::span::{LineIndex, Span},
+};
+
+use super::{Annotation, Diagnostic, Report};
+
+// spans only know where they start, so the end is looked up in the source
+pub fn end_of(lines: &LineIndex, span: &Span) -> Span {
+    lines.span(span.end..span.end)
+}
+
+fn position(span: &Span) -> Json {
+    Json::Object(vec![
+        ("line", Json::Number(span.line)),
+        ("column", Json::Number(span.column)),
+        ("offset", Json::Number(span.start)),
+    ])
+}
+
+fn range(lines: &LineIndex, span: &Span) -> Json {
+    Json::Object(vec![
+        ("start", position(span)),
+        ("end", position(&end_of(lines, span))),
+    ])
+}
+
+fn label(lines: &LineIndex, annotation: &Annotation, primary: bool) -> Json {
+    Json::Object(vec![
+        ("range", range(lines, &annotation.span)),
+        ("message", Json::optional(annotation.message.clone())),
+        ("primary", Json::Bool(primary)),
+    ])
+}
+
+fn record(filename: &str, lines: &LineIndex, diagnostic: &Diagnostic) -> Json {
+    let labels = diagnostic
+        .primary
+        .iter()
+        .map(|annotation| label(lines, annotation, true))
+        .chain(
+            diagnostic
+                .secondary
+                .iter()
+                .map(|annotation| label(lines, annotation, false)),
+        )
+        .collect();
+
+    let suggestions = diagnostic
+        .suggestions
+        .iter()
+        .map(|suggestion| {
+            Json::Object(vec![
+                ("range", range(lines, &suggestion.span)),
+                ("replacement", Json::string(&suggestion.replacement)),
+                ("message", Json::string(&suggestion.message)),
+            ])
+        })
+        .collect();
+
+    // the problems of the driver belong to no file
+    let file = Some(filename).filter(|filename| !filename.is_empty());
+
+    Json::Object(vec![
+        ("file", Json::optional(file)),
+        ("severity", Json::string(diagnostic.severity.to_string())),
+        ("code", Json::optional(diagnostic.code)),
+        (
+            "option",
+            Json::optional(diagnostic.warning.map(|warning| format!("-W{warning}"))),
+        ),
+        ("message", Json::string(&diagnostic.message)),
+        (
+            "range",
+            match &diagnostic.primary {
+                Some(primary) => range(lines, &primary.span),
+                None => Json::Null,
+            },
+        ),
+        ("labels", Json::Array(labels)),
+        (
+            "notes",
+            Json::Array(diagnostic.notes.iter().map(Json::string).collect()),
+        ),
+        ("suggestions", Json::Array(suggestions)),
+    ])
+}
+
+// one array with a record for every diagnostic of every input, in the order
+// they were found
+pub fn render(reports: &[Report]) -> Json {
+    let records = reports.iter().flat_map(|report| {
+        let lines = LineIndex::new(report.source);
+
+        report
+            .diagnostics
+            .iter()
+            .map(move |diagnostic| record(report.filename, &lines, diagnostic))
+    });
+
+    Json::Array(records.collect())
+}
@@ -0,0 +1,174 @@
+use std::fmt::Write;
+
+use crate::types::span::Span;
+
+use super::{Annotation, Diagnostic};
+
+// blanks out the line up to the offset, keeping tabs so that whatever is
+// printed after it lines up with the source
+fn padding(line: &str, offset: usize) -> String {
+    line[..offset]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+// renders diagnostics the way rustc does: a header, the location, and the
+// offending source lines with the annotated spans underlined
+pub struct Renderer<'a> {
+    filename: &'a str,
+    source: &'a str,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(filename: &'a str, source: &'a str) -> Self {
+        Renderer { filename, source }
+    }
+
+    // the whole line a span starts on, without its newline
+    fn line_of(&self, span: &Span) -> &'a str {
+        let start = span.start + 1 - span.column;
+        let end = self.source[start..]
+            .find('\n')
+            .map_or(self.source.len(), |i| start + i);
+
+        &self.source[start..end]
+    }
+
+    // spans that run past the end of their line are cut off there, and
+    // empty ones still get a single caret
+    fn underline(&self, annotation: &Annotation, marker: char) -> String {
+        let span = &annotation.span;
+        let line = self.line_of(span);
+        let offset = span.column - 1;
+
+        let end = (span.end - span.start).min(line.len() - offset);
+        let width = line[offset..offset + end].chars().count().max(1);
+
+        let mut underline = padding(line, offset) + &marker.to_string().repeat(width);
+
+        if let Some(message) = &annotation.message {
+            underline.push(' ');
+            underline.push_str(message);
+        }
+
+        underline
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let mut output = String::new();
+
+        match diagnostic.code {
+            Some(code) => write!(output, "{}[{code}]", diagnostic.severity),
+            None => write!(output, "{}", diagnostic.severity),
+        }
+        .unwrap();
+        write!(output, ": {}", diagnostic.message).unwrap();
+
+        // the flag to turn the warning off with, as gcc shows it
+        match diagnostic.warning {
+            Some(warning) if diagnostic.is_error() => writeln!(output, " [-Werror={warning}]"),
+            Some(warning) => writeln!(output, " [-W{warning}]"),
+            None => writeln!(output),
+        }
+        .unwrap();
+
+        let mut annotations: Vec<(&Annotation, char)> = diagnostic
+            .primary
+            .iter()
+            .map(|annotation| (annotation, '^'))
+            .chain(
+                diagnostic
+                    .secondary
+                    .iter()
+                    .map(|annotation| (annotation, '-')),
+            )
+            .collect();
+
+        let last_line = annotations
+            .iter()
+            .map(|(annotation, _)| annotation.span.line)
+            .chain(diagnostic.suggestions.iter().map(|s| s.span.line))
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(last_line.to_string().len());
+
+        match &diagnostic.primary {
+            Some(primary) => writeln!(
+                output,
+                "{gutter}--> {}:{}:{}",
+                self.filename, primary.span.line, primary.span.column
+            ),
+            None => writeln!(output, "{gutter}--> {}", self.filename),
+        }
+        .unwrap();
+
+        if !annotations.is_empty() {
+            writeln!(output, "{gutter} |").unwrap();
+
+            // lines are shown in source order, each once
+            annotations.sort_by_key(|(annotation, _)| annotation.span.line);
+
+            let mut previous = None;
+            for (annotation, marker) in annotations {
+                let line = annotation.span.line;
+
+                if previous != Some(line) {
+                    if previous.is_some_and(|previous| line > previous + 1) {
+                        writeln!(output, "...").unwrap();
+                    }
+
+                    writeln!(
+                        output,
+                        "{line:>width$} | {}",
+                        self.line_of(&annotation.span),
+                        width = gutter.len()
+                    )
+                    .unwrap();
+                    previous = Some(line);
+                }
+
+                writeln!(output, "{gutter} | {}", self.underline(annotation, marker)).unwrap();
+            }
+        }
+
+        for note in diagnostic.notes.iter() {
+            writeln!(output, "{gutter} = note: {note}").unwrap();
+        }
+
+        for suggestion in diagnostic.suggestions.iter() {
+            let span = &suggestion.span;
+            let line = self.line_of(span);
+            let offset = span.column - 1;
+            let end = (offset + span.end - span.start).min(line.len());
+
+            let patched = format!(
+                "{}{}{}",
+                &line[..offset],
+                suggestion.replacement,
+                &line[end..]
+            );
+            writeln!(output, "help: {}", suggestion.message).unwrap();
+            writeln!(output, "{gutter} |").unwrap();
+            writeln!(
+                output,
+                "{:>width$} | {patched}",
+                span.line,
+                width = gutter.len()
+            )
+            .unwrap();
+
+            // the underline is measured against the patched line
+            let width = suggestion.replacement.chars().count().max(1);
+            writeln!(
+                output,
+                "{gutter} | {}{}",
+                padding(line, offset),
+                "+".repeat(width)
+            )
+            .unwrap();
+        }
+
+        output
+    }
+}
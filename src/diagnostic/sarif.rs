@@ -0,0 +1,233 @@
+use crate::{
+    json::Json,
+    types::span::{LineIndex, Span},
+};
+
+use super::{json::end_of, Diagnostic, Report, Severity};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+// sarif wants an id for every result, errors have their code and warnings
+// the flag that turns them off
+fn rule_id(diagnostic: &Diagnostic) -> Option<String> {
+    match (diagnostic.code, diagnostic.warning) {
+        (Some(code), _) => Some(code.to_string()),
+        (None, Some(warning)) => Some(format!("-W{warning}")),
+        (None, None) => None,
+    }
+}
+
+// an input of the run, results point at it by its place in the artifacts
+struct Artifact<'a> {
+    uri: &'a str,
+    index: usize,
+    lines: LineIndex,
+}
+
+impl Artifact<'_> {
+    fn location(&self) -> Json {
+        Json::Object(vec![
+            ("uri", Json::string(self.uri)),
+            ("index", Json::Number(self.index)),
+        ])
+    }
+}
+
+fn region(lines: &LineIndex, span: &Span) -> Json {
+    let end = end_of(lines, span);
+
+    Json::Object(vec![
+        ("startLine", Json::Number(span.line)),
+        ("startColumn", Json::Number(span.column)),
+        ("endLine", Json::Number(end.line)),
+        ("endColumn", Json::Number(end.column)),
+        ("charOffset", Json::Number(span.start)),
+        ("charLength", Json::Number(span.end - span.start)),
+    ])
+}
+
+// related locations need an id, the primary one does not
+fn location(artifact: &Artifact, span: &Span, message: Option<&str>, id: Option<usize>) -> Json {
+    let mut fields = vec![];
+    if let Some(id) = id {
+        fields.push(("id", Json::Number(id)));
+    }
+
+    fields.push((
+        "physicalLocation",
+        Json::Object(vec![
+            ("artifactLocation", artifact.location()),
+            ("region", region(&artifact.lines, span)),
+        ]),
+    ));
+
+    if let Some(message) = message {
+        fields.push((
+            "message",
+            Json::Object(vec![("text", Json::string(message))]),
+        ));
+    }
+
+    Json::Object(fields)
+}
+
+fn result(artifact: &Artifact, diagnostic: &Diagnostic) -> Json {
+    let level = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+
+    // notes have no place of their own, so they go below the message
+    let text = std::iter::once(diagnostic.message.clone())
+        .chain(diagnostic.notes.iter().map(|note| format!("note: {note}")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut fields = vec![];
+    if let Some(rule_id) = rule_id(diagnostic) {
+        fields.push(("ruleId", Json::String(rule_id)));
+    }
+    fields.push(("level", Json::string(level)));
+    fields.push(("message", Json::Object(vec![("text", Json::String(text))])));
+
+    if let Some(primary) = &diagnostic.primary {
+        fields.push((
+            "locations",
+            Json::Array(vec![location(
+                artifact,
+                &primary.span,
+                primary.message.as_deref(),
+                None,
+            )]),
+        ));
+    }
+
+    if !diagnostic.secondary.is_empty() {
+        let related = diagnostic
+            .secondary
+            .iter()
+            .enumerate()
+            .map(|(id, annotation)| {
+                location(
+                    artifact,
+                    &annotation.span,
+                    annotation.message.as_deref(),
+                    Some(id),
+                )
+            })
+            .collect();
+
+        fields.push(("relatedLocations", Json::Array(related)));
+    }
+
+    if !diagnostic.suggestions.is_empty() {
+        let fixes = diagnostic
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                let replacement = Json::Object(vec![
+                    ("deletedRegion", region(&artifact.lines, &suggestion.span)),
+                    (
+                        "insertedContent",
+                        Json::Object(vec![("text", Json::string(&suggestion.replacement))]),
+                    ),
+                ]);
+
+                Json::Object(vec![
+                    (
+                        "description",
+                        Json::Object(vec![("text", Json::string(&suggestion.message))]),
+                    ),
+                    (
+                        "artifactChanges",
+                        Json::Array(vec![Json::Object(vec![
+                            ("artifactLocation", artifact.location()),
+                            ("replacements", Json::Array(vec![replacement])),
+                        ])]),
+                    ),
+                ])
+            })
+            .collect();
+
+        fields.push(("fixes", Json::Array(fixes)));
+    }
+
+    Json::Object(fields)
+}
+
+// a log with a single run, with every input as an artifact and every rule
+// that one of the results uses
+pub fn render(reports: &[Report]) -> Json {
+    // the problems of the driver belong to no file, so they have no
+    // artifact of their own and their results no locations
+    let mut index = 0;
+    let artifacts: Vec<Artifact> = reports
+        .iter()
+        .map(|report| {
+            let artifact = Artifact {
+                uri: report.filename,
+                index,
+                lines: LineIndex::new(report.source),
+            };
+            index += !report.filename.is_empty() as usize;
+            artifact
+        })
+        .collect();
+
+    let mut rules: Vec<String> = reports
+        .iter()
+        .flat_map(|report| report.diagnostics.iter().filter_map(rule_id))
+        .collect();
+    rules.sort();
+    rules.dedup();
+
+    let rules = rules
+        .into_iter()
+        .map(|id| Json::Object(vec![("id", Json::String(id))]))
+        .collect();
+
+    let results = reports
+        .iter()
+        .zip(&artifacts)
+        .flat_map(|(report, artifact)| {
+            report
+                .diagnostics
+                .iter()
+                .map(|diagnostic| result(artifact, diagnostic))
+        })
+        .collect();
+
+    let files = artifacts
+        .iter()
+        .filter(|artifact| !artifact.uri.is_empty())
+        .map(|artifact| {
+            Json::Object(vec![(
+                "location",
+                Json::Object(vec![("uri", Json::string(artifact.uri))]),
+            )])
+        })
+        .collect();
+
+    Json::Object(vec![
+        ("$schema", Json::string(SCHEMA)),
+        ("version", Json::string("2.1.0")),
+        (
+            "runs",
+            Json::Array(vec![Json::Object(vec![
+                (
+                    "tool",
+                    Json::Object(vec![(
+                        "driver",
+                        Json::Object(vec![
+                            ("name", Json::string(env!("CARGO_PKG_NAME"))),
+                            ("version", Json::string(env!("CARGO_PKG_VERSION"))),
+                            ("rules", Json::Array(rules)),
+                        ]),
+                    )]),
+                ),
+                ("artifacts", Json::Array(files)),
+                ("results", Json::Array(results)),
+            ])]),
+        ),
+    ])
+}
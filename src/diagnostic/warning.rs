@@ -0,0 +1,142 @@
+use std::{collections::HashMap, fmt::Display};
+
+// the flag that controls a warning decides whether it is shown at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Warning {
+    Attributes,
+    Overflow,
+    IntConversion,
+    UnusedVariable,
+    ReturnType,
+    Parentheses,
+    UnreachableCode,
+    UnusedParameter,
+    ImplicitFallthrough,
+    Shadow,
+    InvalidNoreturn,
+}
+
+// which warnings are on without being asked for, like in gcc
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Group {
+    Default,
+    All,
+    Extra,
+    None,
+}
+
+impl Warning {
+    pub const ALL: [Warning; 11] = [
+        Warning::Attributes,
+        Warning::Overflow,
+        Warning::IntConversion,
+        Warning::UnusedVariable,
+        Warning::ReturnType,
+        Warning::Parentheses,
+        Warning::UnreachableCode,
+        Warning::UnusedParameter,
+        Warning::ImplicitFallthrough,
+        Warning::Shadow,
+        Warning::InvalidNoreturn,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Warning::Attributes => "attributes",
+            Warning::Overflow => "overflow",
+            Warning::IntConversion => "int-conversion",
+            Warning::UnusedVariable => "unused-variable",
+            Warning::ReturnType => "return-type",
+            Warning::Parentheses => "parentheses",
+            Warning::UnreachableCode => "unreachable-code",
+            Warning::UnusedParameter => "unused-parameter",
+            Warning::ImplicitFallthrough => "implicit-fallthrough",
+            Warning::Shadow => "shadow",
+            Warning::InvalidNoreturn => "invalid-noreturn",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Warning> {
+        Warning::ALL.into_iter().find(|w| w.name() == name)
+    }
+
+    fn group(self) -> Group {
+        match self {
+            Warning::Attributes
+            | Warning::Overflow
+            | Warning::IntConversion
+            | Warning::InvalidNoreturn => Group::Default,
+            Warning::UnusedVariable
+            | Warning::ReturnType
+            | Warning::Parentheses
+            | Warning::UnreachableCode => Group::All,
+            Warning::UnusedParameter | Warning::ImplicitFallthrough => Group::Extra,
+            Warning::Shadow => Group::None,
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// the -W flags of the command line. a flag naming a single warning wins
+// over -Wall, -Wextra and -W[no-]unused no matter the order, as in gcc
+#[derive(Debug, Clone, Default)]
+pub struct WarningOptions {
+    all: bool,
+    extra: bool,
+    error: bool,
+    // -Wunused and -Wno-unused, for every unused-* warning at once
+    unused: Option<bool>,
+    explicit: HashMap<Warning, bool>,
+}
+
+impl WarningOptions {
+    // takes a flag without its -W, e.g. "all" or "no-shadow"
+    pub fn apply(&mut self, flag: &str) -> Result<(), String> {
+        match flag {
+            "all" => self.all = true,
+            "extra" => self.extra = true,
+            "error" => self.error = true,
+            "no-error" => self.error = false,
+            "unused" => self.unused = Some(true),
+            "no-unused" => self.unused = Some(false),
+            _ => {
+                let (name, enabled) = match flag.strip_prefix("no-") {
+                    Some(name) => (name, false),
+                    None => (flag, true),
+                };
+
+                let warning = Warning::from_name(name)
+                    .ok_or_else(|| format!("Unknown warning option '-W{flag}'"))?;
+                self.explicit.insert(warning, enabled);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, warning: Warning) -> bool {
+        let unused = self
+            .unused
+            .filter(|_| warning.name().starts_with("unused-"));
+
+        self.explicit
+            .get(&warning)
+            .copied()
+            .or(unused)
+            .unwrap_or(match warning.group() {
+                Group::Default => true,
+                Group::All => self.all,
+                Group::Extra => self.extra,
+                Group::None => false,
+            })
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.error
+    }
+}
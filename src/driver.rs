@@ -0,0 +1,603 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use c_riscv::{diagnostic::Format, FreestandingOptions, Target};
+
+// programs linked against libc, debug information and assembly the built-in
+// assembler does not handle are left to the gnu toolchain
+pub const TOOLCHAIN: &str = "riscv64-unknown-elf-gcc";
+
+pub const USAGE: &str = "Usage: c-riscv [options] <file>...";
+
+pub const HELP: &str = "\
+Options:
+  -o <file>                Write the output to <file>, or to stdout for '-'
+  -S                       Stop after generating assembly, <stem>.s for each input
+  -c                       Stop after assembling, <stem>.o for each input
+  -O<n>                    Optimization level, the default -O1 runs the peephole
+                           optimizer and -O0 turns it off
+  -march=<arch>            Target architecture, an rv32 one (default rv32imafdc)
+  -mabi=<abi>              Target abi, an ilp32 one (default ilp32)
+  -g                       Emit debug information, assembling and linking with
+                           the toolchain
+  -I <dir>, -D <macro>[=<value>], -U <macro>
+                           Accepted for compatibility, there is no preprocessor
+  -Wall, -Wextra, -Werror, -W[no-]unused, -W[no-]<warning>
+                           Turn warnings on or off
+  --diagnostics-format=text|json|sarif
+                           How errors and warnings are written to stderr, all
+                           the inputs in one document for json and sarif
+  --emit=<stage>[=<file>],...
+                           Write out stages instead of compiling: tokens, ast,
+                           typed-ast, ir, cfg or asm, to <stem>.<stage> by default
+  --emit-format=text|json  How the stages are written
+  --freestanding           Link with startup code and the built-in linker instead
+                           of libc, no toolchain is needed
+  --rom-origin=<addr>, --rom-length=<len>, --ram-origin=<addr>, --ram-length=<len>
+                           The memory map of a freestanding program
+  --exit-device=<addr>     Where a freestanding main stores its return value
+  --version                Print the version
+  --help                   Print this help
+
+Inputs ending in .s or .S are assembled and inputs ending in .o or .a are
+only linked, everything else is compiled as C. Input and output files can be
+'-' for stdin and stdout.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+// what the driver does with an input, going by its extension like gcc
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputKind {
+    Source,
+    Assembly,
+    Object,
+}
+
+impl Input {
+    pub fn kind(&self) -> InputKind {
+        let extension = match self {
+            Input::Stdin => None,
+            Input::File(path) => path.extension().and_then(|extension| extension.to_str()),
+        };
+
+        match extension {
+            Some("s" | "S") => InputKind::Assembly,
+            Some("o" | "a") => InputKind::Object,
+            _ => InputKind::Source,
+        }
+    }
+
+    // what diagnostics call the file
+    pub fn name(&self) -> String {
+        match self {
+            Input::Stdin => "<stdin>".to_string(),
+            Input::File(path) => path.display().to_string(),
+        }
+    }
+
+    // outputs are named after their input, like gcc does
+    pub fn stem(&self) -> String {
+        match self {
+            Input::Stdin => "a".to_string(),
+            Input::File(path) => path
+                .file_stem()
+                .map_or("a".to_string(), |stem| stem.to_string_lossy().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    Stdout,
+    File(PathBuf),
+}
+
+// the stages of the compiler that --emit can write out, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Tokens,
+    Ast,
+    TypedAst,
+    Ir,
+    Cfg,
+    Asm,
+}
+
+impl Stage {
+    fn from_name(name: &str) -> Option<Stage> {
+        match name {
+            "tokens" => Some(Stage::Tokens),
+            "ast" => Some(Stage::Ast),
+            "typed-ast" => Some(Stage::TypedAst),
+            "ir" => Some(Stage::Ir),
+            "cfg" => Some(Stage::Cfg),
+            "asm" => Some(Stage::Asm),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Stage::Tokens => "tokens",
+            Stage::Ast => "ast",
+            Stage::TypedAst => "typed-ast",
+            Stage::Ir => "ir",
+            Stage::Cfg => "cfg",
+            Stage::Asm => "s",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emit {
+    pub stage: Stage,
+    pub output: Option<Output>,
+}
+
+// what to do with the files, on top of how to compile them
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub inputs: Vec<Input>,
+    pub output: Option<Output>,
+    pub assembly_only: bool,
+    pub objects_only: bool,
+    pub debug: bool,
+    pub format: Format,
+    pub emit: Vec<Emit>,
+    pub emit_json: bool,
+    // flags that are accepted but have nothing to do, to warn about
+    pub ignored: Vec<String>,
+    pub compile: c_riscv::Options,
+}
+
+impl Options {
+    // the inputs that are compiled, the rest are assembled or linked as they are
+    pub fn sources(&self) -> impl Iterator<Item = &Input> {
+        self.inputs
+            .iter()
+            .filter(|input| input.kind() == InputKind::Source)
+    }
+
+    // the executable
+    pub fn output(&self) -> Output {
+        self.output
+            .clone()
+            .unwrap_or_else(|| Output::File(PathBuf::from("a.out")))
+    }
+
+    // what -S or -c makes out of one input. -o can only name it when there
+    // is a single input, which parse_args checks
+    pub fn unit_output(&self, input: &Input, extension: &str) -> Output {
+        self.output
+            .clone()
+            .unwrap_or_else(|| Output::File(PathBuf::from(format!("{}.{extension}", input.stem()))))
+    }
+
+    // -o names the output when only one stage is written without a file
+    pub fn emit_output(&self, input: &Input, emit: &Emit) -> Output {
+        if let Some(output) = &emit.output {
+            return output.clone();
+        }
+
+        match &self.output {
+            Some(output) if self.emit.len() == 1 => output.clone(),
+            _ => {
+                let json = if self.emit_json { ".json" } else { "" };
+                let name = format!("{}.{}{json}", input.stem(), emit.stage.extension());
+                Output::File(PathBuf::from(name))
+            }
+        }
+    }
+
+    // next to the output, or named after the first input when there is no
+    // output file
+    pub fn linker_script(&self) -> PathBuf {
+        match &self.output {
+            Some(Output::File(path)) => path.with_extension("ld"),
+            _ => PathBuf::from(format!("{}.ld", self.inputs[0].stem())),
+        }
+    }
+}
+
+pub enum Action {
+    Compile(Box<Options>),
+    Help,
+    Version,
+}
+
+fn parse_address(value: &str) -> Result<u32, String> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(&hex.replace('_', ""), 16),
+        None => value.replace('_', "").parse(),
+    };
+
+    parsed.map_err(|_| format!("Invalid address or length: {value}"))
+}
+
+fn parse_output(value: &str) -> Output {
+    match value {
+        "-" => Output::Stdout,
+        _ => Output::File(PathBuf::from(value)),
+    }
+}
+
+// e.g. ast,asm=out.s or ir=-
+fn parse_emit(value: &str) -> Result<Vec<Emit>, String> {
+    value
+        .split(',')
+        .map(|entry| {
+            let (name, output) = match entry.split_once('=') {
+                Some((name, path)) => (name, Some(parse_output(path))),
+                None => (entry, None),
+            };
+
+            let stage = Stage::from_name(name)
+                .ok_or_else(|| format!("Unknown stage '{name}' for --emit"))?;
+
+            Ok(Emit { stage, output })
+        })
+        .collect()
+}
+
+fn parse_optimization(level: &str) -> Result<u32, String> {
+    match level {
+        "" => Ok(1),
+        "s" | "z" | "fast" => Ok(2),
+        _ => level
+            .parse()
+            .map_err(|_| format!("Invalid optimization level '-O{level}'")),
+    }
+}
+
+// flags that take their value after an =, e.g. --emit=asm
+const VALUE_FLAGS: [&str; 10] = [
+    "--rom-origin",
+    "--rom-length",
+    "--ram-origin",
+    "--ram-length",
+    "--exit-device",
+    "--diagnostics-format",
+    "--emit",
+    "--emit-format",
+    "-march",
+    "-mabi",
+];
+
+// only the flags above are split, an input like a=b.c is a file name
+fn split_value(arg: &str) -> Option<(&'static str, &str)> {
+    VALUE_FLAGS
+        .iter()
+        .find_map(|&flag| Some((flag, arg.strip_prefix(flag)?.strip_prefix('=')?)))
+}
+
+// the value of a flag like -o or -I, either glued to it or the next argument
+fn value_of(
+    flag: &str,
+    glued: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<String, String> {
+    if !glued.is_empty() {
+        return Ok(glued.to_string());
+    }
+
+    args.next()
+        .ok_or_else(|| format!("Missing argument to '{flag}'"))
+}
+
+// the last valid --diagnostics-format, looked for before the arguments are
+// parsed so that their errors can be written in it
+pub fn diagnostics_format(args: &[String]) -> Format {
+    args.iter()
+        .rev()
+        .filter_map(|arg| match split_value(arg) {
+            Some(("--diagnostics-format", value)) => Format::from_name(value),
+            _ => None,
+        })
+        .next()
+        .unwrap_or_default()
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Action, String> {
+    let mut args = args.into_iter();
+
+    let mut inputs = Vec::new();
+    let mut march = Target::default().march().to_string();
+    let mut mabi = Target::default().mabi().to_string();
+    let mut freestanding = false;
+    let mut memory = FreestandingOptions::default();
+    let mut options = Options {
+        inputs: Vec::new(),
+        output: None,
+        assembly_only: false,
+        objects_only: false,
+        debug: false,
+        format: Format::default(),
+        emit: Vec::new(),
+        emit_json: false,
+        ignored: Vec::new(),
+        compile: c_riscv::Options::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        match split_value(&arg) {
+            Some(("--rom-origin", value)) => memory.rom_origin = parse_address(value)?,
+            Some(("--rom-length", value)) => memory.rom_length = parse_address(value)?,
+            Some(("--ram-origin", value)) => memory.ram_origin = parse_address(value)?,
+            Some(("--ram-length", value)) => memory.ram_length = parse_address(value)?,
+            Some(("--exit-device", value)) => memory.exit_device = Some(parse_address(value)?),
+            Some(("--diagnostics-format", value)) => {
+                options.format = Format::from_name(value)
+                    .ok_or_else(|| format!("Unknown diagnostics format '{value}'"))?
+            }
+            Some(("--emit", value)) => options.emit.extend(parse_emit(value)?),
+            Some(("--emit-format", "text")) => options.emit_json = false,
+            Some(("--emit-format", "json")) => options.emit_json = true,
+            Some(("--emit-format", value)) => {
+                return Err(format!("Unknown emit format '{value}'"));
+            }
+            Some(("-march", value)) => march = value.to_string(),
+            Some(("-mabi", value)) => mabi = value.to_string(),
+            _ if arg.starts_with("-W") => options.compile.warnings.apply(&arg[2..])?,
+            _ if arg.starts_with("-O") => {
+                options.compile.optimization = parse_optimization(&arg[2..])?
+            }
+            _ if arg.starts_with("-o") => {
+                let value = value_of("-o", &arg[2..], &mut args)?;
+                options.output = Some(parse_output(&value));
+            }
+            // there is no preprocessor to pass these on to
+            _ if arg.starts_with("-I") || arg.starts_with("-D") || arg.starts_with("-U") => {
+                let value = value_of(&arg[..2], &arg[2..], &mut args)?;
+                options.ignored.push(format!("{}{value}", &arg[..2]));
+            }
+            None if arg == "-S" => options.assembly_only = true,
+            None if arg == "-c" => options.objects_only = true,
+            None if arg == "-g" => options.debug = true,
+            None if arg == "--freestanding" => freestanding = true,
+            None if arg == "--help" => return Ok(Action::Help),
+            None if arg == "--version" => return Ok(Action::Version),
+            None if arg == "-" || !arg.starts_with('-') => {
+                let input = match arg.as_str() {
+                    "-" => Input::Stdin,
+                    _ => Input::File(PathBuf::from(arg)),
+                };
+
+                if input == Input::Stdin && inputs.contains(&Input::Stdin) {
+                    return Err("Standard input can only be read once".to_string());
+                }
+
+                inputs.push(input);
+            }
+            _ => return Err(format!("Unknown option '{arg}'")),
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err(format!("No input file\n{USAGE}"));
+    }
+
+    // every input gets an output of its own then, so -o can not name them
+    let per_input = options.assembly_only || options.objects_only || !options.emit.is_empty();
+    if per_input && inputs.len() > 1 && options.output.is_some() {
+        return Err("Cannot specify -o with -S, -c or --emit with multiple files".to_string());
+    }
+
+    options.inputs = inputs;
+
+    options.compile.target = Target::new(march, mabi)?;
+    options.compile.freestanding = freestanding.then_some(memory);
+
+    Ok(Action::Compile(Box::new(options)))
+}
+
+fn toolchain(options: &Options) -> Command {
+    let mut command = Command::new(TOOLCHAIN);
+    command
+        .arg(format!("-march={}", options.compile.target.march()))
+        .arg(format!("-mabi={}", options.compile.target.mabi()));
+
+    if options.debug {
+        command.arg("-g");
+    }
+
+    command
+}
+
+fn run(mut command: Command) -> Result<(), String> {
+    let status = command
+        .status()
+        .map_err(|error| format!("Could not run {TOOLCHAIN}: {error}"))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{TOOLCHAIN} failed with {status}"))
+    }
+}
+
+// an object file out of one assembly file, for -g and for what the built-in
+// assembler does not handle, like floating point or compressed instructions
+pub fn assemble(options: &Options, assembly: &Path, output: &Path) -> Result<(), String> {
+    let mut command = toolchain(options);
+    command.arg("-c").arg(assembly).arg("-o").arg(output);
+
+    run(command)
+}
+
+// hands the object files to the gnu toolchain to get an executable out of
+// them, linked against libc or, with a linker script, freestanding. symbols
+// one unit leaves undefined are resolved against the others here
+pub fn link(
+    options: &Options,
+    inputs: &[PathBuf],
+    linker_script: Option<&Path>,
+    output: &Path,
+) -> Result<(), String> {
+    let mut command = toolchain(options);
+    command.args(inputs).arg("-o").arg(output);
+
+    if let Some(linker_script) = linker_script {
+        command.arg("-nostdlib").arg("-T").arg(linker_script);
+    }
+
+    run(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        match parse_args(args.iter().map(|arg| arg.to_string()))? {
+            Action::Compile(options) => Ok(*options),
+            Action::Help => Err("help".to_string()),
+            Action::Version => Err("version".to_string()),
+        }
+    }
+
+    fn file(path: &str) -> Input {
+        Input::File(PathBuf::from(path))
+    }
+
+    #[test]
+    fn inputs_and_outputs() {
+        let options = parse(&["-S", "main.c", "-o", "-"]).unwrap();
+        assert_eq!(options.inputs, [file("main.c")]);
+        assert_eq!(options.output, Some(Output::Stdout));
+        assert!(options.assembly_only);
+        assert!(!options.debug);
+
+        let options = parse(&["-ofoo", "-g", "-", "start.s", "lib.o"]).unwrap();
+        assert!(options.debug);
+        assert_eq!(options.output, Some(Output::File(PathBuf::from("foo"))));
+        assert_eq!(
+            options.inputs,
+            [Input::Stdin, file("start.s"), file("lib.o")]
+        );
+        let kinds: Vec<_> = options.inputs.iter().map(Input::kind).collect();
+        assert_eq!(
+            kinds,
+            [InputKind::Source, InputKind::Assembly, InputKind::Object]
+        );
+    }
+
+    #[test]
+    fn inputs_can_contain_equals_signs() {
+        let options = parse(&["a=b.c", "--emit=x.c"]);
+        assert_eq!(options.unwrap_err(), "Unknown stage 'x.c' for --emit");
+
+        let options = parse(&["a=b.c", "-march=rv32im"]).unwrap();
+        assert_eq!(options.inputs, [file("a=b.c")]);
+        assert_eq!(options.compile.target.march(), "rv32im");
+
+        // only the known flags take a value after =
+        assert_eq!(
+            parse(&["--march=rv32im", "main.c"]).unwrap_err(),
+            "Unknown option '--march=rv32im'"
+        );
+    }
+
+    #[test]
+    fn optimization_is_on_by_default() {
+        let level = |args: &[&str]| parse(args).unwrap().compile.optimization;
+
+        assert_eq!(level(&["main.c"]), 1);
+        assert_eq!(level(&["-O0", "main.c"]), 0);
+        assert_eq!(level(&["-O", "main.c"]), 1);
+        assert_eq!(level(&["-Os", "main.c"]), 2);
+        assert_eq!(
+            parse(&["-Ox", "main.c"]).unwrap_err(),
+            "Invalid optimization level '-Ox'"
+        );
+    }
+
+    #[test]
+    fn preprocessor_flags_are_ignored_with_their_values() {
+        let options = parse(&["-I", "include", "-DDEBUG=1", "main.c", "-UNDEBUG"]).unwrap();
+
+        assert_eq!(options.inputs, [file("main.c")]);
+        assert_eq!(options.ignored, ["-Iinclude", "-DDEBUG=1", "-UNDEBUG"]);
+        assert_eq!(
+            parse(&["main.c", "-I"]).unwrap_err(),
+            "Missing argument to '-I'"
+        );
+    }
+
+    #[test]
+    fn freestanding_memory_map() {
+        let options = parse(&[
+            "--freestanding",
+            "--rom-origin=0x2000_0000",
+            "--ram-length=65536",
+            "--exit-device=0x100000",
+            "main.c",
+        ])
+        .unwrap();
+
+        let memory = options.compile.freestanding.unwrap();
+        assert_eq!(memory.rom_origin, 0x2000_0000);
+        assert_eq!(memory.ram_length, 0x1_0000);
+        assert_eq!(memory.exit_device, Some(0x10_0000));
+        assert_eq!(memory.ram_origin, FreestandingOptions::default().ram_origin);
+
+        // without --freestanding the memory map has no use
+        let options = parse(&["--rom-origin=0", "main.c"]).unwrap();
+        assert!(options.compile.freestanding.is_none());
+    }
+
+    #[test]
+    fn warnings_and_formats() {
+        let options = parse(&[
+            "-Wall",
+            "-Wno-unused-variable",
+            "--diagnostics-format=sarif",
+            "a.c",
+        ]);
+        let options = options.unwrap();
+        assert_eq!(options.format, Format::Sarif);
+        assert!(!options
+            .compile
+            .warnings
+            .is_enabled(c_riscv::diagnostic::warning::Warning::UnusedVariable));
+
+        let options = parse(&["-Wextra", "-Wno-unused", "a.c"]).unwrap();
+        assert!(!options
+            .compile
+            .warnings
+            .is_enabled(c_riscv::diagnostic::warning::Warning::UnusedParameter));
+
+        assert_eq!(
+            parse(&["-Wbogus", "a.c"]).unwrap_err(),
+            "Unknown warning option '-Wbogus'"
+        );
+        assert_eq!(
+            parse(&["--diagnostics-format=xml", "a.c"]).unwrap_err(),
+            "Unknown diagnostics format 'xml'"
+        );
+    }
+
+    #[test]
+    fn invalid_command_lines() {
+        assert!(parse(&[]).unwrap_err().starts_with("No input file"));
+        assert_eq!(
+            parse(&["-", "-"]).unwrap_err(),
+            "Standard input can only be read once"
+        );
+        assert_eq!(
+            parse(&["-S", "-o", "out.s", "a.c", "b.c"]).unwrap_err(),
+            "Cannot specify -o with -S, -c or --emit with multiple files"
+        );
+        assert_eq!(parse(&["--bogus"]).unwrap_err(), "Unknown option '--bogus'");
+        assert_eq!(parse(&["--help", "a.c"]).unwrap_err(), "help");
+        assert_eq!(parse(&["--version"]).unwrap_err(), "version");
+    }
+}
@@ -0,0 +1,273 @@
+use std::fmt::{Display, Write};
+
+use crate::{
+    json::Json,
+    lexer::Token,
+    riscv::{cfg::FunctionGraph, instruction::Instruction},
+    types::span::Span,
+};
+
+pub mod ast;
+
+// what the stages look like from the outside, used by --emit. the text is
+// meant for people and the json for tools, both stay the same between runs
+// on the same source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    kind: &'static str,
+    // what the node is to its parent, e.g. the condition of an if
+    role: Option<&'static str>,
+    properties: Vec<(&'static str, String)>,
+    span: Option<Span>,
+    children: Vec<Node>,
+}
+
+pub trait Dump {
+    fn dump(&self) -> Node;
+}
+
+impl Node {
+    pub fn new(kind: &'static str) -> Self {
+        Node {
+            kind,
+            role: None,
+            properties: Vec::new(),
+            span: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, key: &'static str, value: impl ToString) -> Self {
+        self.properties.push((key, value.to_string()));
+        self
+    }
+
+    // nodes the compiler made up itself have no place in the source
+    pub fn at(self, span: Span) -> Self {
+        Node {
+            span: (span != Span::default()).then_some(span),
+            ..self
+        }
+    }
+
+    pub fn child(mut self, child: Node) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn child_as(self, role: &'static str, child: Node) -> Self {
+        self.child(Node {
+            role: Some(role),
+            ..child
+        })
+    }
+
+    pub fn children<'a, T: Dump + 'a>(self, items: impl IntoIterator<Item = &'a T>) -> Self {
+        items
+            .into_iter()
+            .fold(self, |node, item| node.child(item.dump()))
+    }
+
+    fn write_text(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{:width$}", "", width = depth * 2)?;
+
+        if let Some(role) = self.role {
+            write!(f, "{role}: ")?;
+        }
+
+        f.write_str(self.kind)?;
+
+        for (key, value) in self.properties.iter() {
+            write!(f, " {key}={value:?}")?;
+        }
+
+        if let Some(span) = self.span {
+            write!(f, " <{}:{}>", span.line, span.column)?;
+        }
+
+        f.write_char('\n')?;
+
+        for child in self.children.iter() {
+            child.write_text(f, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut fields = vec![("kind", Json::string(self.kind))];
+
+        if let Some(role) = self.role {
+            fields.push(("role", Json::string(role)));
+        }
+
+        for (key, value) in self.properties.iter() {
+            fields.push((key, Json::string(value)));
+        }
+
+        if let Some(span) = self.span {
+            fields.push((
+                "span",
+                Json::Object(vec![
+                    ("line", Json::Number(span.line)),
+                    ("column", Json::Number(span.column)),
+                    ("start", Json::Number(span.start)),
+                    ("end", Json::Number(span.end)),
+                ]),
+            ));
+        }
+
+        if !self.children.is_empty() {
+            fields.push((
+                "children",
+                Json::Array(self.children.iter().map(Node::to_json).collect()),
+            ));
+        }
+
+        Json::Object(fields)
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_text(f, 0)
+    }
+}
+
+impl Dump for Token {
+    fn dump(&self) -> Node {
+        Node::new(self.kind.name())
+            .with("text", self.kind.text())
+            .at(self.span)
+    }
+}
+
+pub fn tokens_text(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| token.dump().to_string())
+        .collect()
+}
+
+pub fn tokens_json(tokens: &[Token]) -> Json {
+    Json::Array(tokens.iter().map(|token| token.dump().to_json()).collect())
+}
+
+// an instruction the way the ir shows it, pseudoinstructions are not expanded
+fn instruction_text(instruction: &Instruction) -> String {
+    match instruction {
+        Instruction::Comment(comment) => format!("# {comment}"),
+        Instruction::Label(label) => format!("{label}:"),
+        Instruction::Symbol(directive) => format!(".{directive}"),
+        Instruction::Raw(text) => text.clone(),
+        _ => {
+            let mnemonic = instruction.mnemonic().unwrap_or_default();
+            let operands = instruction.operands();
+
+            if operands.is_empty() {
+                mnemonic.to_string()
+            } else {
+                format!("{mnemonic} {}", operands.join(", "))
+            }
+        }
+    }
+}
+
+fn instruction_json(instruction: &Instruction) -> Json {
+    match instruction {
+        Instruction::Comment(comment) => Json::Object(vec![("comment", Json::string(comment))]),
+        Instruction::Label(label) => Json::Object(vec![("label", Json::string(label))]),
+        Instruction::Symbol(directive) => {
+            Json::Object(vec![("directive", Json::string(directive))])
+        }
+        Instruction::Raw(text) => Json::Object(vec![("asm", Json::string(text))]),
+        _ => Json::Object(vec![
+            ("op", Json::optional(instruction.mnemonic())),
+            (
+                "operands",
+                Json::Array(
+                    instruction
+                        .operands()
+                        .into_iter()
+                        .map(Json::String)
+                        .collect(),
+                ),
+            ),
+        ]),
+    }
+}
+
+// labels stick out, everything else is indented below them
+pub fn ir_text(instructions: &[Instruction]) -> String {
+    instructions
+        .iter()
+        .map(|instruction| match instruction {
+            Instruction::Label(_) => instruction_text(instruction) + "\n",
+            _ => format!("    {}\n", instruction_text(instruction)),
+        })
+        .collect()
+}
+
+pub fn ir_json(instructions: &[Instruction]) -> Json {
+    Json::Array(instructions.iter().map(instruction_json).collect())
+}
+
+pub fn cfg_text(functions: &[FunctionGraph]) -> String {
+    let mut text = String::new();
+
+    for function in functions {
+        let _ = writeln!(text, "function {}", function.name);
+
+        for block in function.blocks.iter() {
+            let _ = writeln!(
+                text,
+                "  block {} -> [{}]",
+                block.label,
+                block.successors.join(", ")
+            );
+
+            for instruction in block.instructions.iter() {
+                let _ = writeln!(text, "    {}", instruction_text(instruction));
+            }
+        }
+    }
+
+    text
+}
+
+pub fn cfg_json(functions: &[FunctionGraph]) -> Json {
+    let functions = functions
+        .iter()
+        .map(|function| {
+            let blocks = function
+                .blocks
+                .iter()
+                .map(|block| {
+                    Json::Object(vec![
+                        ("label", Json::string(&block.label)),
+                        (
+                            "instructions",
+                            Json::Array(block.instructions.iter().map(instruction_json).collect()),
+                        ),
+                        (
+                            "successors",
+                            Json::Array(block.successors.iter().map(Json::string).collect()),
+                        ),
+                    ])
+                })
+                .collect();
+
+            Json::Object(vec![
+                ("function", Json::string(&function.name)),
+                ("blocks", Json::Array(blocks)),
+            ])
+        })
+        .collect();
+
+    Json::Array(functions)
+}
+
+// the assembly as it is written out, one line per entry
+pub fn asm_json(assembly: &str) -> Json {
+    Json::Array(assembly.lines().map(Json::string).collect())
+}
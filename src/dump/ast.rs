@@ -0,0 +1,337 @@
+use crate::{
+    parser::Case,
+    types::{
+        attribute::{Attribute, AttributeArgument},
+        declaration::{Declaration, InitDeclarator},
+        expression::{Builtin, Expression, ExpressionKind, UnaryOp, ValueCategory},
+        function_definition::{FunctionArgument, FunctionDefinition, FunctionKind},
+        program::{Program, ProgramStatement},
+        scope::{Label, LabelKind, Scope, ScopeItem},
+        statement::{AsmOperand, ForInit, JumpStatement, Statement, StatementKind},
+    },
+};
+
+use super::{Dump, Node};
+
+impl Dump for Program {
+    fn dump(&self) -> Node {
+        Node::new("Program").children(&self.functions)
+    }
+}
+
+impl Dump for ProgramStatement {
+    fn dump(&self) -> Node {
+        match self {
+            ProgramStatement::FunctionDefinition(function) => function.dump(),
+            ProgramStatement::Declaration(declaration) => declaration.dump(),
+        }
+    }
+}
+
+impl Dump for FunctionDefinition {
+    fn dump(&self) -> Node {
+        let mut node = Node::new("FunctionDefinition")
+            .with("name", &self.name)
+            .with("return_type", &self.return_type);
+
+        node = match self.kind {
+            FunctionKind::Normal => node,
+            FunctionKind::Interrupt(mode) => {
+                node.with("kind", format!("interrupt({mode:?})").to_lowercase())
+            }
+            FunctionKind::Naked => node.with("kind", "naked"),
+        };
+
+        node.at(self.span)
+            .children(&self.attributes)
+            .children(&self.arguments)
+            .child_as("body", self.body.dump())
+    }
+}
+
+impl Dump for FunctionArgument {
+    fn dump(&self) -> Node {
+        Node::new("Parameter")
+            .with("name", &self.unique_name)
+            .with("type", &self.datatype)
+            .at(self.span)
+    }
+}
+
+impl Dump for Attribute {
+    fn dump(&self) -> Node {
+        self.arguments.iter().fold(
+            Node::new("Attribute")
+                .with("name", &self.name)
+                .at(self.span),
+            |node, argument| match argument {
+                AttributeArgument::Identifier(name) => {
+                    node.child(Node::new("Identifier").with("name", name))
+                }
+                AttributeArgument::String(value) => {
+                    node.child(Node::new("String").with("value", value))
+                }
+                AttributeArgument::Expression(expression) => node.child(expression.dump()),
+            },
+        )
+    }
+}
+
+impl Dump for Declaration {
+    fn dump(&self) -> Node {
+        Node::new("Declaration")
+            .at(self.span)
+            .children(&self.attributes)
+            .children(&self.declarators)
+    }
+}
+
+impl Dump for InitDeclarator {
+    fn dump(&self) -> Node {
+        let node = Node::new("Declarator")
+            .with("name", &self.name)
+            .with("type", &self.datatype)
+            .at(self.span)
+            .children(&self.attributes);
+
+        let node = match self.external {
+            true => node.with("storage", "extern"),
+            false => node,
+        };
+
+        match &self.value {
+            Some(value) => node.child_as("value", value.dump()),
+            None => node,
+        }
+    }
+}
+
+impl Dump for Scope {
+    fn dump(&self) -> Node {
+        Node::new("Scope").at(self.span).children(&self.items)
+    }
+}
+
+impl Dump for ScopeItem {
+    fn dump(&self) -> Node {
+        match self {
+            ScopeItem::Statement(statement) => statement.dump(),
+            ScopeItem::Declaration(declaration) => declaration.dump(),
+            ScopeItem::Label(label) => label.dump(),
+        }
+    }
+}
+
+impl Dump for Label {
+    fn dump(&self) -> Node {
+        let node = match &self.kind {
+            LabelKind::Named(name) => Node::new("Label").with("name", name),
+            LabelKind::Case { id, value } => {
+                Node::new("Case").with("value", value).with("switch", id)
+            }
+            LabelKind::Default { id } => Node::new("Default").with("switch", id),
+        };
+
+        node.at(self.span)
+    }
+}
+
+impl Dump for AsmOperand {
+    fn dump(&self) -> Node {
+        let node = Node::new("AsmOperand");
+        let node = match &self.name {
+            Some(name) => node.with("name", name),
+            None => node,
+        };
+
+        node.with("constraint", &self.constraint)
+            .at(self.span)
+            .child(self.expression.dump())
+    }
+}
+
+fn optional(node: Node, role: &'static str, expression: Option<&Expression>) -> Node {
+    match expression {
+        Some(expression) => node.child_as(role, expression.dump()),
+        None => node,
+    }
+}
+
+impl Dump for Statement {
+    fn dump(&self) -> Node {
+        let node = match &self.kind {
+            StatementKind::Jump { statement } => match statement {
+                JumpStatement::Return { expression } => {
+                    optional(Node::new("Return"), "value", expression.as_ref())
+                }
+                JumpStatement::Break { id } => Node::new("Break").with("target", id),
+                JumpStatement::Continue { id } => Node::new("Continue").with("target", id),
+                JumpStatement::__Ebreak => Node::new("Ebreak"),
+            },
+            StatementKind::Expression { expression } => {
+                Node::new("ExpressionStatement").child(expression.dump())
+            }
+            StatementKind::Scope { scope } => return scope.dump(),
+            StatementKind::If { statement } => {
+                let node = Node::new("If")
+                    .child_as("condition", statement.condition.dump())
+                    .child_as("then", statement.then_block.dump());
+
+                match &statement.else_block {
+                    Some(else_block) => node.child_as("else", else_block.dump()),
+                    None => node,
+                }
+            }
+            StatementKind::While { statement } => Node::new("While")
+                .with("id", &statement.id)
+                .child_as("condition", statement.condition.dump())
+                .child_as("body", statement.block.dump()),
+            StatementKind::For { statement } => {
+                let node = Node::new("For").with("id", &statement.id);
+
+                let node = match &statement.init {
+                    Some(ForInit::Declaration(declaration)) => {
+                        node.child_as("init", declaration.dump())
+                    }
+                    Some(ForInit::Expression(expression)) => {
+                        node.child_as("init", expression.dump())
+                    }
+                    None => node,
+                };
+
+                let node = optional(node, "condition", statement.condition.as_ref());
+                let node = optional(node, "increment", statement.increment.as_ref());
+                node.child_as("body", statement.block.dump())
+            }
+            StatementKind::Switch { statement } => {
+                let cases: Vec<String> = statement
+                    .cases
+                    .iter()
+                    .map(|case| match case {
+                        Case::Default => "default".to_string(),
+                        Case::Case(value) => value.to_string(),
+                    })
+                    .collect();
+
+                Node::new("Switch")
+                    .with("id", &statement.id)
+                    .with("cases", cases.join(", "))
+                    .child_as("expression", statement.expression.dump())
+                    .child_as("body", statement.body.dump())
+            }
+            StatementKind::Asm { statement } => {
+                let node = Node::new("Asm")
+                    .with("template", &statement.template)
+                    .with("volatile", statement.volatile)
+                    .with("basic", statement.basic);
+
+                let node = match statement.clobbers.as_slice() {
+                    [] => node,
+                    clobbers => node.with("clobbers", clobbers.join(", ")),
+                };
+
+                let node = statement.outputs.iter().fold(node, |node, operand| {
+                    node.child_as("output", operand.dump())
+                });
+
+                statement
+                    .inputs
+                    .iter()
+                    .fold(node, |node, operand| node.child_as("input", operand.dump()))
+            }
+            StatementKind::Null => Node::new("Null"),
+            StatementKind::Fallthrough => Node::new("Fallthrough"),
+        };
+
+        node.at(self.span)
+    }
+}
+
+fn builtin_name(builtin: &Builtin) -> String {
+    match builtin {
+        Builtin::Csrr(csr) => format!("csrr {csr}"),
+        Builtin::Csrw(csr) => format!("csrw {csr}"),
+        Builtin::Csrs(csr) => format!("csrs {csr}"),
+        Builtin::Csrc(csr) => format!("csrc {csr}"),
+        Builtin::Csrrw(csr) => format!("csrrw {csr}"),
+        Builtin::Wfi => "wfi".to_string(),
+        Builtin::Fence => "fence".to_string(),
+        Builtin::FenceI => "fence.i".to_string(),
+        Builtin::Ecall => "ecall".to_string(),
+    }
+}
+
+impl Dump for Expression {
+    fn dump(&self) -> Node {
+        let node = match &self.kind {
+            ExpressionKind::Number(literal) => Node::new("Number")
+                .with("value", literal.value)
+                .with("literal_type", literal.datatype),
+            ExpressionKind::UnaryOp(operation) => {
+                let (operand, fixity) = match operation {
+                    UnaryOp::PostfixIncrement(operand) | UnaryOp::PostfixDecrement(operand) => {
+                        (operand, "postfix")
+                    }
+                    UnaryOp::Plus(operand)
+                    | UnaryOp::Negation(operand)
+                    | UnaryOp::BitwiseNot(operand)
+                    | UnaryOp::LogicalNot(operand)
+                    | UnaryOp::PrefixIncrement(operand)
+                    | UnaryOp::PrefixDecrement(operand)
+                    | UnaryOp::Ref(operand)
+                    | UnaryOp::Deref(operand) => (operand, "prefix"),
+                };
+
+                Node::new("Unary")
+                    .with("operator", operation.symbol())
+                    .with("fixity", fixity)
+                    .child(operand.dump())
+            }
+            ExpressionKind::BinaryOp(operation) => {
+                let (lhs, rhs) = operation.operands();
+
+                Node::new("Binary")
+                    .with("operator", operation.symbol())
+                    .child(lhs.dump())
+                    .child(rhs.dump())
+            }
+            ExpressionKind::TernaryOp(operation) => Node::new("Conditional")
+                .child_as("condition", operation.condition.dump())
+                .child_as("then", operation.then_expr.dump())
+                .child_as("else", operation.else_expr.dump()),
+            ExpressionKind::Variable(name) => Node::new("Variable").with("name", name),
+            ExpressionKind::Global(name) => Node::new("Global").with("name", name),
+            ExpressionKind::FunctionSymbol(name) => Node::new("Function").with("name", name),
+            ExpressionKind::Call(call) => Node::new("Call")
+                .child_as("callee", call.expression.dump())
+                .children(&call.arguments),
+            ExpressionKind::Builtin(call) => Node::new("Builtin")
+                .with("name", builtin_name(&call.builtin))
+                .children(&call.arguments),
+            ExpressionKind::Subscript(subscript) => Node::new("Subscript")
+                .child(subscript.expression.dump())
+                .child_as("index", subscript.index.dump()),
+            ExpressionKind::Member(member) => Node::new("Member")
+                .with("name", &member.member)
+                .with("arrow", member.dereference)
+                .child(member.expression.dump()),
+            ExpressionKind::Conversion(conversion) => Node::new("Conversion")
+                .with("conversion", format!("{:?}", conversion.kind))
+                .child(conversion.expression.dump()),
+        };
+
+        // only the typed ast has these
+        let node = match &self.datatype {
+            Some(datatype) => node.with("type", datatype),
+            None => node,
+        };
+
+        let node = match self.category {
+            Some(ValueCategory::LValue) => node.with("category", "lvalue"),
+            Some(ValueCategory::RValue) => node.with("category", "rvalue"),
+            None => node,
+        };
+
+        node.at(self.span)
+    }
+}
@@ -0,0 +1,532 @@
+// the parts of elf32 that riscv objects and executables use, enough to
+// assemble and link without the gnu toolchain
+use std::collections::HashMap;
+
+pub use executable::{Executable, LoadedSection};
+
+mod executable;
+mod relocatable;
+
+const EM_RISCV: u16 = 243;
+const ET_REL: u16 = 1;
+const ET_EXEC: u16 = 2;
+
+const HEADER_SIZE: u32 = 52;
+const PROGRAM_HEADER_SIZE: u32 = 32;
+const SECTION_HEADER_SIZE: u32 = 40;
+const SYMBOL_SIZE: u32 = 16;
+const RELOCATION_SIZE: u32 = 12;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+
+const SHF_WRITE: u32 = 0x1;
+const SHF_ALLOC: u32 = 0x2;
+const SHF_EXECINSTR: u32 = 0x4;
+const SHF_INFO_LINK: u32 = 0x40;
+
+const SHN_UNDEF: u16 = 0;
+const SHN_ABS: u16 = 0xfff1;
+const SHN_COMMON: u16 = 0xfff2;
+
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+const STT_FILE: u8 = 4;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+
+// e_flags, the float abi and whether compressed instructions may be mixed in
+pub const EF_RISCV_RVC: u32 = 0x1;
+pub const EF_RISCV_FLOAT_ABI_SINGLE: u32 = 0x2;
+pub const EF_RISCV_FLOAT_ABI_DOUBLE: u32 = 0x4;
+pub const EF_RISCV_FLOAT_ABI: u32 = 0x6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelocationKind {
+    Absolute32 = 1,
+    Branch = 16,
+    Jal = 17,
+    Call = 18,
+    CallPlt = 19,
+    PcrelHi20 = 23,
+    // these point at the auipc with the pcrel_hi20, not at the target
+    PcrelLo12I = 24,
+    PcrelLo12S = 25,
+    Hi20 = 26,
+    Lo12I = 27,
+    Lo12S = 28,
+    // the padding before it can be shrunk to keep the alignment after relaxing
+    Align = 43,
+    RvcBranch = 44,
+    RvcJump = 45,
+    // the relocation before it at the same offset may be relaxed
+    Relax = 51,
+}
+
+impl RelocationKind {
+    fn from_number(number: u32) -> Option<RelocationKind> {
+        let kind = match number {
+            1 => RelocationKind::Absolute32,
+            16 => RelocationKind::Branch,
+            17 => RelocationKind::Jal,
+            18 => RelocationKind::Call,
+            19 => RelocationKind::CallPlt,
+            23 => RelocationKind::PcrelHi20,
+            24 => RelocationKind::PcrelLo12I,
+            25 => RelocationKind::PcrelLo12S,
+            26 => RelocationKind::Hi20,
+            27 => RelocationKind::Lo12I,
+            28 => RelocationKind::Lo12S,
+            43 => RelocationKind::Align,
+            44 => RelocationKind::RvcBranch,
+            45 => RelocationKind::RvcJump,
+            51 => RelocationKind::Relax,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SectionKind {
+    Code,
+    ReadOnly,
+    Data,
+    // takes no room in the file, it is zeroed at startup
+    Uninitialized,
+}
+
+impl SectionKind {
+    // how gas picks the kind when the flags are not spelled out
+    pub fn from_name(name: &str) -> SectionKind {
+        let prefixed = |prefix: &str| name == prefix || name.starts_with(&format!("{prefix}."));
+
+        if prefixed(".text") {
+            SectionKind::Code
+        } else if prefixed(".rodata") || prefixed(".srodata") {
+            SectionKind::ReadOnly
+        } else if prefixed(".bss") || prefixed(".sbss") {
+            SectionKind::Uninitialized
+        } else {
+            SectionKind::Data
+        }
+    }
+
+    // the kind of a section read back from its header
+    fn from_header(kind: u32, flags: u32) -> SectionKind {
+        if kind == SHT_NOBITS {
+            SectionKind::Uninitialized
+        } else if flags & SHF_EXECINSTR != 0 {
+            SectionKind::Code
+        } else if flags & SHF_WRITE != 0 {
+            SectionKind::Data
+        } else {
+            SectionKind::ReadOnly
+        }
+    }
+
+    fn flags(self) -> u32 {
+        match self {
+            SectionKind::Code => SHF_ALLOC | SHF_EXECINSTR,
+            SectionKind::ReadOnly => SHF_ALLOC,
+            SectionKind::Data | SectionKind::Uninitialized => SHF_ALLOC | SHF_WRITE,
+        }
+    }
+
+    fn header_kind(self) -> u32 {
+        match self {
+            SectionKind::Uninitialized => SHT_NOBITS,
+            _ => SHT_PROGBITS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    pub offset: u32,
+    pub kind: RelocationKind,
+    // index into the symbols of the object, align and relax have none
+    pub symbol: Option<usize>,
+    pub addend: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub kind: SectionKind,
+    pub alignment: u32,
+    // zeros for uninitialized sections, only the length counts then
+    pub data: Vec<u8>,
+    pub relocations: Vec<Relocation>,
+}
+
+impl Section {
+    pub fn new(name: &str, kind: SectionKind) -> Section {
+        Section {
+            name: name.to_string(),
+            kind,
+            alignment: if kind == SectionKind::Code { 4 } else { 1 },
+            data: Vec::new(),
+            relocations: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Local,
+    Global,
+    Weak,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    None,
+    Function,
+    Object,
+    // stands for the start of a section, relocations from other assemblers
+    // use it for labels that are not in the symbol table
+    Section,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub binding: Binding,
+    pub kind: SymbolKind,
+    // index into the sections, none for symbols other objects define
+    pub section: Option<usize>,
+    pub value: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Object {
+    pub flags: u32,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+}
+
+// names are looked up while writing, every name is stored once
+struct StringTable {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn new() -> StringTable {
+        StringTable {
+            bytes: vec![0],
+            offsets: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, name: &str) -> u32 {
+        if name.is_empty() {
+            return 0;
+        }
+
+        if let Some(&offset) = self.offsets.get(name) {
+            return offset;
+        }
+
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend(name.as_bytes());
+        self.bytes.push(0);
+        self.offsets.insert(name.to_string(), offset);
+        offset
+    }
+}
+
+#[derive(Default)]
+struct SectionHeader {
+    name: u32,
+    kind: u32,
+    flags: u32,
+    address: u32,
+    offset: u32,
+    size: u32,
+    link: u32,
+    info: u32,
+    alignment: u32,
+    entry_size: u32,
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend(value.to_le_bytes());
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend(value.to_le_bytes());
+}
+
+fn align(bytes: &mut Vec<u8>, alignment: usize) {
+    bytes.resize(bytes.len().next_multiple_of(alignment), 0);
+}
+
+// local symbols have to come before the others in the symbol table, so the
+// order the symbols are written in is not the order they are in
+fn symbol_order(symbols: &[Symbol]) -> Vec<usize> {
+    let (mut order, others): (Vec<usize>, Vec<usize>) =
+        (0..symbols.len()).partition(|&index| symbols[index].binding == Binding::Local);
+    order.extend(others);
+    order
+}
+
+// what both kinds of file end with: the symbol table, the names and the
+// section headers. the headers of the sections before them are collected
+// here as they are written
+struct Tables {
+    section_names: StringTable,
+    headers: Vec<SectionHeader>,
+}
+
+impl Tables {
+    fn new() -> Tables {
+        Tables {
+            section_names: StringTable::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    fn section(&mut self, name: &str, header: SectionHeader) {
+        let name = self.section_names.add(name);
+        self.headers.push(SectionHeader { name, ..header });
+    }
+
+    // the index the next section gets, 0 is the null section
+    fn next_index(&self) -> u32 {
+        self.headers.len() as u32 + 1
+    }
+
+    // symbols with no section are undefined in objects and absolute in
+    // executables, `outside` is the section index they get
+    fn symbols(&mut self, bytes: &mut Vec<u8>, symbols: &[Symbol], order: &[usize], outside: u16) {
+        let mut names = StringTable::new();
+
+        align(bytes, 4);
+        let offset = bytes.len() as u32;
+        bytes.extend([0; SYMBOL_SIZE as usize]);
+
+        for &index in order.iter() {
+            let symbol = &symbols[index];
+
+            let binding = match symbol.binding {
+                Binding::Local => STB_LOCAL,
+                Binding::Global => STB_GLOBAL,
+                Binding::Weak => STB_WEAK,
+            };
+            let kind = match symbol.kind {
+                SymbolKind::None => STT_NOTYPE,
+                SymbolKind::Function => STT_FUNC,
+                SymbolKind::Object => STT_OBJECT,
+                SymbolKind::Section => STT_SECTION,
+            };
+
+            push_u32(bytes, names.add(&symbol.name));
+            push_u32(bytes, symbol.value);
+            push_u32(bytes, symbol.size);
+            bytes.push((binding << 4) | kind);
+            bytes.push(0);
+            push_u16(
+                bytes,
+                symbol.section.map_or(outside, |section| section as u16 + 1),
+            );
+        }
+
+        let locals = order
+            .iter()
+            .take_while(|&&index| symbols[index].binding == Binding::Local)
+            .count() as u32;
+
+        let header = SectionHeader {
+            kind: SHT_SYMTAB,
+            offset,
+            size: bytes.len() as u32 - offset,
+            // the names come right after
+            link: self.next_index() + 1,
+            info: locals + 1,
+            alignment: 4,
+            entry_size: SYMBOL_SIZE,
+            ..SectionHeader::default()
+        };
+        self.section(".symtab", header);
+
+        self.strings(bytes, ".strtab", &names.bytes);
+    }
+
+    fn strings(&mut self, bytes: &mut Vec<u8>, name: &str, table: &[u8]) {
+        let offset = bytes.len() as u32;
+        bytes.extend(table);
+
+        let header = SectionHeader {
+            kind: SHT_STRTAB,
+            offset,
+            size: table.len() as u32,
+            alignment: 1,
+            ..SectionHeader::default()
+        };
+        self.section(name, header);
+    }
+
+    // writes the section names and the headers, the file header needs to
+    // know where they start and how many there are
+    fn finish(mut self, bytes: &mut Vec<u8>) -> (u32, u16) {
+        self.section_names.add(".shstrtab");
+        let table = std::mem::take(&mut self.section_names.bytes);
+        self.strings(bytes, ".shstrtab", &table);
+
+        align(bytes, 4);
+        let start = bytes.len() as u32;
+        bytes.extend([0; SECTION_HEADER_SIZE as usize]);
+
+        for header in self.headers.iter() {
+            push_u32(bytes, header.name);
+            push_u32(bytes, header.kind);
+            push_u32(bytes, header.flags);
+            push_u32(bytes, header.address);
+            push_u32(bytes, header.offset);
+            push_u32(bytes, header.size);
+            push_u32(bytes, header.link);
+            push_u32(bytes, header.info);
+            push_u32(bytes, header.alignment);
+            push_u32(bytes, header.entry_size);
+        }
+
+        (start, self.headers.len() as u16 + 1)
+    }
+}
+
+struct FileHeader {
+    kind: u16,
+    flags: u32,
+    entry: u32,
+    program_headers: u16,
+    section_headers: u32,
+    section_count: u16,
+}
+
+impl FileHeader {
+    // over the room left for it at the start of the file
+    fn write(&self, bytes: &mut [u8]) {
+        let mut header = Vec::new();
+        header.extend(b"\x7fELF");
+        // 32 bit, little endian, version 1, system v abi
+        header.extend([1, 1, 1, 0]);
+        header.extend([0; 8]);
+        push_u16(&mut header, self.kind);
+        push_u16(&mut header, EM_RISCV);
+        push_u32(&mut header, 1);
+        push_u32(&mut header, self.entry);
+        // the program headers, if any, follow the file header
+        let program_headers = if self.program_headers > 0 {
+            HEADER_SIZE
+        } else {
+            0
+        };
+        push_u32(&mut header, program_headers);
+        push_u32(&mut header, self.section_headers);
+        push_u32(&mut header, self.flags);
+        push_u16(&mut header, HEADER_SIZE as u16);
+        push_u16(
+            &mut header,
+            PROGRAM_HEADER_SIZE as u16 * self.program_headers.min(1),
+        );
+        push_u16(&mut header, self.program_headers);
+        push_u16(&mut header, SECTION_HEADER_SIZE as u16);
+        push_u16(&mut header, self.section_count);
+        // the section names are the last section
+        push_u16(&mut header, self.section_count - 1);
+
+        bytes[..HEADER_SIZE as usize].copy_from_slice(&header);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_survive_a_round_trip() {
+        let section = |name: &str, kind, alignment, data: &[u8], relocations| Section {
+            name: name.to_string(),
+            kind,
+            alignment,
+            data: data.to_vec(),
+            relocations,
+        };
+        let symbol = |name: &str, binding, kind, section, value, size| Symbol {
+            name: name.to_string(),
+            binding,
+            kind,
+            section,
+            value,
+            size,
+        };
+        let relocation = |offset, kind, symbol, addend| Relocation {
+            offset,
+            kind,
+            symbol,
+            addend,
+        };
+
+        // the locals come first, the way the symbol table has them
+        let object = Object {
+            flags: EF_RISCV_FLOAT_ABI_DOUBLE,
+            sections: vec![
+                section(
+                    ".text",
+                    SectionKind::Code,
+                    4,
+                    &[
+                        0x97, 0, 0, 0, 0xe7, 0x80, 0, 0, 0x13, 0, 0, 0, 0x67, 0x80, 0, 0,
+                    ],
+                    vec![
+                        relocation(0, RelocationKind::Call, Some(6), 0),
+                        relocation(0, RelocationKind::Relax, None, 0),
+                        relocation(8, RelocationKind::Align, None, 4),
+                    ],
+                ),
+                section(
+                    ".data",
+                    SectionKind::Data,
+                    4,
+                    &[1, 2, 3, 4, 0, 0, 0, 0],
+                    vec![relocation(4, RelocationKind::Absolute32, Some(1), -4)],
+                ),
+                section(".bss", SectionKind::Uninitialized, 8, &[0; 16], vec![]),
+                section(".rodata", SectionKind::ReadOnly, 1, b"hi\0", vec![]),
+            ],
+            symbols: vec![
+                symbol("", Binding::Local, SymbolKind::Section, Some(0), 0, 0),
+                symbol("", Binding::Local, SymbolKind::Section, Some(1), 0, 0),
+                symbol("counter", Binding::Local, SymbolKind::Object, Some(2), 8, 4),
+                symbol(
+                    "main",
+                    Binding::Global,
+                    SymbolKind::Function,
+                    Some(0),
+                    0,
+                    16,
+                ),
+                symbol("table", Binding::Global, SymbolKind::Object, Some(1), 0, 8),
+                symbol("hook", Binding::Weak, SymbolKind::Function, Some(0), 12, 4),
+                symbol("printf", Binding::Global, SymbolKind::None, None, 0, 0),
+            ],
+        };
+
+        let parsed = Object::parse(&object.to_bytes()).unwrap();
+        assert_eq!(parsed, object);
+
+        // and writing it again gives the same bytes
+        assert_eq!(parsed.to_bytes(), object.to_bytes());
+    }
+}
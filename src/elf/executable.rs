@@ -0,0 +1,112 @@
+// what the linker writes, sections at their final addresses and a segment
+// for each of them to load
+use super::*;
+
+const PT_LOAD: u32 = 1;
+
+const PF_X: u32 = 0x1;
+const PF_W: u32 = 0x2;
+const PF_R: u32 = 0x4;
+
+#[derive(Debug, Clone)]
+pub struct LoadedSection {
+    pub name: String,
+    pub kind: SectionKind,
+    // where it runs
+    pub address: u32,
+    // where it is loaded, only different for data that startup code copies
+    // out of rom
+    pub load_address: u32,
+    pub alignment: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Executable {
+    pub flags: u32,
+    pub entry: u32,
+    pub sections: Vec<LoadedSection>,
+    // the values are addresses, symbols with no section are absolute
+    pub symbols: Vec<Symbol>,
+}
+
+impl Executable {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let segments = self.sections.len() as u32;
+        let mut tables = Tables::new();
+
+        // the file header and the program headers are written once the
+        // sections are placed
+        let mut bytes = vec![0; (HEADER_SIZE + segments * PROGRAM_HEADER_SIZE) as usize];
+        let mut program_headers = Vec::new();
+
+        for section in self.sections.iter() {
+            // loaders map pages, the offset in the file and the address have
+            // to agree below the alignment
+            let alignment = section.alignment.max(1);
+            let padding = section
+                .address
+                .wrapping_sub(bytes.len() as u32)
+                .rem_euclid(alignment);
+            bytes.resize(bytes.len() + padding as usize, 0);
+            let offset = bytes.len() as u32;
+
+            let file_size = match section.kind {
+                SectionKind::Uninitialized => 0,
+                _ => {
+                    bytes.extend(&section.data);
+                    section.data.len() as u32
+                }
+            };
+
+            let flags = match section.kind {
+                SectionKind::Code => PF_R | PF_X,
+                SectionKind::ReadOnly => PF_R,
+                SectionKind::Data | SectionKind::Uninitialized => PF_R | PF_W,
+            };
+
+            for field in [
+                PT_LOAD,
+                offset,
+                section.address,
+                section.load_address,
+                file_size,
+                section.data.len() as u32,
+                flags,
+                alignment,
+            ] {
+                push_u32(&mut program_headers, field);
+            }
+
+            let header = SectionHeader {
+                kind: section.kind.header_kind(),
+                flags: section.kind.flags(),
+                address: section.address,
+                offset,
+                size: section.data.len() as u32,
+                alignment,
+                ..SectionHeader::default()
+            };
+            tables.section(&section.name, header);
+        }
+
+        let start = HEADER_SIZE as usize;
+        bytes[start..start + program_headers.len()].copy_from_slice(&program_headers);
+
+        let order = symbol_order(&self.symbols);
+        tables.symbols(&mut bytes, &self.symbols, &order, SHN_ABS);
+        let (section_headers, section_count) = tables.finish(&mut bytes);
+
+        let header = FileHeader {
+            kind: ET_EXEC,
+            flags: self.flags,
+            entry: self.entry,
+            program_headers: segments as u16,
+            section_headers,
+            section_count,
+        };
+        header.write(&mut bytes);
+
+        bytes
+    }
+}
@@ -0,0 +1,320 @@
+// what the assembler writes and the linker reads
+use super::*;
+
+// the fields of a file, read with bounds checks since objects come from
+// anywhere
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl Reader<'_> {
+    fn slice(&self, offset: u32, size: u32) -> Result<&[u8], String> {
+        let start = offset as usize;
+        let end = start.checked_add(size as usize);
+
+        end.and_then(|end| self.bytes.get(start..end))
+            .ok_or_else(|| "Truncated ELF file".to_string())
+    }
+
+    fn u8(&self, offset: u32) -> Result<u8, String> {
+        Ok(self.slice(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: u32) -> Result<u16, String> {
+        let bytes = self.slice(offset, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, offset: u32) -> Result<u32, String> {
+        let bytes = self.slice(offset, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // a name out of a string table
+    fn name(&self, table: &SectionHeader, offset: u32) -> Result<String, String> {
+        let names = self.slice(table.offset, table.size)?;
+        let name = names
+            .get(offset as usize..)
+            .and_then(|name| name.split(|&byte| byte == 0).next())
+            .ok_or_else(|| "Invalid name offset".to_string())?;
+
+        Ok(String::from_utf8_lossy(name).to_string())
+    }
+
+    fn section_header(&self, offset: u32) -> Result<SectionHeader, String> {
+        Ok(SectionHeader {
+            name: self.u32(offset)?,
+            kind: self.u32(offset + 4)?,
+            flags: self.u32(offset + 8)?,
+            address: self.u32(offset + 12)?,
+            offset: self.u32(offset + 16)?,
+            size: self.u32(offset + 20)?,
+            link: self.u32(offset + 24)?,
+            info: self.u32(offset + 28)?,
+            alignment: self.u32(offset + 32)?,
+            entry_size: self.u32(offset + 36)?,
+        })
+    }
+}
+
+impl Object {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let order = symbol_order(&self.symbols);
+        let mut table_index = vec![0; self.symbols.len()];
+        for (position, &index) in order.iter().enumerate() {
+            // the null symbol comes first
+            table_index[index] = position as u32 + 1;
+        }
+
+        let mut tables = Tables::new();
+
+        // the header is written last, once the section headers are placed
+        let mut bytes = vec![0; HEADER_SIZE as usize];
+
+        for section in self.sections.iter() {
+            align(&mut bytes, section.alignment.max(1) as usize);
+            let offset = bytes.len() as u32;
+
+            if section.kind != SectionKind::Uninitialized {
+                bytes.extend(&section.data);
+            }
+
+            let header = SectionHeader {
+                kind: section.kind.header_kind(),
+                flags: section.kind.flags(),
+                offset,
+                size: section.data.len() as u32,
+                alignment: section.alignment,
+                ..SectionHeader::default()
+            };
+            tables.section(&section.name, header);
+        }
+
+        // the symbol table follows the sections and their relocations
+        let symbol_table = tables.next_index()
+            + self
+                .sections
+                .iter()
+                .filter(|section| !section.relocations.is_empty())
+                .count() as u32;
+
+        for (index, section) in self.sections.iter().enumerate() {
+            if section.relocations.is_empty() {
+                continue;
+            }
+
+            align(&mut bytes, 4);
+            let offset = bytes.len() as u32;
+
+            for relocation in section.relocations.iter() {
+                push_u32(&mut bytes, relocation.offset);
+                push_u32(
+                    &mut bytes,
+                    (relocation.symbol.map_or(0, |symbol| table_index[symbol]) << 8)
+                        | relocation.kind as u32,
+                );
+                push_u32(&mut bytes, relocation.addend as u32);
+            }
+
+            let header = SectionHeader {
+                kind: SHT_RELA,
+                flags: SHF_INFO_LINK,
+                offset,
+                size: bytes.len() as u32 - offset,
+                link: symbol_table,
+                info: index as u32 + 1,
+                alignment: 4,
+                entry_size: RELOCATION_SIZE,
+                ..SectionHeader::default()
+            };
+            tables.section(&format!(".rela{}", section.name), header);
+        }
+
+        tables.symbols(&mut bytes, &self.symbols, &order, SHN_UNDEF);
+        let (section_headers, section_count) = tables.finish(&mut bytes);
+
+        let header = FileHeader {
+            kind: ET_REL,
+            flags: self.flags,
+            entry: 0,
+            program_headers: 0,
+            section_headers,
+            section_count,
+        };
+        header.write(&mut bytes);
+
+        bytes
+    }
+
+    // the sections that end up in memory, with their symbols and relocations.
+    // the rest, like debug information, is left out
+    pub fn parse(bytes: &[u8]) -> Result<Object, String> {
+        if bytes.starts_with(b"!<arch>\n") {
+            return Err("Archives are not supported by the built-in linker".to_string());
+        }
+
+        let file = Reader { bytes };
+
+        if file.slice(0, 4)? != b"\x7fELF" {
+            return Err("Not an ELF file".to_string());
+        }
+
+        if file.u8(4)? != 1 || file.u8(5)? != 1 {
+            return Err("Not a 32 bit little endian ELF file".to_string());
+        }
+
+        if file.u16(16)? != ET_REL || file.u16(18)? != EM_RISCV {
+            return Err("Not a RISC-V relocatable object".to_string());
+        }
+
+        let flags = file.u32(36)?;
+        let section_headers = file.u32(32)?;
+        let section_count = file.u16(48)? as u32;
+        let section_names = file.u16(50)? as u32;
+
+        let headers = (0..section_count)
+            .map(|index| file.section_header(section_headers + index * SECTION_HEADER_SIZE))
+            .collect::<Result<Vec<_>, _>>()?;
+        let header = |index: u32| {
+            headers
+                .get(index as usize)
+                .ok_or_else(|| format!("Invalid section index {index}"))
+        };
+        let names = header(section_names)?;
+
+        // elf section index to the index of the kept section
+        let mut section_index = vec![None; headers.len()];
+        let mut sections = Vec::new();
+
+        for (index, header) in headers.iter().enumerate() {
+            let loaded = matches!(header.kind, SHT_PROGBITS | SHT_NOBITS);
+            if !loaded || header.flags & SHF_ALLOC == 0 {
+                continue;
+            }
+
+            let kind = SectionKind::from_header(header.kind, header.flags);
+            let data = match kind {
+                SectionKind::Uninitialized => vec![0; header.size as usize],
+                _ => file.slice(header.offset, header.size)?.to_vec(),
+            };
+
+            section_index[index] = Some(sections.len());
+            sections.push(Section {
+                name: file.name(names, header.name)?,
+                kind,
+                alignment: header.alignment.max(1),
+                data,
+                relocations: Vec::new(),
+            });
+        }
+
+        let mut symbols = Vec::new();
+        // elf symbol index to the index of the kept symbol
+        let mut symbol_index = Vec::new();
+
+        if let Some(table) = headers.iter().find(|header| header.kind == SHT_SYMTAB) {
+            let symbol_names = header(table.link)?;
+
+            let offsets = (table.offset..table.offset + table.size).step_by(SYMBOL_SIZE as usize);
+
+            // the first symbol is the null symbol
+            symbol_index.push(None);
+
+            for offset in offsets.skip(1) {
+                let info = file.u8(offset + 12)?;
+                let shndx = file.u16(offset + 14)?;
+
+                let binding = match info >> 4 {
+                    STB_LOCAL => Binding::Local,
+                    STB_GLOBAL => Binding::Global,
+                    STB_WEAK => Binding::Weak,
+                    binding => return Err(format!("Unsupported symbol binding {binding}")),
+                };
+                let kind = match info & 0xf {
+                    STT_FUNC => SymbolKind::Function,
+                    STT_OBJECT => SymbolKind::Object,
+                    STT_SECTION => SymbolKind::Section,
+                    STT_FILE => {
+                        symbol_index.push(None);
+                        continue;
+                    }
+                    _ => SymbolKind::None,
+                };
+
+                let name = file.name(symbol_names, file.u32(offset)?)?;
+
+                let section = match shndx {
+                    SHN_UNDEF => None,
+                    SHN_ABS | SHN_COMMON => {
+                        return Err(format!(
+                            "Absolute and common symbols like `{name}` are not supported"
+                        ));
+                    }
+                    index => match section_index.get(index as usize) {
+                        Some(&Some(section)) => Some(section),
+                        // in a section that is left out
+                        _ => {
+                            symbol_index.push(None);
+                            continue;
+                        }
+                    },
+                };
+
+                symbol_index.push(Some(symbols.len()));
+                symbols.push(Symbol {
+                    name,
+                    binding,
+                    kind,
+                    section,
+                    value: file.u32(offset + 4)?,
+                    size: file.u32(offset + 8)?,
+                });
+            }
+        }
+
+        for header in headers.iter().filter(|header| header.kind == SHT_RELA) {
+            let Some(Some(section)) = section_index.get(header.info as usize) else {
+                continue;
+            };
+
+            for offset in
+                (header.offset..header.offset + header.size).step_by(RELOCATION_SIZE as usize)
+            {
+                let info = file.u32(offset + 4)?;
+
+                let kind = RelocationKind::from_number(info & 0xff)
+                    .ok_or_else(|| format!("Unsupported relocation type {}", info & 0xff))?;
+
+                let symbol = match info >> 8 {
+                    0 => None,
+                    index => Some(
+                        symbol_index
+                            .get(index as usize)
+                            .copied()
+                            .flatten()
+                            .ok_or_else(|| format!("Invalid symbol index {index}"))?,
+                    ),
+                };
+
+                sections[*section].relocations.push(Relocation {
+                    offset: file.u32(offset)?,
+                    kind,
+                    symbol,
+                    addend: file.u32(offset + 8)? as i32,
+                });
+            }
+        }
+
+        for section in sections.iter_mut() {
+            section
+                .relocations
+                .sort_by_key(|relocation| relocation.offset);
+        }
+
+        Ok(Object {
+            flags,
+            sections,
+            symbols,
+        })
+    }
+}
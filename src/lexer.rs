@@ -0,0 +1,276 @@
+use std::fmt::Display;
+
+use crate::{
+    diagnostic::{code, Diagnostic, Diagnostics, ErrorReported},
+    types::span::{LineIndex, Span},
+};
+
+// reserved words, the lexer never makes an identifier out of one
+pub const KEYWORDS: &[&str] = &[
+    "asm",
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "__asm",
+    "__asm__",
+    "__attribute",
+    "__attribute__",
+    "__ebreak",
+    "__volatile",
+    "__volatile__",
+];
+
+type LexResult<T> = Result<T, Box<Diagnostic>>;
+
+// longest first, so that `<<=` is not taken for `<<` and `=`
+const PUNCTUATORS: &[&str] = &[
+    "...", "<<=", ">>=", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+=",
+    "-=", "*=", "/=", "%=", "&=", "^=", "|=", "[", "]", "(", ")", "{", "}", ".", "&", "*", "+",
+    "-", "~", "!", "/", "%", "<", ">", "^", "|", "?", ":", ";", "=", ",",
+];
+
+// literals are kept as they are written, the parser works out their values
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Keyword(&'static str),
+    Identifier(String),
+    Number(String),
+    Character(String),
+    String(String),
+    Punctuator(&'static str),
+}
+
+impl TokenKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenKind::Keyword(_) => "keyword",
+            TokenKind::Identifier(_) => "identifier",
+            TokenKind::Number(_) => "number",
+            TokenKind::Character(_) => "character",
+            TokenKind::String(_) => "string",
+            TokenKind::Punctuator(_) => "punctuator",
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            TokenKind::Keyword(text) | TokenKind::Punctuator(text) => text,
+            TokenKind::Identifier(text)
+            | TokenKind::Number(text)
+            | TokenKind::Character(text)
+            | TokenKind::String(text) => text,
+        }
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    position: usize,
+    lines: LineIndex,
+    tokens: Vec<Token>,
+}
+
+impl Lexer<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_while(&mut self, predicate: impl Fn(char) -> bool) {
+        let length = self
+            .rest()
+            .find(|c| !predicate(c))
+            .unwrap_or(self.rest().len());
+        self.position += length;
+    }
+
+    // whitespace, comments and line continuations
+    fn skip_trivia(&mut self) -> LexResult<()> {
+        loop {
+            let rest = self.rest();
+
+            if rest.starts_with(|c: char| c.is_ascii_whitespace()) {
+                self.skip_while(|c| c.is_ascii_whitespace());
+            } else if rest.starts_with("\\\n") {
+                self.position += 2;
+            } else if rest.starts_with("//") {
+                self.skip_while(|c| c != '\n');
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let start = self.position;
+                let Some(end) = comment.find("*/") else {
+                    self.position = self.source.len();
+                    return Err(Box::new(
+                        Diagnostic::error("Unterminated comment")
+                            .with_code(code::SYNTAX)
+                            .at(self.lines.span(start..start + 2)),
+                    ));
+                };
+                self.position += end + 4;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    // everything up to the closing quote, skipping over escaped characters
+    fn quoted(&mut self, quote: char, what: &str) -> LexResult<String> {
+        let start = self.position;
+        self.position += 1;
+
+        let mut escaped = false;
+        for (i, c) in self.source[self.position..].char_indices() {
+            match c {
+                '\n' => break,
+                '\\' if !escaped => escaped = true,
+                c if c == quote && !escaped => {
+                    self.position += i + 1;
+                    return Ok(self.source[start..self.position].to_string());
+                }
+                _ => escaped = false,
+            }
+        }
+
+        self.skip_while(|c| c != '\n');
+        Err(Box::new(
+            Diagnostic::error(format!("Unterminated {what}"))
+                .with_code(code::SYNTAX)
+                .with_label(
+                    self.lines.span(start..start + 1),
+                    format!("{what} starts here"),
+                ),
+        ))
+    }
+
+    // a pp-number, e.g. 0x1fUL or 1'000, checked by the parser later on. a
+    // doubled separator is kept too, so that it is reported as one
+    fn number(&mut self) -> String {
+        let start = self.position;
+        self.position += 1;
+
+        loop {
+            let mut chars = self.rest().chars();
+            match (chars.next(), chars.next()) {
+                (Some('\''), Some(c)) if c.is_ascii_alphanumeric() || c == '\'' => {
+                    self.position += 1
+                }
+                (Some(c), _) if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                    self.position += 1
+                }
+                _ => break,
+            }
+        }
+
+        self.source[start..self.position].to_string()
+    }
+
+    fn next_token(&mut self) -> LexResult<Option<TokenKind>> {
+        let Some(c) = self.peek() else {
+            return Ok(None);
+        };
+
+        let kind = if c.is_ascii_alphabetic() || c == '_' {
+            let start = self.position;
+            self.skip_while(|c| c.is_ascii_alphanumeric() || c == '_');
+            let word = &self.source[start..self.position];
+
+            match KEYWORDS.iter().find(|keyword| **keyword == word) {
+                Some(keyword) => TokenKind::Keyword(keyword),
+                None => TokenKind::Identifier(word.to_string()),
+            }
+        } else if c.is_ascii_digit() {
+            TokenKind::Number(self.number())
+        } else if c == '"' {
+            TokenKind::String(self.quoted('"', "string literal")?)
+        } else if c == '\'' {
+            TokenKind::Character(self.quoted('\'', "character literal")?)
+        } else if let Some(punctuator) = PUNCTUATORS.iter().find(|p| self.rest().starts_with(**p)) {
+            self.position += punctuator.len();
+            TokenKind::Punctuator(punctuator)
+        } else {
+            let start = self.position;
+            self.position += c.len_utf8();
+            return Err(Box::new(
+                Diagnostic::error(format!("Unexpected character '{c}'"))
+                    .with_code(code::SYNTAX)
+                    .at(self.lines.span(start..self.position)),
+            ));
+        };
+
+        Ok(Some(kind))
+    }
+}
+
+// problems are reported and skipped over, so that one bad character does
+// not hide everything after it
+pub fn tokenize(source: &str, diagnostics: &mut Diagnostics) -> Result<Vec<Token>, ErrorReported> {
+    let mut lexer = Lexer {
+        source,
+        position: 0,
+        lines: LineIndex::new(source),
+        tokens: Vec::new(),
+    };
+
+    loop {
+        if let Err(diagnostic) = lexer.skip_trivia() {
+            diagnostics.report(*diagnostic);
+        }
+
+        let start = lexer.position;
+        match lexer.next_token() {
+            Ok(Some(kind)) => {
+                let span = lexer.lines.span(start..lexer.position);
+                lexer.tokens.push(Token { kind, span });
+            }
+            Ok(None) => break,
+            Err(diagnostic) => diagnostics.report(*diagnostic),
+        }
+    }
+
+    diagnostics.check(lexer.tokens)
+}
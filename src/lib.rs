@@ -0,0 +1,197 @@
+// a c compiler for rv32. the stages can be run one at a time to get at the
+// tokens, the ast, the typed ast and the instructions, or all at once with
+// `compile`
+pub mod diagnostic;
+mod dump;
+mod elf;
+mod json;
+mod lexer;
+mod parser;
+mod riscv;
+mod semantic;
+pub mod target;
+mod types;
+mod utils;
+
+pub use diagnostic::{warning::WarningOptions, Diagnostic, Diagnostics, ErrorReported};
+pub use lexer::Token;
+pub use riscv::{
+    freestanding::{linker_script, FreestandingOptions},
+    instruction::Instruction,
+    values::{Csr, Immediate, Register, RegisterWithOffset},
+};
+pub use target::Target;
+pub use types::program::Program;
+
+// the nodes of the tree that parse and analyze return, for walking it
+pub mod ast {
+    pub use crate::types::{
+        attribute::{Attribute, AttributeArgument, AttributeList},
+        datatype::{Argument, Datatype, IntegerType},
+        declaration::{Declaration, Declarator, InitDeclarator},
+        expression::{
+            BinaryOp, Builtin, BuiltinCall, Call, Conversion, ConversionKind, Expression,
+            ExpressionKind, IntegerLiteral, Member, Subscript, TernaryOp, UnaryOp, ValueCategory,
+        },
+        function_definition::{FunctionArgument, FunctionDefinition, FunctionKind, InterruptMode},
+        program::{Program, ProgramStatement},
+        scope::{Label, LabelKind, Scope, ScopeItem},
+        span::Span,
+        statement::{
+            AsmOperand, AsmStatement, ForInit, ForStatement, IfStatement, JumpStatement, Statement,
+            StatementKind, SwitchStatement, WhileStatement,
+        },
+    };
+}
+
+// what --emit writes out for each stage, as text and as json
+pub use dump::{asm_json, cfg_json, cfg_text, ir_json, ir_text, tokens_json, tokens_text, Dump};
+pub use json::Json;
+pub use riscv::cfg::build as control_flow;
+
+use elf::Object;
+use riscv::{assembler::Assembler, linker::Input};
+use types::program::ProgramStatement;
+
+// everything that changes what a source file compiles to
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub target: Target,
+    // 0 leaves the generated code as it is, the default 1 runs the peephole
+    // optimizer
+    pub optimization: u32,
+    pub warnings: WarningOptions,
+    // startup code for running without an operating system
+    pub freestanding: Option<FreestandingOptions>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            target: Target::default(),
+            optimization: 1,
+            warnings: WarningOptions::default(),
+            freestanding: None,
+        }
+    }
+}
+
+pub fn tokenize(source: &str, diagnostics: &mut Diagnostics) -> Result<Vec<Token>, ErrorReported> {
+    lexer::tokenize(source, diagnostics)
+}
+
+pub fn parse(tokens: &[Token], diagnostics: &mut Diagnostics) -> Result<Program, ErrorReported> {
+    parser::parse_program(tokens, diagnostics)
+}
+
+// resolves names and types, the result has every conversion spelled out
+pub fn analyze(program: Program, diagnostics: &mut Diagnostics) -> Result<Program, ErrorReported> {
+    semantic::analyze_program(program, diagnostics)
+}
+
+pub fn generate(
+    program: Program,
+    options: &Options,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<Instruction>, ErrorReported> {
+    let defines_main = program.functions.iter().any(|statement| {
+        matches!(statement, ProgramStatement::FunctionDefinition(function) if function.name == "main")
+    });
+
+    let mut instructions = riscv::compile_program(program, diagnostics)?;
+
+    // the startup code runs before main, so it goes first. only the unit
+    // that defines main gets it, the others are linked against it
+    if let (Some(freestanding), true) = (&options.freestanding, defines_main) {
+        let mut startup = riscv::freestanding::crt0(freestanding);
+        startup.extend(instructions);
+        instructions = startup;
+    }
+
+    Ok(instructions)
+}
+
+pub fn optimize(instructions: Vec<Instruction>, options: &Options) -> Vec<Instruction> {
+    if options.optimization > 0 {
+        riscv::optimize_program(instructions)
+    } else {
+        instructions
+    }
+}
+
+// all the stages, with warnings and errors going to the diagnostics
+pub fn compile(
+    source: &str,
+    options: &Options,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<Instruction>, ErrorReported> {
+    let tokens = tokenize(source, diagnostics)?;
+    let ast = parse(&tokens, diagnostics)?;
+    let typed_ast = analyze(ast, diagnostics)?;
+    let instructions = generate(typed_ast, options, diagnostics)?;
+    Ok(optimize(instructions, options))
+}
+
+pub fn assembly(instructions: &[Instruction]) -> String {
+    instructions
+        .iter()
+        .map(|instruction| instruction.to_string() + "\n")
+        .collect()
+}
+
+// an elf relocatable object of the instructions, no assembler needed
+pub fn object(instructions: &[Instruction], options: &Options) -> Result<Vec<u8>, String> {
+    let mut assembler = Assembler::new(&options.target);
+
+    for instruction in instructions.iter() {
+        assembler
+            .instruction(instruction)
+            .map_err(|error| format!("{error} in `{instruction}`"))?;
+    }
+
+    Ok(assembler.finish()?.to_bytes())
+}
+
+// the same for assembly source, e.g. a .s file
+pub fn assemble(source: &str, options: &Options) -> Result<Vec<u8>, String> {
+    let mut assembler = Assembler::new(&options.target);
+
+    for (number, line) in source.lines().enumerate() {
+        assembler
+            .line(line)
+            .map_err(|error| format!("line {}: {error}", number + 1))?;
+    }
+
+    Ok(assembler.finish()?.to_bytes())
+}
+
+// an executable out of objects, like the ones of `object` and `assemble`,
+// laid out in the memory map of a freestanding program. the names are the
+// files the objects came from
+pub fn link(
+    objects: &[(String, Vec<u8>)],
+    memory: &FreestandingOptions,
+) -> Result<Vec<u8>, String> {
+    let inputs = objects
+        .iter()
+        .map(|(name, bytes)| {
+            let object = Object::parse(bytes).map_err(|error| format!("{name}: {error}"))?;
+            Ok(Input {
+                name: name.clone(),
+                object,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(riscv::linker::link(inputs, memory)?.to_bytes())
+}
+
+// warnings are only returned when there is an error among them, use
+// `compile` to get at them otherwise
+pub fn compile_to_assembly(source: &str, options: &Options) -> Result<String, Vec<Diagnostic>> {
+    let mut diagnostics = Diagnostics::new(options.warnings.clone());
+
+    compile(source, options, &mut diagnostics)
+        .map(|instructions| assembly(&instructions))
+        .map_err(|ErrorReported| diagnostics.iter().cloned().collect())
+}
@@ -1,44 +1,575 @@
 use std::env;
-use std::fs::File;
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
-mod parser;
-mod riscv;
-mod types;
-mod utils;
+mod driver;
 
-use parser::parse_program;
-use riscv::{compile_program, optimize_program};
+use c_riscv::{
+    analyze, asm_json, assemble, assembly, cfg_json, cfg_text, compile, control_flow,
+    diagnostic::{json, render::Renderer, sarif, Format, Report, Severity},
+    generate, ir_json, ir_text, link, linker_script, object, optimize, parse, tokenize,
+    tokens_json, tokens_text, Diagnostic, Diagnostics, Dump, ErrorReported, FreestandingOptions,
+    Instruction, Json, WarningOptions,
+};
+use driver::{parse_args, Action, Input, InputKind, Options, Output, Stage, HELP, USAGE};
+
+// why the driver stops: a problem of its own, like a file it can not write,
+// or errors in the sources that have been reported already
+enum Failure {
+    Message(String),
+    Reported,
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure::Message(message)
+    }
+}
+
+impl From<ErrorReported> for Failure {
+    fn from(_: ErrorReported) -> Self {
+        Failure::Reported
+    }
+}
+
+// what was found in one input, the structured formats put all of them in
+// one document
+struct Found {
+    filename: String,
+    source: String,
+    diagnostics: Diagnostics,
+}
+
+// everything goes to stderr, stdout can be the output of the compiler. text
+// is written right away, json and sarif need all of the diagnostics in one
+// document, so they are kept until main is done
+struct Reporter {
+    format: Format,
+    found: Vec<Found>,
+}
+
+impl Reporter {
+    fn report(&mut self, found: Found) {
+        match self.format {
+            Format::Text => {
+                let renderer = Renderer::new(&found.filename, &found.source);
+                for diagnostic in found.diagnostics.iter() {
+                    eprintln!("{}", renderer.render(diagnostic));
+                }
+            }
+            Format::Json | Format::Sarif => self.found.push(found),
+        }
+    }
+
+    // for problems that are not about the source, like a bad command line.
+    // they belong to no input, so they have no file in the structured formats
+    fn driver_diagnostic(&mut self, diagnostic: Diagnostic) {
+        match self.format {
+            Format::Text => eprintln!("{}: {}", diagnostic.severity, diagnostic.message),
+            Format::Json | Format::Sarif => {
+                let mut diagnostics = Diagnostics::new(WarningOptions::default());
+                diagnostics.report(diagnostic);
+                self.report(Found {
+                    filename: String::new(),
+                    source: String::new(),
+                    diagnostics,
+                });
+            }
+        }
+    }
+
+    // the structured formats are written even when there is nothing in them
+    fn finish(self) {
+        let reports: Vec<Report> = self
+            .found
+            .iter()
+            .map(|found| Report {
+                filename: &found.filename,
+                source: &found.source,
+                diagnostics: &found.diagnostics,
+            })
+            .collect();
+
+        match self.format {
+            Format::Text => {}
+            Format::Json => eprintln!("{}", json::render(&reports)),
+            Format::Sarif => eprintln!("{}", sarif::render(&reports)),
+        }
+    }
+}
+
+// an input that the requested steps have no use for, like an object with -S
+fn unused(reporter: &mut Reporter, input: &Input) {
+    reporter.driver_diagnostic(Diagnostic::new(
+        Severity::Warning,
+        format!(
+            "{}: linker input file unused because linking not done",
+            input.name()
+        ),
+    ));
+}
+
+// a flag that there is nothing to do for, like -I without a preprocessor
+fn ignored(reporter: &mut Reporter, flag: &str) {
+    reporter.driver_diagnostic(Diagnostic::new(
+        Severity::Warning,
+        format!("'{flag}' ignored because there is no preprocessor"),
+    ));
+}
+
+fn read_input(input: &Input) -> Result<String, String> {
+    let contents = match input {
+        Input::Stdin => {
+            let mut contents = String::new();
+            std::io::stdin()
+                .read_to_string(&mut contents)
+                .map(|_| contents)
+        }
+        Input::File(path) => std::fs::read_to_string(path),
+    };
+
+    contents.map_err(|error| format!("Could not read {}: {error}", input.name()))
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|error| format!("Could not read {}: {error}", path.display()))
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), String> {
+    std::fs::write(path, contents)
+        .map_err(|error| format!("Could not write {}: {error}", path.display()))
+}
+
+fn write_output(output: &Output, contents: &[u8]) -> Result<(), String> {
+    match output {
+        Output::Stdout => std::io::stdout()
+            .write_all(contents)
+            .map_err(|error| format!("Could not write to stdout: {error}")),
+        Output::File(path) => write_file(path, contents),
+    }
+}
+
+// the instructions of one translation unit, and what was found compiling it
+fn compile_input(
+    options: &Options,
+    file: &Input,
+) -> Result<(Result<Vec<Instruction>, ErrorReported>, Found), String> {
+    let source = read_input(file)?;
+
+    let mut diagnostics = Diagnostics::new(options.compile.warnings.clone());
+    let compiled = compile(&source, &options.compile, &mut diagnostics);
+
+    let found = Found {
+        filename: file.name(),
+        source,
+        diagnostics,
+    };
+    Ok((compiled, found))
+}
+
+// every unit is compiled before giving up, so that all of their errors are
+// reported in one run. the units share nothing, external symbols are left
+// for the linker to resolve
+fn compile_sources<'o>(
+    options: &'o Options,
+    reporter: &mut Reporter,
+) -> Result<Vec<(&'o Input, Vec<Instruction>)>, Failure> {
+    // reported as they are found, so that an input that can not be read
+    // does not lose what was found in the ones before it
+    let mut compiled = Vec::new();
+    for input in options.sources() {
+        let (instructions, found) = compile_input(options, input)?;
+        reporter.report(found);
+        compiled.push((input, instructions));
+    }
+
+    compiled
+        .into_iter()
+        .map(|(input, instructions)| Ok((input, instructions?)))
+        .collect()
+}
+
+// everything the toolchain needs goes into a directory of our own
+fn temporary() -> Result<PathBuf, String> {
+    let temporary = env::temp_dir().join(format!("c-riscv-{}", std::process::id()));
+    std::fs::create_dir_all(&temporary)
+        .map_err(|error| format!("Could not create {}: {error}", temporary.display()))?;
+    Ok(temporary)
+}
+
+// the toolchain's object for the assembly of one input
+fn toolchain_object(options: &Options, input: &Input, assembly: &str) -> Result<Vec<u8>, String> {
+    let temporary = temporary()?;
+    let source = temporary.join(format!("{}.s", input.stem()));
+    let object = temporary.join(format!("{}.o", input.stem()));
+    write_file(&source, assembly.as_bytes())?;
+
+    let assembled = driver::assemble(options, &source, &object).and_then(|()| read_file(&object));
+
+    let _ = std::fs::remove_file(&source);
+    let _ = std::fs::remove_file(&object);
+    let _ = std::fs::remove_dir(&temporary);
+    assembled
+}
+
+// the built-in assembler has no debug information to emit and only knows
+// rv32ima and zicsr, the toolchain is tried for everything else
+fn assemble_unit(
+    options: &Options,
+    input: &Input,
+    built_in: impl FnOnce() -> Result<Vec<u8>, String>,
+    assembly: impl FnOnce() -> String,
+) -> Result<Vec<u8>, String> {
+    let error = match options.debug {
+        true => None,
+        false => match built_in() {
+            Ok(object) => return Ok(object),
+            Err(error) => Some(error),
+        },
+    };
+
+    toolchain_object(options, input, &assembly()).map_err(|toolchain| match error {
+        Some(error) => format!("{}: {error}\nnote: {toolchain}", input.name()),
+        None => format!("{}: {toolchain}", input.name()),
+    })
+}
+
+// every input and its object, none for the inputs that are objects already
+type Objects<'i> = Vec<(&'i Input, Option<Vec<u8>>)>;
+
+// an object for every input that is not one already, in the order of the
+// inputs
+fn assemble_units<'i>(
+    options: &'i Options,
+    units: Vec<(&Input, Vec<Instruction>)>,
+) -> Result<Objects<'i>, String> {
+    let mut units = units.into_iter();
+
+    options
+        .inputs
+        .iter()
+        .map(|input| {
+            let object = match input.kind() {
+                InputKind::Source => {
+                    let (_, instructions) = units.next().expect("every source was compiled");
+                    assemble_unit(
+                        options,
+                        input,
+                        || object(&instructions, &options.compile),
+                        || assembly(&instructions),
+                    )?
+                }
+                InputKind::Assembly => {
+                    let text = read_input(input)?;
+                    assemble_unit(
+                        options,
+                        input,
+                        || assemble(&text, &options.compile),
+                        || text.clone(),
+                    )?
+                }
+                InputKind::Object => return Ok((input, None)),
+            };
+
+            Ok((input, Some(object)))
+        })
+        .collect()
+}
+
+// writes the stage wherever --emit asked for it, if it asked for it at all
+fn emit(
+    options: &Options,
+    file: &Input,
+    stage: Stage,
+    text: impl FnOnce() -> String,
+    json: impl FnOnce() -> Json,
+) -> Result<(), String> {
+    let Some(emit) = options.emit.iter().find(|emit| emit.stage == stage) else {
+        return Ok(());
+    };
+
+    let contents = if options.emit_json {
+        json().to_string() + "\n"
+    } else {
+        text()
+    };
+
+    write_output(&options.emit_output(file, emit), contents.as_bytes())
+}
+
+// runs the stages up to the last one that is written out
+fn emit_stages(
+    options: &Options,
+    file: &Input,
+    input: &str,
+    diagnostics: &mut Diagnostics,
+) -> Result<(), Failure> {
+    let last = options.emit.iter().map(|emit| emit.stage).max();
+    let wants = |stage: Stage| last.is_some_and(|last| last >= stage);
+
+    let tokens = tokenize(input, diagnostics)?;
+    emit(
+        options,
+        file,
+        Stage::Tokens,
+        || tokens_text(&tokens),
+        || tokens_json(&tokens),
+    )?;
+
+    if !wants(Stage::Ast) {
+        return Ok(());
+    }
+
+    let ast = parse(&tokens, diagnostics)?;
+    emit(
+        options,
+        file,
+        Stage::Ast,
+        || ast.dump().to_string(),
+        || ast.dump().to_json(),
+    )?;
+
+    if !wants(Stage::TypedAst) {
+        return Ok(());
+    }
+
+    let typed_ast = analyze(ast, diagnostics)?;
+    emit(
+        options,
+        file,
+        Stage::TypedAst,
+        || typed_ast.dump().to_string(),
+        || typed_ast.dump().to_json(),
+    )?;
+
+    if !wants(Stage::Ir) {
+        return Ok(());
+    }
+
+    let instructions = generate(typed_ast, &options.compile, diagnostics)?;
+    emit(
+        options,
+        file,
+        Stage::Ir,
+        || ir_text(&instructions),
+        || ir_json(&instructions),
+    )?;
+
+    let graph = control_flow(&instructions);
+    emit(
+        options,
+        file,
+        Stage::Cfg,
+        || cfg_text(&graph),
+        || cfg_json(&graph),
+    )?;
+
+    let assembly = assembly(&optimize(instructions, &options.compile));
+    emit(
+        options,
+        file,
+        Stage::Asm,
+        || assembly.clone(),
+        || asm_json(&assembly),
+    )?;
+
+    Ok(())
+}
+
+// -c, the objects are written out as they are
+fn write_objects(
+    options: &Options,
+    reporter: &mut Reporter,
+    objects: &[(&Input, Option<Vec<u8>>)],
+) -> Result<(), String> {
+    for (input, object) in objects.iter() {
+        match object {
+            Some(object) => write_output(&options.unit_output(input, "o"), object)?,
+            None => unused(reporter, input),
+        }
+    }
+
+    match &options.compile.freestanding {
+        Some(memory) => write_file(&options.linker_script(), linker_script(memory).as_bytes()),
+        None => Ok(()),
+    }
+}
+
+// a freestanding program needs nothing of the toolchain, the built-in
+// linker lays it out like the linker script would
+fn link_freestanding(
+    options: &Options,
+    memory: &FreestandingOptions,
+    objects: Objects,
+) -> Result<(), String> {
+    let objects: Vec<(String, Vec<u8>)> = objects
+        .into_iter()
+        .map(|(input, object)| {
+            let object = match (input, object) {
+                (_, Some(object)) => object,
+                (Input::File(path), None) => read_file(path)?,
+                (Input::Stdin, None) => unreachable!("stdin is always compiled as a source"),
+            };
+
+            Ok((input.name(), object))
+        })
+        .collect::<Result<_, String>>()?;
+
+    let executable = link(&objects, memory)?;
+    write_output(&options.output(), &executable)
+}
+
+// programs that need libc are left to the toolchain, and so are
+// freestanding ones with -g, the built-in linker drops debug information. it
+// gets the files in the order they were given, which is the order the linker
+// looks at them in
+fn link_toolchain(
+    options: &Options,
+    memory: Option<&FreestandingOptions>,
+    objects: &[(&Input, Option<Vec<u8>>)],
+) -> Result<(), String> {
+    let temporary = temporary()?;
+
+    let script = match memory {
+        Some(memory) => {
+            let path = temporary.join("link.ld");
+            write_file(&path, linker_script(memory).as_bytes())?;
+            Some(path)
+        }
+        None => None,
+    };
+
+    // numbered, two inputs can have the same stem when they are in different
+    // directories
+    let paths: Vec<PathBuf> = objects
+        .iter()
+        .enumerate()
+        .map(|(index, (input, object))| match (input, object) {
+            (_, Some(object)) => {
+                let path = temporary.join(format!("{index}-{}.o", input.stem()));
+                write_file(&path, object)?;
+                Ok(path)
+            }
+            (Input::File(path), None) => Ok(path.clone()),
+            (Input::Stdin, None) => unreachable!("stdin is always compiled as a source"),
+        })
+        .collect::<Result<_, String>>()?;
+
+    let output = options.output();
+    let executable = match &output {
+        Output::File(path) => path.clone(),
+        Output::Stdout => temporary.join("a.out"),
+    };
+
+    let mut linked = driver::link(options, &paths, script.as_deref(), &executable);
+
+    if linked.is_ok() && output == Output::Stdout {
+        linked = read_file(&executable).and_then(|executable| write_output(&output, &executable));
+    }
+
+    let _ = std::fs::remove_dir_all(&temporary);
+    linked
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        panic!("Usage: {} <filename>", args[0]);
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // known before the command line is parsed, so that its errors are
+    // written in the format too
+    let mut reporter = Reporter {
+        format: driver::diagnostics_format(&args),
+        found: Vec::new(),
+    };
+
+    let ran = match parse_args(args) {
+        Ok(Action::Compile(options)) => run(&options, &mut reporter),
+        Ok(Action::Help) => {
+            println!("{USAGE}\n\n{HELP}");
+            return;
+        }
+        Ok(Action::Version) => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(message) => Err(Failure::Message(message)),
+    };
+
+    if let Err(Failure::Message(message)) = &ran {
+        reporter.driver_diagnostic(Diagnostic::error(message.as_str()));
     }
 
-    let filename = &args[1];
+    reporter.finish();
 
-    let input_file_path = format!("tests/{}.c", filename);
-    let output_file_path = format!("output/{}.s", filename);
+    if ran.is_err() {
+        std::process::exit(1);
+    }
+}
 
-    let mut file = File::open(input_file_path).unwrap();
-    let mut contents = String::new();
-    file.read_to_string(&mut contents).unwrap();
+fn run(options: &Options, reporter: &mut Reporter) -> Result<(), Failure> {
+    for flag in options.ignored.iter() {
+        ignored(reporter, flag);
+    }
+
+    if !options.emit.is_empty() {
+        let mut failed = false;
+
+        for file in options.inputs.iter() {
+            if file.kind() != InputKind::Source {
+                unused(reporter, file);
+                continue;
+            }
+
+            let source = read_input(file)?;
 
-    let input = contents.as_str();
+            let mut diagnostics = Diagnostics::new(options.compile.warnings.clone());
+            let emitted = emit_stages(options, file, &source, &mut diagnostics);
+            reporter.report(Found {
+                filename: file.name(),
+                source,
+                diagnostics,
+            });
+
+            match emitted {
+                Err(Failure::Message(message)) => return Err(Failure::Message(message)),
+                emitted => failed |= emitted.is_err(),
+            }
+        }
+
+        return match failed {
+            true => Err(Failure::Reported),
+            false => Ok(()),
+        };
+    }
+
+    let units = compile_sources(options, reporter)?;
+
+    if options.assembly_only {
+        for (input, instructions) in units.iter() {
+            let assembly = assembly(instructions);
+            write_output(&options.unit_output(input, "s"), assembly.as_bytes())?;
+        }
+
+        for input in options.inputs.iter() {
+            if input.kind() != InputKind::Source {
+                unused(reporter, input);
+            }
+        }
+
+        if let Some(memory) = &options.compile.freestanding {
+            write_file(&options.linker_script(), linker_script(memory).as_bytes())?;
+        }
+
+        return Ok(());
+    }
 
-    let ast = parse_program(input).unwrap();
-    let compiled_output = compile_program(ast);
-    let compiled_output = optimize_program(compiled_output);
+    let objects = assemble_units(options, units)?;
 
-    println!("Compiled Output:");
-    compiled_output.iter().for_each(|x| {
-        println!("{x:?}");
-    });
+    let linked = match &options.compile.freestanding {
+        _ if options.objects_only => write_objects(options, reporter, &objects),
+        Some(memory) if options.debug => link_toolchain(options, Some(memory), &objects),
+        Some(memory) => link_freestanding(options, memory, objects),
+        None => link_toolchain(options, None, &objects),
+    };
 
-    let mut file = File::create(output_file_path).unwrap();
-    compiled_output.iter().for_each(|x| {
-        file.write_all(x.to_string().as_bytes()).unwrap();
-        file.write_all("\n".as_bytes()).unwrap();
-    });
+    Ok(linked?)
 }
@@ -1,21 +1,39 @@
 use std::sync::Arc;
 
-use winnow::{PResult, Parser, Stateful};
+use winnow::{
+    error::{ContextError, ErrMode},
+    stream::Location,
+    Located, PResult, Parser, Stateful,
+};
 
 use crate::{
-    riscv::compile::Compile, types::datatype::{Argument, Datatype}, utils::random_name::unique_identifier,
+    diagnostic::{
+        code,
+        warning::{Warning, WarningOptions},
+        Diagnostic, Diagnostics, ErrorReported,
+    },
+    lexer::Token,
+    types::{
+        attribute::{Attribute, AttributeList},
+        datatype::{Argument, Datatype},
+        program::Program,
+        span::Span,
+    },
 };
 
+mod attribute;
 mod declaration;
 pub mod expression;
 mod function_definition;
 mod identifier;
+mod keyword;
 mod number;
 mod program;
+mod recovery;
 mod scope;
 mod statement;
+mod string;
 mod trivial_tokens;
-mod whitespace;
 
 mod binary_operation;
 
@@ -24,19 +42,24 @@ pub struct ParserVariable {
     name: String,
     pub unique_name: String,
     pub datatype: Datatype,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub struct ParserStaticSymbol {
     pub name: String,
     pub datatype: Datatype,
+    pub defined: bool,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone)]
 pub enum ParserSymbol {
     Variable(ParserVariable),
     Argument(ParserVariable),
-    Function(ParserStaticSymbol),
+    // functions and extern objects, which the linker resolves
+    Static(ParserStaticSymbol),
 }
 
 #[derive(Debug, Clone)]
@@ -44,17 +67,29 @@ pub struct ParserScopeState {
     symbols: Vec<Arc<ParserSymbol>>,
 }
 
+impl Default for ParserScopeState {
+    fn default() -> Self {
+        ParserScopeState::new()
+    }
+}
+
 impl ParserScopeState {
     pub fn new() -> Self {
         ParserScopeState { symbols: vec![] }
     }
 
-    pub fn add_variable(&mut self, name: String, datatype: Datatype) -> ParserSymbol {
-        let unique_name = unique_identifier(Some(name.as_str()), None);
+    pub fn add_variable(
+        &mut self,
+        name: String,
+        unique_name: String,
+        datatype: Datatype,
+        span: Span,
+    ) -> ParserSymbol {
         self.add_argument(ParserSymbol::Variable(ParserVariable {
-            name: name.clone(),
-            unique_name: unique_name.clone(),
-            datatype: datatype.clone(),
+            name,
+            unique_name,
+            datatype,
+            span,
         }))
     }
 
@@ -70,12 +105,10 @@ impl ParserScopeState {
     pub fn get_symbol(&self, variable: &str) -> Option<Arc<ParserSymbol>> {
         self.symbols
             .iter()
-            .find(|v| {
-                return match v.as_ref() {
-                    ParserSymbol::Variable(v) => v.name == variable,
-                    ParserSymbol::Argument(v) => v.name == variable,
-                    ParserSymbol::Function(_) => false,
-                };
+            .find(|v| match v.as_ref() {
+                ParserSymbol::Variable(v) => v.name == variable,
+                ParserSymbol::Argument(v) => v.name == variable,
+                ParserSymbol::Static(_) => false,
             })
             .cloned()
     }
@@ -87,7 +120,7 @@ impl ParserScopeState {
             .filter_map(|s| match s.as_ref() {
                 ParserSymbol::Variable(v) => Some(v.clone()),
                 ParserSymbol::Argument(_) => None,
-                ParserSymbol::Function(_) => None,
+                ParserSymbol::Static(_) => None,
             })
             .collect()
     }
@@ -112,20 +145,93 @@ pub struct ParserState {
     function_scope: ParserScopeState,
     static_symbols: Vec<ParserStaticSymbol>,
     loop_state: Vec<LoopState>,
+    // numbers the variables and loops of the function, so that their names
+    // are unique without depending on anything but the source
+    names: usize,
+    // where each token is, the stream itself only knows token indices
+    spans: Vec<Span>,
+    diagnostics: Diagnostics,
+    // how deep the tree being built is at this point
+    nesting: usize,
 }
 
 impl ParserState {
-    pub fn new() -> Self {
+    pub fn new(tokens: &[Token], options: WarningOptions) -> Self {
         ParserState {
             scope: vec![ParserScopeState::new()],
             function_scope: ParserScopeState::new(),
             loop_state: vec![],
+            names: 0,
             static_symbols: vec![],
+            spans: tokens.iter().map(|token| token.span).collect(),
+            diagnostics: Diagnostics::new(options),
+            nesting: 0,
+        }
+    }
+
+    // from the first to the last token of the range, an empty range is the
+    // point right after the token before it
+    pub fn span(&self, range: std::ops::Range<usize>) -> Span {
+        if range.is_empty() {
+            return self.end_of(range.start);
+        }
+
+        self.spans[range.start].to(&self.spans[range.end - 1])
+    }
+
+    // tokens never span lines, so the end is on the line they start on
+    pub fn end_of(&self, index: usize) -> Span {
+        let Some(previous) = index.checked_sub(1).and_then(|i| self.spans.get(i)) else {
+            return Span {
+                line: 1,
+                column: 1,
+                ..Span::default()
+            };
+        };
+
+        Span {
+            start: previous.end,
+            end: previous.end,
+            line: previous.line,
+            column: previous.column + (previous.end - previous.start),
         }
     }
 
+    // problems that do not stop the parse are reported here, parsing goes
+    // on so that everything wrong with the file is found in one run
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.report(diagnostic);
+    }
+
+    // how many scopes and loops are open, a statement that fails halfway
+    // through can leave some of its own open
+    pub fn depth(&self) -> (usize, usize) {
+        (self.scope.len(), self.loop_state.len())
+    }
+
+    pub fn restore_depth(&mut self, (scopes, loops): (usize, usize)) {
+        self.scope.truncate(scopes);
+        self.loop_state.truncate(loops);
+    }
+
+    fn next_number(&mut self) -> usize {
+        self.names += 1;
+        self.names - 1
+    }
+
+    // every function numbers its names from 0 again
+    pub fn restart_numbering(&mut self) {
+        self.names = 0;
+    }
+
+    // x.3 for the fourth thing named in the function, the dot keeps it apart
+    // from every name the source can use
+    pub fn unique_name(&mut self, name: &str) -> String {
+        format!("{name}.{}", self.next_number())
+    }
+
     pub fn push_loop(&mut self, t: String, l: bool) -> String {
-        let id = unique_identifier(Some(t.as_str()), None);
+        let id = format!("{t}_{}", self.next_number());
         self.loop_state.push(LoopState {
             id: id.clone(),
             is_loop: l,
@@ -150,18 +256,19 @@ impl ParserState {
         self.loop_state.iter().rev().find(|l| !l.is_loop)
     }
 
+    // only the outermost scope is open, outside of any function
+    pub fn at_file_scope(&self) -> bool {
+        self.scope.len() == 1
+    }
+
     pub fn push_scope(&mut self) {
         self.scope.push(ParserScopeState::new());
     }
 
     pub fn push_case_to_switch(&mut self, case: Case) {
-        let loop_state_index = self.loop_state
-            .iter()
-            .rev()
-            .position(|l| !l.is_loop)
-            .unwrap_or_else(|| panic!("Switch not found"));
-        
-        self.loop_state[loop_state_index].cases.push(case);
+        if let Some(switch) = self.loop_state.iter_mut().rev().find(|l| !l.is_loop) {
+            switch.cases.push(case);
+        }
     }
 
     pub fn pop_scope(&mut self) -> ParserScopeState {
@@ -172,8 +279,35 @@ impl ParserState {
         self.scope.last_mut().unwrap()
     }
 
-    pub fn add_variable(&mut self, variable: String, datatype: Datatype) -> ParserSymbol {
-        let variable = self.get_current_scope().add_variable(variable, datatype);
+    pub fn add_variable(
+        &mut self,
+        variable: String,
+        datatype: Datatype,
+        span: Span,
+    ) -> ParserSymbol {
+        // a name from the current scope would be a redeclaration instead
+        let outer = &self.scope[..self.scope.len() - 1];
+        if let Some(previous) = outer.iter().rev().find_map(|s| s.get_symbol(&variable)) {
+            let (kind, previous) = match previous.as_ref() {
+                ParserSymbol::Argument(previous) => ("parameter", previous.span),
+                ParserSymbol::Variable(previous) => ("local", previous.span),
+                ParserSymbol::Static(previous) => ("function", previous.span),
+            };
+
+            self.diagnostics.report(
+                Diagnostic::warning(
+                    Warning::Shadow,
+                    format!("Declaration of '{variable}' shadows a previous {kind}"),
+                )
+                .at(span)
+                .with_secondary(previous, "shadowed declaration is here"),
+            );
+        }
+
+        let unique_name = self.unique_name(&variable);
+        let variable = self
+            .get_current_scope()
+            .add_variable(variable, unique_name, datatype, span);
         self.function_scope.insert_variable(variable.clone());
         variable
     }
@@ -185,16 +319,91 @@ impl ParserState {
     }
 
     pub fn add_static_symbol(&mut self, symbol: ParserStaticSymbol) {
-        self.static_symbols.push(symbol);
+        if let Some(existing) = self
+            .static_symbols
+            .iter_mut()
+            .find(|s| s.name == symbol.name)
+        {
+            if !existing.datatype.is_compatible(&symbol.datatype) {
+                let what = match symbol.datatype {
+                    Datatype::Function { .. } => "function ",
+                    _ => "",
+                };
+                let diagnostic =
+                    Diagnostic::error(format!("Conflicting types for {what}'{}'", symbol.name))
+                        .with_code(code::CONFLICTING_TYPES)
+                        .with_label(symbol.span, format!("declared as '{}'", symbol.datatype))
+                        .with_secondary(
+                            existing.span,
+                            format!("previously declared as '{}'", existing.datatype),
+                        );
+
+                self.diagnostics.report(diagnostic);
+                return;
+            }
+
+            // attributes accumulate over all declarations of the function
+            let mut attributes = existing.attributes.clone();
+            attributes.merge_attributes(&symbol.attributes);
+
+            // a declaration never overrides a definition, but a definition
+            // completes an earlier declaration
+            if symbol.defined || !existing.defined {
+                *existing = symbol;
+            }
+
+            existing.attributes = attributes;
+        } else {
+            self.static_symbols.push(symbol);
+        }
+    }
+
+    pub fn get_static_symbol(&self, name: &str) -> Option<&ParserStaticSymbol> {
+        self.static_symbols.iter().find(|s| s.name == name)
+    }
+
+    pub fn attributes_of(&self, name: &str) -> Vec<Attribute> {
+        self.static_symbols
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.attributes.clone())
+            .unwrap_or_default()
     }
 
-    pub fn start_function_scope(&mut self, name: String, arguments: Vec<Argument>, ret: Datatype) {
+    // a function or an extern object, defined here or in another unit
+    pub fn declare_static(
+        &mut self,
+        name: String,
+        datatype: Datatype,
+        attributes: Vec<Attribute>,
+        span: Span,
+    ) {
+        self.add_static_symbol(ParserStaticSymbol {
+            name,
+            datatype,
+            defined: false,
+            attributes,
+            span,
+        });
+    }
+
+    pub fn start_function_scope(
+        &mut self,
+        name: String,
+        arguments: Vec<Argument>,
+        ret: Datatype,
+        attributes: Vec<Attribute>,
+        span: Span,
+    ) {
         self.add_static_symbol(ParserStaticSymbol {
             name,
             datatype: Datatype::Function {
                 return_type: Arc::new(ret),
                 arguments,
             },
+            defined: true,
+            attributes,
+            span,
         });
         self.function_scope = ParserScopeState::new();
     }
@@ -208,47 +417,83 @@ impl ParserState {
                 self.static_symbols
                     .iter()
                     .find(|s| s.name == symbol)
-                    .map(|s| Arc::new(ParserSymbol::Function(s.clone())).as_ref().clone())
+                    .map(|s| Arc::new(ParserSymbol::Static(s.clone())).as_ref().clone())
                     .map(Arc::new)
             })
             .map(|f| f.as_ref().clone())
     }
+}
 
-    pub fn get_by_unique_name(&self, unique_name: &str) -> Option<ParserSymbol> {
-        self.scope
-            .iter()
-            .rev()
-            .find_map(|s| {
-                s.symbols
-                    .iter()
-                    .find(|v| match v.as_ref() {
-                        ParserSymbol::Variable(v) => v.unique_name == unique_name,
-                        ParserSymbol::Argument(v) => v.unique_name == unique_name,
-                        ParserSymbol::Function(_) => false,
-                    })
-                    .cloned()
-            })
-            .or_else(|| {
-                self.static_symbols
-                    .iter()
-                    .find(|s| s.name == unique_name)
-                    .map(|s| Arc::new(ParserSymbol::Function(s.clone())))
-            })
-            .map(|f| f.as_ref().clone())
+pub type Stream<'is> = Stateful<Located<&'is [Token]>, ParserState>;
+
+// runs a parser and also returns the span of what it consumed
+pub fn spanned<'is, O>(
+    mut parser: impl Parser<Stream<'is>, O, ContextError>,
+) -> impl Parser<Stream<'is>, (O, Span), ContextError> {
+    move |input: &mut Stream<'is>| {
+        let start = input.location();
+        let output = parser.parse_next(input)?;
+        let span = input.state.span(start..input.location());
+
+        Ok((output, span))
+    }
+}
+
+// every level of the tree is a few frames of recursion in each stage that
+// walks it, so trees deeper than this are turned down before one of them
+// runs out of stack. C11 5.2.4.1 asks for 63 levels of parentheses and 127
+// of blocks
+const MAX_NESTING: usize = 256;
+
+// one level deeper into the tree, for the operator chains that are folded
+// in a loop rather than by recursion. the caller restores the nesting
+pub fn deeper(input: &mut Stream) -> PResult<()> {
+    if input.state.nesting < MAX_NESTING {
+        input.state.nesting += 1;
+        return Ok(());
     }
+
+    let location = input.location();
+    let length = usize::from(!input.is_empty());
+    let span = input.state.span(location..location + length);
+    input.state.report(
+        Diagnostic::error(format!(
+            "Nesting deeper than {MAX_NESTING} levels is not supported"
+        ))
+        .with_code(code::UNSUPPORTED)
+        .at(span),
+    );
+
+    Err(ErrMode::Cut(ContextError::new()))
 }
 
-pub type Stream<'is> = Stateful<&'is str, ParserState>;
+// runs a parser one level deeper, the nesting is restored however it ends
+pub fn nested<'is, O>(
+    mut parser: impl Parser<Stream<'is>, O, ContextError>,
+) -> impl Parser<Stream<'is>, O, ContextError> {
+    move |input: &mut Stream<'is>| {
+        let nesting = input.state.nesting;
+        let result = deeper(input).and_then(|()| parser.parse_next(input));
+        input.state.nesting = nesting;
+        result
+    }
+}
 
-pub fn parse_program(input: &str) -> PResult<impl Compile + '_> {
+pub fn parse_program(
+    tokens: &[Token],
+    diagnostics: &mut Diagnostics,
+) -> Result<Program, ErrorReported> {
     let mut stream = Stream {
-        input,
-        state: ParserState::new(),
+        input: Located::new(tokens),
+        state: ParserState::new(tokens, diagnostics.options().clone()),
     };
 
     let ast = program::parse_program.parse_next(&mut stream);
 
-    println!("Generated AST: {:#?}", ast);
+    let state = std::mem::take(&mut stream.state.diagnostics);
+    diagnostics.extend(state);
 
-    ast
+    // syntax errors are recovered from, so the ast is only good when
+    // nothing was reported
+    diagnostics.check(ast.map_err(|_| ErrorReported)?)
 }
@@ -0,0 +1,153 @@
+use winnow::{combinator, stream::Location, PResult, Parser};
+
+use crate::{
+    diagnostic::{code, warning::Warning, Diagnostic},
+    types::attribute::{Attribute, AttributeArgument},
+};
+
+use super::{
+    expression::{fold::Fold, parse_assignment_expression},
+    identifier::{parse_identifier, parse_word},
+    keyword::keyword_of,
+    string::parse_string_literal,
+    trivial_tokens::{parse_close_paren, parse_comma, parse_open_paren},
+    ParserState, Stream,
+};
+
+fn parse_attribute_argument(input: &mut Stream) -> PResult<AttributeArgument> {
+    // a lone identifier is a word like in mode(SI), not a variable
+    combinator::alt((
+        parse_string_literal.map(AttributeArgument::String),
+        combinator::terminated(
+            parse_identifier,
+            combinator::peek(combinator::alt((parse_comma, parse_close_paren))),
+        )
+        .map(|identifier| AttributeArgument::Identifier(identifier.to_string())),
+        parse_assignment_expression.map(AttributeArgument::Expression),
+    ))
+    .parse_next(input)
+}
+
+fn parse_attribute(input: &mut Stream) -> PResult<Attribute> {
+    let start = input.location();
+
+    // __name__ is the same attribute as name
+    let name = parse_word(input)?;
+    let name = name
+        .strip_prefix("__")
+        .and_then(|name| name.strip_suffix("__"))
+        .unwrap_or(name)
+        .to_string();
+
+    let arguments = combinator::opt(combinator::delimited(
+        parse_open_paren,
+        combinator::separated(0.., parse_attribute_argument, parse_comma),
+        parse_close_paren,
+    ))
+    .parse_next(input)?;
+
+    Ok(Attribute {
+        name,
+        arguments: arguments.unwrap_or_default(),
+        span: input.state.span(start..input.location()),
+    })
+}
+
+// __attribute__((a, b(1), c("x")))
+fn parse_attribute_specifier(input: &mut Stream) -> PResult<Vec<Attribute>> {
+    keyword_of(&["__attribute__", "__attribute"]).parse_next(input)?;
+
+    parse_open_paren(input)?;
+    parse_open_paren(input)?;
+
+    // empty entries are allowed, e.g. __attribute__((a,,b))
+    let attributes: Vec<Option<Attribute>> =
+        combinator::separated(0.., combinator::opt(parse_attribute), parse_comma)
+            .parse_next(input)?;
+
+    parse_close_paren(input)?;
+    parse_close_paren(input)?;
+
+    Ok(attributes.into_iter().flatten().collect())
+}
+
+fn invalid(attribute: &Attribute, message: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(message)
+        .with_code(code::INVALID_ATTRIBUTE)
+        .at(attribute.span)
+}
+
+fn validate_attribute(state: &mut ParserState, attribute: &Attribute) {
+    let name = attribute.name.as_str();
+    let arguments = attribute.arguments.as_slice();
+
+    let diagnostic = match name {
+        // checked together with the function it is attached to
+        "interrupt" => None,
+        "section" => attribute.string_argument().is_none().then(|| {
+            invalid(
+                attribute,
+                "The section attribute takes a single string argument",
+            )
+        }),
+        "aligned" => match arguments {
+            [] => None,
+            [AttributeArgument::Expression(alignment)] => match alignment.fold() {
+                Some(value) if value > 0 && (value & (value - 1)) == 0 => None,
+                Some(value) if value > 0 => Some(
+                    invalid(attribute, "Requested alignment is not a power of 2").with_suggestion(
+                        alignment.span,
+                        (value as u32).next_power_of_two().to_string(),
+                        "round it up to the next power of 2",
+                    ),
+                ),
+                Some(_) => Some(invalid(
+                    attribute,
+                    "Requested alignment is not a power of 2",
+                )),
+                None => Some(invalid(
+                    attribute,
+                    "Requested alignment is not an integer constant",
+                )),
+            },
+            _ => Some(invalid(
+                attribute,
+                "The aligned attribute takes at most one argument",
+            )),
+        },
+        "weak" | "used" | "noreturn" | "naked" | "fallthrough" => {
+            (!arguments.is_empty()).then(|| {
+                invalid(
+                    attribute,
+                    format!("The {name} attribute takes no arguments"),
+                )
+            })
+        }
+        _ => Some(
+            Diagnostic::warning(
+                Warning::Attributes,
+                format!("Unknown attribute '{name}' ignored"),
+            )
+            .at(attribute.span),
+        ),
+    };
+
+    if let Some(diagnostic) = diagnostic {
+        state.report(diagnostic);
+    }
+}
+
+pub fn parse_attributes(input: &mut Stream) -> PResult<Vec<Attribute>> {
+    let attributes: Vec<Attribute> = combinator::repeat(0.., parse_attribute_specifier)
+        .fold(Vec::new, |mut acc, attributes| {
+            acc.extend(attributes);
+            acc
+        })
+        .parse_next(input)?;
+
+    attributes
+        .iter()
+        .for_each(|attribute| validate_attribute(&mut input.state, attribute));
+
+    Ok(attributes)
+}
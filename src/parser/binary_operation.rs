@@ -1,74 +1,187 @@
-use winnow::PResult;
-
-use crate::types::expression::Expression;
-
-use super::Stream;
-
-mod level_10;
-mod level_11;
-mod level_12;
-mod level_13;
-mod level_14;
-mod level_15;
-mod level_3;
-mod level_4;
-mod level_5;
-mod level_6;
-mod level_7;
-mod level_8;
-mod level_9;
-
-#[derive(Debug)]
-pub struct HalfBinaryOp {
-    rhs: Expression,
-    op: String,
+use std::sync::Arc;
+
+use winnow::{combinator, stream::Stream as _, PResult, Parser};
+
+use crate::{
+    lexer::TokenKind,
+    types::expression::{BinaryOp, Expression, ExpressionKind, TernaryOp},
+};
+
+use super::{
+    deeper,
+    expression::{parse_expression, parse_factor},
+    nested,
+    recovery::expect,
+    trivial_tokens::{parse_colon, parse_comma, parse_question_mark},
+    Stream,
+};
+
+type Operation = fn(Arc<Expression>, Arc<Expression>) -> BinaryOp;
+
+// how tightly the left-associative binary operators bind, from || at 1 up to
+// the multiplicative operators at 10
+fn binary_operator(punctuator: &str) -> Option<(u8, Operation)> {
+    let operator: (u8, Operation) = match punctuator {
+        "||" => (1, BinaryOp::LogicalOr),
+        "&&" => (2, BinaryOp::LogicalAnd),
+        "|" => (3, BinaryOp::BitwiseOr),
+        "^" => (4, BinaryOp::BitwiseXor),
+        "&" => (5, BinaryOp::BitwiseAnd),
+        "==" => (6, BinaryOp::Equals),
+        "!=" => (6, BinaryOp::NotEquals),
+        "<" => (7, BinaryOp::LessThan),
+        ">" => (7, BinaryOp::GreaterThan),
+        "<=" => (7, BinaryOp::LessThanEquals),
+        ">=" => (7, BinaryOp::GreaterThanEquals),
+        "<<" => (8, BinaryOp::LeftShift),
+        ">>" => (8, BinaryOp::RightShift),
+        "+" => (9, BinaryOp::Addition),
+        "-" => (9, BinaryOp::Subtraction),
+        "*" => (10, BinaryOp::Multiplication),
+        "/" => (10, BinaryOp::Division),
+        "%" => (10, BinaryOp::Modulus),
+        _ => return None,
+    };
+
+    Some(operator)
 }
 
-pub fn parse_binary_operation(input: &mut Stream<'_>) -> PResult<Expression> {
-    level_15::parse_level_15_expression(input)
+fn assignment_operator(punctuator: &str) -> Option<Operation> {
+    let operation: Operation = match punctuator {
+        "=" => BinaryOp::Assignment,
+        "+=" => BinaryOp::AssignmentAddition,
+        "-=" => BinaryOp::AssignmentSubtraction,
+        "*=" => BinaryOp::AssignmentMultiplication,
+        "/=" => BinaryOp::AssignmentDivision,
+        "%=" => BinaryOp::AssignmentModulus,
+        "<<=" => BinaryOp::AssignmentShiftLeft,
+        ">>=" => BinaryOp::AssignmentShiftRight,
+        "&=" => BinaryOp::AssignmentBitwiseAnd,
+        "^=" => BinaryOp::AssignmentBitwiseXor,
+        "|=" => BinaryOp::AssignmentBitwiseOr,
+        _ => return None,
+    };
+
+    Some(operation)
 }
 
-#[macro_export]
-macro_rules! create_term_parser {
-    ($parser_name:tt, $half_parser_name:tt, $operator_parser:expr, $lower_level_term_parser:expr, $operation_creator:expr) => {
-        fn $half_parser_name(
-            input: &mut Stream,
-        ) -> PResult<$crate::parser::binary_operation::HalfBinaryOp> {
-            parse_whitespace(input)?;
-
-            let op = $operator_parser(input)?.to_string();
-            let rhs = $lower_level_term_parser(input)?;
-            Ok($crate::parser::binary_operation::HalfBinaryOp { rhs, op })
-        }
+// the operator the next token is, if it is one the lookup knows about
+fn peek_operator<T>(input: &Stream, lookup: impl Fn(&str) -> Option<T>) -> Option<T> {
+    match input.first().map(|token| &token.kind) {
+        Some(TokenKind::Punctuator(punctuator)) => lookup(punctuator),
+        _ => None,
+    }
+}
+
+fn binary(operation: Operation, lhs: Expression, rhs: Expression) -> Expression {
+    let span = lhs.span.to(&rhs.span);
+
+    Expression::from(ExpressionKind::BinaryOp(operation(
+        Arc::new(lhs),
+        Arc::new(rhs),
+    )))
+    .with_span(span)
+}
+
+// precedence climbing: operators that bind at least as tightly as `minimum`
+// are folded into the left operand, anything looser is left to the caller
+fn parse_binary_expression(input: &mut Stream, minimum: u8) -> PResult<Expression> {
+    let lhs = parse_factor(input)?;
 
-        pub fn $parser_name(input: &mut Stream) -> PResult<Expression> {
-            parse_whitespace(input)?;
-
-            let mut lhs = $lower_level_term_parser(input)?;
-
-            let half_binary_expressions: Vec<$crate::parser::binary_operation::HalfBinaryOp> =
-                winnow::combinator::repeat_till(
-                    0..,
-                    $half_parser_name,
-                    winnow::combinator::not($operator_parser),
-                )
-                .map(|v| v.0)
-                .parse_next(input)?;
-
-            for half_binary_expression in half_binary_expressions {
-                let op =
-                    $operation_creator(lhs, half_binary_expression.rhs, half_binary_expression.op);
-
-                if let Some(op) = op {
-                    lhs = Expression::BinaryOp(op);
-                } else {
-                    return Err(winnow::error::ErrMode::Backtrack(
-                        winnow::error::ContextError::new(),
-                    ));
-                }
-            }
-
-            Ok(lhs)
+    // the left operand gets a level deeper with every operator folded in
+    let nesting = input.state.nesting;
+    let expression = parse_binary_operators(input, minimum, lhs);
+    input.state.nesting = nesting;
+    expression
+}
+
+fn parse_binary_operators(
+    input: &mut Stream,
+    minimum: u8,
+    mut lhs: Expression,
+) -> PResult<Expression> {
+    while let Some((precedence, operation)) = peek_operator(input, binary_operator) {
+        if precedence < minimum {
+            break;
         }
+
+        let _ = input.next_slice(1);
+        deeper(input)?;
+
+        let rhs = expect(
+            |input: &mut Stream| parse_binary_expression(input, precedence + 1),
+            "an expression",
+        )
+        .parse_next(input)?;
+
+        lhs = binary(operation, lhs, rhs);
+    }
+
+    Ok(lhs)
+}
+
+fn parse_conditional_expression(input: &mut Stream) -> PResult<Expression> {
+    let condition = parse_binary_expression(input, 1)?;
+
+    if combinator::opt(parse_question_mark)
+        .parse_next(input)?
+        .is_none()
+    {
+        return Ok(condition);
+    }
+
+    let then_expr = expect(parse_expression, "an expression").parse_next(input)?;
+
+    expect(parse_colon, "`:` in conditional expression").parse_next(input)?;
+
+    let else_expr =
+        expect(nested(parse_conditional_expression), "an expression").parse_next(input)?;
+    let span = condition.span.to(&else_expr.span);
+
+    Ok(Expression::from(ExpressionKind::TernaryOp(TernaryOp {
+        condition: Arc::new(condition),
+        then_expr: Arc::new(then_expr),
+        else_expr: Arc::new(else_expr),
+    }))
+    .with_span(span))
+}
+
+// everything except the comma operator, used wherever a comma already
+// has a meaning of its own (initializers, function arguments). assignments
+// are right-associative, so the right side is another assignment
+pub fn parse_assignment_operation(input: &mut Stream<'_>) -> PResult<Expression> {
+    nested(parse_assignment).parse_next(input)
+}
+
+fn parse_assignment(input: &mut Stream<'_>) -> PResult<Expression> {
+    let lhs = parse_conditional_expression(input)?;
+
+    let Some(operation) = peek_operator(input, assignment_operator) else {
+        return Ok(lhs);
     };
+
+    let _ = input.next_slice(1);
+
+    let rhs = expect(parse_assignment_operation, "an expression").parse_next(input)?;
+
+    Ok(binary(operation, lhs, rhs))
+}
+
+pub fn parse_binary_operation(input: &mut Stream<'_>) -> PResult<Expression> {
+    let lhs = parse_assignment_operation(input)?;
+
+    let nesting = input.state.nesting;
+    let expression = parse_comma_operators(input, lhs);
+    input.state.nesting = nesting;
+    expression
+}
+
+fn parse_comma_operators(input: &mut Stream<'_>, mut lhs: Expression) -> PResult<Expression> {
+    while combinator::opt(parse_comma).parse_next(input)?.is_some() {
+        deeper(input)?;
+        let rhs = expect(parse_assignment_operation, "an expression").parse_next(input)?;
+        lhs = binary(BinaryOp::Comma, lhs, rhs);
+    }
+
+    Ok(lhs)
 }
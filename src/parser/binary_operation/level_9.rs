@@ -1,30 +0,0 @@
-use std::sync::Arc;
-
-use winnow::{PResult, Parser};
-
-use crate::{
-    create_term_parser, parser::{trivial_tokens::parse_caret, whitespace::parse_whitespace, Stream}, types::expression::{BinaryOp, Expression}
-};
-
-use super::level_8::parse_level_8_expression;
-
-pub fn parse_level_9_binary_operator<'s>(input: &mut Stream<'s>) -> PResult<&'s str> {
-    parse_whitespace(input)?;
-
-    parse_caret(input)
-}
-
-pub fn level_9_operation_creator(lhs: Expression, rhs: Expression, op: String) -> Option<BinaryOp> {
-    match op.as_str() {
-        "^" => Some(BinaryOp::BitwiseXor(Arc::new(lhs), Arc::new(rhs))),
-        _ => None,
-    }
-}
-
-create_term_parser!(
-    parse_level_9_expression,
-    parse_level_9_operation,
-    parse_level_9_binary_operator,
-    parse_level_8_expression,
-    level_9_operation_creator
-);
@@ -1,46 +1,98 @@
 use std::sync::Arc;
 
-use winnow::{combinator, PResult, Parser};
+use winnow::{combinator, stream::Location, PResult, Parser};
 
 use crate::{
+    diagnostic::{code, warning::Warning, Diagnostic},
+    lexer::TokenKind,
     parser::ParserSymbol,
     types::{
-        datatype::{Argument, Datatype},
-        declaration::{Declaration, Declarator},
+        attribute::{Attribute, AttributeList},
+        datatype::{Argument, Datatype, IntegerType},
+        declaration::{Declaration, Declarator, InitDeclarator},
         expression::Expression,
+        span::Span,
     },
 };
 
 use super::{
-    expression::parse_expression,
+    attribute::parse_attributes,
+    expression::{parse_assignment_expression, parse_expression},
+    recovery::expect,
+    spanned,
     trivial_tokens::{
-        parse_close_bracket, parse_close_paren, parse_equals, parse_open_bracket, parse_open_paren,
-        parse_star, parse_void,
+        parse_close_bracket, parse_close_paren, parse_comma, parse_equals, parse_open_bracket,
+        parse_open_paren, parse_star,
     },
-    whitespace::parse_whitespace,
     Stream,
 };
-use crate::parser::identifier::parse_identifier;
+use crate::parser::{
+    identifier::parse_declared_identifier,
+    keyword::{keyword, keyword_of},
+};
+
+const TYPE_SPECIFIERS: &[&str] = &["int", "long", "signed", "unsigned"];
+
+fn parse_type_specifier<'s>(input: &mut Stream<'s>) -> PResult<&'s str> {
+    keyword_of(TYPE_SPECIFIERS).parse_next(input)
+}
+
+// whether a declaration starts here, looking past the attributes that can
+// come before its type
+pub fn at_declaration(input: &Stream) -> bool {
+    let mut depth = 0usize;
+    let mut after_attribute = false;
+
+    for token in input.iter() {
+        match (&token.kind, depth) {
+            (TokenKind::Punctuator("("), _) if depth > 0 || after_attribute => depth += 1,
+            (TokenKind::Punctuator(")"), 1..) => depth -= 1,
+            (_, 1..) => {}
+            (TokenKind::Keyword("__attribute__" | "__attribute"), 0) => after_attribute = true,
+            (TokenKind::Keyword(keyword), 0) => {
+                return *keyword == "extern" || TYPE_SPECIFIERS.contains(keyword)
+            }
+            _ => return false,
+        }
+    }
+
+    false
+}
 
 pub fn parse_primitive_datatype(input: &mut Stream) -> PResult<Datatype> {
-    parse_whitespace(input)?;
+    // specifiers can come in any order, e.g. long unsigned int long
+    let specifiers: Vec<_> = combinator::repeat(1.., parse_type_specifier).parse_next(input)?;
+    let count = |name: &str| specifiers.iter().filter(|s| **s == name).count();
 
-    let datatype = parse_identifier(input)?;
+    let unsigned = count("unsigned") == 1;
 
-    match datatype {
-        "int" => Ok(Datatype::Int),
-        _ => Err(winnow::error::ErrMode::Backtrack(
+    if count("unsigned") + count("signed") > 1 || count("int") > 1 {
+        return Err(winnow::error::ErrMode::Backtrack(
             winnow::error::ContextError::new(),
-        )),
+        ));
     }
+
+    let integer = match (unsigned, count("long")) {
+        (false, 0) => IntegerType::Int,
+        (true, 0) => IntegerType::UnsignedInt,
+        (false, 1) => IntegerType::Long,
+        (true, 1) => IntegerType::UnsignedLong,
+        (false, 2) => IntegerType::LongLong,
+        (true, 2) => IntegerType::UnsignedLongLong,
+        _ => {
+            return Err(winnow::error::ErrMode::Backtrack(
+                winnow::error::ContextError::new(),
+            ))
+        }
+    };
+
+    Ok(Datatype::Integer(integer))
 }
 
 fn parse_declaration_value(input: &mut Stream) -> PResult<Expression> {
-    parse_whitespace(input)?;
-
     parse_equals(input)?;
 
-    parse_expression(input)
+    expect(parse_assignment_expression, "an initializer").parse_next(input)
 }
 
 #[derive(Debug, Clone)]
@@ -52,7 +104,7 @@ struct Param {
 #[derive(Debug, Clone)]
 enum InnerDeclarator {
     Abstract,
-    Identifier(String),
+    Identifier(String, Span),
     Pointer(Arc<InnerDeclarator>),
     Function {
         params: Vec<Param>,
@@ -64,247 +116,105 @@ enum InnerDeclarator {
     },
 }
 
-fn parse_paren_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
+// whether the `(` at the start of the input opens a parameter list instead
+// of a parenthesized declarator, like the second one in int (*)(int)
+fn at_parameter_list(input: &Stream) -> bool {
+    match input.get(1).map(|token| &token.kind) {
+        Some(TokenKind::Punctuator(")")) => true,
+        Some(TokenKind::Keyword(keyword)) => {
+            *keyword == "void" || TYPE_SPECIFIERS.contains(keyword)
+        }
+        _ => false,
+    }
+}
 
+fn parse_paren_declarator(input: &mut Stream, abstract_allowed: bool) -> PResult<InnerDeclarator> {
     parse_open_paren(input)?;
 
-    let declarator = parse_inner_declarator(input)?;
+    let declarator = parse_inner_declarator(input, abstract_allowed)?;
 
-    if let InnerDeclarator::Abstract = declarator {
-        return Err(winnow::error::ErrMode::Backtrack(
-            winnow::error::ContextError::new(),
-        ));
-    }
-
-    parse_close_paren(input)?;
+    expect(parse_close_paren, "`)`").parse_next(input)?;
 
     Ok(declarator)
 }
 
-fn parse_simple_declarator<'s>(input: &mut Stream<'s>) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    combinator::alt((
-        parse_identifier.map(|i| InnerDeclarator::Identifier(i.to_string())),
-        parse_paren_declarator,
-    ))
-    .parse_next(input)
-}
-
 fn parse_param(input: &mut Stream) -> PResult<Param> {
-    parse_whitespace(input)?;
-
     combinator::seq!(Param {
         datatype: parse_primitive_datatype,
-        declarator: combinator::alt((parse_inner_declarator, parse_inner_abstract_declarator)),
+        declarator: |input: &mut Stream| parse_inner_declarator(input, true),
     })
     .parse_next(input)
 }
 
 fn parse_param_list(input: &mut Stream) -> PResult<Vec<Param>> {
-    parse_whitespace(input)?;
-
     parse_open_paren(input)?;
 
     combinator::alt((
-        combinator::repeat_till(0.., parse_param, parse_close_paren).map(|v| v.0),
-        combinator::terminated(parse_void, parse_close_paren).map(|_| Vec::new()),
+        combinator::terminated(keyword("void"), parse_close_paren).map(|_| Vec::new()),
+        combinator::terminated(
+            combinator::separated(0.., parse_param, parse_comma),
+            parse_close_paren,
+        ),
     ))
     .parse_next(input)
 }
 
-fn parse_function_declarator<'s>(input: &mut Stream<'s>) -> PResult<InnerDeclarator> {
-    let declarator = parse_simple_declarator(input)?;
-
-    if let InnerDeclarator::Abstract = declarator {
-        return Err(winnow::error::ErrMode::Backtrack(
-            winnow::error::ContextError::new(),
-        ));
-    }
-
-    let params = parse_param_list(input)?;
-
-    Ok(InnerDeclarator::Function {
-        declarator: Arc::new(declarator),
-        params,
-    })
-}
-
 fn parse_array_size(input: &mut Stream) -> PResult<Expression> {
-    parse_whitespace(input)?;
-
     parse_open_bracket(input)?;
 
-    let size = parse_expression(input)?;
+    let size = expect(parse_expression, "an array size").parse_next(input)?;
 
-    parse_close_bracket(input)?;
+    expect(parse_close_bracket, "`]`").parse_next(input)?;
 
     Ok(size)
 }
 
-fn parse_array_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    let declarator = parse_simple_declarator(input)?;
-
-    if let InnerDeclarator::Abstract = declarator {
-        return Err(winnow::error::ErrMode::Backtrack(
-            winnow::error::ContextError::new(),
-        ));
+// the name can only be left out where `abstract_allowed` says so, as in the
+// parameters of a declaration like int (*f)(int, long *)
+fn parse_inner_declarator(input: &mut Stream, abstract_allowed: bool) -> PResult<InnerDeclarator> {
+    if combinator::opt(parse_star).parse_next(input)?.is_some() {
+        return parse_inner_declarator(input, abstract_allowed)
+            .map(|declarator| InnerDeclarator::Pointer(Arc::new(declarator)));
     }
 
-    let size = parse_array_size(input)?;
-
-    Ok(InnerDeclarator::Array {
-        declarator: Arc::new(declarator),
-        length: size,
-    })
-}
-
-fn parse_direct_declarator<'s>(input: &mut Stream<'s>) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    combinator::alt((
-        parse_function_declarator,
-        parse_array_declarator,
-        parse_simple_declarator,
-    ))
-    .parse_next(input)
-}
-
-fn parse_pointer_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    parse_star(input)?;
-
-    parse_inner_declarator
-        .map(Arc::new)
-        .map(InnerDeclarator::Pointer)
-        .parse_next(input)
-}
-
-fn parse_abstract_paren_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    parse_open_paren(input)?;
-
-    let inner_declarator = parse_inner_abstract_declarator(input)?;
-
-    parse_close_paren(input)?;
-
-    Ok(inner_declarator)
-}
-
-fn parse_abstract_length(input: &mut Stream) -> PResult<Expression> {
-    parse_whitespace(input)?;
-
-    parse_open_bracket(input)?;
-
-    let length = parse_expression(input)?;
-
-    parse_close_bracket(input)?;
-
-    Ok(length)
-}
-
-fn parse_abstract_array_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    let inner_declarator = parse_abstract_paren_declarator(input);
-
-    let lengths: Vec<_> = combinator::repeat(0.., parse_abstract_length).parse_next(input)?;
-
-    let mut inner_declarator = if let Ok(inner_declarator) = inner_declarator {
-        inner_declarator
-    } else {
-        InnerDeclarator::Abstract
+    let mut declarator = match input.first().map(|token| &token.kind) {
+        Some(TokenKind::Punctuator("(")) if !at_parameter_list(input) => {
+            parse_paren_declarator(input, abstract_allowed)?
+        }
+        Some(TokenKind::Identifier(_) | TokenKind::Keyword(_)) => {
+            let (name, span) = spanned(parse_declared_identifier).parse_next(input)?;
+            InnerDeclarator::Identifier(name.to_string(), span)
+        }
+        _ if abstract_allowed => InnerDeclarator::Abstract,
+        _ => {
+            return Err(winnow::error::ErrMode::Backtrack(
+                winnow::error::ContextError::new(),
+            ))
+        }
     };
 
-    for length in lengths {
-        inner_declarator = InnerDeclarator::Array {
-            declarator: Arc::new(inner_declarator),
-            length,
+    // suffixes bind left to right, so a[2][3] is an array of 2 arrays of 3
+    loop {
+        declarator = match input.first().map(|token| &token.kind) {
+            Some(TokenKind::Punctuator("(")) => InnerDeclarator::Function {
+                params: parse_param_list(input)?,
+                declarator: Arc::new(declarator),
+            },
+            Some(TokenKind::Punctuator("[")) => InnerDeclarator::Array {
+                length: parse_array_size(input)?,
+                declarator: Arc::new(declarator),
+            },
+            _ => return Ok(declarator),
         };
     }
-
-    Ok(inner_declarator)
-}
-
-fn parse_abstract_direct_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    combinator::alt((
-        parse_abstract_paren_declarator,
-        parse_abstract_array_declarator,
-    ))
-    .parse_next(input)
-}
-
-fn parse_abstract_pointer_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    parse_star(input)?;
-
-    let inner_declarator = combinator::opt(parse_inner_abstract_declarator).parse_next(input)?;
-
-    if let Some(inner_declarator) = inner_declarator {
-        Ok(InnerDeclarator::Pointer(Arc::new(inner_declarator)))
-    } else {
-        Ok(InnerDeclarator::Pointer(Arc::new(
-            InnerDeclarator::Abstract,
-        )))
-    }
-}
-
-fn parse_abstract_any_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    Ok(InnerDeclarator::Abstract)
 }
 
-fn parse_inner_abstract_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    println!("input: {:?}", input.input);
+pub fn parse_declarator(input: &mut Stream, base_type: Datatype) -> PResult<Declarator> {
+    let declarator = parse_inner_declarator(input, false)?;
 
-    combinator::alt((
-        parse_abstract_direct_declarator,
-        parse_abstract_pointer_declarator,
-        parse_abstract_any_declarator,
+    process_declarator(declarator, base_type, false).ok_or(winnow::error::ErrMode::Backtrack(
+        winnow::error::ContextError::new(),
     ))
-    .parse_next(input)
-}
-
-fn parse_inner_declarator(input: &mut Stream) -> PResult<InnerDeclarator> {
-    parse_whitespace(input)?;
-
-    let declarator =
-        combinator::alt((parse_pointer_declarator, parse_direct_declarator)).parse_next(input)?;
-
-    if let InnerDeclarator::Abstract = declarator {
-        return Err(winnow::error::ErrMode::Backtrack(
-            winnow::error::ContextError::new(),
-        ));
-    }
-
-    Ok(declarator)
-}
-
-pub fn parse_declarator<'s>(input: &mut Stream<'s>, base_type: Datatype) -> PResult<Declarator> {
-    parse_whitespace(input)?;
-
-    let declarator = parse_inner_declarator(input)?;
-
-    println!("Inner declarator: {:?}", declarator);
-
-    let declarator = process_declarator(declarator, base_type.clone(), false);
-
-    if let Some(declarator) = declarator {
-        Ok(declarator)
-    } else {
-        Err(winnow::error::ErrMode::Backtrack(
-            winnow::error::ContextError::new(),
-        ))
-    }
 }
 
 fn process_declarator(
@@ -313,9 +223,11 @@ fn process_declarator(
     abstract_allowed: bool,
 ) -> Option<Declarator> {
     match declarator {
-        InnerDeclarator::Identifier(name) => Some(Declarator {
+        InnerDeclarator::Identifier(name, span) => Some(Declarator {
             name,
             datatype: base_type,
+            span,
+            parameters: None,
         }),
         InnerDeclarator::Pointer(d) => process_declarator(
             d.as_ref().clone(),
@@ -342,12 +254,20 @@ fn process_declarator(
                     .iter()
                     .map(|p| Argument {
                         name: p.name.clone(),
-                        datatype: Arc::new(p.datatype.clone()),
+                        // parameters of array and function type are adjusted to pointers
+                        datatype: Arc::new(p.datatype.decay()),
                     })
                     .collect(),
             };
 
-            process_declarator(declarator.as_ref().clone(), derived_type, abstract_allowed)
+            let mut declarator =
+                process_declarator(declarator.as_ref().clone(), derived_type, abstract_allowed)?;
+
+            // the innermost parameter list is the one of the declared function,
+            // int (*f(int a))(int b) declares f with the parameter a
+            declarator.parameters.get_or_insert(params);
+
+            Some(declarator)
         }
         InnerDeclarator::Array { length, declarator } => process_declarator(
             declarator.as_ref().clone(),
@@ -358,11 +278,12 @@ fn process_declarator(
             abstract_allowed,
         ),
         InnerDeclarator::Abstract => {
-            println!("Abstract: {:?}", abstract_allowed);
             if abstract_allowed {
                 Some(Declarator {
                     name: String::new(),
                     datatype: base_type,
+                    span: Span::default(),
+                    parameters: None,
                 })
             } else {
                 None
@@ -371,18 +292,109 @@ fn process_declarator(
     }
 }
 
-pub fn parse_declaration(input: &mut Stream) -> PResult<Declaration> {
-    parse_whitespace(input)?;
+// the part of a declaration before its declarators, which applies to every
+// one of them
+#[derive(Debug, Clone)]
+pub struct Specifiers {
+    pub datatype: Datatype,
+    pub attributes: Vec<Attribute>,
+    pub external: bool,
+}
+
+pub fn parse_declaration_specifiers(input: &mut Stream) -> PResult<Specifiers> {
+    let mut attributes = parse_attributes(input)?;
+
+    let external = combinator::opt(keyword("extern"))
+        .parse_next(input)?
+        .is_some();
 
-    let base_type = parse_primitive_datatype(input)?;
+    attributes.merge_attributes(&parse_attributes(input)?);
 
-    let declarator = parse_declarator(input, base_type)?;
+    let datatype = parse_primitive_datatype(input)?;
 
-    println!("Variable: {:?}", declarator);
+    // the storage class can come after the type too, int extern x;
+    let external = external
+        || combinator::opt(keyword("extern"))
+            .parse_next(input)?
+            .is_some();
 
-    let variable = input
-        .state
-        .add_variable(declarator.name.clone(), declarator.datatype.clone());
+    attributes.merge_attributes(&parse_attributes(input)?);
+
+    Ok(Specifiers {
+        datatype,
+        attributes,
+        external,
+    })
+}
+
+// the rest of an init declarator, once its declarator has been parsed
+pub fn finish_init_declarator(
+    input: &mut Stream,
+    start: usize,
+    declarator: Declarator,
+    specifiers: &Specifiers,
+) -> PResult<InitDeclarator> {
+    let mut attributes = specifiers.attributes.clone();
+
+    // int f(void) __attribute__((noreturn)), g(void);
+    attributes.merge_attributes(&parse_attributes(input)?);
+
+    let is_function = matches!(declarator.datatype, Datatype::Function { .. });
+
+    // functions and file-scope objects are symbols for the linker. the
+    // storage of an object is here unless it is extern without an
+    // initializer
+    if is_function || input.state.at_file_scope() {
+        input.state.declare_static(
+            declarator.name.clone(),
+            declarator.datatype.clone(),
+            attributes,
+            declarator.span,
+        );
+
+        // attributes given on earlier declarations apply to this one too
+        let attributes = input.state.attributes_of(&declarator.name);
+
+        let value = match is_function {
+            true => None,
+            false => combinator::opt(parse_declaration_value).parse_next(input)?,
+        };
+
+        return Ok(InitDeclarator {
+            datatype: declarator.datatype,
+            name: declarator.name,
+            external: specifiers.external && value.is_none(),
+            value,
+            attributes,
+            span: input.state.span(start..input.location()),
+        });
+    }
+
+    if specifiers.external {
+        input.state.report(
+            Diagnostic::error("Block-scope extern declarations of objects are not supported")
+                .with_code(code::UNSUPPORTED)
+                .at(declarator.span)
+                .with_note("declare the object extern at file scope instead"),
+        );
+    }
+
+    // none of them apply to objects on the stack
+    for attribute in attributes.iter() {
+        input.state.report(
+            Diagnostic::warning(
+                Warning::Attributes,
+                format!("Attribute '{}' on local variable ignored", attribute.name),
+            )
+            .at(attribute.span),
+        );
+    }
+
+    let variable = input.state.add_variable(
+        declarator.name.clone(),
+        declarator.datatype.clone(),
+        declarator.span,
+    );
 
     let value = combinator::opt(parse_declaration_value).parse_next(input)?;
 
@@ -391,12 +403,60 @@ pub fn parse_declaration(input: &mut Stream) -> PResult<Declaration> {
 
         // these two should be unreachable, but they are here just in case
         ParserSymbol::Argument(var) => var.unique_name,
-        ParserSymbol::Function(fun) => fun.name,
+        ParserSymbol::Static(fun) => fun.name,
     };
 
-    Ok(Declaration {
-        datatype: declarator.datatype.clone(),
+    Ok(InitDeclarator {
+        datatype: declarator.datatype,
         name,
         value,
+        attributes,
+        external: false,
+        span: input.state.span(start..input.location()),
     })
 }
+
+fn parse_init_declarator(input: &mut Stream, specifiers: &Specifiers) -> PResult<InitDeclarator> {
+    let start = input.location();
+
+    let declarator = parse_declarator(input, specifiers.datatype.clone())?;
+
+    finish_init_declarator(input, start, declarator, specifiers)
+}
+
+// the declarators after the first one, which the caller already parsed
+pub fn finish_declaration(
+    input: &mut Stream,
+    start: usize,
+    specifiers: Specifiers,
+    first: InitDeclarator,
+) -> PResult<Declaration> {
+    let mut declarators = vec![first];
+
+    while combinator::opt(parse_comma).parse_next(input)?.is_some() {
+        let declarator = expect(
+            |input: &mut Stream| parse_init_declarator(input, &specifiers),
+            "a declarator",
+        )
+        .parse_next(input)?;
+
+        declarators.push(declarator);
+    }
+
+    Ok(Declaration {
+        base_type: specifiers.datatype,
+        declarators,
+        attributes: specifiers.attributes,
+        span: input.state.span(start..input.location()),
+    })
+}
+
+pub fn parse_declaration(input: &mut Stream) -> PResult<Declaration> {
+    let start = input.location();
+
+    let specifiers = parse_declaration_specifiers(input)?;
+
+    let first = parse_init_declarator(input, &specifiers)?;
+
+    finish_declaration(input, start, specifiers, first)
+}
@@ -1,150 +1,295 @@
 use std::sync::Arc;
 
-use datatypes::GetType;
-use winnow::{combinator, PResult, Parser};
+use winnow::{
+    combinator,
+    stream::{Location, Stream as _},
+    PResult, Parser,
+};
 
 use crate::{
+    diagnostic::{code, Diagnostic},
+    lexer::TokenKind,
     parser::{identifier::parse_identifier, trivial_tokens::parse_comma},
+    riscv::values::Csr,
     types::{
         datatype::Datatype,
-        expression::{Call, Expression, UnaryOp},
+        expression::{
+            Builtin, BuiltinCall, Call, Expression, ExpressionKind, Member, Subscript, UnaryOp,
+        },
     },
 };
 
 use super::{
-    binary_operation::parse_binary_operation,
+    binary_operation::{parse_assignment_operation, parse_binary_operation},
+    deeper, nested,
     number::parse_number,
+    recovery::expect,
+    spanned,
     trivial_tokens::{
-        parse_bang, parse_close_paren, parse_double_minus, parse_double_plus, parse_minus,
-        parse_open_paren, parse_plus, parse_pointer_ampersand, parse_star, parse_tilda,
+        parse_ampersand, parse_arrow, parse_bang, parse_close_bracket, parse_close_paren,
+        parse_dot, parse_double_minus, parse_double_plus, parse_minus, parse_open_bracket,
+        parse_open_paren, parse_plus, parse_star, parse_tilda,
     },
-    whitespace::parse_whitespace,
     ParserSymbol, Stream,
 };
 
-pub mod datatypes;
 pub mod fold;
 
 pub fn parse_expression(input: &mut Stream) -> PResult<Expression> {
-    parse_whitespace(input)?;
-
-    let expression = parse_binary_operation.parse_next(input);
+    parse_binary_operation.parse_next(input)
+}
 
-    if let Ok(ref expression) = expression {
-        expression.get_type(&input.state);
-    }
+pub fn parse_assignment_expression(input: &mut Stream) -> PResult<Expression> {
+    parse_assignment_operation.parse_next(input)
+}
 
-    expression
+pub fn parse_postfix_operator<'s>(input: &mut Stream<'s>) -> PResult<&'s str> {
+    combinator::alt((
+        parse_double_plus,
+        parse_double_minus,
+        parse_arrow,
+        parse_dot,
+        parse_open_paren,
+        parse_open_bracket,
+    ))
+    .parse_next(input)
 }
 
-pub fn parse_postfix_operator<'s>(input: &'s mut Stream) -> PResult<&'s str> {
-    parse_whitespace(input)?;
+fn parse_call_arguments(input: &mut Stream<'_>) -> PResult<Vec<Expression>> {
+    let arguments =
+        combinator::separated(0.., parse_assignment_expression, parse_comma).parse_next(input)?;
 
-    combinator::alt((parse_double_plus, parse_double_minus, parse_open_paren)).parse_next(input)
+    expect(parse_close_paren, "`)` after call arguments").parse_next(input)?;
+
+    Ok(arguments)
+}
+
+// the next token is enough to tell what kind of primary expression follows
+fn parse_primary_expression(input: &mut Stream<'_>) -> PResult<Expression> {
+    match input.first().map(|token| &token.kind) {
+        Some(TokenKind::Identifier(name)) if Builtin::is_builtin(name) => {
+            parse_builtin_expression(input)
+        }
+        Some(TokenKind::Identifier(_)) => parse_variable_expression(input),
+        Some(TokenKind::Number(_)) => parse_number_expression(input),
+        _ => parse_paren_expression(input),
+    }
 }
 
 pub fn parse_term(input: &mut Stream<'_>) -> PResult<Expression> {
-    parse_whitespace(input)?;
+    let start = input.location();
 
-    let expression = combinator::alt((
-        parse_variable_expression,
-        parse_number_expression,
-        parse_paren_expression,
-    ))
-    .parse_next(input)?;
+    let expression = spanned(parse_primary_expression)
+        .map(|(expression, span)| expression.with_span(span))
+        .parse_next(input)?;
 
-    let postfix_operator = parse_postfix_operator(input);
+    // each postfix operator is a level deeper, until the whole term is done
+    let nesting = input.state.nesting;
+    let expression = parse_postfix_operations(input, start, expression);
+    input.state.nesting = nesting;
+    expression
+}
 
-    println!("postfix operator {postfix_operator:?}");
+// postfix operators all share the same precedence and are left-associative,
+// so keep applying them to the expression built so far
+fn parse_postfix_operations(
+    input: &mut Stream<'_>,
+    start: usize,
+    mut expression: Expression,
+) -> PResult<Expression> {
+    loop {
+        let checkpoint = input.checkpoint();
+
+        let postfix = match parse_postfix_operator(input) {
+            Ok(postfix) => postfix,
+            Err(_) => {
+                input.reset(&checkpoint);
+                break;
+            }
+        };
+        deeper(input)?;
 
-    if let Ok(postfix) = postfix_operator {
-        return match postfix {
-            "++" => Ok(Expression::UnaryOp(UnaryOp::PostfixIncrement(Arc::new(
-                expression,
-            )))),
-            "--" => Ok(Expression::UnaryOp(UnaryOp::PostfixDecrement(Arc::new(
-                expression,
-            )))),
+        let postfix_expression: Expression = match postfix {
+            "++" => ExpressionKind::UnaryOp(UnaryOp::PostfixIncrement(Arc::new(expression))).into(),
+            "--" => ExpressionKind::UnaryOp(UnaryOp::PostfixDecrement(Arc::new(expression))).into(),
             "(" => {
-                let arguments =
-                    combinator::separated(0.., parse_expression, parse_comma).parse_next(input)?;
-
-                parse_close_paren(input)?;
+                let arguments = parse_call_arguments(input)?;
 
-                Ok(Expression::Call(Call {
+                ExpressionKind::Call(Call {
                     expression: Arc::new(expression),
                     arguments,
-                }))
+                })
+                .into()
+            }
+            "[" => {
+                let index = expect(parse_expression, "an expression").parse_next(input)?;
+
+                expect(parse_close_bracket, "`]`").parse_next(input)?;
+
+                ExpressionKind::Subscript(Subscript {
+                    expression: Arc::new(expression),
+                    index: Arc::new(index),
+                })
+                .into()
+            }
+            "." | "->" => {
+                let member = expect(parse_identifier, "a member name").parse_next(input)?;
+
+                ExpressionKind::Member(Member {
+                    expression: Arc::new(expression),
+                    member: member.to_string(),
+                    dereference: postfix == "->",
+                })
+                .into()
+            }
+            _ => {
+                return Err(winnow::error::ErrMode::Backtrack(
+                    winnow::error::ContextError::new(),
+                ))
             }
-            _ => Err(winnow::error::ErrMode::Backtrack(
-                winnow::error::ContextError::new(),
-            )),
         };
+
+        expression = postfix_expression.with_span(input.state.span(start..input.location()));
     }
 
     Ok(expression)
 }
 
 pub fn parse_factor(input: &mut Stream<'_>) -> PResult<Expression> {
-    parse_whitespace(input)?;
-
-    combinator::alt((parse_term, parse_unary_expression)).parse_next(input)
+    match input.first().map(|token| &token.kind) {
+        Some(TokenKind::Punctuator("&" | "*" | "+" | "-" | "~" | "!" | "++" | "--")) => {
+            parse_unary_expression(input)
+        }
+        _ => parse_term(input),
+    }
 }
 
 pub fn parse_variable_expression(input: &mut Stream<'_>) -> PResult<Expression> {
-    parse_whitespace(input)?;
-
-    let identifier = parse_identifier(input)?;
+    let (identifier, span) = spanned(parse_identifier).parse_next(input)?;
     let identifier = identifier.to_string();
 
     let symbol = input.state.get_symbol(&identifier);
 
     if let Some(symbol) = symbol {
         match symbol {
-            ParserSymbol::Function(function) => {
-                Ok(Expression::FunctionSymbol(function.name.clone()))
-            }
+            ParserSymbol::Static(symbol) => match symbol.datatype {
+                Datatype::Function { .. } => {
+                    Ok(ExpressionKind::FunctionSymbol(symbol.name.clone()).into())
+                }
+                _ => Ok(ExpressionKind::Global(symbol.name.clone()).into()),
+            },
             ParserSymbol::Variable(variable) => {
-                Ok(Expression::Variable(variable.unique_name.clone()))
+                Ok(ExpressionKind::Variable(variable.unique_name.clone()).into())
             }
             ParserSymbol::Argument(variable) => {
-                Ok(Expression::Variable(variable.unique_name.clone()))
+                Ok(ExpressionKind::Variable(variable.unique_name.clone()).into())
             }
         }
     } else {
-        Err(winnow::error::ErrMode::Backtrack(
-            winnow::error::ContextError::new(),
-        ))
+        // nothing else can start with an identifier here, so the parse goes
+        // on as if it had been declared
+        input.state.report(
+            Diagnostic::error(format!("Use of undeclared identifier '{identifier}'"))
+                .with_code(code::UNDECLARED)
+                .at(span),
+        );
+
+        Ok(ExpressionKind::Variable(identifier).into())
     }
 }
 
-pub fn parse_paren_expression(input: &mut Stream<'_>) -> PResult<Expression> {
-    parse_whitespace(input)?;
+// the csr operand is a symbolic name like mstatus or a csr number
+fn parse_csr(input: &mut Stream<'_>) -> PResult<Csr> {
+    if let Ok((number, span)) = spanned(parse_number).parse_next(input) {
+        if number.value > 0xFFF {
+            input.state.report(
+                Diagnostic::error(format!("CSR number {:#x} is out of range", number.value))
+                    .with_code(code::INVALID_CSR)
+                    .at(span)
+                    .with_note("CSR numbers are 12 bits wide"),
+            );
+        }
+
+        return Ok(Csr(number.value as u16 & 0xFFF));
+    }
 
-    let expression = combinator::seq!(parse_open_paren, parse_expression, parse_close_paren)
-        .map(|(_, expression, _)| expression)
+    let (name, span) = spanned(parse_identifier).parse_next(input)?;
+
+    Ok(Csr::from_name(name).unwrap_or_else(|| {
+        input.state.report(
+            Diagnostic::error(format!("Unknown CSR \"{name}\""))
+                .with_code(code::INVALID_CSR)
+                .at(span),
+        );
+        Csr(0)
+    }))
+}
+
+pub fn parse_builtin_expression(input: &mut Stream<'_>) -> PResult<Expression> {
+    let identifier = parse_identifier(input)?;
+
+    let (builtin, arguments) = if let Some(builtin) = Builtin::takes_csr(identifier) {
+        parse_open_paren(input)?;
+
+        let (csr, span) = spanned(parse_csr).parse_next(input)?;
+        let builtin = builtin(csr);
+
+        if csr.is_read_only() && builtin.writes_csr() {
+            input.state.report(
+                Diagnostic::error(format!("CSR {csr} is read-only"))
+                    .with_code(code::INVALID_CSR)
+                    .at(span)
+                    .with_note("CSRs numbered 0xc00 and up are read-only"),
+            );
+        }
+
+        let arguments = combinator::repeat(
+            0..,
+            combinator::preceded(parse_comma, parse_assignment_expression),
+        )
         .parse_next(input)?;
 
+        expect(parse_close_paren, "`)` after builtin arguments").parse_next(input)?;
+
+        (builtin, arguments)
+    } else if let Some(builtin) = Builtin::from_name(identifier) {
+        parse_open_paren(input)?;
+
+        (builtin, parse_call_arguments(input)?)
+    } else {
+        return Err(winnow::error::ErrMode::Backtrack(
+            winnow::error::ContextError::new(),
+        ));
+    };
+
+    Ok(ExpressionKind::Builtin(BuiltinCall { builtin, arguments }).into())
+}
+
+pub fn parse_paren_expression(input: &mut Stream<'_>) -> PResult<Expression> {
+    let expression = combinator::seq!(
+        parse_open_paren,
+        expect(parse_expression, "an expression"),
+        expect(parse_close_paren, "`)`")
+    )
+    .map(|(_, expression, _)| expression)
+    .parse_next(input)?;
+
     Ok(expression)
 }
 
 pub fn parse_optional_expression(input: &mut Stream<'_>) -> PResult<Option<Expression>> {
-    parse_whitespace(input)?;
-
     combinator::opt(parse_expression).parse_next(input)
 }
 
 pub fn parse_number_expression(input: &mut Stream<'_>) -> PResult<Expression> {
-    parse_whitespace(input)?;
-
-    parse_number.map(Expression::Number).parse_next(input)
+    parse_number
+        .map(|number| ExpressionKind::Number(number).into())
+        .parse_next(input)
 }
 
 pub fn parse_unary_operator<'s>(input: &mut Stream<'s>) -> PResult<&'s str> {
-    parse_whitespace(input)?;
-
     combinator::alt((
-        parse_pointer_ampersand,
+        parse_ampersand,
         parse_star,
         parse_plus,
         parse_minus,
@@ -157,86 +302,53 @@ pub fn parse_unary_operator<'s>(input: &mut Stream<'s>) -> PResult<&'s str> {
 }
 
 pub fn parse_unary_ref_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
-    let factor = parse_factor(input)?;
-
-    let factor_type = factor.get_type(&input.state);
-
-    if let Datatype::Function { .. } = factor_type {
-        return Ok(UnaryOp::Nothing(Arc::new(factor)));
-    }
-
-    Ok(UnaryOp::Ref(Arc::new(factor)))
+    expect(parse_factor, "an expression")
+        .map(|v| UnaryOp::Ref(Arc::new(v)))
+        .parse_next(input)
 }
 
 pub fn parse_unary_deref_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
-    let factor = parse_factor(input)?;
-
-    let mut factor_type = factor.get_type(&input.state);
-    while let Datatype::Pointer { inner } = factor_type {
-        factor_type = inner.as_ref().clone();
-    }
-
-    if let Datatype::Function { .. } = factor_type {
-        return Ok(UnaryOp::Nothing(Arc::new(factor)));
-    }
-
-    Ok(UnaryOp::Deref(Arc::new(factor)))
+    expect(parse_factor, "an expression")
+        .map(|v| UnaryOp::Deref(Arc::new(v)))
+        .parse_next(input)
 }
 
 pub fn parse_unary_plus_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
-    parse_factor
+    expect(parse_factor, "an expression")
         .map(|v| UnaryOp::Plus(Arc::new(v)))
         .parse_next(input)
 }
 
 pub fn parse_unary_negation_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
-    parse_factor
+    expect(parse_factor, "an expression")
         .map(|v| UnaryOp::Negation(Arc::new(v)))
         .parse_next(input)
 }
 
 pub fn parse_unary_bitwise_not_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
-    parse_factor
+    expect(parse_factor, "an expression")
         .map(|v| UnaryOp::BitwiseNot(Arc::new(v)))
         .parse_next(input)
 }
 
 pub fn parse_unary_logical_not_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
-    parse_factor
+    expect(parse_factor, "an expression")
         .map(|v| UnaryOp::LogicalNot(Arc::new(v)))
         .parse_next(input)
 }
 
 pub fn parse_prefix_increment_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
-    parse_factor
+    expect(parse_factor, "an expression")
         .map(|v| UnaryOp::PrefixIncrement(Arc::new(v)))
         .parse_next(input)
 }
 pub fn parse_prefix_decrement_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
-    parse_factor
+    expect(parse_factor, "an expression")
         .map(|v| UnaryOp::PrefixDecrement(Arc::new(v)))
         .parse_next(input)
 }
 
 pub fn parse_unary_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
-    parse_whitespace(input)?;
-
     combinator::dispatch! {parse_unary_operator;
         "+" => parse_unary_plus_operation,
         "-" => parse_unary_negation_operation,
@@ -252,9 +364,7 @@ pub fn parse_unary_operation(input: &mut Stream<'_>) -> PResult<UnaryOp> {
 }
 
 pub fn parse_unary_expression(input: &mut Stream<'_>) -> PResult<Expression> {
-    parse_whitespace(input)?;
-
-    parse_unary_operation
-        .map(Expression::UnaryOp)
+    nested(spanned(parse_unary_operation))
+        .map(|(op, span)| Expression::from(ExpressionKind::UnaryOp(op)).with_span(span))
         .parse_next(input)
 }
@@ -1,19 +1,142 @@
-use crate::types::expression::Expression;
+use crate::types::{
+    datatype::Datatype,
+    expression::{BinaryOp, ConversionKind, Expression, ExpressionKind, UnaryOp},
+};
 
 pub trait Fold {
     fn fold(&self) -> Option<i32>;
 }
 
+// the parser folds before there are types, so then they come from the
+// literals the way the usual arithmetic conversions would have them. every
+// integer is a word wide, so unsigned wins whenever it is involved
+fn is_unsigned(expression: &Expression) -> bool {
+    if let Some(datatype) = expression.datatype.as_deref() {
+        return matches!(datatype, Datatype::Integer(integer) if !integer.is_signed());
+    }
+
+    match &expression.kind {
+        ExpressionKind::Number(number) => !number.datatype.is_signed(),
+        ExpressionKind::UnaryOp(
+            UnaryOp::Plus(operand) | UnaryOp::Negation(operand) | UnaryOp::BitwiseNot(operand),
+        ) => is_unsigned(operand),
+        ExpressionKind::BinaryOp(BinaryOp::LeftShift(lhs, _) | BinaryOp::RightShift(lhs, _)) => {
+            is_unsigned(lhs)
+        }
+        ExpressionKind::BinaryOp(
+            BinaryOp::Addition(lhs, rhs)
+            | BinaryOp::Subtraction(lhs, rhs)
+            | BinaryOp::Multiplication(lhs, rhs)
+            | BinaryOp::Division(lhs, rhs)
+            | BinaryOp::Modulus(lhs, rhs)
+            | BinaryOp::BitwiseAnd(lhs, rhs)
+            | BinaryOp::BitwiseXor(lhs, rhs)
+            | BinaryOp::BitwiseOr(lhs, rhs),
+        ) => is_unsigned(lhs) || is_unsigned(rhs),
+        _ => false,
+    }
+}
+
+// a constant has to be in range of its type (C11 6.6), so signed overflow,
+// division by zero and shifts by the width or more are not constants
+fn fold_binary(op: &BinaryOp) -> Option<i32> {
+    let (lhs, rhs) = op.operands();
+
+    // the operand that is not evaluated does not have to be a constant
+    match op {
+        BinaryOp::LogicalAnd(..) => {
+            return match lhs.fold()? {
+                0 => Some(0),
+                _ => rhs.fold().map(|value| (value != 0) as i32),
+            };
+        }
+        BinaryOp::LogicalOr(..) => {
+            return match lhs.fold()? {
+                0 => rhs.fold().map(|value| (value != 0) as i32),
+                _ => Some(1),
+            };
+        }
+        _ => {}
+    }
+
+    let (a, b) = (lhs.fold()?, rhs.fold()?);
+    let unsigned = is_unsigned(lhs) || is_unsigned(rhs);
+    let (ua, ub) = (a as u32, b as u32);
+
+    let value = match op {
+        BinaryOp::Addition(..) if unsigned => ua.wrapping_add(ub) as i32,
+        BinaryOp::Addition(..) => a.checked_add(b)?,
+        BinaryOp::Subtraction(..) if unsigned => ua.wrapping_sub(ub) as i32,
+        BinaryOp::Subtraction(..) => a.checked_sub(b)?,
+        BinaryOp::Multiplication(..) if unsigned => ua.wrapping_mul(ub) as i32,
+        BinaryOp::Multiplication(..) => a.checked_mul(b)?,
+        BinaryOp::Division(..) if unsigned => ua.checked_div(ub)? as i32,
+        BinaryOp::Division(..) => a.checked_div(b)?,
+        BinaryOp::Modulus(..) if unsigned => ua.checked_rem(ub)? as i32,
+        BinaryOp::Modulus(..) => a.checked_rem(b)?,
+        BinaryOp::BitwiseAnd(..) => a & b,
+        BinaryOp::BitwiseXor(..) => a ^ b,
+        BinaryOp::BitwiseOr(..) => a | b,
+        // the type of a shift is the one of its left operand
+        BinaryOp::LeftShift(..) | BinaryOp::RightShift(..) if !(0..32).contains(&b) => return None,
+        BinaryOp::LeftShift(..) if is_unsigned(lhs) => (ua << b) as i32,
+        // shifting a negative value or a one bit out of the word is an
+        // overflow, into the sign bit is fine like it is for gcc
+        BinaryOp::LeftShift(..) if a < 0 || a.leading_zeros() < b as u32 => return None,
+        BinaryOp::LeftShift(..) => a << b,
+        BinaryOp::RightShift(..) if is_unsigned(lhs) => (ua >> b) as i32,
+        BinaryOp::RightShift(..) => a >> b,
+        BinaryOp::LessThan(..) if unsigned => (ua < ub) as i32,
+        BinaryOp::LessThan(..) => (a < b) as i32,
+        BinaryOp::GreaterThan(..) if unsigned => (ua > ub) as i32,
+        BinaryOp::GreaterThan(..) => (a > b) as i32,
+        BinaryOp::LessThanEquals(..) if unsigned => (ua <= ub) as i32,
+        BinaryOp::LessThanEquals(..) => (a <= b) as i32,
+        BinaryOp::GreaterThanEquals(..) if unsigned => (ua >= ub) as i32,
+        BinaryOp::GreaterThanEquals(..) => (a >= b) as i32,
+        BinaryOp::Equals(..) => (a == b) as i32,
+        BinaryOp::NotEquals(..) => (a != b) as i32,
+        // assignments and the comma operator are never constant
+        _ => return None,
+    };
+
+    Some(value)
+}
+
 impl Fold for Expression {
     fn fold(&self) -> Option<i32> {
-        match self {
-            Expression::Number(num) => Some(*num),
-            Expression::UnaryOp(_) => None,
-            Expression::BinaryOp(_) => None,
-            Expression::TernaryOp(_) => None,
-            Expression::Variable(_) => None,
-            Expression::FunctionSymbol(_) => None,
-            Expression::Call(_) => None,
+        match &self.kind {
+            // the wider literals are long long, which analysis rejects
+            ExpressionKind::Number(num) => u32::try_from(num.value).ok().map(|value| value as i32),
+            ExpressionKind::Conversion(conversion)
+                if conversion.kind == ConversionKind::Integer =>
+            {
+                conversion.expression.fold()
+            }
+            ExpressionKind::UnaryOp(UnaryOp::Plus(operand)) => operand.fold(),
+            ExpressionKind::UnaryOp(UnaryOp::Negation(operand)) if is_unsigned(operand) => {
+                operand.fold().map(i32::wrapping_neg)
+            }
+            ExpressionKind::UnaryOp(UnaryOp::Negation(operand)) => {
+                operand.fold().and_then(i32::checked_neg)
+            }
+            ExpressionKind::UnaryOp(UnaryOp::BitwiseNot(operand)) => {
+                operand.fold().map(|value| !value)
+            }
+            ExpressionKind::UnaryOp(UnaryOp::LogicalNot(operand)) => {
+                operand.fold().map(|value| (value == 0) as i32)
+            }
+            ExpressionKind::UnaryOp(_) => None,
+            ExpressionKind::BinaryOp(op) => fold_binary(op),
+            ExpressionKind::TernaryOp(_) => None,
+            ExpressionKind::Variable(_) => None,
+            ExpressionKind::Global(_) => None,
+            ExpressionKind::FunctionSymbol(_) => None,
+            ExpressionKind::Call(_) => None,
+            ExpressionKind::Builtin(_) => None,
+            ExpressionKind::Subscript(_) => None,
+            ExpressionKind::Member(_) => None,
+            ExpressionKind::Conversion(_) => None,
         }
     }
 }
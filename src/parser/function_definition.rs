@@ -1,76 +1,168 @@
-use winnow::PResult;
+use winnow::{stream::Location, PResult};
 
 use crate::{
+    diagnostic::{code, Diagnostic},
     types::{
+        attribute::{Attribute, AttributeArgument, AttributeList},
         datatype::Datatype,
         declaration::Declarator,
-        function_definition::{FunctionArgument, FunctionDefinition},
+        function_definition::{FunctionArgument, FunctionDefinition, FunctionKind, InterruptMode},
     },
-    utils::random_name::unique_identifier,
 };
 
-use super::{
-    declaration::{parse_declarator, parse_primitive_datatype},
-    scope::parse_scope,
-    whitespace::parse_whitespace,
-    ParserSymbol, ParserVariable, Stream,
-};
+use super::{scope::parse_scope, ParserState, ParserSymbol, ParserVariable, Stream};
+
+// every parameter named like an earlier one, along with that earlier one
+fn duplicate_arguments(
+    arguments: &[FunctionArgument],
+) -> Vec<(&FunctionArgument, &FunctionArgument)> {
+    arguments
+        .iter()
+        .enumerate()
+        .filter_map(|(i, argument)| {
+            arguments[..i]
+                .iter()
+                .find(|earlier| !argument.name.is_empty() && earlier.name == argument.name)
+                .map(|earlier| (argument, earlier))
+        })
+        .collect()
+}
 
-fn duplicate_checker(input: impl Iterator<Item = String>) -> bool {
-    let mut seen = std::collections::HashSet::new();
-    for x in input {
-        if !seen.insert(x) {
-            return true;
+fn function_kind(state: &mut ParserState, attributes: &Vec<Attribute>) -> FunctionKind {
+    if let Some(naked) = attributes.find_attribute("naked") {
+        if let Some(interrupt) = attributes.find_attribute("interrupt") {
+            state.report(
+                Diagnostic::error("The naked and interrupt attributes are incompatible")
+                    .with_code(code::INVALID_ATTRIBUTE)
+                    .at(interrupt.span)
+                    .with_secondary(naked.span, "naked given here"),
+            );
         }
+
+        return FunctionKind::Naked;
     }
-    false
-}
 
-pub fn parse_function_definition(input: &mut Stream) -> PResult<FunctionDefinition> {
-    parse_whitespace(input)?;
+    let Some(interrupt) = attributes.find_attribute("interrupt") else {
+        return FunctionKind::Normal;
+    };
 
-    let base_type = parse_primitive_datatype(input)?;
+    let mode = match interrupt.arguments.as_slice() {
+        [] => InterruptMode::Machine,
+        [AttributeArgument::String(mode)] => {
+            match mode.as_str() {
+                "machine" => InterruptMode::Machine,
+                "supervisor" => InterruptMode::Supervisor,
+                "user" => InterruptMode::User,
+                _ => {
+                    state.report(
+                    Diagnostic::error(format!("Unknown interrupt type \"{mode}\""))
+                        .with_code(code::INVALID_ATTRIBUTE)
+                        .at(interrupt.span)
+                        .with_note("the interrupt type is one of \"machine\", \"supervisor\" or \"user\""),
+                );
+                    InterruptMode::Machine
+                }
+            }
+        }
+        _ => {
+            state.report(
+                Diagnostic::error("The interrupt attribute takes a single string argument")
+                    .with_code(code::INVALID_ATTRIBUTE)
+                    .at(interrupt.span),
+            );
+            InterruptMode::Machine
+        }
+    };
 
-    let declarator = parse_declarator(input, base_type)?;
+    FunctionKind::Interrupt(mode)
+}
 
+// the body of a function whose declaration the caller already parsed, up to
+// the `{` that tells it apart from a plain declaration
+pub fn parse_function_definition(
+    input: &mut Stream,
+    start: usize,
+    attributes: Vec<Attribute>,
+    declarator: Declarator,
+) -> PResult<FunctionDefinition> {
     if let Datatype::Function {
         return_type,
         ref arguments,
     } = declarator.datatype
     {
+        let parameters = declarator.parameters.unwrap_or_default();
+
         let name = declarator.name;
 
-        if input.state.static_symbols.iter().any(|x| x.name == name) {
-            return Err(winnow::error::ErrMode::Backtrack(
-                winnow::error::ContextError::new(),
-            ));
-        }
+        if let Some(previous) = input.state.get_static_symbol(&name).filter(|s| s.defined) {
+            let diagnostic = Diagnostic::error(format!("Redefinition of '{name}'"))
+                .with_code(code::CONFLICTING_TYPES)
+                .at(declarator.span)
+                .with_secondary(previous.span, "previous definition is here");
 
-        if arguments.iter().any(|x| x.name.len() == 0) {
-            return Err(winnow::error::ErrMode::Backtrack(
-                winnow::error::ContextError::new(),
-            ));
+            input.state.report(diagnostic);
         }
 
-        if duplicate_checker(arguments.iter().map(|x| x.name.to_string())) {
-            panic!("Duplicate argument names in function definition");
+        // unnamed parameters have no span of their own
+        if arguments.iter().any(|x| x.name.is_empty()) {
+            input.state.report(
+                Diagnostic::error(format!("Parameter name omitted in definition of '{name}'"))
+                    .with_code(code::SYNTAX)
+                    .at(declarator.span),
+            );
         }
 
-        input.state.start_function_scope(
-            name.to_string(),
-            arguments.to_vec(),
-            return_type.as_ref().clone(),
-        );
+        input.state.restart_numbering();
 
         let function_arguments = arguments
             .iter()
-            .map(|a| FunctionArgument {
+            .zip(parameters.iter())
+            .map(|(a, parameter)| FunctionArgument {
                 name: a.name.to_string(),
-                unique_name: unique_identifier(Some(&a.name), None),
+                unique_name: input.state.unique_name(&a.name),
                 datatype: a.datatype.as_ref().clone(),
+                span: parameter.span,
             })
             .collect::<Vec<_>>();
 
+        for (argument, earlier) in duplicate_arguments(&function_arguments) {
+            input.state.report(
+                Diagnostic::error(format!("Redefinition of parameter '{}'", argument.name))
+                    .with_code(code::DUPLICATE_PARAMETER)
+                    .at(argument.span)
+                    .with_secondary(earlier.span, "previous definition is here"),
+            );
+        }
+
+        // attributes given on earlier declarations apply to the definition too
+        let mut merged = input.state.attributes_of(&name);
+        merged.merge_attributes(&attributes);
+        let attributes = merged;
+
+        let kind = function_kind(&mut input.state, &attributes);
+
+        if let (FunctionKind::Interrupt(_), Some(first)) = (&kind, function_arguments.first()) {
+            let last = function_arguments.last().unwrap_or(first);
+
+            input.state.report(
+                Diagnostic::error("Interrupt handlers cannot take arguments")
+                    .with_code(code::INTERRUPT_HANDLER)
+                    .at(first.span.to(&last.span))
+                    .with_note("the handler is entered from a trap, nothing passes it arguments"),
+            );
+        }
+
+        input.state.start_function_scope(
+            name.to_string(),
+            arguments.to_vec(),
+            return_type.as_ref().clone(),
+            attributes.clone(),
+            declarator.span,
+        );
+
+        // parameters live in their own scope, enclosing the function body
+        input.state.push_scope();
+
         function_arguments.iter().for_each(|arg| {
             input
                 .state
@@ -78,17 +170,23 @@ pub fn parse_function_definition(input: &mut Stream) -> PResult<FunctionDefiniti
                     name: arg.name.to_string(),
                     unique_name: arg.unique_name.to_string(),
                     datatype: arg.datatype.clone(),
+                    span: arg.span,
                 }));
         });
 
         let body = parse_scope(input)?;
 
+        input.state.pop_scope();
+
         Ok(FunctionDefinition {
             return_type: return_type.as_ref().clone(),
             arguments: function_arguments,
             name,
             body,
             scope_state: input.state.function_scope.clone(),
+            attributes,
+            kind,
+            span: input.state.span(start..input.location()),
         })
     } else {
         Err(winnow::error::ErrMode::Backtrack(
@@ -1,13 +1,47 @@
-use winnow::{token, PResult, Parser};
+use winnow::{
+    error::{ContextError, ErrMode},
+    PResult,
+};
 
-use super::{whitespace::parse_whitespace, Stream};
+use crate::{
+    diagnostic::{code, Diagnostic},
+    lexer::{Token, TokenKind},
+};
+
+use super::{trivial_tokens::parse_token_if, Stream};
 
 pub fn parse_identifier<'s>(input: &mut Stream<'s>) -> PResult<&'s str> {
-    parse_whitespace(input)?;
-    (
-        token::one_of(('a'..='z', 'A'..='Z', '_')),
-        token::take_while(0.., ('0'..='9', 'a'..='z', 'A'..='Z', '_')),
-    )
-        .take()
-        .parse_next(input)
+    parse_token_if(input, |kind| matches!(kind, TokenKind::Identifier(_)))
+        .map(|token| token.kind.text())
+}
+
+// attribute names are not reserved, __attribute__((const)) is fine
+pub fn parse_word<'s>(input: &mut Stream<'s>) -> PResult<&'s str> {
+    parse_token_if(input, |kind| {
+        matches!(kind, TokenKind::Identifier(_) | TokenKind::Keyword(_))
+    })
+    .map(|token| token.kind.text())
+}
+
+// a name that is being declared. keywords are reserved, so `int return;` is
+// reported here instead of being taken apart by whatever comes next
+pub fn parse_declared_identifier<'s>(input: &mut Stream<'s>) -> PResult<&'s str> {
+    if let Some(Token {
+        kind: TokenKind::Keyword(keyword),
+        span,
+    }) = input.first()
+    {
+        let diagnostic =
+            Diagnostic::error(format!("Expected an identifier, found keyword `{keyword}`"))
+                .with_code(code::SYNTAX)
+                .at(*span)
+                .with_note(format!(
+                    "`{keyword}` is a reserved keyword and can not be used as a name"
+                ));
+
+        input.state.report(diagnostic);
+        return Err(ErrMode::Cut(ContextError::new()));
+    }
+
+    parse_identifier(input)
 }
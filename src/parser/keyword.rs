@@ -0,0 +1,25 @@
+use winnow::{error::ContextError, PResult, Parser};
+
+use crate::lexer::TokenKind;
+
+use super::{trivial_tokens::parse_token_if, Stream};
+
+pub fn keyword<'s>(keyword: &'static str) -> impl Parser<Stream<'s>, &'s str, ContextError> {
+    move |input: &mut Stream<'s>| -> PResult<&'s str> {
+        parse_token_if(input, |kind| *kind == TokenKind::Keyword(keyword))
+            .map(|token| token.kind.text())
+    }
+}
+
+// any one of the keywords, e.g. the spellings of asm
+pub fn keyword_of<'s>(
+    keywords: &'static [&'static str],
+) -> impl Parser<Stream<'s>, &'s str, ContextError> {
+    move |input: &mut Stream<'s>| -> PResult<&'s str> {
+        parse_token_if(input, |kind| match kind {
+            TokenKind::Keyword(keyword) => keywords.contains(keyword),
+            _ => false,
+        })
+        .map(|token| token.kind.text())
+    }
+}
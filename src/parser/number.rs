@@ -1,54 +1,219 @@
-use winnow::{combinator, token, PResult, Parser};
+use winnow::{
+    error::{AddContext, ContextError, ErrMode, StrContext, StrContextValue},
+    stream::Stream as _,
+    PResult,
+};
 
-use super::{whitespace::parse_whitespace, Stream};
+use crate::{
+    diagnostic::{code, warning::Warning, Diagnostic},
+    lexer::TokenKind,
+    types::{datatype::IntegerType, expression::IntegerLiteral},
+};
 
-pub fn parse_hex_number(input: &mut Stream) -> PResult<i32> {
-    parse_whitespace(input)?;
+use super::{trivial_tokens::parse_token_if, Stream};
 
-    "0x".parse_next(input)?;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Base {
+    Decimal,
+    Hexadecimal,
+    Octal,
+    Binary,
+}
+
+impl Base {
+    fn radix(&self) -> u32 {
+        match self {
+            Base::Decimal => 10,
+            Base::Hexadecimal => 16,
+            Base::Octal => 8,
+            Base::Binary => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Suffix {
+    None,
+    Unsigned,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+}
+
+// what is wrong with a literal, and what was expected instead
+struct LiteralError {
+    label: &'static str,
+    expected: &'static str,
+}
+
+fn literal_error(label: &'static str, expected: &'static str) -> LiteralError {
+    LiteralError { label, expected }
+}
+
+// the base and what is left of the literal once its prefix is taken off
+fn split_base(literal: &str) -> (Base, &str) {
+    if let Some(rest) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        (Base::Hexadecimal, rest)
+    } else if let Some(rest) = literal
+        .strip_prefix("0b")
+        .or_else(|| literal.strip_prefix("0B"))
+    {
+        (Base::Binary, rest)
+    } else if literal.starts_with('0') {
+        // a leading 0 makes the literal octal, which also covers a plain 0.
+        // it stays with the digits, a separator may follow it as in 0'17
+        (Base::Octal, literal)
+    } else {
+        (Base::Decimal, literal)
+    }
+}
 
-    token::take_while(2.., |c: char| c.is_ascii_hexdigit())
-        .parse_next(input)
-        .map(|s| i32::from_str_radix(s, 16).unwrap())
+// digits may be separated by a single ' since C23, e.g. 1'000'000
+fn split_digits(base: Base, rest: &str) -> Result<(String, &str), LiteralError> {
+    let length = rest
+        .find(|c: char| !c.is_digit(base.radix()) && c != '\'')
+        .unwrap_or(rest.len());
+    let (digits, rest) = rest.split_at(length);
+
+    if digits.starts_with('\'') || digits.ends_with('\'') || digits.contains("''") {
+        return Err(literal_error(
+            "digit separator",
+            "a digit on both sides of a digit separator",
+        ));
+    }
+
+    if digits.is_empty() {
+        return Err(literal_error(
+            "integer literal",
+            "a digit of the literal's base",
+        ));
+    }
+    let digits = digits.replace('\'', "");
+
+    Ok((digits, rest))
 }
 
-pub fn parse_binary_number(input: &mut Stream) -> PResult<i32> {
-    parse_whitespace(input)?;
+fn split_suffix(rest: &str) -> Result<(Suffix, &str), LiteralError> {
+    let length = rest
+        .find(|c| !matches!(c, 'u' | 'U' | 'l' | 'L'))
+        .unwrap_or(rest.len());
+    let (suffix, rest) = rest.split_at(length);
 
-    "0b".parse_next(input)?;
+    // the u may come before or after the l or ll, but ll must not be mixed case
+    let (unsigned, long) = if let Some(long) = suffix.strip_prefix(['u', 'U']) {
+        (true, long)
+    } else if let Some(long) = suffix.strip_suffix(['u', 'U']) {
+        (true, long)
+    } else {
+        (false, suffix)
+    };
 
-    token::take_while(2.., |c: char| c == '0' || c == '1')
-        .parse_next(input)
-        .map(|s| i32::from_str_radix(s, 2).unwrap())
+    let suffix = match (unsigned, long) {
+        (false, "") => Some(Suffix::None),
+        (true, "") => Some(Suffix::Unsigned),
+        (false, "l" | "L") => Some(Suffix::Long),
+        (true, "l" | "L") => Some(Suffix::UnsignedLong),
+        (false, "ll" | "LL") => Some(Suffix::LongLong),
+        (true, "ll" | "LL") => Some(Suffix::UnsignedLongLong),
+        _ => None,
+    };
+
+    suffix
+        .map(|suffix| (suffix, rest))
+        .ok_or_else(|| literal_error("integer suffix", "one of u, l, ul, ll or ull"))
 }
 
-pub fn parse_octal_number(input: &mut Stream) -> PResult<i32> {
-    parse_whitespace(input)?;
+// C11 6.4.4.1: the type of an integer constant is the first of the
+// candidate types in which its value can be represented
+fn candidate_types(base: Base, suffix: Suffix) -> &'static [IntegerType] {
+    use IntegerType::*;
 
-    "0o".parse_next(input)?;
+    let decimal = base == Base::Decimal;
 
-    token::take_while(2.., |c: char| c.is_ascii_digit())
-        .parse_next(input)
-        .map(|s| i32::from_str_radix(s, 8).unwrap())
+    match suffix {
+        Suffix::None if decimal => &[Int, Long, LongLong],
+        Suffix::None => &[
+            Int,
+            UnsignedInt,
+            Long,
+            UnsignedLong,
+            LongLong,
+            UnsignedLongLong,
+        ],
+        Suffix::Unsigned => &[UnsignedInt, UnsignedLong, UnsignedLongLong],
+        Suffix::Long if decimal => &[Long, LongLong],
+        Suffix::Long => &[Long, UnsignedLong, LongLong, UnsignedLongLong],
+        Suffix::UnsignedLong => &[UnsignedLong, UnsignedLongLong],
+        Suffix::LongLong if decimal => &[LongLong],
+        Suffix::LongLong => &[LongLong, UnsignedLongLong],
+        Suffix::UnsignedLongLong => &[UnsignedLongLong],
+    }
 }
 
-pub fn parse_decimal_number(input: &mut Stream) -> PResult<i32> {
-    parse_whitespace(input)?;
+fn parse_literal(literal: &str) -> Result<(Base, Suffix, u64), LiteralError> {
+    let (base, rest) = split_base(literal);
+    let (digits, rest) = split_digits(base, rest)?;
+    let (suffix, rest) = split_suffix(rest)?;
+
+    // things like 09 or 12abc are neither numbers nor identifiers
+    if !rest.is_empty() {
+        return Err(literal_error(
+            "integer literal",
+            "a digit of the literal's base",
+        ));
+    }
+
+    let value = u64::from_str_radix(&digits, base.radix())
+        .map_err(|_| literal_error("integer literal", "a value that fits in unsigned long long"))?;
 
-    token::take_while(1.., '0'..='9')
-        .parse_next(input)
-        .map(|s| s.parse().unwrap())
+    Ok((base, suffix, value))
 }
 
-pub fn parse_number(input: &mut Stream) -> PResult<i32> {
-    parse_whitespace(input)?;
+pub fn parse_number(input: &mut Stream) -> PResult<IntegerLiteral> {
+    let checkpoint = input.checkpoint();
+
+    let token = parse_token_if(input, |kind| matches!(kind, TokenKind::Number(_)))?;
+    let span = token.span;
+
+    let (base, suffix, value) = parse_literal(token.kind.text()).map_err(|error| {
+        input.state.report(
+            Diagnostic::error(format!("Invalid {}", error.label))
+                .with_code(code::SYNTAX)
+                .at(span)
+                .with_note(format!("expected {}", error.expected)),
+        );
+
+        let error = ContextError::new()
+            .add_context(input, &checkpoint, StrContext::Label(error.label))
+            .add_context(
+                input,
+                &checkpoint,
+                StrContext::Expected(StrContextValue::Description(error.expected)),
+            );
+
+        // the literal was recognised, so there is no point in trying anything else
+        ErrMode::Cut(error)
+    })?;
+
+    let datatype = candidate_types(base, suffix)
+        .iter()
+        .find(|t| value <= t.max_value())
+        .copied()
+        .unwrap_or_else(|| {
+            // there is no larger signed type to fall back to
+            input.state.report(
+                Diagnostic::warning(
+                    Warning::Overflow,
+                    "Integer literal is too large to be represented in a signed integer type, interpreting as unsigned",
+                )
+                .at(span),
+            );
+            IntegerType::UnsignedLongLong
+        });
 
-    // todo: add support for negative numbers, floats etc
-    combinator::alt((
-        parse_hex_number,
-        parse_binary_number,
-        parse_octal_number,
-        parse_decimal_number,
-    ))
-    .parse_next(input)
+    Ok(IntegerLiteral { value, datatype })
 }
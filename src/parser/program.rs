@@ -1,26 +1,58 @@
-use winnow::{combinator, PResult, Parser};
+use winnow::{stream::Location, PResult, Parser};
 
-use crate::types::program::{Program, ProgramStatement};
+use crate::types::{
+    datatype::Datatype,
+    program::{Program, ProgramStatement},
+};
 
-use super::{function_definition::parse_function_definition, whitespace::parse_whitespace, Stream};
+use super::{
+    declaration::{
+        finish_declaration, finish_init_declarator, parse_declaration_specifiers, parse_declarator,
+    },
+    function_definition::parse_function_definition,
+    recovery::{expect_semicolon, recover},
+    trivial_tokens::at_punctuator,
+    Stream,
+};
+
+// a definition and a declaration look the same up to the end of the first
+// declarator, where a function type followed by `{` starts a definition
+fn parse_external_declaration(input: &mut Stream) -> PResult<ProgramStatement> {
+    let start = input.location();
+
+    let specifiers = parse_declaration_specifiers(input)?;
+
+    let first = input.location();
+    let declarator = parse_declarator(input, specifiers.datatype.clone())?;
+
+    if matches!(declarator.datatype, Datatype::Function { .. }) && at_punctuator(input, "{") {
+        return parse_function_definition(input, start, specifiers.attributes, declarator)
+            .map(ProgramStatement::FunctionDefinition);
+    }
+
+    let first = finish_init_declarator(input, first, declarator, &specifiers)?;
+    let declaration = finish_declaration(input, start, specifiers, first)?;
+
+    expect_semicolon("`;` after declaration").parse_next(input)?;
+
+    Ok(ProgramStatement::Declaration(declaration))
+}
 
 pub fn parse_program(input: &mut Stream) -> PResult<Program> {
-    parse_whitespace(input)?;
-
-    let functions = combinator::repeat_till(
-        0..,
-        combinator::alt((parse_function_definition.map(ProgramStatement::FunctionDefinition),)),
-        combinator::eof,
-    )
-    .map(|v| v.0)
-    .parse_next(input)?;
-
-    parse_whitespace(input)?;
-
-    if input.len() > 0 {
-        return Err(winnow::error::ErrMode::Backtrack(
-            winnow::error::ContextError::new(),
-        ));
+    let mut functions = Vec::new();
+
+    loop {
+        if input.is_empty() {
+            break;
+        }
+
+        let statement = recover(
+            input,
+            parse_external_declaration,
+            "a declaration or a function definition",
+        )?;
+
+        functions.extend(statement);
     }
 
     Ok(Program { functions })
@@ -0,0 +1,180 @@
+use winnow::{
+    error::{AddContext, ContextError, ErrMode, StrContext, StrContextValue},
+    stream::{Location, Stream as _},
+    PResult, Parser,
+};
+
+use crate::{
+    diagnostic::{code, Diagnostic},
+    lexer::TokenKind,
+};
+
+use super::{trivial_tokens::at_punctuator, Stream};
+
+// the token at the current location, as it is shown after "found"
+fn found(input: &Stream) -> String {
+    match input.first().map(|token| &token.kind) {
+        None => "end of file".to_string(),
+        Some(TokenKind::Keyword(keyword)) => format!("keyword `{keyword}`"),
+        Some(kind) => format!("`{kind}`"),
+    }
+}
+
+// the diagnostic is made from the context, so the error and what the user
+// sees always agree
+fn report_expected(
+    input: &mut Stream,
+    error: ContextError,
+    expected: &'static str,
+) -> ContextError {
+    let checkpoint = input.checkpoint();
+    let error = error.add_context(
+        input,
+        &checkpoint,
+        StrContext::Expected(StrContextValue::Description(expected)),
+    );
+
+    let message = error
+        .context()
+        .find_map(|context| match context {
+            StrContext::Expected(expected) => Some(format!("Expected {expected}")),
+            _ => None,
+        })
+        .unwrap_or_else(|| "Syntax error".to_string());
+
+    let location = input.location();
+    let length = usize::from(!input.is_empty());
+    let span = input.state.span(location..location + length);
+
+    input.state.report(
+        Diagnostic::error(message)
+            .with_code(code::SYNTAX)
+            .with_label(span, format!("found {}", found(input))),
+    );
+
+    error
+}
+
+// once a construct has been recognised the rest of it has to follow, so a
+// failure is reported right away instead of backtracking into alternatives
+// that can not match either
+pub fn expect<'is, O>(
+    mut parser: impl Parser<Stream<'is>, O, ContextError>,
+    expected: &'static str,
+) -> impl Parser<Stream<'is>, O, ContextError> {
+    move |input: &mut Stream<'is>| {
+        let checkpoint = input.checkpoint();
+
+        match parser.parse_next(input) {
+            Err(ErrMode::Backtrack(error)) => {
+                input.reset(&checkpoint);
+                Err(ErrMode::Cut(report_expected(input, error, expected)))
+            }
+            result => result,
+        }
+    }
+}
+
+// a missing semicolon is reported where it belongs, right after the last
+// token, and parsing goes on as if it was there
+pub fn expect_semicolon<'is>(expected: &'static str) -> impl Parser<Stream<'is>, (), ContextError> {
+    move |input: &mut Stream<'is>| {
+        if at_punctuator(input, ";") {
+            let _ = input.next_slice(1);
+            return Ok(());
+        }
+
+        let span = input.state.end_of(input.location());
+        input.state.report(
+            Diagnostic::error(format!("Expected {expected}"))
+                .with_code(code::SYNTAX)
+                .at(span)
+                .with_suggestion(span, ";", "insert the missing `;`"),
+        );
+
+        Ok(())
+    }
+}
+
+// skips a broken statement or declaration from its start: up to and
+// including its `;` or the block that ends it, or up to the `}` that
+// closes the enclosing block. semicolons inside a for loop header are
+// part of it, any other unclosed parenthesis is assumed to be a mistake
+fn synchronize(input: &mut Stream) {
+    let mut braces = 0usize;
+    // for each open parenthesis or bracket, whether it is a for loop header
+    let mut parens: Vec<bool> = Vec::new();
+    let mut after_for = false;
+
+    while let Some(token) = input.first() {
+        let punctuator = match token.kind {
+            TokenKind::Punctuator(punctuator) => punctuator,
+            TokenKind::Keyword(keyword) => {
+                after_for = keyword == "for";
+                let _ = input.next_slice(1);
+                continue;
+            }
+            _ => "",
+        };
+
+        if punctuator == "}" && braces == 0 {
+            return;
+        }
+
+        let _ = input.next_slice(1);
+
+        match punctuator {
+            "(" | "[" => parens.push(after_for),
+            ")" | "]" => {
+                parens.pop();
+            }
+            "{" => braces += 1,
+            "}" => {
+                braces -= 1;
+                if braces == 0 {
+                    return;
+                }
+            }
+            ";" if braces == 0 && parens.last() != Some(&true) => return,
+            _ => {}
+        }
+
+        after_for = false;
+    }
+}
+
+// runs the parser for one statement or declaration, and when it fails,
+// reports it, skips past it and gives back nothing, so that the caller can
+// carry on with the next one
+pub fn recover<'is, O>(
+    input: &mut Stream<'is>,
+    mut parser: impl Parser<Stream<'is>, O, ContextError>,
+    expected: &'static str,
+) -> PResult<Option<O>> {
+    let checkpoint = input.checkpoint();
+    let start = input.location();
+    let depth = input.state.depth();
+
+    match parser.parse_next(input) {
+        Ok(output) => return Ok(Some(output)),
+        // cuts have been reported where they were made
+        Err(ErrMode::Cut(_)) => {}
+        Err(ErrMode::Backtrack(error)) => {
+            input.reset(&checkpoint);
+            report_expected(input, error, expected);
+        }
+        Err(error) => return Err(error),
+    }
+
+    input.state.restore_depth(depth);
+
+    input.reset(&checkpoint);
+    synchronize(input);
+
+    // a stray closing brace at file scope would stop the skipping forever
+    if input.location() == start {
+        let _ = input.next_token();
+    }
+
+    Ok(None)
+}
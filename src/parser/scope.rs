@@ -1,99 +1,159 @@
-use winnow::{combinator, PResult, Parser};
-
-use crate::types::scope::{Label, Scope, ScopeItem};
+use winnow::{combinator, stream::Location, PResult, Parser};
+
+use crate::{
+    diagnostic::{code, Diagnostic},
+    lexer::TokenKind,
+    types::{
+        scope::{Label, LabelKind, Scope, ScopeItem},
+        span::Span,
+    },
+};
 
 use super::{
-    declaration::parse_declaration,
+    declaration::{at_declaration, parse_declaration},
     expression::{fold::Fold, parse_expression},
     identifier::parse_identifier,
+    keyword::keyword_of,
+    recovery::{expect, expect_semicolon, recover},
+    spanned,
     statement::parse_statement,
-    trivial_tokens::{parse_close_scope, parse_colon, parse_open_scope, parse_semicolon},
-    whitespace::parse_whitespace,
+    trivial_tokens::{parse_close_scope, parse_colon, parse_open_scope},
     Case, Stream,
 };
 
 pub fn parse_statement_scope_item(input: &mut Stream<'_>) -> PResult<ScopeItem> {
-    parse_whitespace(input)?;
-
     parse_statement.map(ScopeItem::Statement).parse_next(input)
 }
 
 pub fn parse_declaration_scope_item(input: &mut Stream<'_>) -> PResult<ScopeItem> {
-    parse_whitespace(input)?;
-
-    combinator::terminated(parse_declaration, parse_semicolon)
+    combinator::terminated(parse_declaration, expect_semicolon("`;` after declaration"))
         .map(ScopeItem::Declaration)
         .parse_next(input)
 }
 
 pub fn parse_scope_item(input: &mut Stream<'_>) -> PResult<ScopeItem> {
-    parse_whitespace(input)?;
-
-    combinator::alt((
-        parse_declaration_scope_item,
-        parse_statement_scope_item,
-        parse_label,
-    ))
-    .parse_next(input)
+    // labels are told apart from statements by their `:`, `x:` would
+    // otherwise be taken for an expression statement that lacks its `;`
+    match input.first().map(|token| &token.kind) {
+        Some(TokenKind::Keyword("case" | "default")) => parse_label(input),
+        Some(TokenKind::Identifier(_))
+            if matches!(
+                input.get(1).map(|token| &token.kind),
+                Some(TokenKind::Punctuator(":"))
+            ) =>
+        {
+            parse_label(input)
+        }
+        _ if at_declaration(input) => parse_declaration_scope_item(input),
+        _ => parse_statement_scope_item(input),
+    }
 }
 
 pub fn parse_scope(input: &mut Stream<'_>) -> PResult<Scope> {
-    parse_whitespace(input)?;
+    let start = input.location();
+    let open = input.state.span(start..start + 1);
+
+    parse_open_scope(input)?;
 
     input.state.push_scope();
 
-    parse_open_scope.parse_next(input)?;
+    let mut items = Vec::new();
+
+    loop {
+        if combinator::opt(parse_close_scope)
+            .parse_next(input)?
+            .is_some()
+        {
+            break;
+        }
+
+        if input.is_empty() {
+            let end = input.location();
+            input.state.report(
+                Diagnostic::error("Expected `}`")
+                    .with_code(code::SYNTAX)
+                    .with_label(input.state.span(end..end), "found end of file")
+                    .with_secondary(open, "to match this `{`"),
+            );
+            break;
+        }
+
+        // a broken item is skipped, the rest of the scope is still parsed
+        let item = recover(input, parse_scope_item, "a statement or a declaration")?;
 
-    let items = combinator::repeat_till(0.., parse_scope_item, parse_close_scope)
-        .map(|v| v.0)
-        .parse_next(input)?;
+        items.extend(item);
+    }
 
     input.state.pop_scope();
 
-    let scope = Scope { items };
+    let span = input.state.span(start..input.location());
 
-    Ok(scope)
+    Ok(Scope { items, span })
+}
+
+// the id of the enclosing switch, labels outside of one are reported
+fn switch_id(input: &mut Stream, label: &str, span: Span) -> String {
+    match input.state.get_switch() {
+        Some(switch) => switch.id.clone(),
+        None => {
+            input.state.report(
+                Diagnostic::error(format!("'{label}' label not within a switch statement"))
+                    .with_code(code::CASE_OUTSIDE_SWITCH)
+                    .at(span),
+            );
+            String::new()
+        }
+    }
 }
 
 pub fn parse_label(input: &mut Stream) -> PResult<ScopeItem> {
-    parse_whitespace(input)?;
+    let (kind, span) = spanned(parse_label_kind).parse_next(input)?;
+
+    Ok(ScopeItem::Label(Label { kind, span }))
+}
 
-    let name = parse_identifier(input)?;
+fn parse_label_kind(input: &mut Stream) -> PResult<LabelKind> {
+    let (name, span) = spanned(combinator::alt((
+        keyword_of(&["default", "case"]),
+        parse_identifier,
+    )))
+    .parse_next(input)?;
 
     let label = if name == "default" {
-        parse_colon(input)?;
-        let id = input
-            .state
-            .get_switch()
-            .unwrap_or_else(|| panic!("Default label outside of switch"))
-            .id
-            .clone();
+        expect(parse_colon, "`:` after `default`").parse_next(input)?;
+        let id = switch_id(input, "default", span);
 
         input.state.push_case_to_switch(Case::Default);
 
-        Label::Default { id }
+        LabelKind::Default { id }
     } else if name == "case" {
-        let value = parse_expression(input)?;
-        parse_colon(input)?;
-        let id = input
-            .state
-            .get_switch()
-            .unwrap_or_else(|| panic!("Case label outside of switch"))
-            .id
-            .clone();
-
-        let value = value.fold();
-
-        if let Some(value) = value {
-            input.state.push_case_to_switch(Case::Case(value));
-            Label::Case { id, value }
-        } else {
-            panic!("Case label must be a constant expression");
-        }
+        let expression = expect(parse_expression, "an expression").parse_next(input)?;
+        expect(parse_colon, "`:` after case value").parse_next(input)?;
+        let id = switch_id(input, "case", span);
+
+        let value = expression.fold().unwrap_or_else(|| {
+            input.state.report(
+                Diagnostic::error("Case label must be a constant expression")
+                    .with_code(code::NOT_CONSTANT)
+                    .at(expression.span),
+            );
+            0
+        });
+
+        input.state.push_case_to_switch(Case::Case(value));
+        LabelKind::Case { id, value }
     } else {
         parse_colon(input)?;
-        Label::Named(name.to_string())
+
+        input.state.report(
+            Diagnostic::error(format!("Label '{name}' is not supported"))
+                .with_code(code::UNSUPPORTED)
+                .at(span)
+                .with_note("there is no goto, so only case and default labels are supported"),
+        );
+
+        LabelKind::Named(name.to_string())
     };
 
-    Ok(ScopeItem::Label(label))
+    Ok(label)
 }
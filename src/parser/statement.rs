@@ -2,64 +2,103 @@ use std::sync::Arc;
 
 use winnow::{combinator, error, PResult, Parser};
 
-use crate::types::statement::{
-    ForInit, ForStatement, IfStatement, JumpStatement, Statement, SwitchStatement, WhileStatement,
+use crate::{
+    diagnostic::{code, warning::Warning, Diagnostic},
+    lexer::TokenKind,
+    types::{
+        attribute::AttributeList,
+        statement::{
+            AsmOperand, AsmStatement, ForInit, ForStatement, IfStatement, JumpStatement, Statement,
+            StatementKind, SwitchStatement, WhileStatement,
+        },
+    },
 };
 
 use super::{
-    declaration::parse_declaration,
-    expression::{parse_expression, parse_optional_expression},
+    attribute::parse_attributes,
+    declaration::{at_declaration, parse_declaration},
+    expression::{fold::Fold, parse_expression, parse_optional_expression},
     identifier::parse_identifier,
+    keyword::{keyword, keyword_of},
+    nested,
+    recovery::{expect, expect_semicolon},
     scope::parse_scope,
-    trivial_tokens::{parse_close_paren, parse_open_paren, parse_semicolon},
-    whitespace::parse_whitespace,
-    Stream,
+    spanned,
+    string::parse_string_literal,
+    trivial_tokens::{
+        parse_close_bracket, parse_close_paren, parse_colon, parse_comma, parse_open_bracket,
+        parse_open_paren, parse_semicolon,
+    },
+    ParserState, Stream,
 };
 
+// the first token decides what kind of statement it is, anything that is
+// not recognised by it is an expression statement
 pub fn parse_statement(input: &mut Stream<'_>) -> PResult<Statement> {
-    parse_whitespace(input)?;
-    combinator::alt((
-        parse_jump_statement,
-        parse_if_statement,
-        parse_while_statement,
-        parse_for_statement,
-        parse_switch_statement,
-        parse_expression_statement,
-        parse_scope_statement,
-        parse_null_statement,
-    ))
+    nested(spanned(|input: &mut Stream<'_>| {
+        match input.first().map(|token| &token.kind) {
+            Some(TokenKind::Keyword("break" | "return" | "continue" | "__ebreak")) => {
+                parse_jump_statement(input)
+            }
+            Some(TokenKind::Keyword("if")) => parse_if_statement(input),
+            Some(TokenKind::Keyword("while")) => parse_while_statement(input),
+            Some(TokenKind::Keyword("for")) => parse_for_statement(input),
+            Some(TokenKind::Keyword("switch")) => parse_switch_statement(input),
+            Some(TokenKind::Keyword("asm" | "__asm" | "__asm__")) => parse_asm_statement(input),
+            Some(TokenKind::Keyword("__attribute__" | "__attribute")) => {
+                parse_attribute_statement(input)
+            }
+            Some(TokenKind::Punctuator("{")) => parse_scope_statement(input),
+            Some(TokenKind::Punctuator(";")) => parse_null_statement(input),
+            _ => parse_expression_statement(input),
+        }
+    }))
+    .map(|(kind, span)| Statement { kind, span })
     .parse_next(input)
 }
 
-pub fn parse_scope_statement(input: &mut Stream<'_>) -> PResult<Statement> {
-    parse_whitespace(input)?;
-
-    combinator::seq!(Statement::Scope { scope: parse_scope }).parse_next(input)
+pub fn parse_scope_statement(input: &mut Stream<'_>) -> PResult<StatementKind> {
+    combinator::seq!(StatementKind::Scope { scope: parse_scope }).parse_next(input)
 }
 
-pub fn parse_null_statement(input: &mut Stream<'_>) -> PResult<Statement> {
-    parse_whitespace(input)?;
-
-    combinator::seq!(Statement::Null{
+pub fn parse_null_statement(input: &mut Stream<'_>) -> PResult<StatementKind> {
+    combinator::seq!(StatementKind::Null{
         _: parse_semicolon,
     })
     .parse_next(input)
 }
 
-pub fn parse_expression_statement(input: &mut Stream<'_>) -> PResult<Statement> {
-    parse_whitespace(input)?;
+// attributes on a null statement, only fallthrough means anything there
+pub fn parse_attribute_statement(input: &mut Stream<'_>) -> PResult<StatementKind> {
+    let (attributes, span) = spanned(parse_attributes).parse_next(input)?;
+
+    if attributes.is_empty() {
+        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
+    }
+
+    expect_semicolon("`;` after attribute").parse_next(input)?;
+
+    if attributes.has_attribute("fallthrough") {
+        return Ok(StatementKind::Fallthrough);
+    }
+
+    input.state.report(
+        Diagnostic::warning(Warning::Attributes, "Attribute on null statement ignored").at(span),
+    );
+
+    Ok(StatementKind::Null)
+}
 
-    combinator::seq!(Statement::Expression{
+pub fn parse_expression_statement(input: &mut Stream<'_>) -> PResult<StatementKind> {
+    combinator::seq!(StatementKind::Expression{
         expression: parse_expression,
-        _: parse_semicolon,
+        _: expect_semicolon("`;` after expression"),
     })
     .parse_next(input)
 }
 
-pub fn parse_jump_statement(input: &mut Stream<'_>) -> PResult<Statement> {
-    parse_whitespace(input)?;
-
-    combinator::seq!(Statement::Jump {
+pub fn parse_jump_statement(input: &mut Stream<'_>) -> PResult<StatementKind> {
+    combinator::seq!(StatementKind::Jump {
         statement: combinator::alt((
             parse_break_jump,
             parse_return_jump,
@@ -71,116 +110,97 @@ pub fn parse_jump_statement(input: &mut Stream<'_>) -> PResult<Statement> {
 }
 
 pub fn parse_ebreak_jump(input: &mut Stream<'_>) -> PResult<JumpStatement> {
-    parse_whitespace(input)?;
-
-    let identifier = parse_identifier(input)?;
-    if identifier != "__ebreak" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
+    keyword("__ebreak").parse_next(input)?;
 
-    parse_semicolon(input)?;
+    expect_semicolon("`;` after `__ebreak`").parse_next(input)?;
 
     Ok(JumpStatement::__Ebreak)
 }
 
 pub fn parse_break_jump(input: &mut Stream<'_>) -> PResult<JumpStatement> {
-    parse_whitespace(input)?;
-
-    let identifier = parse_identifier(input)?;
-    if identifier != "break" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
-
-    parse_semicolon(input)?;
+    let (_, span) = spanned(keyword("break")).parse_next(input)?;
+
+    expect_semicolon("`;` after `break`").parse_next(input)?;
+
+    let id = match input.state.get_loop_or_switch() {
+        Some(_loop) => _loop.id.clone(),
+        None => {
+            input.state.report(
+                Diagnostic::error("Break statement not within loop or switch")
+                    .with_code(code::JUMP_OUTSIDE_LOOP)
+                    .at(span),
+            );
+            String::new()
+        }
+    };
 
-    if let Some(_loop) = input.state.get_loop_or_switch() {
-        Ok(JumpStatement::Break {
-            id: _loop.id.clone(),
-        })
-    } else {
-        todo!("Error: break statement outside of loop");
-    }
+    Ok(JumpStatement::Break { id })
 }
 
 pub fn parse_return_jump(input: &mut Stream) -> PResult<JumpStatement> {
-    parse_whitespace(input)?;
-
-    let identifier = parse_identifier(input)?;
-    if identifier != "return" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
+    keyword("return").parse_next(input)?;
 
     combinator::seq!(JumpStatement::Return{
             expression: parse_optional_expression,
-            _: parse_semicolon,
+            _: expect_semicolon("`;` after return statement"),
     })
     .parse_next(input)
 }
 
 pub fn parse_continue_jump(input: &mut Stream) -> PResult<JumpStatement> {
-    parse_whitespace(input)?;
-
-    let identifier = parse_identifier(input)?;
-    if identifier != "continue" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
-
-    parse_semicolon(input)?;
+    let (_, span) = spanned(keyword("continue")).parse_next(input)?;
+
+    expect_semicolon("`;` after `continue`").parse_next(input)?;
+
+    let id = match input.state.get_loop() {
+        Some(_loop) => _loop.id.clone(),
+        None => {
+            input.state.report(
+                Diagnostic::error("Continue statement not within a loop")
+                    .with_code(code::JUMP_OUTSIDE_LOOP)
+                    .at(span),
+            );
+            String::new()
+        }
+    };
 
-    if let Some(_loop) = input.state.get_loop() {
-        Ok(JumpStatement::Continue {
-            id: _loop.id.clone(),
-        })
-    } else {
-        todo!("Error: continue statement outside of loop");
-    }
+    Ok(JumpStatement::Continue { id })
 }
 
 pub fn parse_else(input: &mut Stream) -> PResult<Statement> {
-    parse_whitespace(input)?;
+    keyword("else").parse_next(input)?;
 
-    let identifier = parse_identifier(input)?;
-    if identifier != "else" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
-
-    parse_statement(input)
+    expect(parse_statement, "a statement").parse_next(input)
 }
 
-pub fn parse_if_statement(input: &mut Stream) -> PResult<Statement> {
-    parse_whitespace(input)?;
-
-    let identifier = parse_identifier(input)?;
-    if identifier != "if" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
+pub fn parse_if_statement(input: &mut Stream) -> PResult<StatementKind> {
+    keyword("if").parse_next(input)?;
 
     combinator::seq!(IfStatement {
-        condition: parse_expression,
-        then_block: parse_statement.map(Arc::new),
+        _: expect(parse_open_paren, "`(` after `if`"),
+        condition: expect(parse_expression, "an expression"),
+        _: expect(parse_close_paren, "`)`"),
+        then_block: expect(parse_statement, "a statement").map(Arc::new),
         else_block: combinator::opt(parse_else.map(Arc::new)),
     })
-    .map(|s| Statement::If { statement: s })
+    .map(|s| StatementKind::If { statement: s })
     .parse_next(input)
 }
 
-pub fn parse_while_statement(input: &mut Stream) -> PResult<Statement> {
-    parse_whitespace(input)?;
+pub fn parse_while_statement(input: &mut Stream) -> PResult<StatementKind> {
+    keyword("while").parse_next(input)?;
 
-    let identifier = parse_identifier(input)?;
-    if identifier != "while" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
-
-    parse_open_paren(input)?;
+    expect(parse_open_paren, "`(` after `while`").parse_next(input)?;
 
-    let condition = parse_expression(input)?;
+    let condition = expect(parse_expression, "an expression").parse_next(input)?;
 
-    parse_close_paren(input)?;
+    expect(parse_close_paren, "`)`").parse_next(input)?;
 
     let id = input.state.push_loop("while".to_string(), true);
 
-    let block = parse_statement(input).map(Arc::new)?;
+    let block = expect(parse_statement, "a statement")
+        .map(Arc::new)
+        .parse_next(input)?;
 
     let while_statement = WhileStatement {
         condition,
@@ -188,86 +208,74 @@ pub fn parse_while_statement(input: &mut Stream) -> PResult<Statement> {
         id,
     };
 
-    Ok(Statement::While {
+    Ok(StatementKind::While {
         statement: while_statement,
     })
 }
 
 pub fn parse_for_init(input: &mut Stream) -> PResult<ForInit> {
-    parse_whitespace(input)?;
-
-    combinator::alt((
-        parse_declaration.map(ForInit::Declaration),
-        parse_expression.map(ForInit::Expression),
-    ))
-    .parse_next(input)
+    if at_declaration(input) {
+        parse_declaration
+            .map(ForInit::Declaration)
+            .parse_next(input)
+    } else {
+        parse_expression.map(ForInit::Expression).parse_next(input)
+    }
 }
 
-pub fn parse_for_statement(input: &mut Stream) -> PResult<Statement> {
-    parse_whitespace(input)?;
-
-    let identifier = parse_identifier(input)?;
-    if identifier != "for" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
+pub fn parse_for_statement(input: &mut Stream) -> PResult<StatementKind> {
+    keyword("for").parse_next(input)?;
 
     input.state.push_scope();
 
-    parse_open_paren(input)?;
+    expect(parse_open_paren, "`(` after `for`").parse_next(input)?;
 
     let init = combinator::opt(parse_for_init).parse_next(input)?;
 
-    println!("{:?}, {}", init, input);
-
-    parse_semicolon(input)?;
+    expect(parse_semicolon, "`;` after for loop initializer").parse_next(input)?;
 
     let condition = combinator::opt(parse_expression).parse_next(input)?;
 
-    parse_semicolon(input)?;
+    expect(parse_semicolon, "`;` after for loop condition").parse_next(input)?;
 
     let update = combinator::opt(parse_expression).parse_next(input)?;
 
-    parse_close_paren(input)?;
+    expect(parse_close_paren, "`)`").parse_next(input)?;
 
     let id = input.state.push_loop("for".to_string(), true);
 
-    let block = parse_statement(input)?;
+    let block = expect(parse_statement, "a statement").parse_next(input)?;
 
     input.state.pop_loop();
     input.state.pop_scope();
 
-    Ok(Statement::For {
-        statement: ForStatement {
+    Ok(StatementKind::For {
+        statement: Arc::new(ForStatement {
             init,
             condition,
             increment: update,
             block: Arc::new(block),
             id,
-        },
+        }),
     })
 }
 
-pub fn parse_switch_statement(input: &mut Stream) -> PResult<Statement> {
-    parse_whitespace(input)?;
-
-    let identifier = parse_identifier(input)?;
-    if identifier != "switch" {
-        return Err(error::ErrMode::Backtrack(error::ContextError::new()));
-    }
+pub fn parse_switch_statement(input: &mut Stream) -> PResult<StatementKind> {
+    keyword("switch").parse_next(input)?;
 
-    parse_open_paren(input)?;
+    expect(parse_open_paren, "`(` after `switch`").parse_next(input)?;
 
-    let expression = parse_expression(input)?;
+    let expression = expect(parse_expression, "an expression").parse_next(input)?;
 
-    parse_close_paren(input)?;
+    expect(parse_close_paren, "`)`").parse_next(input)?;
 
     let id = input.state.push_loop("switch".to_string(), false);
 
-    let block = parse_statement(input)?;
+    let block = expect(parse_statement, "a statement").parse_next(input)?;
 
     let switch_state = input.state.pop_loop();
 
-    Ok(Statement::Switch {
+    Ok(StatementKind::Switch {
         statement: SwitchStatement {
             expression,
             body: Arc::new(block),
@@ -276,3 +284,135 @@ pub fn parse_switch_statement(input: &mut Stream) -> PResult<Statement> {
         },
     })
 }
+
+fn parse_asm_operand_name(input: &mut Stream) -> PResult<String> {
+    parse_open_bracket(input)?;
+
+    let name = expect(parse_identifier, "an operand name")
+        .parse_next(input)?
+        .to_string();
+
+    expect(parse_close_bracket, "`]`").parse_next(input)?;
+
+    Ok(name)
+}
+
+fn parse_asm_operand(input: &mut Stream) -> PResult<AsmOperand> {
+    let ((name, constraint, expression), span) = spanned(combinator::seq!(
+        combinator::opt(parse_asm_operand_name),
+        parse_string_literal,
+        _: expect(parse_open_paren, "`(` after operand constraint"),
+        expect(parse_expression, "an expression"),
+        _: expect(parse_close_paren, "`)`"),
+    ))
+    .parse_next(input)?;
+
+    Ok(AsmOperand {
+        name,
+        constraint,
+        expression,
+        span,
+    })
+}
+
+fn parse_asm_operands(input: &mut Stream) -> PResult<Vec<AsmOperand>> {
+    parse_colon(input)?;
+
+    combinator::separated(0.., parse_asm_operand, parse_comma).parse_next(input)
+}
+
+fn parse_asm_clobbers(input: &mut Stream) -> PResult<Vec<String>> {
+    parse_colon(input)?;
+
+    combinator::separated(0.., parse_string_literal, parse_comma).parse_next(input)
+}
+
+fn invalid_operand(operand: &AsmOperand, message: impl Into<String>) -> Diagnostic {
+    Diagnostic::error(message)
+        .with_code(code::INVALID_ASM)
+        .at(operand.span)
+}
+
+fn validate_asm_operand(state: &mut ParserState, operand: &AsmOperand, output: bool) {
+    let constraint = operand.constraint.as_str();
+
+    let kind = if output {
+        match constraint.strip_prefix(['=', '+']) {
+            Some(kind) => kind,
+            None => {
+                state.report(
+                    invalid_operand(operand, "Output operand constraint lacks '=' or '+'")
+                        .with_note(format!(
+                            "use \"={constraint}\" if the operand is only written, \"+{constraint}\" if it is also read"
+                        )),
+                );
+                return;
+            }
+        }
+    } else {
+        constraint
+    };
+
+    match kind {
+        "r" | "m" => {}
+        "i" if !output => {
+            if operand.expression.fold().is_none() {
+                state.report(invalid_operand(
+                    operand,
+                    "Operand of an \"i\" constraint must be a constant",
+                ));
+            }
+        }
+        _ => state.report(invalid_operand(
+            operand,
+            format!("Unsupported asm constraint \"{constraint}\""),
+        )),
+    }
+}
+
+pub fn parse_asm_statement(input: &mut Stream) -> PResult<StatementKind> {
+    keyword_of(&["asm", "__asm", "__asm__"]).parse_next(input)?;
+
+    let qualifiers: Vec<_> = combinator::repeat(
+        0..,
+        keyword_of(&["volatile", "__volatile__", "__volatile", "inline"]),
+    )
+    .parse_next(input)?;
+
+    let volatile = qualifiers.iter().any(|q| q.contains("volatile"));
+
+    expect(parse_open_paren, "`(` after `asm`").parse_next(input)?;
+
+    let (template, template_span) =
+        spanned(expect(parse_string_literal, "an assembler template string")).parse_next(input)?;
+
+    let outputs = combinator::opt(parse_asm_operands).parse_next(input)?;
+    let inputs = combinator::opt(parse_asm_operands).parse_next(input)?;
+    let clobbers = combinator::opt(parse_asm_clobbers).parse_next(input)?;
+
+    expect(parse_close_paren, "`)`").parse_next(input)?;
+    expect_semicolon("`;` after asm statement").parse_next(input)?;
+
+    let basic = outputs.is_none();
+
+    let statement = AsmStatement {
+        template,
+        template_span,
+        outputs: outputs.unwrap_or_default(),
+        inputs: inputs.unwrap_or_default(),
+        clobbers: clobbers.unwrap_or_default(),
+        volatile,
+        basic,
+    };
+
+    statement
+        .outputs
+        .iter()
+        .for_each(|operand| validate_asm_operand(&mut input.state, operand, true));
+    statement
+        .inputs
+        .iter()
+        .for_each(|operand| validate_asm_operand(&mut input.state, operand, false));
+
+    Ok(StatementKind::Asm { statement })
+}
@@ -0,0 +1,57 @@
+use winnow::{combinator, PResult, Parser};
+
+use crate::{
+    diagnostic::{code, Diagnostic},
+    lexer::TokenKind,
+};
+
+use super::{trivial_tokens::parse_token_if, Stream};
+
+fn escape_sequence(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' | '"' | '\'' => Some(c),
+        _ => None,
+    }
+}
+
+fn parse_single_string_literal(input: &mut Stream) -> PResult<String> {
+    let token = parse_token_if(input, |kind| matches!(kind, TokenKind::String(_)))?;
+
+    // the lexer made sure that the quotes are there
+    let text = token.kind.text();
+    let mut characters = text[1..text.len() - 1].chars();
+    let mut string = String::new();
+
+    while let Some(c) = characters.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+
+        let escaped = characters.next().unwrap_or_default();
+        string.push(escape_sequence(escaped).unwrap_or_else(|| {
+            input.state.report(
+                Diagnostic::error(format!("Unknown escape sequence `\\{escaped}`"))
+                    .with_code(code::SYNTAX)
+                    .at(token.span),
+            );
+            escaped
+        }));
+    }
+
+    Ok(string)
+}
+
+// adjacent string literals are concatenated, "a" "b" is the same as "ab"
+pub fn parse_string_literal(input: &mut Stream) -> PResult<String> {
+    combinator::repeat(1.., parse_single_string_literal)
+        .fold(String::new, |mut acc, s| {
+            acc.push_str(&s);
+            acc
+        })
+        .parse_next(input)
+}
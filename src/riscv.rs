@@ -1,12 +1,24 @@
 use compile::{Compile, CompilerState};
 
+use crate::diagnostic::{Diagnostics, ErrorReported};
+
+pub mod assembler;
+pub mod cfg;
 pub mod compile;
-mod instruction;
-mod values;
+pub mod freestanding;
+pub mod instruction;
+pub mod linker;
+pub mod values;
 
-pub fn compile_program(program: impl Compile) -> Vec<instruction::Instruction> {
+pub fn compile_program(
+    program: impl Compile,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<instruction::Instruction>, ErrorReported> {
     let mut state = CompilerState::new();
-    program.compile(&mut state)
+    let instructions = program.compile(&mut state);
+
+    diagnostics.extend(state.diagnostics);
+    diagnostics.check(instructions)
 }
 
 pub fn optimize_program(program: Vec<instruction::Instruction>) -> Vec<instruction::Instruction> {
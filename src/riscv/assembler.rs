@@ -0,0 +1,1249 @@
+// turns instructions, or assembly text, into an elf relocatable object. every
+// reference to a global label becomes a relocation, even within a section,
+// so that the linker is free to relax the code in between
+use std::collections::HashMap;
+
+use encode::{b_type, encode, fits_signed, j_type, Fixup};
+use operand::{is_symbol, split_operands, split_statements, Operand};
+
+use crate::{
+    elf::{Binding, Object, Relocation, RelocationKind, Section, SectionKind, Symbol, SymbolKind},
+    target::Target,
+};
+
+use super::instruction::Instruction;
+
+pub mod encode;
+pub mod operand;
+
+const NOP: u32 = 0x0000_0013;
+// jal zero, the offset is filled in like for any other jal
+const JUMP: u32 = 0x0000_006f;
+// where the offset of a branch goes
+const BRANCH_OFFSET: u32 = 0xfe00_0f80;
+
+// a relocation whose symbol is only known once the whole input is seen
+struct Pending {
+    section: usize,
+    offset: u32,
+    fixup: Fixup,
+    relax: bool,
+}
+
+// nops that line code up without the linker being told about them, so they
+// have to be redone when code before them grows
+struct Padding {
+    section: usize,
+    offset: u32,
+    length: u32,
+    alignment: u32,
+}
+
+// what .size says, either a number or the distance from the symbol to where
+// the directive is, or to a label after it the way clang puts it
+enum Size {
+    Fixed(u32),
+    Until(usize, u32),
+    To(String),
+}
+
+pub struct Assembler {
+    // never has the rvc flag, nothing is compressed
+    flags: u32,
+    sections: Vec<Section>,
+    section: usize,
+    relax: bool,
+    // .option push and pop
+    saved_relax: Vec<bool>,
+    // where each label is, in the order they were defined
+    labels: Vec<(String, usize, u32)>,
+    label_index: HashMap<String, usize>,
+    bindings: HashMap<String, Binding>,
+    kinds: HashMap<String, SymbolKind>,
+    sizes: HashMap<String, Size>,
+    // how often each numeric label like 1: has been defined so far
+    numeric_labels: HashMap<String, usize>,
+    pending: Vec<Pending>,
+    paddings: Vec<Padding>,
+}
+
+fn parse_string(text: &str) -> Result<Vec<u8>, String> {
+    let invalid = || format!("Invalid string {text}");
+
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+
+    let mut bytes = Vec::new();
+    let mut characters = inner.chars();
+
+    while let Some(c) = characters.next() {
+        let c = match c {
+            '\\' => match characters.next().ok_or_else(invalid)? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                c @ ('\\' | '"' | '\'') => c,
+                _ => return Err(invalid()),
+            },
+            c => c,
+        };
+
+        let mut buffer = [0; 4];
+        bytes.extend(c.encode_utf8(&mut buffer).as_bytes());
+    }
+
+    Ok(bytes)
+}
+
+fn parse_size(text: &str) -> Result<i64, String> {
+    operand::parse_number(text.trim()).ok_or_else(|| format!("Expected a number, found `{text}`"))
+}
+
+// the first argument of a directive, .balign and .align take a fill value
+// after it
+fn first(arguments: &str) -> &str {
+    split_operands(arguments)
+        .first()
+        .copied()
+        .unwrap_or_default()
+}
+
+fn numeric_label(name: &str, count: usize) -> String {
+    format!(".L{name}.{count}")
+}
+
+impl Assembler {
+    // gas starts out with these, so objects always have them
+    pub fn new(target: &Target) -> Assembler {
+        Assembler {
+            flags: target.elf_flags(),
+            sections: vec![
+                Section::new(".text", SectionKind::Code),
+                Section::new(".data", SectionKind::Data),
+                Section::new(".bss", SectionKind::Uninitialized),
+            ],
+            section: 0,
+            relax: true,
+            saved_relax: Vec::new(),
+            labels: Vec::new(),
+            label_index: HashMap::new(),
+            bindings: HashMap::new(),
+            kinds: HashMap::new(),
+            sizes: HashMap::new(),
+            numeric_labels: HashMap::new(),
+            pending: Vec::new(),
+            paddings: Vec::new(),
+        }
+    }
+
+    pub fn instruction(&mut self, instruction: &Instruction) -> Result<(), String> {
+        match instruction {
+            Instruction::Comment(_) => Ok(()),
+            Instruction::Label(label) => self.label(label),
+            Instruction::Symbol(directive) => self.directive(directive),
+            Instruction::Raw(text) => self.line(text),
+            _ => {
+                if let Some(expanded) = instruction.expand() {
+                    return expanded
+                        .iter()
+                        .try_for_each(|instruction| self.instruction(instruction));
+                }
+
+                let operands = instruction
+                    .operands()
+                    .iter()
+                    .map(|operand| Operand::parse(operand))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.emit(instruction.mnemonic().unwrap_or_default(), &operands)
+            }
+        }
+    }
+
+    // a line of assembly text, with any number of labels in front
+    pub fn line(&mut self, line: &str) -> Result<(), String> {
+        for statement in split_statements(line) {
+            let mut statement = statement;
+
+            while let Some((label, rest)) = statement.split_once(':') {
+                let label = label.trim();
+                if !is_symbol(label) && !label.chars().all(|c| c.is_ascii_digit()) {
+                    break;
+                }
+
+                self.label(label)?;
+                statement = rest.trim();
+            }
+
+            if statement.is_empty() {
+                continue;
+            }
+
+            if let Some(directive) = statement.strip_prefix('.') {
+                self.directive(directive)?;
+                continue;
+            }
+
+            let (mnemonic, operands) = statement
+                .split_once(char::is_whitespace)
+                .unwrap_or((statement, ""));
+
+            let operands = split_operands(operands)
+                .into_iter()
+                .map(Operand::parse)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            self.emit(mnemonic, &operands)?;
+        }
+
+        Ok(())
+    }
+
+    fn current(&mut self) -> &mut Section {
+        &mut self.sections[self.section]
+    }
+
+    fn offset(&self) -> u32 {
+        self.sections[self.section].data.len() as u32
+    }
+
+    fn label(&mut self, name: &str) -> Result<(), String> {
+        let name = if name.chars().all(|c| c.is_ascii_digit()) {
+            let count = self.numeric_labels.entry(name.to_string()).or_default();
+            *count += 1;
+            numeric_label(name, *count)
+        } else {
+            name.to_string()
+        };
+
+        if self.label_index.contains_key(&name) {
+            return Err(format!("Symbol `{name}` is already defined"));
+        }
+
+        self.label_index.insert(name.clone(), self.labels.len());
+        self.labels.push((name, self.section, self.offset()));
+        Ok(())
+    }
+
+    // 1b is the last 1: before the reference, 1f the next one after it
+    fn symbol_name(&self, name: &str) -> Result<String, String> {
+        let Some(number) = name
+            .strip_suffix(['b', 'f'])
+            .filter(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+        else {
+            return Ok(name.to_string());
+        };
+
+        let count = self.numeric_labels.get(number).copied().unwrap_or(0);
+
+        if name.ends_with('f') {
+            Ok(numeric_label(number, count + 1))
+        } else if count > 0 {
+            Ok(numeric_label(number, count))
+        } else {
+            Err(format!("No label `{number}:` before `{name}`"))
+        }
+    }
+
+    fn data(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let section = self.current();
+
+        if section.kind == SectionKind::Uninitialized {
+            return Err(format!(
+                "Only zeros can go into the uninitialized section `{}`",
+                section.name
+            ));
+        }
+
+        section.data.extend(bytes);
+        Ok(())
+    }
+
+    fn fixup(&mut self, offset: u32, mut fixup: Fixup, relax: bool) -> Result<(), String> {
+        fixup.symbol = self.symbol_name(&fixup.symbol)?;
+
+        self.pending.push(Pending {
+            section: self.section,
+            offset,
+            fixup,
+            relax,
+        });
+
+        Ok(())
+    }
+
+    fn emit(&mut self, mnemonic: &str, operands: &[Operand]) -> Result<(), String> {
+        for encoded in encode(mnemonic, operands)? {
+            let offset = self.offset();
+
+            if let Some(fixup) = encoded.fixup {
+                self.fixup(offset, fixup, self.relax)?;
+            }
+
+            self.data(&encoded.word.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn switch_section(&mut self, name: &str, kind: Option<SectionKind>) {
+        match self
+            .sections
+            .iter()
+            .position(|section| section.name == name)
+        {
+            Some(index) => self.section = index,
+            None => {
+                let kind = kind.unwrap_or_else(|| SectionKind::from_name(name));
+                self.sections.push(Section::new(name, kind));
+                self.section = self.sections.len() - 1;
+            }
+        }
+    }
+
+    // .section name, "flags", @type
+    fn section_directive(&mut self, arguments: &str) -> Result<(), String> {
+        let arguments = split_operands(arguments);
+
+        let Some(name) = arguments.first().filter(|name| !name.is_empty()) else {
+            return Err("Missing section name".to_string());
+        };
+
+        let kind = arguments.get(1).map(|flags| {
+            let nobits = arguments.get(2) == Some(&"@nobits");
+
+            if flags.contains('x') {
+                SectionKind::Code
+            } else if flags.contains('w') && nobits {
+                SectionKind::Uninitialized
+            } else if flags.contains('w') {
+                SectionKind::Data
+            } else {
+                SectionKind::ReadOnly
+            }
+        });
+
+        self.switch_section(name, kind);
+        Ok(())
+    }
+
+    // code is padded with nops. when relaxing, the linker may remove code
+    // before this, so the worst case padding is put in and the linker is
+    // told how much of it to keep
+    fn align(&mut self, alignment: u32) -> Result<(), String> {
+        if !alignment.is_power_of_two() {
+            return Err(format!("Alignment {alignment} is not a power of two"));
+        }
+
+        let relax = self.relax;
+        let section = self.current();
+        section.alignment = section.alignment.max(alignment);
+
+        if section.kind != SectionKind::Code {
+            let length = section.data.len().next_multiple_of(alignment as usize);
+            section.data.resize(length, 0);
+            return Ok(());
+        }
+
+        let length = section.data.len().next_multiple_of(4);
+        section.data.resize(length, 0);
+
+        let padding = if relax && alignment > 4 {
+            section.relocations.push(Relocation {
+                offset: section.data.len() as u32,
+                kind: RelocationKind::Align,
+                symbol: None,
+                addend: alignment as i32 - 4,
+            });
+            alignment as usize - 4
+        } else {
+            section.data.len().next_multiple_of(alignment as usize) - section.data.len()
+        };
+
+        if alignment > 4 && !relax {
+            self.paddings.push(Padding {
+                section: self.section,
+                offset: self.offset(),
+                length: padding as u32,
+                alignment,
+            });
+        }
+
+        let section = self.current();
+        for _ in 0..padding / 4 {
+            section.data.extend(NOP.to_le_bytes());
+        }
+
+        Ok(())
+    }
+
+    // replaces the bytes at an offset, moving everything after them along
+    fn splice(&mut self, section: usize, at: u32, removed: u32, inserted: &[u8]) {
+        let end = at + removed;
+        let moved = |offset: &mut u32| {
+            if *offset >= end {
+                *offset = *offset - removed + inserted.len() as u32;
+            }
+        };
+
+        self.sections[section]
+            .data
+            .splice(at as usize..end as usize, inserted.iter().copied());
+
+        self.sections[section]
+            .relocations
+            .iter_mut()
+            .for_each(|relocation| moved(&mut relocation.offset));
+
+        for (_, label_section, offset) in self.labels.iter_mut() {
+            if *label_section == section {
+                moved(offset);
+            }
+        }
+
+        for pending in self.pending.iter_mut() {
+            if pending.section == section {
+                moved(&mut pending.offset);
+            }
+        }
+
+        for padding in self.paddings.iter_mut() {
+            if padding.section == section {
+                moved(&mut padding.offset);
+            }
+        }
+
+        for size in self.sizes.values_mut() {
+            if let Size::Until(size_section, offset) = size {
+                if *size_section == section {
+                    moved(offset);
+                }
+            }
+        }
+    }
+
+    // gas turns a branch whose label is too far away into the opposite
+    // branch over a jal, which reaches a lot further. the jal moves the code
+    // after it, which can put other branches out of range, so this goes on
+    // until every one of them fits
+    fn relax_branches(&mut self) {
+        let out_of_range = |assembler: &Assembler, pending: &Pending| {
+            let index = assembler
+                .label_index
+                .get(&pending.fixup.symbol)
+                .filter(|_| pending.fixup.symbol.starts_with(".L"));
+
+            match (pending.fixup.kind, index) {
+                (RelocationKind::Branch, Some(&index)) => {
+                    let (_, section, offset) = assembler.labels[index];
+                    let relative = offset as i64 + pending.fixup.addend - pending.offset as i64;
+                    section == pending.section && !fits_signed(relative, 13)
+                }
+                _ => false,
+            }
+        };
+
+        while let Some(index) = self
+            .pending
+            .iter()
+            .position(|pending| out_of_range(self, pending))
+        {
+            let (section, offset) = (self.pending[index].section, self.pending[index].offset);
+
+            self.splice(section, offset + 4, 0, &JUMP.to_le_bytes());
+
+            // the opposite condition differs in the lowest bit of funct3
+            let at = offset as usize;
+            let data = &mut self.sections[section].data;
+            let word = u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+            let inverted = (word & !BRANCH_OFFSET) ^ (1 << 12) | b_type(8);
+            data[at..at + 4].copy_from_slice(&inverted.to_le_bytes());
+
+            let pending = &mut self.pending[index];
+            pending.offset += 4;
+            pending.fixup.kind = RelocationKind::Jal;
+
+            self.realign(section);
+        }
+    }
+
+    // in order, since redoing one moves the ones after it
+    fn realign(&mut self, section: usize) {
+        for index in 0..self.paddings.len() {
+            let Padding {
+                section: padding_section,
+                offset,
+                length,
+                alignment,
+            } = self.paddings[index];
+
+            let needed = offset.next_multiple_of(alignment) - offset;
+            if padding_section != section || needed == length {
+                continue;
+            }
+
+            if needed > length {
+                let nops = NOP.to_le_bytes().repeat(((needed - length) / 4) as usize);
+                self.splice(section, offset + length, 0, &nops);
+            } else {
+                self.splice(section, offset + needed, length - needed, &[]);
+            }
+
+            let padding = &mut self.paddings[index];
+            padding.offset = offset;
+            padding.length = needed;
+        }
+    }
+
+    // .byte, .half and .word, each value a number or a symbol
+    fn values(&mut self, arguments: &str, size: usize) -> Result<(), String> {
+        for argument in split_operands(arguments) {
+            let value = match Operand::parse(argument)? {
+                Operand::Number(value) => value,
+                Operand::Symbol(symbol, addend) if size == 4 => {
+                    let fixup = Fixup {
+                        kind: RelocationKind::Absolute32,
+                        symbol,
+                        addend,
+                    };
+                    self.fixup(self.offset(), fixup, false)?;
+                    0
+                }
+                _ => return Err(format!("Invalid value `{argument}`")),
+            };
+
+            self.data(&value.to_le_bytes()[..size])?;
+        }
+
+        Ok(())
+    }
+
+    fn bind(&mut self, names: &str, binding: Binding) {
+        for name in names.split(',') {
+            self.bindings.insert(name.trim().to_string(), binding);
+        }
+    }
+
+    pub fn directive(&mut self, directive: &str) -> Result<(), String> {
+        let (name, arguments) = directive
+            .split_once(char::is_whitespace)
+            .map_or((directive, ""), |(name, arguments)| {
+                (name, arguments.trim())
+            });
+
+        match name {
+            "text" | "data" | "bss" | "rodata" => self.switch_section(&format!(".{name}"), None),
+            "section" => self.section_directive(arguments)?,
+            "globl" | "global" => self.bind(arguments, Binding::Global),
+            "weak" => self.bind(arguments, Binding::Weak),
+            "local" => self.bind(arguments, Binding::Local),
+            "type" => {
+                let [symbol, kind] = split_operands(arguments)[..] else {
+                    return Err(format!("Invalid .type `{arguments}`"));
+                };
+                let kind = match kind.trim_start_matches(['@', '%']) {
+                    "function" => SymbolKind::Function,
+                    "object" => SymbolKind::Object,
+                    _ => SymbolKind::None,
+                };
+                self.kinds.insert(symbol.to_string(), kind);
+            }
+            "size" => {
+                let [symbol, size] = split_operands(arguments)[..] else {
+                    return Err(format!("Invalid .size `{arguments}`"));
+                };
+                let expression: String = size.split_whitespace().collect();
+                let end = expression
+                    .strip_suffix(symbol)
+                    .and_then(|end| end.strip_suffix('-'));
+                let size = if end == Some(".") {
+                    Size::Until(self.section, self.current().data.len() as u32)
+                } else if let Some(end) = end.filter(|end| is_symbol(end)) {
+                    Size::To(end.to_string())
+                } else {
+                    let size = parse_size(size)?;
+                    Size::Fixed(
+                        size.try_into()
+                            .map_err(|_| format!("Invalid size {size}"))?,
+                    )
+                };
+                self.sizes.insert(symbol.to_string(), size);
+            }
+            "option" => match arguments {
+                "push" => self.saved_relax.push(self.relax),
+                "pop" => {
+                    self.relax = self
+                        .saved_relax
+                        .pop()
+                        .ok_or("`.option pop` without a `.option push`")?
+                }
+                "relax" => self.relax = true,
+                "norelax" => self.relax = false,
+                // nothing is compressed, and the code is never position
+                // independent
+                "rvc" | "norvc" | "pic" | "nopic" => (),
+                _ if arguments.starts_with("arch") => (),
+                _ => return Err(format!("Unknown option `{arguments}`")),
+            },
+            // alignment is a power of two on riscv
+            "align" | "p2align" => {
+                let exponent = parse_size(first(arguments))?;
+                if !(0..32).contains(&exponent) {
+                    return Err(format!("Invalid alignment `{arguments}`"));
+                }
+                self.align(1 << exponent)?
+            }
+            "balign" => {
+                let alignment = parse_size(first(arguments))?;
+                self.align(alignment.try_into().map_err(|_| "Invalid alignment")?)?
+            }
+            "byte" => self.values(arguments, 1)?,
+            "half" | "short" | "2byte" => self.values(arguments, 2)?,
+            "word" | "long" | "4byte" => self.values(arguments, 4)?,
+            "zero" | "space" | "skip" => {
+                let size = parse_size(arguments)?;
+                let size = usize::try_from(size).map_err(|_| format!("Invalid size {size}"))?;
+                let section = self.current();
+                section.data.resize(section.data.len() + size, 0);
+            }
+            "string" | "asciz" | "ascii" => {
+                for string in split_operands(arguments) {
+                    let mut bytes = parse_string(string)?;
+                    if name != "ascii" {
+                        bytes.push(0);
+                    }
+                    self.data(&bytes)?;
+                }
+            }
+            // nothing reads these
+            "file" | "ident" | "attribute" | "addrsig" | "addrsig_sym" => (),
+            _ if name.starts_with("cfi_") => (),
+            _ => return Err(format!("Unknown directive `.{name}`")),
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Object, String> {
+        self.relax_branches();
+
+        let mut object = Object {
+            flags: self.flags,
+            sections: self.sections,
+            symbols: Vec::new(),
+        };
+
+        for index in 0..object.sections.len() {
+            object.symbols.push(Symbol {
+                name: String::new(),
+                binding: Binding::Local,
+                kind: SymbolKind::Section,
+                section: Some(index),
+                value: 0,
+                size: 0,
+            });
+        }
+
+        let mut symbol_index: HashMap<String, usize> = HashMap::new();
+        let add_symbol =
+            |object: &mut Object, symbol_index: &mut HashMap<String, usize>, symbol: Symbol| {
+                symbol_index.insert(symbol.name.clone(), object.symbols.len());
+                object.symbols.push(symbol);
+            };
+
+        // .L labels are for this object only and stay out of the symbol
+        // table. references to one are to its section plus an offset
+        // instead, which the linker moves along when relaxing removes code
+        let local = |name: &str| {
+            let index = *self
+                .label_index
+                .get(name)
+                .filter(|_| name.starts_with(".L"))?;
+            let (_, section, offset) = self.labels[index];
+            Some((section, offset))
+        };
+
+        // branches within the section in .option norelax code are resolved
+        // here, unless there is alignment padding in between that the
+        // linker may shrink. nothing in between those is relaxed, as the
+        // linker has no relocation left to fix them up with
+        let mut resolved = vec![false; self.pending.len()];
+        let mut spans = Vec::new();
+        for (index, pending) in self.pending.iter().enumerate() {
+            let Some((section, offset)) = local(&pending.fixup.symbol) else {
+                continue;
+            };
+
+            let branch = matches!(
+                pending.fixup.kind,
+                RelocationKind::Branch | RelocationKind::Jal
+            );
+            if pending.relax || !branch || pending.section != section {
+                continue;
+            }
+
+            let (start, end) = (pending.offset.min(offset), pending.offset.max(offset));
+            let padded = object.sections[section]
+                .relocations
+                .iter()
+                .any(|relocation| {
+                    relocation.kind == RelocationKind::Align
+                        && (start..end).contains(&relocation.offset)
+                });
+
+            if !padded {
+                resolved[index] = true;
+                spans.push((section, start..end));
+            }
+        }
+
+        let mut pending = self.pending;
+        for pending in pending.iter_mut() {
+            if spans.iter().any(|(section, span)| {
+                pending.section == *section && span.contains(&pending.offset)
+            }) {
+                pending.relax = false;
+            }
+        }
+
+        // where the size of a symbol ends, for the ones it is not a number
+        let end = |size: &Size| match size {
+            Size::Fixed(_) => None,
+            Size::Until(section, offset) => Some((*section, *offset)),
+            Size::To(label) => {
+                let (_, section, offset) = self.labels[*self.label_index.get(label)?];
+                Some((section, offset))
+            }
+        };
+
+        for (name, section, value) in self.labels.iter() {
+            if name.starts_with(".L") {
+                continue;
+            }
+
+            let size = match self.sizes.get(name) {
+                None => 0,
+                Some(Size::Fixed(size)) => *size,
+                Some(size) => match end(size) {
+                    Some((end_section, end)) if end_section == *section && end >= *value => {
+                        end - value
+                    }
+                    _ => return Err(format!("Invalid size of `{name}`")),
+                },
+            };
+
+            let symbol = Symbol {
+                name: name.clone(),
+                binding: self.bindings.get(name).copied().unwrap_or(Binding::Local),
+                kind: self.kinds.get(name).copied().unwrap_or(SymbolKind::None),
+                section: Some(*section),
+                value: *value,
+                size,
+            };
+            add_symbol(&mut object, &mut symbol_index, symbol);
+        }
+
+        let undefined = |name: &str| Symbol {
+            name: name.to_string(),
+            binding: match self.bindings.get(name) {
+                Some(Binding::Weak) => Binding::Weak,
+                _ => Binding::Global,
+            },
+            kind: SymbolKind::None,
+            section: None,
+            value: 0,
+            size: 0,
+        };
+
+        // sorted, so that the symbol table does not depend on hash order
+        let mut declared: Vec<&String> = self.bindings.keys().collect();
+        declared.sort();
+
+        for name in declared {
+            if !self.label_index.contains_key(name) && self.bindings[name] != Binding::Local {
+                add_symbol(&mut object, &mut symbol_index, undefined(name));
+            }
+        }
+
+        for (index, pending) in pending.iter().enumerate() {
+            let Fixup {
+                kind,
+                symbol: name,
+                addend,
+            } = &pending.fixup;
+            let mut addend = *addend;
+
+            let symbol = match (symbol_index.get(name), local(name)) {
+                (Some(&symbol), _) => symbol,
+                (None, Some((section, offset))) => {
+                    if resolved[index] {
+                        let relative = offset as i64 + addend - pending.offset as i64;
+                        let (fits, bits) = match kind {
+                            RelocationKind::Branch => {
+                                (fits_signed(relative, 13), b_type(relative as u32))
+                            }
+                            _ => (fits_signed(relative, 21), j_type(relative as u32)),
+                        };
+                        if !fits {
+                            return Err(format!("Branch to `{name}` is out of range"));
+                        }
+
+                        let data = &mut object.sections[section].data;
+                        let at = pending.offset as usize;
+                        let word = u32::from_le_bytes([
+                            data[at],
+                            data[at + 1],
+                            data[at + 2],
+                            data[at + 3],
+                        ]);
+                        data[at..at + 4].copy_from_slice(&(word | bits).to_le_bytes());
+                        continue;
+                    }
+
+                    // the section symbols come first, in the order of the
+                    // sections
+                    addend += offset as i64;
+                    section
+                }
+                (None, None) if name.starts_with(".L") => {
+                    return Err(format!("Undefined label `{name}`"));
+                }
+                (None, None) => {
+                    add_symbol(&mut object, &mut symbol_index, undefined(name));
+                    object.symbols.len() - 1
+                }
+            };
+
+            let relocations = &mut object.sections[pending.section].relocations;
+            relocations.push(Relocation {
+                offset: pending.offset,
+                kind: *kind,
+                symbol: Some(symbol),
+                addend: addend as i32,
+            });
+
+            let relaxable = matches!(
+                kind,
+                RelocationKind::Call
+                    | RelocationKind::Hi20
+                    | RelocationKind::Lo12I
+                    | RelocationKind::Lo12S
+            );
+            if pending.relax && relaxable {
+                relocations.push(Relocation {
+                    offset: pending.offset,
+                    kind: RelocationKind::Relax,
+                    symbol: None,
+                    addend: 0,
+                });
+            }
+        }
+
+        // the linker wants the relocations of a section in order
+        for section in object.sections.iter_mut() {
+            section
+                .relocations
+                .sort_by_key(|relocation| relocation.offset);
+        }
+
+        Ok(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    // the expected words are the ones the gnu assembler produces for the
+    // same lines
+    use crate::{
+        assemble,
+        elf::{
+            Object, RelocationKind, SymbolKind, EF_RISCV_FLOAT_ABI_DOUBLE,
+            EF_RISCV_FLOAT_ABI_SINGLE,
+        },
+        Options, Target,
+    };
+
+    fn object(source: &str) -> Object {
+        let bytes = assemble(source, &Options::default()).unwrap();
+        Object::parse(&bytes).unwrap()
+    }
+
+    fn words(object: &Object) -> Vec<u32> {
+        object.sections[0]
+            .data
+            .chunks(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn local_labels_stay_out_of_the_symbol_table() {
+        let object = object(
+            "f:
+                 beqz a0, .Lend
+             .Lloop:
+                 addi a0, a0, -1
+                 bnez a0, .Lloop
+                 call g
+             .Lend:
+                 call g
+                 j .Lloop
+                 ret",
+        );
+
+        let names: Vec<_> = object.symbols.iter().map(|symbol| &symbol.name).collect();
+        assert!(
+            names.iter().all(|name| !name.starts_with(".L")),
+            "{names:?}"
+        );
+        assert!(names.iter().any(|name| *name == "f"));
+        assert!(names.iter().any(|name| *name == "g"));
+
+        // the branches are to the section, so that the linker can still
+        // relax the calls in between
+        assert_eq!(
+            words(&object),
+            [
+                0x0005_0063,
+                0xfff5_0513,
+                0x0005_1063,
+                0x0000_0097,
+                0x0000_80e7,
+                0x0000_0097,
+                0x0000_80e7,
+                0x0000_006f,
+                0x0000_8067,
+            ]
+        );
+
+        let relocations: Vec<_> = object.sections[0]
+            .relocations
+            .iter()
+            .map(|relocation| {
+                (
+                    relocation.offset,
+                    relocation.kind,
+                    relocation.symbol,
+                    relocation.addend,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            relocations,
+            [
+                (0x0, RelocationKind::Branch, Some(0), 0x14),
+                (0x8, RelocationKind::Branch, Some(0), 0x4),
+                (0xc, RelocationKind::Call, relocations[2].2, 0),
+                (0xc, RelocationKind::Relax, None, 0),
+                (0x14, RelocationKind::Call, relocations[4].2, 0),
+                (0x14, RelocationKind::Relax, None, 0),
+                (0x1c, RelocationKind::Jal, Some(0), 0x4),
+            ]
+        );
+    }
+
+    #[test]
+    fn local_branches_without_relaxation_are_resolved_in_place() {
+        let object = object(
+            "    .option norelax
+             f:
+                 beqz a0, .Lend
+                 call g
+             .Lend:
+                 ret",
+        );
+
+        assert_eq!(
+            words(&object),
+            [0x0005_0663, 0x0000_0097, 0x0000_80e7, 0x0000_8067]
+        );
+
+        let relocations: Vec<_> = object.sections[0]
+            .relocations
+            .iter()
+            .map(|relocation| (relocation.offset, relocation.kind))
+            .collect();
+        assert_eq!(relocations, [(0x4, RelocationKind::Call)]);
+    }
+
+    #[test]
+    fn sizes_and_types_survive_the_round_trip() {
+        let object = object(
+            "    .globl f
+                 .type f, @function
+             f:
+                 addi a0, a0, 1
+                 ret
+                 .size f, . - f
+                 .type g, %function
+             g:
+                 ret
+             .Lg_end:
+                 .size g, .Lg_end-g
+                 .data
+                 .type value, @object
+             value:
+                 .word 1, 2
+                 .size value, 8",
+        );
+
+        let symbol = |name: &str| {
+            let symbol = object
+                .symbols
+                .iter()
+                .find(|symbol| symbol.name == name)
+                .unwrap();
+            (symbol.kind, symbol.size)
+        };
+
+        assert_eq!(symbol("f"), (SymbolKind::Function, 8));
+        assert_eq!(symbol("g"), (SymbolKind::Function, 4));
+        assert_eq!(symbol("value"), (SymbolKind::Object, 8));
+    }
+
+    #[test]
+    fn references_to_local_labels_in_other_sections_use_the_section() {
+        let object = object(
+            "    .data
+                 .word 1
+             .Lvalue:
+                 .word 2
+                 .text
+             f:
+                 lui a0, %hi(.Lvalue)
+                 lw a0, %lo(.Lvalue)(a0)
+                 ret",
+        );
+
+        assert!(object
+            .symbols
+            .iter()
+            .all(|symbol| !symbol.name.starts_with(".L")));
+
+        let relocations = object.sections[0]
+            .relocations
+            .iter()
+            .filter(|relocation| relocation.kind != RelocationKind::Relax);
+
+        for relocation in relocations {
+            let symbol = &object.symbols[relocation.symbol.unwrap()];
+            assert_eq!(symbol.section, Some(1));
+            assert_eq!(relocation.addend, 4);
+        }
+    }
+
+    #[test]
+    fn branches_out_of_range_jump_instead() {
+        let object = object(
+            "    .option norelax
+             f:
+                 beqz a0, .Lfar
+                 .skip 8192
+                 .balign 16
+             .Lfar:
+                 ret",
+        );
+        let words = words(&object);
+
+        // the opposite branch skips over a jal that goes the whole way, and the
+        // padding is redone for the four bytes the jal adds
+        assert_eq!(words[..2], [0x0005_1463, 0x00c0_206f]);
+        assert_eq!(words.len(), 2 + 8192 / 4 + 2 + 1);
+        assert_eq!(words[words.len() - 1], 0x0000_8067);
+    }
+
+    // every line is assembled on its own and checked against the words it
+    // stands for
+    fn assert_encodings(lines: &[(&str, &[u32])]) {
+        for (line, expected) in lines {
+            assert_eq!(words(&object(line)), *expected, "{line}");
+        }
+    }
+
+    #[test]
+    fn base_multiply_and_csr_instructions() {
+        assert_encodings(&[
+            ("add a0, a1, a2", &[0x00c5_8533]),
+            ("sub t0, t1, t2", &[0x4073_02b3]),
+            ("sll s0, s1, a3", &[0x00d4_9433]),
+            ("slt a4, a5, a6", &[0x0107_a733]),
+            ("sltu a7, s2, s3", &[0x0139_38b3]),
+            ("xor s4, s5, s6", &[0x016a_ca33]),
+            ("srl s7, s8, s9", &[0x019c_5bb3]),
+            ("sra s10, s11, t3", &[0x41cd_dd33]),
+            ("or t4, t5, t6", &[0x01ff_6eb3]),
+            ("and ra, sp, gp", &[0x0031_70b3]),
+            ("mul a0, a1, a2", &[0x02c5_8533]),
+            ("mulh a0, a1, a2", &[0x02c5_9533]),
+            ("mulhsu a0, a1, a2", &[0x02c5_a533]),
+            ("mulhu a0, a1, a2", &[0x02c5_b533]),
+            ("div a0, a1, a2", &[0x02c5_c533]),
+            ("divu a0, a1, a2", &[0x02c5_d533]),
+            ("rem a0, a1, a2", &[0x02c5_e533]),
+            ("remu a0, a1, a2", &[0x02c5_f533]),
+            ("addi a0, a1, -2048", &[0x8005_8513]),
+            ("slti a0, a1, 2047", &[0x7ff5_a513]),
+            ("sltiu a0, a1, 1", &[0x0015_b513]),
+            ("xori a0, a1, -1", &[0xfff5_c513]),
+            ("ori a0, a1, 0x7f", &[0x07f5_e513]),
+            ("andi a0, a1, 255", &[0x0ff5_f513]),
+            ("slli a0, a1, 31", &[0x01f5_9513]),
+            ("srli a0, a1, 1", &[0x0015_d513]),
+            ("srai a0, a1, 7", &[0x4075_d513]),
+            ("lb a0, -1(sp)", &[0xfff1_0503]),
+            ("lh a0, 2(sp)", &[0x0021_1503]),
+            ("lw a0, 4(sp)", &[0x0041_2503]),
+            ("lbu a0, 2047(sp)", &[0x7ff1_4503]),
+            ("lhu a0, -2048(sp)", &[0x8001_5503]),
+            ("sb a0, -1(sp)", &[0xfea1_0fa3]),
+            ("sh a0, 2(sp)", &[0x00a1_1123]),
+            ("sw ra, 12(sp)", &[0x0011_2623]),
+            ("lui a0, 0xfffff", &[0xffff_f537]),
+            ("auipc a0, 1", &[0x0000_1517]),
+            ("jalr ra", &[0x0000_80e7]),
+            ("jalr t0, 8(a1)", &[0x0085_82e7]),
+            ("jr t0", &[0x0002_8067]),
+            ("ret", &[0x0000_8067]),
+            ("li a0, 2047", &[0x7ff0_0513]),
+            ("li a0, -2048", &[0x8000_0513]),
+            ("li a0, 0x12345678", &[0x1234_5537, 0x6785_0513]),
+            ("li a0, 0x80000000", &[0x8000_0537]),
+            ("li a0, 0x1000", &[0x0000_1537]),
+            ("nop", &[0x0000_0013]),
+            ("mv a0, a1", &[0x0005_8513]),
+            ("not a0, a1", &[0xfff5_c513]),
+            ("neg a0, a1", &[0x40b0_0533]),
+            ("seqz a0, a1", &[0x0015_b513]),
+            ("snez a0, a1", &[0x00b0_3533]),
+            ("sltz a0, a1", &[0x0005_a533]),
+            ("sgtz a0, a1", &[0x00b0_2533]),
+            ("csrrw a0, mstatus, a1", &[0x3005_9573]),
+            ("csrrs a0, mie, zero", &[0x3040_2573]),
+            ("csrrc a0, mtvec, a1", &[0x3055_b573]),
+            ("csrrwi a0, mscratch, 5", &[0x3402_d573]),
+            ("csrrsi a0, 0x300, 31", &[0x300f_e573]),
+            ("csrrci zero, mip, 1", &[0x3440_f073]),
+            ("csrr a0, mepc", &[0x3410_2573]),
+            ("csrw mtvec, a0", &[0x3055_1073]),
+            ("csrs mie, a1", &[0x3045_a073]),
+            ("csrc mstatus, a2", &[0x3006_3073]),
+            ("csrwi mscratch, 3", &[0x3401_d073]),
+            ("csrsi mstatus, 8", &[0x3004_6073]),
+            ("csrci mstatus, 8", &[0x3004_7073]),
+            ("ecall", &[0x0000_0073]),
+            ("ebreak", &[0x0010_0073]),
+            ("mret", &[0x3020_0073]),
+            ("sret", &[0x1020_0073]),
+            ("wfi", &[0x1050_0073]),
+        ]);
+    }
+
+    #[test]
+    fn fences_and_atomics() {
+        assert_encodings(&[
+            ("fence", &[0x0ff0_000f]),
+            ("fence rw, rw", &[0x0330_000f]),
+            ("fence r, w", &[0x0210_000f]),
+            ("fence iorw, o", &[0x0f40_000f]),
+            ("fence.i", &[0x0000_100f]),
+            ("fence.tso", &[0x8330_000f]),
+            ("lr.w a0, (a1)", &[0x1005_a52f]),
+            ("lr.w.aq a0, (a1)", &[0x1405_a52f]),
+            ("sc.w a0, a2, (a1)", &[0x18c5_a52f]),
+            ("sc.w.rl a0, a2, (a1)", &[0x1ac5_a52f]),
+            ("amoswap.w a0, a2, (a1)", &[0x08c5_a52f]),
+            ("amoadd.w.aqrl a0, a2, 0(a1)", &[0x06c5_a52f]),
+            ("amoxor.w a0, a2, (a1)", &[0x20c5_a52f]),
+            ("amoand.w a0, a2, (a1)", &[0x60c5_a52f]),
+            ("amoor.w a0, a2, (a1)", &[0x40c5_a52f]),
+            ("amomin.w a0, a2, (a1)", &[0x80c5_a52f]),
+            ("amomax.w a0, a2, (a1)", &[0xa0c5_a52f]),
+            ("amominu.w a0, a2, (a1)", &[0xc0c5_a52f]),
+            ("amomaxu.w.aq t0, t1, (t2)", &[0xe463_a2af]),
+        ]);
+
+        for line in [
+            "fence rr, w",
+            "fence x, w",
+            "fence 1, 2",
+            "lr.w a0, 4(a1)",
+            "sc.w a0, (a1)",
+        ] {
+            let error = assemble(line, &Options::default()).unwrap_err();
+            assert!(error.contains("Invalid operands"), "{line}: {error}");
+        }
+    }
+
+    #[test]
+    fn pc_relative_halves() {
+        let object = object(
+            ".Lpcrel:
+                 auipc a0, %pcrel_hi(value)
+                 lw a1, %pcrel_lo(.Lpcrel)(a0)
+                 sw a1, %pcrel_lo(.Lpcrel)(a0)
+             1:
+                 auipc a0, %pcrel_hi(value+8)
+                 addi a0, a0, %pcrel_lo(1b)",
+        );
+
+        assert_eq!(
+            words(&object),
+            [
+                0x0000_0517,
+                0x0005_2583,
+                0x00b5_2023,
+                0x0000_0517,
+                0x0005_0513
+            ]
+        );
+
+        // the lower halves are relative to the auipc of their label, which is
+        // in the text section
+        let relocations: Vec<_> = object.sections[0]
+            .relocations
+            .iter()
+            .map(|relocation| {
+                let symbol = &object.symbols[relocation.symbol.unwrap()];
+                (
+                    relocation.offset,
+                    relocation.kind,
+                    symbol.name.as_str(),
+                    relocation.addend,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            relocations,
+            [
+                (0x0, RelocationKind::PcrelHi20, "value", 0),
+                (0x4, RelocationKind::PcrelLo12I, "", 0),
+                (0x8, RelocationKind::PcrelLo12S, "", 0),
+                (0xc, RelocationKind::PcrelHi20, "value", 8),
+                (0x10, RelocationKind::PcrelLo12I, "", 0xc),
+            ]
+        );
+    }
+
+    // nothing is compressed, even when the target has the c extension
+    #[test]
+    fn flags_follow_the_abi_and_what_is_emitted() {
+        let flags = |march: &str, mabi: &str| {
+            let options = Options {
+                target: Target::new(march, mabi).unwrap(),
+                ..Options::default()
+            };
+            let bytes = assemble("ret", &options).unwrap();
+            Object::parse(&bytes).unwrap().flags
+        };
+
+        assert_eq!(flags("rv32imafdc", "ilp32"), 0);
+        assert_eq!(flags("rv32imac", "ilp32"), 0);
+        assert_eq!(flags("rv32imafc", "ilp32f"), EF_RISCV_FLOAT_ABI_SINGLE);
+        assert_eq!(flags("rv32gc", "ilp32d"), EF_RISCV_FLOAT_ABI_DOUBLE);
+    }
+}
@@ -0,0 +1,613 @@
+use crate::{
+    elf::RelocationKind,
+    riscv::values::{Csr, Register},
+};
+
+use super::operand::Operand;
+
+// a symbol whose address the linker fills into an instruction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixup {
+    pub kind: RelocationKind,
+    pub symbol: String,
+    pub addend: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encoded {
+    pub word: u32,
+    pub fixup: Option<Fixup>,
+}
+
+const OP: u32 = 0x33;
+const OP_IMM: u32 = 0x13;
+const LOAD: u32 = 0x03;
+const STORE: u32 = 0x23;
+const BRANCH: u32 = 0x63;
+const LUI: u32 = 0x37;
+const AUIPC: u32 = 0x17;
+const JAL: u32 = 0x6f;
+const JALR: u32 = 0x67;
+const SYSTEM: u32 = 0x73;
+const AMO: u32 = 0x2f;
+const MISC_MEM: u32 = 0x0f;
+
+fn plain(word: u32) -> Encoded {
+    Encoded { word, fixup: None }
+}
+
+fn fixed(word: u32, kind: RelocationKind, symbol: &str, addend: i64) -> Encoded {
+    Encoded {
+        word,
+        fixup: Some(Fixup {
+            kind,
+            symbol: symbol.to_string(),
+            addend,
+        }),
+    }
+}
+
+fn number(register: &Register) -> u32 {
+    register.clone() as u32
+}
+
+fn r_type(funct3: u32, funct7: u32, rd: &Register, rs1: &Register, rs2: &Register) -> u32 {
+    (funct7 << 25)
+        | (number(rs2) << 20)
+        | (number(rs1) << 15)
+        | (funct3 << 12)
+        | (number(rd) << 7)
+        | OP
+}
+
+fn i_type(opcode: u32, funct3: u32, rd: &Register, rs1: &Register, immediate: i64) -> u32 {
+    i_immediate(immediate as u32)
+        | (number(rs1) << 15)
+        | (funct3 << 12)
+        | (number(rd) << 7)
+        | opcode
+}
+
+fn s_type(funct3: u32, rs2: &Register, rs1: &Register, immediate: i64) -> u32 {
+    s_immediate(immediate as u32)
+        | (number(rs2) << 20)
+        | (number(rs1) << 15)
+        | (funct3 << 12)
+        | STORE
+}
+
+fn u_type(opcode: u32, rd: &Register, upper: u32) -> u32 {
+    (upper << 12) | (number(rd) << 7) | opcode
+}
+
+// the immediate of an instruction, put where its format has the bits
+pub fn i_immediate(value: u32) -> u32 {
+    (value & 0xfff) << 20
+}
+
+pub fn s_immediate(value: u32) -> u32 {
+    ((value >> 5 & 0x7f) << 25) | ((value & 0x1f) << 7)
+}
+
+// the offset of a branch or a jal
+pub fn b_type(offset: u32) -> u32 {
+    ((offset >> 12 & 0x1) << 31)
+        | ((offset >> 5 & 0x3f) << 25)
+        | ((offset >> 1 & 0xf) << 8)
+        | ((offset >> 11 & 0x1) << 7)
+}
+
+pub fn j_type(offset: u32) -> u32 {
+    ((offset >> 20 & 0x1) << 31)
+        | ((offset >> 1 & 0x3ff) << 21)
+        | ((offset >> 11 & 0x1) << 20)
+        | (offset & 0xff000)
+}
+
+// lr.w, sc.w and the amo*.w of the a extension, rs2 is zero for lr.w
+fn amo_type(funct5: u32, ordering: u32, rd: &Register, rs1: &Register, rs2: &Register) -> u32 {
+    (funct5 << 27)
+        | (ordering << 25)
+        | (number(rs2) << 20)
+        | (number(rs1) << 15)
+        | (2 << 12)
+        | (number(rd) << 7)
+        | AMO
+}
+
+fn csr_type(funct3: u32, rd: &Register, csr: u32, rs1: u32) -> u32 {
+    (csr << 20) | (rs1 << 15) | (funct3 << 12) | (number(rd) << 7) | SYSTEM
+}
+
+// the low 12 bits sign extended, and what has to go into the upper 20 bits
+// for the two to add up to the value
+fn split(value: i32) -> (u32, i64) {
+    let low = (value << 20) >> 20;
+    let high = (value.wrapping_sub(low) as u32) >> 12;
+    (high, low as i64)
+}
+
+pub fn fits_signed(value: i64, bits: u32) -> bool {
+    let limit = 1 << (bits - 1);
+    (-limit..limit).contains(&value)
+}
+
+fn register_operation(mnemonic: &str) -> Option<(u32, u32)> {
+    let operation = match mnemonic {
+        "add" => (0, 0),
+        "sub" => (0, 0x20),
+        "sll" => (1, 0),
+        "slt" => (2, 0),
+        "sltu" => (3, 0),
+        "xor" => (4, 0),
+        "srl" => (5, 0),
+        "sra" => (5, 0x20),
+        "or" => (6, 0),
+        "and" => (7, 0),
+        "mul" => (0, 1),
+        "mulh" => (1, 1),
+        "mulhsu" => (2, 1),
+        "mulhu" => (3, 1),
+        "div" => (4, 1),
+        "divu" => (5, 1),
+        "rem" => (6, 1),
+        "remu" => (7, 1),
+        _ => return None,
+    };
+
+    Some(operation)
+}
+
+fn immediate_operation(mnemonic: &str) -> Option<u32> {
+    let funct3 = match mnemonic {
+        "addi" => 0,
+        "slti" => 2,
+        "sltiu" => 3,
+        "xori" => 4,
+        "ori" => 6,
+        "andi" => 7,
+        _ => return None,
+    };
+
+    Some(funct3)
+}
+
+fn shift_operation(mnemonic: &str) -> Option<(u32, u32)> {
+    match mnemonic {
+        "slli" => Some((1, 0)),
+        "srli" => Some((5, 0)),
+        "srai" => Some((5, 0x20)),
+        _ => None,
+    }
+}
+
+fn load_width(mnemonic: &str) -> Option<u32> {
+    match mnemonic {
+        "lb" => Some(0),
+        "lh" => Some(1),
+        "lw" => Some(2),
+        "lbu" => Some(4),
+        "lhu" => Some(5),
+        _ => None,
+    }
+}
+
+fn store_width(mnemonic: &str) -> Option<u32> {
+    match mnemonic {
+        "sb" => Some(0),
+        "sh" => Some(1),
+        "sw" => Some(2),
+        _ => None,
+    }
+}
+
+fn branch_condition(mnemonic: &str) -> Option<u32> {
+    match mnemonic {
+        "beq" => Some(0),
+        "bne" => Some(1),
+        "blt" => Some(4),
+        "bge" => Some(5),
+        "bltu" => Some(6),
+        "bgeu" => Some(7),
+        _ => None,
+    }
+}
+
+// the branches against zero and the ones with their operands swapped
+fn branch_pseudo<'o>(
+    mnemonic: &str,
+    operands: &'o [Operand],
+) -> Option<(&'static str, Register, Register, &'o Operand)> {
+    let (real, rs1, rs2, target) = match (mnemonic, operands) {
+        ("beqz", [Operand::Register(rs), target]) => ("beq", rs, &Register::Zero, target),
+        ("bnez", [Operand::Register(rs), target]) => ("bne", rs, &Register::Zero, target),
+        ("blez", [Operand::Register(rs), target]) => ("bge", &Register::Zero, rs, target),
+        ("bgez", [Operand::Register(rs), target]) => ("bge", rs, &Register::Zero, target),
+        ("bltz", [Operand::Register(rs), target]) => ("blt", rs, &Register::Zero, target),
+        ("bgtz", [Operand::Register(rs), target]) => ("blt", &Register::Zero, rs, target),
+        ("bgt", [Operand::Register(rs), Operand::Register(rt), target]) => ("blt", rt, rs, target),
+        ("ble", [Operand::Register(rs), Operand::Register(rt), target]) => ("bge", rt, rs, target),
+        ("bgtu", [Operand::Register(rs), Operand::Register(rt), target]) => {
+            ("bltu", rt, rs, target)
+        }
+        ("bleu", [Operand::Register(rs), Operand::Register(rt), target]) => {
+            ("bgeu", rt, rs, target)
+        }
+        _ => return None,
+    };
+
+    Some((real, rs1.clone(), rs2.clone(), target))
+}
+
+fn csr_operation(mnemonic: &str) -> Option<u32> {
+    match mnemonic {
+        "csrrw" => Some(1),
+        "csrrs" => Some(2),
+        "csrrc" => Some(3),
+        "csrrwi" => Some(5),
+        "csrrsi" => Some(6),
+        "csrrci" => Some(7),
+        _ => None,
+    }
+}
+
+// csrr and friends, which leave out the destination or the source
+fn csr_pseudo(mnemonic: &str) -> Option<&'static str> {
+    match mnemonic {
+        "csrw" => Some("csrrw"),
+        "csrs" => Some("csrrs"),
+        "csrc" => Some("csrrc"),
+        "csrwi" => Some("csrrwi"),
+        "csrsi" => Some("csrrsi"),
+        "csrci" => Some("csrrci"),
+        _ => None,
+    }
+}
+
+fn system(mnemonic: &str) -> Option<u32> {
+    match mnemonic {
+        "ecall" => Some(0x0000_0073),
+        "ebreak" => Some(0x0010_0073),
+        "uret" => Some(0x0020_0073),
+        "sret" => Some(0x1020_0073),
+        "mret" => Some(0x3020_0073),
+        "wfi" => Some(0x1050_0073),
+        "fence.i" => Some(0x0000_100f),
+        "fence.tso" => Some(0x8330_000f),
+        _ => None,
+    }
+}
+
+// the word's amo operation and its aq and rl bits, from a mnemonic like
+// amoadd.w.aqrl
+fn atomic_operation(mnemonic: &str) -> Option<(u32, u32)> {
+    let (operation, ordering) = match mnemonic.rsplit_once('.') {
+        Some((operation, "aq")) => (operation, 2),
+        Some((operation, "rl")) => (operation, 1),
+        Some((operation, "aqrl")) => (operation, 3),
+        _ => (mnemonic, 0),
+    };
+
+    let funct5 = match operation {
+        "lr.w" => 0x02,
+        "sc.w" => 0x03,
+        "amoswap.w" => 0x01,
+        "amoadd.w" => 0x00,
+        "amoxor.w" => 0x04,
+        "amoand.w" => 0x0c,
+        "amoor.w" => 0x08,
+        "amomin.w" => 0x10,
+        "amomax.w" => 0x14,
+        "amominu.w" => 0x18,
+        "amomaxu.w" => 0x1c,
+        _ => return None,
+    };
+
+    Some((funct5, ordering))
+}
+
+// what a fence orders before and after it, a set like rw out of i, o, r
+// and w
+fn fence_set(operand: &Operand) -> Option<u32> {
+    let Operand::Symbol(name, 0) = operand else {
+        return None;
+    };
+
+    name.chars().try_fold(0, |set, c| {
+        let bit = match c {
+            'i' => 8,
+            'o' => 4,
+            'r' => 2,
+            'w' => 1,
+            _ => return None,
+        };
+        (set & bit == 0).then_some(set | bit)
+    })
+}
+
+fn csr_number(operand: &Operand) -> Option<u32> {
+    match operand {
+        Operand::Symbol(name, 0) => Csr::from_name(name).map(|Csr(number)| number as u32),
+        Operand::Number(number @ 0..=0xfff) => Some(*number as u32),
+        _ => None,
+    }
+}
+
+// the pc relative counterpart of a %lo relocation
+fn pcrel_low(kind: RelocationKind) -> RelocationKind {
+    match kind {
+        RelocationKind::Lo12S => RelocationKind::PcrelLo12S,
+        _ => RelocationKind::PcrelLo12I,
+    }
+}
+
+pub fn encode(mnemonic: &str, operands: &[Operand]) -> Result<Vec<Encoded>, String> {
+    use Operand::{Hi, Lo, Memory, Number, PcrelHi, PcrelLo, Register as Reg, Symbol};
+
+    let invalid = || format!("Invalid operands for `{mnemonic}`");
+    let out_of_range = |value: &i64| format!("Immediate {value} is out of range for `{mnemonic}`");
+
+    // the 12 bit immediate of i and s type instructions, or the relocation
+    // that fills it in
+    let low = |operand: &Operand, kind: RelocationKind| match operand {
+        Number(value) if fits_signed(*value, 12) => Ok((*value, None)),
+        Number(value) => Err(out_of_range(value)),
+        Lo(symbol, addend) => Ok((0, Some((kind, symbol.clone(), *addend)))),
+        PcrelLo(label, addend) => Ok((0, Some((pcrel_low(kind), label.clone(), *addend)))),
+        _ => Err(invalid()),
+    };
+
+    let with = |word: u32, fixup: Option<(RelocationKind, String, i64)>| match fixup {
+        Some((kind, symbol, addend)) => fixed(word, kind, &symbol, addend),
+        None => plain(word),
+    };
+
+    if let Some((funct3, funct7)) = register_operation(mnemonic) {
+        let [Reg(rd), Reg(rs1), Reg(rs2)] = operands else {
+            return Err(invalid());
+        };
+        return Ok(vec![plain(r_type(funct3, funct7, rd, rs1, rs2))]);
+    }
+
+    if let Some(funct3) = immediate_operation(mnemonic) {
+        let [Reg(rd), Reg(rs1), immediate] = operands else {
+            return Err(invalid());
+        };
+        let (immediate, fixup) = low(immediate, RelocationKind::Lo12I)?;
+        return Ok(vec![with(
+            i_type(OP_IMM, funct3, rd, rs1, immediate),
+            fixup,
+        )]);
+    }
+
+    if let Some((funct3, funct7)) = shift_operation(mnemonic) {
+        let [Reg(rd), Reg(rs1), Number(amount)] = operands else {
+            return Err(invalid());
+        };
+        if !(0..32).contains(amount) {
+            return Err(out_of_range(amount));
+        }
+        let word = i_type(OP_IMM, funct3, rd, rs1, (funct7 << 5) as i64 | amount);
+        return Ok(vec![plain(word)]);
+    }
+
+    if let Some(width) = load_width(mnemonic) {
+        let [Reg(rd), Memory(offset, rs1)] = operands else {
+            return Err(invalid());
+        };
+        let (offset, fixup) = low(offset, RelocationKind::Lo12I)?;
+        return Ok(vec![with(i_type(LOAD, width, rd, rs1, offset), fixup)]);
+    }
+
+    if let Some(width) = store_width(mnemonic) {
+        let [Reg(rs2), Memory(offset, rs1)] = operands else {
+            return Err(invalid());
+        };
+        let (offset, fixup) = low(offset, RelocationKind::Lo12S)?;
+        return Ok(vec![with(s_type(width, rs2, rs1, offset), fixup)]);
+    }
+
+    // the offsets of branches and jumps are left to the linker, which knows
+    // where the target ends up
+    if let Some(funct3) = branch_condition(mnemonic) {
+        let [Reg(rs1), Reg(rs2), Symbol(target, addend)] = operands else {
+            return Err(invalid());
+        };
+        let word = (number(rs2) << 20) | (number(rs1) << 15) | (funct3 << 12) | BRANCH;
+        return Ok(vec![fixed(word, RelocationKind::Branch, target, *addend)]);
+    }
+
+    if let Some((real, rs1, rs2, target)) = branch_pseudo(mnemonic, operands) {
+        return encode(real, &[Reg(rs1), Reg(rs2), target.clone()]);
+    }
+
+    if let Some(funct3) = csr_operation(mnemonic) {
+        let [Reg(rd), csr, source] = operands else {
+            return Err(invalid());
+        };
+        let csr = csr_number(csr).ok_or_else(invalid)?;
+        let source = match source {
+            Reg(rs1) if funct3 < 4 => number(rs1),
+            Number(value @ 0..=31) if funct3 > 4 => *value as u32,
+            _ => return Err(invalid()),
+        };
+        return Ok(vec![plain(csr_type(funct3, rd, csr, source))]);
+    }
+
+    if let Some(real) = csr_pseudo(mnemonic) {
+        let [csr, source] = operands else {
+            return Err(invalid());
+        };
+        return encode(real, &[Reg(Register::Zero), csr.clone(), source.clone()]);
+    }
+
+    if let Some(word) = system(mnemonic) {
+        let [] = operands else {
+            return Err(invalid());
+        };
+        return Ok(vec![plain(word)]);
+    }
+
+    if let Some((funct5, ordering)) = atomic_operation(mnemonic) {
+        let (rd, rs2, address) = match operands {
+            [Reg(rd), address] if funct5 == 0x02 => (rd, &Register::Zero, address),
+            [Reg(rd), Reg(rs2), address] if funct5 != 0x02 => (rd, rs2, address),
+            _ => return Err(invalid()),
+        };
+        // the address is in a register, an offset can only be zero
+        let Memory(offset, rs1) = address else {
+            return Err(invalid());
+        };
+        if **offset != Number(0) {
+            return Err(invalid());
+        }
+        return Ok(vec![plain(amo_type(funct5, ordering, rd, rs1, rs2))]);
+    }
+
+    let encoded = match (mnemonic, operands) {
+        ("lui", [Reg(rd), Number(upper @ 0..=0xfffff)]) => {
+            vec![plain(u_type(LUI, rd, *upper as u32))]
+        }
+        ("lui", [Reg(rd), Hi(symbol, addend)]) => {
+            vec![fixed(
+                u_type(LUI, rd, 0),
+                RelocationKind::Hi20,
+                symbol,
+                *addend,
+            )]
+        }
+        ("auipc", [Reg(rd), Number(upper @ 0..=0xfffff)]) => {
+            vec![plain(u_type(AUIPC, rd, *upper as u32))]
+        }
+        ("auipc", [Reg(rd), PcrelHi(symbol, addend)]) => {
+            vec![fixed(
+                u_type(AUIPC, rd, 0),
+                RelocationKind::PcrelHi20,
+                symbol,
+                *addend,
+            )]
+        }
+
+        // what the fence orders, everything when it is left out
+        ("fence", []) => vec![plain(0x0ff0_0000 | MISC_MEM)],
+        ("fence", [predecessor, successor]) => {
+            let predecessor = fence_set(predecessor).ok_or_else(invalid)?;
+            let successor = fence_set(successor).ok_or_else(invalid)?;
+            vec![plain(predecessor << 24 | successor << 20 | MISC_MEM)]
+        }
+
+        ("jal", [Symbol(target, addend)]) => encode_jal(&Register::Ra, target, *addend),
+        ("jal", [Reg(rd), Symbol(target, addend)]) => encode_jal(rd, target, *addend),
+        ("j", [Symbol(target, addend)]) => encode_jal(&Register::Zero, target, *addend),
+        ("jalr", [Reg(rs1)]) => vec![plain(i_type(JALR, 0, &Register::Ra, rs1, 0))],
+        ("jalr", [Reg(rd), Memory(offset, rs1)]) => match offset.as_ref() {
+            Number(offset) if fits_signed(*offset, 12) => {
+                vec![plain(i_type(JALR, 0, rd, rs1, *offset))]
+            }
+            _ => return Err(invalid()),
+        },
+        ("jalr", [Reg(rd), Reg(rs1), Number(offset)]) if fits_signed(*offset, 12) => {
+            vec![plain(i_type(JALR, 0, rd, rs1, *offset))]
+        }
+        ("jr", [Reg(rs1)]) => vec![plain(i_type(JALR, 0, &Register::Zero, rs1, 0))],
+        ("ret", []) => vec![plain(i_type(JALR, 0, &Register::Zero, &Register::Ra, 0))],
+        // auipc and jalr, so that the target can be anywhere. the linker
+        // turns this into a single jal when it is close enough
+        ("call", [Symbol(target, addend)]) => {
+            encode_call(&Register::Ra, &Register::Ra, target, *addend)
+        }
+        ("tail", [Symbol(target, addend)]) => {
+            encode_call(&Register::T1, &Register::Zero, target, *addend)
+        }
+
+        ("li", [Reg(rd), Number(value)]) => {
+            if !(i32::MIN as i64..=u32::MAX as i64).contains(value) {
+                return Err(out_of_range(value));
+            }
+            encode_li(rd, *value as i32)
+        }
+        ("li" | "la" | "lla", [Reg(rd), Symbol(symbol, addend)]) => {
+            vec![
+                fixed(u_type(LUI, rd, 0), RelocationKind::Hi20, symbol, *addend),
+                fixed(
+                    i_type(OP_IMM, 0, rd, rd, 0),
+                    RelocationKind::Lo12I,
+                    symbol,
+                    *addend,
+                ),
+            ]
+        }
+
+        ("nop", []) => vec![plain(i_type(
+            OP_IMM,
+            0,
+            &Register::Zero,
+            &Register::Zero,
+            0,
+        ))],
+        ("mv", [Reg(rd), Reg(rs1)]) => vec![plain(i_type(OP_IMM, 0, rd, rs1, 0))],
+        ("not", [Reg(rd), Reg(rs1)]) => vec![plain(i_type(OP_IMM, 4, rd, rs1, -1))],
+        ("neg", [Reg(rd), Reg(rs1)]) => vec![plain(r_type(0, 0x20, rd, &Register::Zero, rs1))],
+        ("seqz", [Reg(rd), Reg(rs1)]) => vec![plain(i_type(OP_IMM, 3, rd, rs1, 1))],
+        ("snez", [Reg(rd), Reg(rs1)]) => vec![plain(r_type(3, 0, rd, &Register::Zero, rs1))],
+        ("sltz", [Reg(rd), Reg(rs1)]) => vec![plain(r_type(2, 0, rd, rs1, &Register::Zero))],
+        ("sgtz", [Reg(rd), Reg(rs1)]) => vec![plain(r_type(2, 0, rd, &Register::Zero, rs1))],
+
+        ("csrr", [Reg(rd), csr]) => {
+            return encode(
+                "csrrs",
+                &[Reg(rd.clone()), csr.clone(), Reg(Register::Zero)],
+            )
+        }
+
+        _ if is_known(mnemonic) => return Err(invalid()),
+        _ => return Err(format!("Unknown instruction `{mnemonic}`")),
+    };
+
+    Ok(encoded)
+}
+
+fn is_known(mnemonic: &str) -> bool {
+    [
+        "lui", "auipc", "fence", "jal", "j", "jalr", "jr", "ret", "call", "tail", "li", "la",
+        "lla", "nop", "mv", "not", "neg", "seqz", "snez", "sltz", "sgtz", "csrr", "beqz", "bnez",
+        "blez", "bgez", "bltz", "bgtz", "bgt", "ble", "bgtu", "bleu",
+    ]
+    .contains(&mnemonic)
+}
+
+fn encode_jal(rd: &Register, target: &str, addend: i64) -> Vec<Encoded> {
+    vec![fixed(
+        number(rd) << 7 | JAL,
+        RelocationKind::Jal,
+        target,
+        addend,
+    )]
+}
+
+fn encode_call(scratch: &Register, rd: &Register, target: &str, addend: i64) -> Vec<Encoded> {
+    vec![
+        fixed(
+            u_type(AUIPC, scratch, 0),
+            RelocationKind::Call,
+            target,
+            addend,
+        ),
+        plain(i_type(JALR, 0, rd, scratch, 0)),
+    ]
+}
+
+// a single addi when the value fits in 12 bits, lui and addi otherwise
+fn encode_li(rd: &Register, value: i32) -> Vec<Encoded> {
+    if fits_signed(value as i64, 12) {
+        return vec![plain(i_type(OP_IMM, 0, rd, &Register::Zero, value as i64))];
+    }
+
+    let (high, low) = split(value);
+    let mut encoded = vec![plain(u_type(LUI, rd, high))];
+    if low != 0 {
+        encoded.push(plain(i_type(OP_IMM, 0, rd, rd, low)));
+    }
+
+    encoded
+}
@@ -0,0 +1,189 @@
+use crate::riscv::values::Register;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Register(Register),
+    Number(i64),
+    // a label, plus an offset from it
+    Symbol(String, i64),
+    // %hi(symbol) and %lo(symbol), the halves of its absolute address
+    Hi(String, i64),
+    Lo(String, i64),
+    // %pcrel_hi(symbol), the upper half of its distance from an auipc, and
+    // %pcrel_lo(label), the lower half for the auipc at the label
+    PcrelHi(String, i64),
+    PcrelLo(String, i64),
+    // offset(register), the offset is a number, a %lo or a %pcrel_lo
+    Memory(Box<Operand>, Register),
+}
+
+pub fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+
+    let number = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits
+        .strip_prefix("0b")
+        .or_else(|| digits.strip_prefix("0B"))
+    {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+
+    Some(if negative { -number } else { number })
+}
+
+pub fn is_symbol(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with(|c: char| c.is_ascii_digit())
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'))
+}
+
+// symbol, symbol+offset or symbol-offset. numeric labels like 1f are
+// symbols too
+fn parse_symbol(text: &str) -> Option<(String, i64)> {
+    let numeric = |name: &str| {
+        name.len() > 1
+            && name.ends_with(['f', 'b'])
+            && name[..name.len() - 1].chars().all(|c| c.is_ascii_digit())
+    };
+
+    let (name, offset) = match text.rfind(['+', '-']) {
+        Some(index) if index > 0 => (
+            text[..index].trim(),
+            parse_number(&text[index..].replace('+', ""))?,
+        ),
+        _ => (text, 0),
+    };
+
+    (is_symbol(name) || numeric(name)).then(|| (name.to_string(), offset))
+}
+
+fn parse_relocation(text: &str) -> Option<Operand> {
+    let (function, rest) = text.strip_prefix('%')?.split_once('(')?;
+    let (name, offset) = parse_symbol(rest.strip_suffix(')')?.trim())?;
+
+    match function {
+        "hi" => Some(Operand::Hi(name, offset)),
+        "lo" => Some(Operand::Lo(name, offset)),
+        "pcrel_hi" => Some(Operand::PcrelHi(name, offset)),
+        "pcrel_lo" => Some(Operand::PcrelLo(name, offset)),
+        _ => None,
+    }
+}
+
+impl Operand {
+    pub fn parse(text: &str) -> Result<Operand, String> {
+        let text = text.trim();
+        let invalid = || format!("Invalid operand `{text}`");
+
+        // offset(register), where the offset can be left out or be a %lo
+        // or a %pcrel_lo
+        if let Some(start) = text.strip_suffix(')').and_then(|text| text.rfind('(')) {
+            let register = Register::from_name(text[start + 1..text.len() - 1].trim());
+
+            if let Some(register) = register {
+                let offset = match text[..start].trim() {
+                    "" => Operand::Number(0),
+                    offset => match Operand::parse(offset)? {
+                        offset @ (Operand::Number(_)
+                        | Operand::Lo(_, _)
+                        | Operand::PcrelLo(_, _)) => offset,
+                        _ => return Err(invalid()),
+                    },
+                };
+
+                return Ok(Operand::Memory(Box::new(offset), register));
+            }
+        }
+
+        if let Some(register) = Register::from_name(text) {
+            return Ok(Operand::Register(register));
+        }
+
+        if let Some(number) = parse_number(text) {
+            return Ok(Operand::Number(number));
+        }
+
+        if text.starts_with('%') {
+            return parse_relocation(text).ok_or_else(invalid);
+        }
+
+        parse_symbol(text)
+            .map(|(name, offset)| Operand::Symbol(name, offset))
+            .ok_or_else(invalid)
+    }
+}
+
+// splits at the commas that are not inside quotes or parentheses
+pub fn split_operands(text: &str) -> Vec<&str> {
+    let mut operands = Vec::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                operands.push(text[start..index].trim());
+                start = index + 1;
+            }
+            _ => (),
+        }
+    }
+
+    let last = text[start..].trim();
+    if !last.is_empty() || !operands.is_empty() {
+        operands.push(last);
+    }
+
+    operands
+}
+
+// the statements of a line of assembly, without its comment. `;` separates
+// statements and `#` starts a comment, except inside strings
+pub fn split_statements(line: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                statements.push(line[start..index].trim());
+                start = index + 1;
+            }
+            '#' if !quoted => {
+                statements.push(line[start..index].trim());
+                start = line.len();
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    statements.push(line[start..].trim());
+    statements.retain(|statement| !statement.is_empty());
+    statements
+}
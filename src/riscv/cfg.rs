@@ -0,0 +1,155 @@
+use std::collections::HashSet;
+
+use super::instruction::{Flow, Instruction};
+
+// straight line code that is only entered at the top and left at the bottom
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub label: String,
+    pub instructions: Vec<Instruction>,
+    pub successors: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionGraph {
+    pub name: String,
+    pub blocks: Vec<BasicBlock>,
+}
+
+// a block under construction, its successors are only known once the
+// block after it is
+struct Block {
+    label: String,
+    instructions: Vec<Instruction>,
+    exit: Flow,
+}
+
+fn finish(name: String, blocks: Vec<Block>) -> FunctionGraph {
+    let labels: Vec<String> = blocks.iter().map(|block| block.label.clone()).collect();
+
+    let blocks = blocks
+        .into_iter()
+        .enumerate()
+        .map(|(i, block)| {
+            let next = labels.get(i + 1).cloned();
+
+            let successors = match block.exit {
+                Flow::Next => next.into_iter().collect(),
+                Flow::Jump(target) => vec![target],
+                Flow::Branch(target) => std::iter::once(target).chain(next).collect(),
+                Flow::Exit => vec![],
+            };
+
+            BasicBlock {
+                label: block.label,
+                instructions: block.instructions,
+                successors,
+            }
+        })
+        .collect();
+
+    FunctionGraph { name, blocks }
+}
+
+// a function starts at a label in a text section that was declared global
+// or weak, and runs until the next one or until the section changes
+pub fn build(instructions: &[Instruction]) -> Vec<FunctionGraph> {
+    let mut functions = Vec::new();
+    let mut declared = HashSet::new();
+    let mut in_text = true;
+    let mut current: Option<(String, Vec<Block>)> = None;
+
+    for instruction in instructions {
+        match instruction {
+            Instruction::Symbol(directive) => {
+                let mut words = directive.split_whitespace();
+                match (words.next(), words.next()) {
+                    (Some("globl" | "weak"), Some(name)) => {
+                        declared.insert(name.to_string());
+                    }
+                    (Some("text"), _) => in_text = true,
+                    (Some("data" | "bss" | "rodata"), _) => in_text = false,
+                    (Some("section"), Some(section)) => in_text = section.starts_with(".text"),
+                    _ => {}
+                }
+
+                if !in_text {
+                    if let Some((name, blocks)) = current.take() {
+                        functions.push(finish(name, blocks));
+                    }
+                }
+            }
+            Instruction::Comment(_) => {}
+            Instruction::Label(label) if in_text && declared.contains(label) => {
+                if let Some((name, blocks)) = current.take() {
+                    functions.push(finish(name, blocks));
+                }
+
+                let entry = Block {
+                    label: label.clone(),
+                    instructions: Vec::new(),
+                    exit: Flow::Next,
+                };
+                current = Some((label.clone(), vec![entry]));
+            }
+            Instruction::Label(label) => {
+                if let Some((_, blocks)) = &mut current {
+                    let last = blocks.last_mut().expect("functions start with a block");
+
+                    // a block that was only opened by a jump before it
+                    if last.instructions.is_empty() && last.label.contains('#') {
+                        last.label = label.clone();
+                    } else {
+                        blocks.push(Block {
+                            label: label.clone(),
+                            instructions: Vec::new(),
+                            exit: Flow::Next,
+                        });
+                    }
+                }
+            }
+            _ => {
+                let Some((name, blocks)) = &mut current else {
+                    continue;
+                };
+
+                let last = blocks.last_mut().expect("functions start with a block");
+                last.instructions.push(instruction.clone());
+
+                let flow = instruction.flow();
+                if flow != Flow::Next {
+                    last.exit = flow;
+
+                    // whatever follows is only reachable through a label
+                    let label = format!("{name}#{}", blocks.len());
+                    blocks.push(Block {
+                        label,
+                        instructions: Vec::new(),
+                        exit: Flow::Next,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some((name, blocks)) = current {
+        functions.push(finish(name, blocks));
+    }
+
+    // blocks opened after the last jump of a function are left empty
+    for function in functions.iter_mut() {
+        let referenced: HashSet<String> = function
+            .blocks
+            .iter()
+            .flat_map(|block| block.successors.clone())
+            .collect();
+
+        function.blocks.retain(|block| {
+            !block.instructions.is_empty()
+                || !block.label.contains('#')
+                || referenced.contains(&block.label)
+        });
+    }
+
+    functions
+}
@@ -1,14 +1,19 @@
+use std::{num::NonZeroUsize, panic, thread};
+
 use crate::{
+    diagnostic::Diagnostics,
     types::{
         datatype::Datatype,
+        function_definition::FunctionKind,
         program::{Program, ProgramStatement},
+        span::Span,
     },
     utils::nearest_multiple::nearest_multiple,
 };
 
 use super::{
     instruction::Instruction,
-    values::{Register, RegisterWithOffset},
+    values::{Immediate, Register, RegisterWithOffset},
 };
 
 const STACK_ALIGNMENT: u32 = 16;
@@ -37,14 +42,11 @@ impl CompilerScope {
         let size = self
             .variables
             .iter()
-            .filter_map(|v| {
-                if v.location == CompilerVariableLocation::Leaked {
-                    None
-                } else {
-                    Some(v.datatype.size())
-                }
+            .filter(|v| v.location != CompilerVariableLocation::Leaked)
+            .try_fold(0u32, |size, v| {
+                size.checked_add(u32::try_from(v.datatype.size()).ok()?)
             })
-            .sum::<usize>() as u32;
+            .expect("frame sizes are checked during semantic analysis");
 
         nearest_multiple(size, STACK_ALIGNMENT) as usize
     }
@@ -57,6 +59,22 @@ impl CompilerScope {
 #[derive(Debug)]
 pub struct CompilerState {
     pub scope: CompilerScope,
+    pub function_name: String,
+    pub function_kind: FunctionKind,
+    // numbers the ifs, ternaries and logical operators of the function
+    pub label_count: usize,
+    pub diagnostics: Diagnostics,
+    // the statement being compiled, for errors found while lowering it
+    pub span: Span,
+    // callee-saved registers that inline assembly clobbers, saved below the
+    // registers every function saves
+    pub saved: Vec<Register>,
+}
+
+impl Default for CompilerState {
+    fn default() -> Self {
+        CompilerState::new()
+    }
 }
 
 impl CompilerState {
@@ -65,25 +83,96 @@ impl CompilerState {
             scope: CompilerScope {
                 variables: Vec::new(),
             },
+            function_name: String::new(),
+            function_kind: FunctionKind::Normal,
+            label_count: 0,
+            diagnostics: Diagnostics::default(),
+            span: Span::default(),
+            saved: Vec::new(),
+        }
+    }
+
+    // the stack the clobbered registers take up, kept 16 byte aligned
+    pub fn saved_size(&self) -> i32 {
+        nearest_multiple(4 * self.saved.len() as u32, STACK_ALIGNMENT) as i32
+    }
+
+    // an id for a construct that needs labels of its own, like if_3. loops
+    // and switches already got theirs from the parser
+    pub fn next_id(&mut self, construct: &str) -> String {
+        self.label_count += 1;
+        format!("{construct}_{}", self.label_count - 1)
+    }
+
+    // a label of the construct with this id, like .Lmain_if_3_end. the .L
+    // prefix keeps it a local label that never makes it to the symbol table
+    pub fn label(&self, id: &str, part: &str) -> String {
+        format!(".L{}_{id}_{part}", self.function_name)
+    }
+
+    // labels can not have a `-` in them, so case -1 is case_minus_1
+    pub fn case_label(&self, id: &str, value: i32) -> String {
+        match value {
+            0.. => self.label(id, &format!("case_{value}")),
+            _ => self.label(id, &format!("case_minus_{}", value.unsigned_abs())),
         }
     }
 
+    // interrupt handlers restore the rest of their registers here before
+    // returning from the trap
+    pub fn interrupt_return_label(&self) -> String {
+        format!(".L{}_interrupt_return", self.function_name)
+    }
+
     pub fn return_from_function(&mut self) -> Vec<Instruction> {
-        vec![
+        assert_ne!(
+            self.function_kind,
+            FunctionKind::Naked,
+            "returns from naked functions are rejected during semantic analysis"
+        );
+
+        let mut instructions = vec![
             Instruction::Comment(String::from("Shrinking stack for the locals")),
             Instruction::Addi(
                 Register::Sp,
                 Register::Sp,
                 (self.scope.variable_size() as i32).into(),
             ),
+        ];
+
+        if !self.saved.is_empty() {
+            instructions.push(Instruction::Comment(String::from(
+                "Restoring the registers inline assembly clobbers",
+            )));
+            for (index, register) in self.saved.iter().enumerate() {
+                instructions.push(Instruction::Lw(
+                    register.clone(),
+                    RegisterWithOffset((4 * index as i32).into(), Register::Sp),
+                ));
+            }
+            instructions.push(Instruction::Addi(
+                Register::Sp,
+                Register::Sp,
+                self.saved_size().into(),
+            ));
+        }
+
+        instructions.extend([
             Instruction::Comment(String::from("Returning the saved variables")),
             Instruction::Lw(Register::S1, RegisterWithOffset(24.into(), Register::Sp)),
             Instruction::Lw(Register::Fp, RegisterWithOffset(16.into(), Register::Sp)),
             Instruction::Lw(Register::Ra, RegisterWithOffset(0.into(), Register::Sp)),
             Instruction::Addi(Register::Sp, Register::Sp, 32.into()),
-            Instruction::RetP,
+            match self.function_kind {
+                FunctionKind::Interrupt(_) => {
+                    Instruction::JP(Immediate::Label(self.interrupt_return_label()))
+                }
+                _ => Instruction::RetP,
+            },
             Instruction::Comment(String::from("return finished")),
-        ]
+        ]);
+
+        instructions
     }
 
     pub fn get_variable(&self, name: &str) -> Option<CompilerVariable> {
@@ -101,6 +190,7 @@ pub trait Compile {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction>;
 }
 
+mod asm;
 mod declaration;
 mod expression;
 mod function_definition;
@@ -111,10 +201,55 @@ impl Compile for ProgramStatement {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
         match self {
             ProgramStatement::FunctionDefinition(function) => function.compile(state),
+            ProgramStatement::Declaration(declaration) => declaration.compile_global(state),
         }
     }
 }
 
+// codegen recurses into nested expressions, so its threads get as much stack
+// as the main thread usually has instead of the 2M spawned threads start with
+const THREAD_STACK_SIZE: usize = 8 << 20;
+
+// functions share nothing while they are compiled, so they are spread over
+// the threads with a state of their own each. the results come back in
+// source order, so the output does not depend on the scheduling
+fn compile_in_parallel(
+    statements: &[ProgramStatement],
+    threads: usize,
+) -> Vec<(Vec<Instruction>, Diagnostics)> {
+    let chunk_size = statements.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = statements
+            .chunks(chunk_size)
+            .map(|chunk| {
+                thread::Builder::new()
+                    .stack_size(THREAD_STACK_SIZE)
+                    .spawn_scoped(scope, move || {
+                        chunk
+                            .iter()
+                            .map(|statement| {
+                                let mut state = CompilerState::new();
+                                let instructions = statement.compile(&mut state);
+                                (instructions, state.diagnostics)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .expect("could not spawn a codegen thread")
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
 impl Compile for Program {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
         let mut instructions = vec![
@@ -126,12 +261,49 @@ impl Compile for Program {
             ),
         ];
 
-        for function in &self.functions {
-            instructions.extend(function.compile(state));
+        for (compiled, diagnostics) in compile_in_parallel(
+            &self.functions,
+            thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        ) {
+            instructions.extend(compiled);
+            state.diagnostics.extend(diagnostics);
         }
 
-        println!("state {state:#?}");
-
         instructions
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use crate::{analyze, parse, tokenize, Diagnostics, WarningOptions};
+
+    use super::*;
+
+    #[test]
+    fn parallel_compilation_matches_serial() {
+        let mut source = String::new();
+        for index in 0..24 {
+            let _ = writeln!(
+                source,
+                "int f{index}(int a) {{ int x = 0; while (x < a) {{ if (x == {index} || a > 9) break; x++; }} return x; }}"
+            );
+        }
+
+        let mut diagnostics = Diagnostics::new(WarningOptions::default());
+        let tokens = tokenize(&source, &mut diagnostics).unwrap();
+        let program = parse(&tokens, &mut diagnostics).unwrap();
+        let program = analyze(program, &mut diagnostics).unwrap();
+
+        let instructions = |threads| {
+            compile_in_parallel(&program.functions, threads)
+                .into_iter()
+                .flat_map(|(instructions, _)| instructions)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(instructions(4), instructions(1));
+        assert_eq!(instructions(24), instructions(1));
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    diagnostic::{code, Diagnostic},
+    parser::expression::fold::Fold,
+    riscv::{
+        instruction::Instruction,
+        values::{Register, RegisterWithOffset},
+    },
+    types::{
+        scope::{Scope, ScopeItem},
+        statement::{AsmOperand, AsmStatement, Statement, StatementKind},
+    },
+};
+
+use super::{expression::AsLhs, Compile, CompilerState};
+
+// a0 and a1 are used by expression codegen, so operands never live in them
+const OPERAND_REGISTERS: [Register; 12] = [
+    Register::T1,
+    Register::T2,
+    Register::T3,
+    Register::T4,
+    Register::T5,
+    Register::T6,
+    Register::A2,
+    Register::A3,
+    Register::A4,
+    Register::A5,
+    Register::A6,
+    Register::A7,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum OperandKind {
+    Register,
+    Memory,
+    Immediate,
+}
+
+#[derive(Debug, Clone)]
+struct BoundOperand<'a> {
+    operand: &'a AsmOperand,
+    kind: OperandKind,
+    output: bool,
+    // +r reads the initial value of the output
+    read_write: bool,
+    register: Option<Register>,
+}
+
+impl BoundOperand<'_> {
+    // what %N is replaced with in the template
+    fn substitution(&self) -> String {
+        match self.kind {
+            OperandKind::Register => self.register.as_ref().unwrap().to_string(),
+            OperandKind::Memory => format!("0({})", self.register.as_ref().unwrap()),
+            OperandKind::Immediate => self.operand.expression.fold().unwrap().to_string(),
+        }
+    }
+
+    // the value the register has to hold before the asm runs, if any
+    fn load(&self, state: &mut CompilerState) -> Option<Vec<Instruction>> {
+        match self.kind {
+            OperandKind::Immediate => None,
+            OperandKind::Memory => Some(
+                self.operand
+                    .expression
+                    .as_lvalue(state)
+                    .expect("memory operands are checked to be lvalues during semantic analysis"),
+            ),
+            OperandKind::Register if !self.output || self.read_write => {
+                Some(self.operand.expression.compile(state))
+            }
+            OperandKind::Register => None,
+        }
+    }
+}
+
+// s0 and s1 are saved by every prologue, the others only when asm clobbers them
+const CALLEE_SAVED: [Register; 10] = [
+    Register::S2,
+    Register::S3,
+    Register::S4,
+    Register::S5,
+    Register::S6,
+    Register::S7,
+    Register::S8,
+    Register::S9,
+    Register::S10,
+    Register::S11,
+];
+
+// the callee-saved registers the asm statements of a function clobber, which
+// its prologue has to save and its epilogue restore
+pub fn clobbered_callee_saved(body: &Scope) -> Vec<Register> {
+    fn walk_scope(scope: &Scope, clobbered: &mut Vec<Register>) {
+        for item in scope.items.iter() {
+            if let ScopeItem::Statement(item) = item {
+                walk_statement(item, clobbered);
+            }
+        }
+    }
+
+    fn walk_statement(statement: &Statement, clobbered: &mut Vec<Register>) {
+        match &statement.kind {
+            StatementKind::Scope { scope: inner } => walk_scope(inner, clobbered),
+            StatementKind::If { statement: inner } => {
+                walk_statement(&inner.then_block, clobbered);
+                if let Some(else_block) = &inner.else_block {
+                    walk_statement(else_block, clobbered);
+                }
+            }
+            StatementKind::While { statement: inner } => walk_statement(&inner.block, clobbered),
+            StatementKind::For { statement: inner } => walk_statement(&inner.block, clobbered),
+            StatementKind::Switch { statement: inner } => walk_statement(&inner.body, clobbered),
+            // unknown names are reported when the statement itself is compiled
+            StatementKind::Asm { statement: asm } => clobbered.extend(
+                asm.clobbered()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|register| CALLEE_SAVED.contains(register)),
+            ),
+            _ => {}
+        }
+    }
+
+    let mut clobbered = Vec::new();
+    walk_scope(body, &mut clobbered);
+
+    clobbered.sort_by_key(|register| register.clone() as u8);
+    clobbered.dedup();
+    clobbered
+}
+
+impl AsmStatement {
+    // memory and cc only order the asm, everything else has to name a register
+    fn clobbered(&self) -> Result<Vec<Register>, Box<Diagnostic>> {
+        self.clobbers
+            .iter()
+            .filter(|name| !matches!(name.as_str(), "memory" | "cc"))
+            .map(|name| {
+                Register::from_name(name).ok_or_else(|| {
+                    Box::new(
+                        Diagnostic::error(format!("Unknown register name \"{name}\" in asm"))
+                            .with_code(code::INVALID_ASM)
+                            .with_note("clobbers are register names, \"memory\" or \"cc\""),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn bind_operands(
+        &self,
+        clobbered: &[Register],
+    ) -> Result<Vec<BoundOperand<'_>>, Box<Diagnostic>> {
+        let mut available = OPERAND_REGISTERS
+            .iter()
+            .filter(|register| !clobbered.contains(register))
+            .cloned();
+
+        let outputs = self.outputs.iter().map(|operand| (operand, true));
+        let inputs = self.inputs.iter().map(|operand| (operand, false));
+
+        outputs
+            .chain(inputs)
+            .map(|(operand, output)| {
+                let kind = match operand.constraint.trim_start_matches(['=', '+']) {
+                    "r" => OperandKind::Register,
+                    "m" => OperandKind::Memory,
+                    "i" => OperandKind::Immediate,
+                    _ => unreachable!("constraints are validated by the parser"),
+                };
+
+                let register = match kind {
+                    OperandKind::Immediate => None,
+                    _ => Some(available.next().ok_or_else(|| {
+                        Box::new(
+                            Diagnostic::error("Too many asm operands")
+                                .with_code(code::INVALID_ASM)
+                                .at(operand.span)
+                                .with_note(format!(
+                                    "there are {} registers for operands, minus the clobbered ones",
+                                    OPERAND_REGISTERS.len()
+                                )),
+                        )
+                    })?),
+                };
+
+                Ok(BoundOperand {
+                    operand,
+                    kind,
+                    output,
+                    read_write: operand.constraint.starts_with('+'),
+                    register,
+                })
+            })
+            .collect()
+    }
+
+    fn substitute(&self, operands: &[BoundOperand<'_>]) -> Result<String, Box<Diagnostic>> {
+        let invalid = |message: String, label: String| {
+            Err(Box::new(
+                Diagnostic::error(message)
+                    .with_code(code::INVALID_ASM)
+                    .with_label(self.template_span, label),
+            ))
+        };
+
+        let mut result = String::new();
+        let mut characters = self.template.chars().peekable();
+
+        while let Some(c) = characters.next() {
+            if c != '%' {
+                result.push(c);
+                continue;
+            }
+
+            let operand = match characters.peek() {
+                Some('%') => {
+                    characters.next();
+                    result.push('%');
+                    continue;
+                }
+                Some('[') => {
+                    characters.next();
+                    let name: String = characters.by_ref().take_while(|c| *c != ']').collect();
+
+                    match operands
+                        .iter()
+                        .find(|o| o.operand.name.as_deref() == Some(name.as_str()))
+                    {
+                        Some(operand) => operand,
+                        None => {
+                            return invalid(
+                                format!("Undefined asm operand name \"{name}\""),
+                                format!("`%[{name}]` used here"),
+                            )
+                        }
+                    }
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    let mut index = String::new();
+                    while let Some(c) = characters.next_if(|c| c.is_ascii_digit()) {
+                        index.push(c);
+                    }
+
+                    // too many digits for a usize is out of range as well
+                    match index.parse::<usize>().ok().and_then(|i| operands.get(i)) {
+                        Some(operand) => operand,
+                        None => {
+                            return invalid(
+                                format!("Asm operand number {index} is out of range"),
+                                format!(
+                                    "`%{index}` used here, there are {} operands",
+                                    operands.len()
+                                ),
+                            )
+                        }
+                    }
+                }
+                _ => {
+                    return invalid(
+                        "Invalid '%' in asm template".to_string(),
+                        "expected an operand number or name after '%'".to_string(),
+                    )
+                }
+            };
+
+            result.push_str(&operand.substitution());
+        }
+
+        Ok(result)
+    }
+}
+
+impl Compile for AsmStatement {
+    fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
+        let raw = |template: &str| {
+            template
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| Instruction::Raw(line.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        if self.basic {
+            return raw(&self.template);
+        }
+
+        let mut instructions = Vec::new();
+
+        // the template is only looked at here, so this is where it is checked
+        let checked = self.clobbered().and_then(|clobbered| {
+            let operands = self.bind_operands(&clobbered)?;
+            Ok((self.substitute(&operands)?, operands, clobbered))
+        });
+
+        let (template, operands, clobbered) = match checked {
+            Ok(checked) => checked,
+            Err(error) => {
+                state.diagnostics.report(error.or_at(state.span));
+                return instructions;
+            }
+        };
+
+        // evaluating one operand may clobber the registers of another, so
+        // everything is computed onto the stack first
+        let mut loaded = Vec::new();
+        for operand in operands.iter() {
+            if let Some(load) = operand.load(state) {
+                instructions.extend(load);
+                instructions.push(Instruction::PushP(Register::A0));
+                loaded.push(operand.register.clone().unwrap());
+            }
+        }
+        for register in loaded.into_iter().rev() {
+            instructions.push(Instruction::PopP(register));
+        }
+
+        // the locals are addressed through fp, so it is back before any output
+        // is stored, the other callee-saved registers are saved by the prologue
+        let frame = clobbered.contains(&Register::Fp);
+        if frame {
+            instructions.push(Instruction::PushP(Register::Fp));
+        }
+        instructions.extend(raw(&template));
+        if frame {
+            instructions.push(Instruction::PopP(Register::Fp));
+        }
+
+        let outputs: Vec<_> = operands
+            .iter()
+            .filter(|operand| operand.output && operand.kind == OperandKind::Register)
+            .collect();
+
+        for output in outputs.iter() {
+            instructions.push(Instruction::PushP(output.register.clone().unwrap()));
+        }
+        for output in outputs.iter().rev() {
+            instructions.extend(
+                output
+                    .operand
+                    .expression
+                    .as_lvalue(state)
+                    .expect("output operands are checked to be lvalues during semantic analysis"),
+            );
+            instructions.push(Instruction::PopP(Register::A1));
+            instructions.push(Instruction::Sw(
+                Register::A1,
+                RegisterWithOffset(0.into(), Register::A0),
+            ));
+        }
+
+        instructions
+    }
+}
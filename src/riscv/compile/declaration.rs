@@ -1,27 +1,122 @@
 use std::sync::Arc;
 
 use crate::{
+    parser::expression::fold::Fold,
     riscv::instruction::Instruction,
     types::{
-        declaration::Declaration,
-        expression::{BinaryOp, Expression},
+        attribute::AttributeList,
+        datatype::Datatype,
+        declaration::{Declaration, InitDeclarator},
+        expression::{BinaryOp, ExpressionKind},
     },
+    utils::nearest_multiple::nearest_multiple,
 };
 
 use super::{Compile, CompilerState};
 
-impl Compile for Declaration {
+// the backend loads and stores whole words, so every object takes at least
+// one and they are aligned to one
+const WORD: u32 = 4;
+
+impl Compile for InitDeclarator {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
         let mut instructions = vec![];
 
+        // a weak declaration makes references to the function weak as well
+        if let Datatype::Function { .. } = self.datatype {
+            if self.attributes.has_attribute("weak") {
+                instructions.push(Instruction::Symbol("weak ".to_string() + &self.name));
+            }
+        }
+
         if let Some(ref value) = self.value {
-            let equivalent = Expression::BinaryOp(BinaryOp::Assignment(
-                Arc::new(Expression::Variable(self.name.clone())),
+            let equivalent = BinaryOp::Assignment(
+                Arc::new(ExpressionKind::Variable(self.name.clone()).into()),
                 Arc::new(value.clone()),
-            ));
+            );
             instructions.extend(equivalent.compile(state));
         }
 
         instructions
     }
 }
+
+impl Compile for Declaration {
+    fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
+        self.declarators
+            .iter()
+            .flat_map(|declarator| declarator.compile(state))
+            .collect()
+    }
+}
+
+impl Declaration {
+    // file-scope objects, the ones with an initializer go into .data and the
+    // others into .bss, which is zeroed before main runs, unless they ask for
+    // a section of their own. declarations without a definition are left to
+    // the linker
+    pub fn compile_global(&self, state: &mut CompilerState) -> Vec<Instruction> {
+        let mut instructions = vec![];
+
+        for declarator in self.declarators.iter() {
+            let weak = declarator.attributes.has_attribute("weak");
+
+            if matches!(declarator.datatype, Datatype::Function { .. }) {
+                instructions.extend(declarator.compile(state));
+                continue;
+            }
+
+            let name = &declarator.name;
+
+            // references to a weak object are weak too
+            if declarator.external {
+                if weak {
+                    instructions.push(Instruction::Symbol(format!("weak {name}")));
+                }
+                continue;
+            }
+
+            let size = nearest_multiple(declarator.datatype.size() as u32, WORD);
+            let alignment = declarator.attributes.alignment().unwrap_or(WORD).max(WORD);
+
+            instructions.push(Instruction::Symbol(
+                match declarator.attributes.find_attribute("section") {
+                    Some(section) => format!(
+                        "section {},\"aw\",@progbits",
+                        section.string_argument().unwrap()
+                    ),
+                    None if declarator.value.is_some() => "data".to_string(),
+                    None => "bss".to_string(),
+                },
+            ));
+            instructions.push(Instruction::Symbol(format!("balign {alignment}")));
+            if weak {
+                instructions.push(Instruction::Symbol(format!("weak {name}")));
+            } else {
+                instructions.push(Instruction::Symbol(format!("globl {name}")));
+            }
+            instructions.push(Instruction::Symbol(format!("type {name}, @object")));
+            instructions.push(Instruction::Label(name.clone()));
+
+            // analysis made sure the initializer is a constant
+            let mut written = 0;
+            if let Some(value) = declarator.value.as_ref() {
+                if let Some(symbol) = value.address_constant() {
+                    instructions.push(Instruction::Symbol(format!("word {symbol}")));
+                    written += WORD;
+                } else if let Some(value) = value.fold() {
+                    instructions.push(Instruction::Symbol(format!("word {value}")));
+                    written += WORD;
+                }
+            }
+
+            if size > written {
+                instructions.push(Instruction::Symbol(format!("zero {}", size - written)));
+            }
+
+            instructions.push(Instruction::Symbol(format!("size {name}, {size}")));
+        }
+
+        instructions
+    }
+}
@@ -5,20 +5,54 @@ use crate::{
         instruction::Instruction,
         values::{Immediate, Register, RegisterWithOffset},
     },
-    types::expression::{BinaryOp, Expression, UnaryOp},
-    utils::{nearest_multiple::nearest_multiple, random_name::unique_identifier},
+    types::{
+        datatype::Datatype,
+        expression::{
+            BinaryOp, Builtin, BuiltinCall, ConversionKind, Expression, ExpressionKind, Subscript,
+            UnaryOp,
+        },
+    },
+    utils::nearest_multiple::nearest_multiple,
 };
 
 use super::{Compile, CompilerState};
 
+// how far ++ and -- move their operand, pointers move by a whole element
+fn step_size(expression: &Expression) -> i32 {
+    match expression.datatype().pointee() {
+        Some(pointee) => pointee.size() as i32,
+        None => 1,
+    }
+}
+
+// pointers compare as unsigned addresses
+fn is_signed(expression: &Expression) -> bool {
+    matches!(expression.datatype(), Datatype::Integer(integer) if integer.is_signed())
+}
+
+// the operands of a compound assignment are not converted to their common
+// type, but with every integer a word wide it is only signed if both are
+fn signed_operands(lhs: &Expression, rhs: &Expression) -> bool {
+    is_signed(lhs) && is_signed(rhs)
+}
+
+// multiplies an integer operand of pointer arithmetic by the element size
+fn scale(register: Register, size: usize) -> Vec<Instruction> {
+    if size == 1 {
+        return Vec::new();
+    }
+
+    vec![
+        Instruction::LiP(Register::T1, (size as i32).into()),
+        Instruction::Mul(register.clone(), register, Register::T1),
+    ]
+}
+
 impl Compile for UnaryOp {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
         let mut instructions = Vec::new();
 
         match self {
-            UnaryOp::Nothing(expression) => {
-                instructions.extend(expression.compile(state));
-            }
             UnaryOp::Negation(expression) => {
                 instructions.extend(expression.compile(state));
                 instructions.push(Instruction::Neg(Register::A0, Register::A0));
@@ -37,7 +71,7 @@ impl Compile for UnaryOp {
             UnaryOp::PrefixIncrement(expression) => {
                 let equivalent = BinaryOp::AssignmentAddition(
                     expression.clone(),
-                    Arc::new(Expression::Number(1)),
+                    Arc::new(Expression::integer_constant(1)),
                 );
 
                 instructions.extend(equivalent.compile(state));
@@ -45,7 +79,7 @@ impl Compile for UnaryOp {
             UnaryOp::PrefixDecrement(expression) => {
                 let equivalent = BinaryOp::AssignmentSubtraction(
                     expression.clone(),
-                    Arc::new(Expression::Number(1)),
+                    Arc::new(Expression::integer_constant(1)),
                 );
 
                 instructions.extend(equivalent.compile(state));
@@ -53,17 +87,25 @@ impl Compile for UnaryOp {
             UnaryOp::PostfixDecrement(expression) => {
                 let equivalent = UnaryOp::PrefixDecrement(expression.clone());
                 instructions.extend(equivalent.compile(state));
-                instructions.push(Instruction::Addi(Register::A0, Register::A0, 1.into()));
+                instructions.push(Instruction::Addi(
+                    Register::A0,
+                    Register::A0,
+                    step_size(expression).into(),
+                ));
             }
             UnaryOp::PostfixIncrement(expression) => {
                 let equivalent = UnaryOp::PrefixIncrement(expression.clone());
                 instructions.extend(equivalent.compile(state));
-                instructions.push(Instruction::Addi(Register::A0, Register::A0, (-1).into()));
+                instructions.push(Instruction::Addi(
+                    Register::A0,
+                    Register::A0,
+                    (-step_size(expression)).into(),
+                ));
             }
             UnaryOp::Ref(expression) => {
                 let lvalue = expression
                     .as_lvalue(state)
-                    .unwrap_or_else(|| panic!("Cannot get a reference to non-lvalue"));
+                    .expect("operands of & are checked to be lvalues during semantic analysis");
                 instructions.extend(lvalue);
             }
             UnaryOp::Deref(expression) => {
@@ -108,9 +150,20 @@ impl Compile for BinaryOp {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
         let mut instructions = Vec::new();
 
+        if let Some(operation) = self.compound_operation() {
+            let (lhs, _) = self.operands();
+            let equivalent = BinaryOp::Assignment(
+                lhs.clone(),
+                Arc::new(ExpressionKind::BinaryOp(operation).into()),
+            );
+
+            return equivalent.compile(state);
+        }
+
         match self {
             BinaryOp::LogicalAnd(lhs, rhs) => {
-                let short_circuit_label = unique_identifier(Some("short_circuit_and"), None);
+                let id = state.next_id("and");
+                let short_circuit_label = state.label(&id, "false");
 
                 // compute the lhs
                 instructions.extend(lhs.compile(state));
@@ -134,8 +187,9 @@ impl Compile for BinaryOp {
                 instructions.push(Instruction::Label(short_circuit_label));
             }
             BinaryOp::LogicalOr(lhs, rhs) => {
-                let short_circuit_label_1 = unique_identifier(Some("short_circuit_or_1"), None);
-                let short_circuit_label_2 = unique_identifier(Some("short_circuit_or_2"), None);
+                let id = state.next_id("or");
+                let short_circuit_label_1 = state.label(&id, "true");
+                let short_circuit_label_2 = state.label(&id, "end");
 
                 // compute the lhs
                 instructions.extend(lhs.compile(state));
@@ -166,16 +220,17 @@ impl Compile for BinaryOp {
                 instructions.push(Instruction::Label(short_circuit_label_2));
             }
             BinaryOp::NotEquals(lhs, rhs) => {
-                let equivalent = UnaryOp::LogicalNot(Arc::new(Expression::BinaryOp(
-                    BinaryOp::Equals((*lhs).clone(), (*rhs).clone()),
-                )))
+                let equivalent = UnaryOp::LogicalNot(Arc::new(
+                    ExpressionKind::BinaryOp(BinaryOp::Equals(lhs.clone(), rhs.clone())).into(),
+                ))
                 .compile(state);
                 instructions.extend(equivalent);
             }
             BinaryOp::LessThan(lhs, rhs) => {
-                let equivalent = UnaryOp::LogicalNot(Arc::new(Expression::BinaryOp(
-                    BinaryOp::GreaterThanEquals(lhs.clone(), rhs.clone()),
-                )));
+                let equivalent = UnaryOp::LogicalNot(Arc::new(
+                    ExpressionKind::BinaryOp(BinaryOp::GreaterThanEquals(lhs.clone(), rhs.clone()))
+                        .into(),
+                ));
                 instructions.extend(equivalent.compile(state));
             }
             BinaryOp::LessThanEquals(lhs, rhs) => {
@@ -184,14 +239,13 @@ impl Compile for BinaryOp {
             }
             BinaryOp::GreaterThanEquals(lhs, rhs) => {
                 let equivalent = BinaryOp::LogicalOr(
-                    Arc::new(Expression::BinaryOp(BinaryOp::Equals(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                    Arc::new(Expression::BinaryOp(BinaryOp::GreaterThan(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
+                    Arc::new(
+                        ExpressionKind::BinaryOp(BinaryOp::Equals(lhs.clone(), rhs.clone())).into(),
+                    ),
+                    Arc::new(
+                        ExpressionKind::BinaryOp(BinaryOp::GreaterThan(lhs.clone(), rhs.clone()))
+                            .into(),
+                    ),
                 );
                 instructions.extend(equivalent.compile(state));
             }
@@ -202,147 +256,93 @@ impl Compile for BinaryOp {
 
                 let lvalue = lhs
                     .as_lvalue(state)
-                    .unwrap_or_else(|| panic!("Cannot assign to non-lvalue"));
+                    .expect("assignments are checked to be to lvalues during semantic analysis");
 
                 instructions.extend(lvalue);
                 instructions.push(Instruction::PopP(Register::A1));
                 instructions.push(Instruction::Sw(
                     Register::A1,
                     RegisterWithOffset(0.into(), Register::A0),
-                ))
-            }
-
-            BinaryOp::AssignmentAddition(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::Addition(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
-
-            BinaryOp::AssignmentSubtraction(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::Subtraction(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
-
-            BinaryOp::AssignmentMultiplication(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::Multiplication(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
-
-            BinaryOp::AssignmentDivision(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::Division(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
-
-            BinaryOp::AssignmentModulus(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::Modulus(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
-
-            BinaryOp::AssignmentShiftLeft(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::LeftShift(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
+                ));
 
-            BinaryOp::AssignmentShiftRight(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::RightShift(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
+                // the value of an assignment is the value that was stored
+                instructions.push(Instruction::MvP(Register::A0, Register::A1));
             }
 
-            BinaryOp::AssignmentBitwiseAnd(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::BitwiseAnd(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
-
-            BinaryOp::AssignmentBitwiseXor(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::BitwiseXor(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
+            // pointer arithmetic counts in elements of the pointed to type
+            BinaryOp::Addition(lhs, rhs) => binary_op_inner!(instructions, lhs, rhs, state, {
+                if let Some(pointee) = lhs.datatype().pointee() {
+                    instructions.extend(scale(Register::A0, pointee.size()));
+                } else if let Some(pointee) = rhs.datatype().pointee() {
+                    instructions.extend(scale(Register::A1, pointee.size()));
+                }
 
-            BinaryOp::AssignmentBitwiseOr(lhs, rhs) => {
-                let equivalent = BinaryOp::Assignment(
-                    lhs.clone(),
-                    Arc::new(Expression::BinaryOp(BinaryOp::BitwiseOr(
-                        lhs.clone(),
-                        rhs.clone(),
-                    ))),
-                );
-                instructions.extend(equivalent.compile(state));
-            }
+                instructions.push(Instruction::Add(Register::A0, Register::A1, Register::A0));
+            }),
+            BinaryOp::Subtraction(lhs, rhs) => binary_op_inner!(instructions, lhs, rhs, state, {
+                match (lhs.datatype().pointee(), rhs.datatype().is_pointer()) {
+                    (Some(pointee), true) => {
+                        instructions.push(Instruction::Sub(
+                            Register::A0,
+                            Register::A1,
+                            Register::A0,
+                        ));
+                        instructions.push(Instruction::LiP(
+                            Register::T1,
+                            (pointee.size() as i32).into(),
+                        ));
+                        instructions.push(Instruction::Div(
+                            Register::A0,
+                            Register::A0,
+                            Register::T1,
+                        ));
+                    }
+                    (Some(pointee), false) => {
+                        instructions.extend(scale(Register::A0, pointee.size()));
+                        instructions.push(Instruction::Sub(
+                            Register::A0,
+                            Register::A1,
+                            Register::A0,
+                        ));
+                    }
+                    _ => {
+                        instructions.push(Instruction::Sub(
+                            Register::A0,
+                            Register::A1,
+                            Register::A0,
+                        ));
+                    }
+                }
+            }),
 
             _ => {
+                let (lhs, rhs) = self.operands();
+                let signed = signed_operands(lhs, rhs);
+                // the shifted value has the type of the left operand
+                let signed_shift = is_signed(lhs);
+
                 match_binary_ops!(
                     instructions,
                     self,
                     state,
                     [
-                        Addition:  {
-                            instructions.push(Instruction::Add(Register::A0, Register::A1, Register::A0));
-                        },
-                        Subtraction: {
-                            instructions.push(Instruction::Sub(Register::A0, Register::A1, Register::A0));
-                        },
                         Multiplication: {
                             // todo: multiply high
                             instructions.push(Instruction::Mul(Register::A0, Register::A1, Register::A0));
                         },
                         Division: {
-                            instructions.push(Instruction::Div(Register::A0, Register::A1, Register::A0));
+                            instructions.push(if signed {
+                                Instruction::Div(Register::A0, Register::A1, Register::A0)
+                            } else {
+                                Instruction::Divu(Register::A0, Register::A1, Register::A0)
+                            });
                         },
                         Modulus: {
-                            instructions.push(Instruction::Rem(Register::A0, Register::A1, Register::A0));
+                            instructions.push(if signed {
+                                Instruction::Rem(Register::A0, Register::A1, Register::A0)
+                            } else {
+                                Instruction::Remu(Register::A0, Register::A1, Register::A0)
+                            });
                         },
                         BitwiseAnd: {
                             instructions.push(Instruction::And(Register::A0, Register::A1, Register::A0));
@@ -354,17 +354,23 @@ impl Compile for BinaryOp {
                             instructions.push(Instruction::Or(Register::A0, Register::A1, Register::A0));
                         },
 
-                        // todo: arithmetic shifts for signed numbers
                         LeftShift: {
                             instructions.push(Instruction::Sll(Register::A0, Register::A1, Register::A0));
                         },
                         RightShift: {
-                            instructions.push(Instruction::Srl(Register::A0, Register::A1, Register::A0));
+                            instructions.push(if signed_shift {
+                                Instruction::Sra(Register::A0, Register::A1, Register::A0)
+                            } else {
+                                Instruction::Srl(Register::A0, Register::A1, Register::A0)
+                            });
                         },
 
-                        // todo: comparison for signed numbers
                         GreaterThan: {
-                            instructions.push(Instruction::Sltu(Register::A0, Register::A0, Register::A1));
+                            instructions.push(if signed {
+                                Instruction::Slt(Register::A0, Register::A0, Register::A1)
+                            } else {
+                                Instruction::Sltu(Register::A0, Register::A0, Register::A1)
+                            });
                         },
 
                         Equals: {
@@ -383,42 +389,109 @@ impl Compile for BinaryOp {
     }
 }
 
+impl Subscript {
+    // computes the address of the subscripted element into a0
+    fn address(&self, state: &mut CompilerState) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+
+        instructions.extend(self.expression.compile(state));
+        instructions.push(Instruction::PushP(Register::A0));
+        instructions.extend(self.index.compile(state));
+        let element = self
+            .expression
+            .datatype()
+            .pointee()
+            .expect("subscripts are normalized to pointer[integer] during semantic analysis");
+        instructions.extend(scale(Register::A0, element.size()));
+        instructions.push(Instruction::PopP(Register::A1));
+        instructions.push(Instruction::Add(Register::A0, Register::A1, Register::A0));
+
+        instructions
+    }
+}
+
+// builtins expand inline instead of being called
+impl Compile for BuiltinCall {
+    fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+
+        // the only builtin argument is the value written to the csr
+        if let Some(value) = self.arguments.first() {
+            instructions.extend(value.compile(state));
+        }
+
+        instructions.push(match self.builtin {
+            Builtin::Csrr(csr) => Instruction::CsrrP(Register::A0, csr),
+            Builtin::Csrw(csr) => Instruction::CsrwP(csr, Register::A0),
+            Builtin::Csrs(csr) => Instruction::CsrsP(csr, Register::A0),
+            Builtin::Csrc(csr) => Instruction::CsrcP(csr, Register::A0),
+            Builtin::Csrrw(csr) => Instruction::Csrrw(Register::A0, csr, Register::A0),
+            Builtin::Wfi => Instruction::Wfi,
+            Builtin::Fence => Instruction::Fence,
+            Builtin::FenceI => Instruction::FenceI,
+            Builtin::Ecall => Instruction::Ecall,
+        });
+
+        // builtins without a result evaluate to 0
+        if !self.builtin.has_result() {
+            instructions.push(Instruction::LiP(Register::A0, 0.into()));
+        }
+
+        instructions
+    }
+}
+
 impl Compile for Expression {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
         let mut instructions = Vec::new();
 
-        match self {
-            Expression::Number(n) => {
-                instructions.push(Instruction::LiP(Register::A0, (*n).into()));
+        match &self.kind {
+            ExpressionKind::Number(n) => {
+                // the bits of the word, so unsigned values above INT_MAX stay
+                // what they are
+                let value = u32::try_from(n.value)
+                    .expect("analysis rejects the literals that do not fit in a word");
+                instructions.push(Instruction::LiP(Register::A0, (value as i32).into()));
             }
-            Expression::UnaryOp(op) => {
+            ExpressionKind::UnaryOp(op) => {
                 instructions.extend(op.compile(state));
             }
-            Expression::BinaryOp(op) => {
+            ExpressionKind::BinaryOp(op) => {
                 instructions.extend(op.compile(state));
             }
-            Expression::FunctionSymbol(name) => {
+            ExpressionKind::FunctionSymbol(name) => {
                 instructions.push(Instruction::LaP(
                     Register::A0,
                     Immediate::Label(name.clone()),
                 ));
             }
-            Expression::Variable(name) => {
+            // the linker fills in where the object ended up
+            ExpressionKind::Global(name) => {
+                instructions.push(Instruction::LaP(
+                    Register::A0,
+                    Immediate::Label(name.clone()),
+                ));
+                instructions.push(Instruction::Lw(
+                    Register::A0,
+                    RegisterWithOffset(0.into(), Register::A0),
+                ));
+            }
+            ExpressionKind::Variable(name) => {
                 if let Some(variable) = state.get_variable(name) {
                     instructions.push(Instruction::Lw(
                         Register::A0,
                         RegisterWithOffset(variable.address.into(), Register::Fp),
                     ));
                 } else {
-                    // error
-                    todo!("Variable not found");
+                    unreachable!("variable {name} was resolved by the parser but has no storage");
                 }
             }
-            Expression::TernaryOp(op) => {
+            ExpressionKind::TernaryOp(op) => {
                 instructions.extend(op.condition.compile(state));
 
-                let end_of_ternary_label = unique_identifier(Some("ternary_end"), None);
-                let start_of_else_label = unique_identifier(Some("ternary_else_start"), None);
+                let id = state.next_id("ternary");
+                let end_of_ternary_label = state.label(&id, "end");
+                let start_of_else_label = state.label(&id, "else");
 
                 instructions.push(Instruction::BeqzP(
                     Register::A0,
@@ -437,7 +510,7 @@ impl Compile for Expression {
 
                 instructions.push(Instruction::Label(end_of_ternary_label));
             }
-            Expression::Call(call) => {
+            ExpressionKind::Call(call) => {
                 // riscv integer calling convention states that the first 8 arguments
                 // should reside in a0-a7. If there are more than 8 arguments, the
                 // remaining arguments are allowed to leak into the stack
@@ -488,8 +561,15 @@ impl Compile for Expression {
                     current_address += 4;
                 }
 
-                instructions.extend(call.expression.compile(state));
-                instructions.push(Instruction::Add(Register::T0, Register::A0, Register::Zero));
+                // a function called by its name is called directly, with a
+                // call the linker can relax. the only address constant a
+                // call can have is the one of a function
+                let direct = call.expression.address_constant();
+
+                if direct.is_none() {
+                    instructions.extend(call.expression.compile(state));
+                    instructions.push(Instruction::Add(Register::T0, Register::A0, Register::Zero));
+                }
 
                 // currently, all arguments are pushed onto the stack
                 // register arguments are in reverse order, and on the top
@@ -521,12 +601,13 @@ impl Compile for Expression {
                     (register_argument_size).into(),
                 ));
 
-                // t0 contains the address of the function to call
-
-                instructions.push(Instruction::Jalr(
-                    Register::Ra,
-                    RegisterWithOffset(0.into(), Register::T0),
-                ));
+                // otherwise t0 contains the address of the function to call
+                instructions.push(match direct {
+                    Some(name) => Instruction::CallP(Immediate::Label(name.to_string())),
+                    None => {
+                        Instruction::Jalr(Register::Ra, RegisterWithOffset(0.into(), Register::T0))
+                    }
+                });
 
                 instructions.push(Instruction::Addi(
                     Register::Sp,
@@ -534,26 +615,66 @@ impl Compile for Expression {
                     (stack_argument_size).into(),
                 ));
             }
+            ExpressionKind::Builtin(call) => instructions.extend(call.compile(state)),
+            ExpressionKind::Subscript(subscript) => {
+                instructions.extend(subscript.address(state));
+                instructions.push(Instruction::Lw(
+                    Register::A0,
+                    RegisterWithOffset(0.into(), Register::A0),
+                ));
+            }
+            ExpressionKind::Member(_) => {
+                unreachable!("member access is rejected during semantic analysis");
+            }
+            ExpressionKind::Conversion(conversion) => match conversion.kind {
+                // the address of an array or function is its value
+                ConversionKind::ArrayToPointer | ConversionKind::FunctionToPointer => {
+                    instructions.extend(
+                        conversion
+                            .expression
+                            .as_lvalue(state)
+                            .expect("only lvalues and functions decay to pointers"),
+                    );
+                }
+                // analysis rejects long long, so every integer and pointer is a
+                // word wide and the bits stay the same
+                ConversionKind::Integer
+                | ConversionKind::IntegerToPointer
+                | ConversionKind::PointerToInteger => {
+                    instructions.extend(conversion.expression.compile(state));
+                }
+            },
         };
 
         instructions
     }
 }
 
-trait AsLhs {
+pub(super) trait AsLhs {
     fn as_lvalue(&self, state: &mut CompilerState) -> Option<Vec<Instruction>>;
 }
 
 impl AsLhs for Expression {
     fn as_lvalue(&self, state: &mut CompilerState) -> Option<Vec<Instruction>> {
-        match self {
-            Expression::Number(_)
-            | Expression::BinaryOp(_)
-            | Expression::FunctionSymbol(_)
-            | Expression::TernaryOp(_)
-            | Expression::Call(_) => None,
+        match &self.kind {
+            ExpressionKind::Number(_)
+            | ExpressionKind::BinaryOp(_)
+            | ExpressionKind::TernaryOp(_)
+            | ExpressionKind::Call(_)
+            | ExpressionKind::Builtin(_)
+            | ExpressionKind::Member(_)
+            | ExpressionKind::Conversion(_) => None,
+
+            ExpressionKind::FunctionSymbol(name) | ExpressionKind::Global(name) => {
+                Some(vec![Instruction::LaP(
+                    Register::A0,
+                    Immediate::Label(name.clone()),
+                )])
+            }
+
+            ExpressionKind::Subscript(subscript) => Some(subscript.address(state)),
 
-            Expression::Variable(name) => {
+            ExpressionKind::Variable(name) => {
                 if let Some(variable) = state.get_variable(name) {
                     Some(vec![Instruction::Addi(
                         Register::A0,
@@ -564,7 +685,7 @@ impl AsLhs for Expression {
                     None
                 }
             }
-            Expression::UnaryOp(op) => match op {
+            ExpressionKind::UnaryOp(op) => match op {
                 UnaryOp::Deref(expression) => Some(expression.compile(state)),
                 _ => None,
             },
@@ -4,27 +4,177 @@ use crate::{
         values::{Register, RegisterWithOffset},
     },
     types::{
-        expression::Expression, function_definition::FunctionDefinition, statement::JumpStatement,
+        attribute::AttributeList,
+        expression::Expression,
+        function_definition::{FunctionDefinition, FunctionKind, InterruptMode},
+        statement::JumpStatement,
     },
     utils::nearest_multiple::nearest_multiple,
 };
 
-use super::{Compile, CompilerState, CompilerVariable, CompilerVariableLocation};
+use super::{
+    asm::clobbered_callee_saved, Compile, CompilerState, CompilerVariable, CompilerVariableLocation,
+};
+
+impl FunctionDefinition {
+    // the directives placing the function and its label
+    fn header(&self) -> Vec<Instruction> {
+        let mut instructions = vec![match self.attributes.find_attribute("section") {
+            Some(section) => Instruction::Symbol(format!(
+                "section {},\"ax\",@progbits",
+                section.string_argument().unwrap()
+            )),
+            None => Instruction::Symbol("text".to_string()),
+        }];
+
+        if let Some(alignment) = self.attributes.alignment() {
+            instructions.push(Instruction::Symbol(format!("balign {alignment}")));
+        }
+
+        // nothing is discarded yet, so used needs no directive of its own
+        if self.attributes.has_attribute("weak") {
+            instructions.push(Instruction::Symbol("weak ".to_string() + &self.name));
+        } else {
+            instructions.push(Instruction::Symbol("globl ".to_string() + &self.name));
+        }
+
+        instructions.push(Instruction::Label(self.name.clone()));
+
+        instructions
+    }
+
+    // a trap can interrupt any code, so a handler has to preserve every register
+    // it touches on top of the ones the regular prologue saves
+    fn wrap_interrupt_handler(
+        &self,
+        mode: InterruptMode,
+        state: &CompilerState,
+        body: Vec<Instruction>,
+    ) -> Vec<Instruction> {
+        let mut touched: Vec<Register> = body
+            .iter()
+            .flat_map(|instruction| {
+                if instruction.is_call() {
+                    let mut registers = instruction.registers();
+                    registers.extend(Register::CALLER_SAVED);
+                    registers
+                } else {
+                    instruction.registers()
+                }
+            })
+            .filter(|register| {
+                !matches!(
+                    register,
+                    Register::Zero | Register::Sp | Register::Ra | Register::Fp | Register::S1
+                )
+            })
+            .collect();
+
+        touched.sort_by_key(|register| register.clone() as u8);
+        touched.dedup();
+
+        let save_size = nearest_multiple(4 * touched.len() as u32, 16) as i32;
+
+        let mut instructions = self.header();
+
+        instructions.push(Instruction::Comment(
+            "Saving the registers used by the interrupt handler".to_owned(),
+        ));
+        instructions.push(Instruction::Addi(
+            Register::Sp,
+            Register::Sp,
+            (-save_size).into(),
+        ));
+
+        for (index, register) in touched.iter().enumerate() {
+            instructions.push(Instruction::Sw(
+                register.clone(),
+                RegisterWithOffset((4 * index as i32).into(), Register::Sp),
+            ));
+        }
+
+        instructions.extend(body);
+
+        instructions.push(Instruction::Label(state.interrupt_return_label()));
+
+        for (index, register) in touched.iter().enumerate() {
+            instructions.push(Instruction::Lw(
+                register.clone(),
+                RegisterWithOffset((4 * index as i32).into(), Register::Sp),
+            ));
+        }
+
+        instructions.push(Instruction::Addi(
+            Register::Sp,
+            Register::Sp,
+            save_size.into(),
+        ));
+
+        instructions.push(match mode {
+            InterruptMode::Machine => Instruction::Mret,
+            InterruptMode::Supervisor => Instruction::Sret,
+            InterruptMode::User => Instruction::Uret,
+        });
+
+        instructions
+    }
+
+    // naked functions have no frame, so there is nowhere to keep variables
+    fn compile_naked(&self, state: &mut CompilerState) -> Vec<Instruction> {
+        assert!(
+            self.scope_state.get_only_variables().is_empty(),
+            "locals of naked functions are rejected during semantic analysis"
+        );
+
+        state.scope.variables = Vec::new();
+
+        let mut instructions = self.header();
+        instructions.extend(self.body.compile(state));
+
+        instructions
+    }
+}
 
 impl Compile for FunctionDefinition {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
+        state.function_name = self.name.clone();
+        state.function_kind = self.kind;
+        state.label_count = 0;
+        state.saved = Vec::new();
+
+        if self.kind == FunctionKind::Naked {
+            return self.compile_naked(state);
+        }
+
         let mut instructions = vec![
-            Instruction::Symbol("globl ".to_string() + &self.name),
-            Instruction::Label(self.name.clone()),
             Instruction::Comment("Function Prologue".to_owned()),
             Instruction::Addi(Register::Sp, Register::Sp, (-32).into()),
             Instruction::Sw(Register::Ra, RegisterWithOffset(0.into(), Register::Sp)),
             Instruction::Sw(Register::Fp, RegisterWithOffset(16.into(), Register::Sp)),
             Instruction::Sw(Register::S1, RegisterWithOffset(24.into(), Register::Sp)),
-            Instruction::Comment(String::from(
-                "Finished function prologue, now allocating space for variables",
-            )),
         ];
+
+        state.saved = clobbered_callee_saved(&self.body);
+        if !state.saved.is_empty() {
+            instructions.push(Instruction::Comment(String::from(
+                "Saving the registers inline assembly clobbers",
+            )));
+            instructions.push(Instruction::Addi(
+                Register::Sp,
+                Register::Sp,
+                (-state.saved_size()).into(),
+            ));
+            for (index, register) in state.saved.iter().enumerate() {
+                instructions.push(Instruction::Sw(
+                    register.clone(),
+                    RegisterWithOffset((4 * index as i32).into(), Register::Sp),
+                ));
+            }
+        }
+
+        instructions.push(Instruction::Comment(String::from(
+            "Finished function prologue, now allocating space for variables",
+        )));
         // handling variables
 
         let function_variables: Vec<_> = self.scope_state.get_only_variables();
@@ -93,7 +243,8 @@ impl Compile for FunctionDefinition {
             current_address += size as i32;
         }
 
-        let mut current_address = 32 + stack_increase; // 32 is for the saved registers
+        // 32 is for the saved registers
+        let mut current_address = 32 + state.saved_size() + stack_increase;
         for stack_variable in self.arguments.iter().skip(8) {
             state.scope.variables.push(CompilerVariable {
                 name: stack_variable.unique_name.clone(),
@@ -114,16 +265,26 @@ impl Compile for FunctionDefinition {
         let body = self.body.compile(state);
         instructions.extend(body);
 
-        instructions.push(Instruction::Comment("Function epilogue".to_owned()));
+        // control never reaches the end of a noreturn function
+        if !self.attributes.has_attribute("noreturn") {
+            instructions.push(Instruction::Comment("Function epilogue".to_owned()));
 
-        let implicit_return = JumpStatement::Return {
-            expression: Some(Expression::Number(0)),
+            let implicit_return = JumpStatement::Return {
+                expression: Some(Expression::integer_constant(0)),
+            }
+            .compile(state);
+            instructions.extend(implicit_return);
         }
-        .compile(state);
-        instructions.extend(implicit_return);
 
         // state.decrease_stack_size(32);
 
-        instructions
+        match self.kind {
+            FunctionKind::Interrupt(mode) => self.wrap_interrupt_handler(mode, state, instructions),
+            _ => {
+                let mut function = self.header();
+                function.extend(instructions);
+                function
+            }
+        }
     }
 }
@@ -1,6 +1,6 @@
 use crate::{
     riscv::instruction::Instruction,
-    types::scope::{Label, Scope, ScopeItem},
+    types::scope::{Label, LabelKind, Scope, ScopeItem},
 };
 
 use super::{Compile, CompilerState};
@@ -28,21 +28,21 @@ impl Compile for ScopeItem {
 }
 
 impl Compile for Label {
-    fn compile(&self, _state: &mut CompilerState) -> Vec<Instruction> {
-        match self {
-            Label::Named(_) => {
-                todo!("named labels");
+    fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
+        match &self.kind {
+            LabelKind::Named(_) => {
+                unreachable!("named labels are rejected by the parser")
             }
-            Label::Case { id, value } => {
+            LabelKind::Case { id, value } => {
                 let mut instructions = Vec::new();
-                let label = id.to_owned() + "____case_" + &value.to_string();
+                let label = state.case_label(id, *value);
                 instructions.push(Instruction::Label(label));
 
                 instructions
             }
-            Label::Default { id } => {
+            LabelKind::Default { id } => {
                 let mut instructions = Vec::new();
-                let label = id.to_owned() + "____default";
+                let label = state.label(id, "default");
                 instructions.push(Instruction::Label(label));
 
                 instructions
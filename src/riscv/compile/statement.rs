@@ -9,27 +9,30 @@ use crate::{
     types::{
         expression::Expression,
         scope::{Scope, ScopeItem},
+        span::Span,
         statement::{
-            ForInit, ForStatement, IfStatement, JumpStatement, Statement, SwitchStatement,
-            WhileStatement,
+            ForInit, ForStatement, IfStatement, JumpStatement, Statement, StatementKind,
+            SwitchStatement, WhileStatement,
         },
     },
-    utils::random_name::unique_identifier,
 };
 
 use super::{Compile, CompilerState};
 
 impl Compile for Statement {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
-        match self {
-            Statement::Jump { statement } => statement.compile(state),
-            Statement::Expression { expression } => expression.compile(state),
-            Statement::Null => Vec::new(),
-            Statement::Scope { scope } => scope.compile(state),
-            Statement::If { statement } => statement.compile(state),
-            Statement::While { statement } => statement.compile(state),
-            Statement::For { statement } => statement.compile(state),
-            Statement::Switch { statement } => statement.compile(state),
+        state.span = self.span;
+
+        match &self.kind {
+            StatementKind::Jump { statement } => statement.compile(state),
+            StatementKind::Expression { expression } => expression.compile(state),
+            StatementKind::Null | StatementKind::Fallthrough => Vec::new(),
+            StatementKind::Scope { scope } => scope.compile(state),
+            StatementKind::If { statement } => statement.compile(state),
+            StatementKind::While { statement } => statement.compile(state),
+            StatementKind::For { statement } => statement.compile(state),
+            StatementKind::Switch { statement } => statement.compile(state),
+            StatementKind::Asm { statement } => statement.compile(state),
         }
     }
 }
@@ -38,8 +41,8 @@ impl Compile for WhileStatement {
     fn compile(&self, state: &mut CompilerState) -> Vec<Instruction> {
         let mut instructions = Vec::new();
 
-        let while_start_label = self.id.clone() + "_start";
-        let while_end_label = self.id.clone() + "_end";
+        let while_start_label = state.label(&self.id, "start");
+        let while_end_label = state.label(&self.id, "end");
 
         instructions.push(Instruction::Label(while_start_label.clone()));
 
@@ -66,23 +69,23 @@ impl Compile for ForStatement {
             match init {
                 ForInit::Declaration(declaration) => ScopeItem::Declaration(declaration),
                 ForInit::Expression(expression) => {
-                    ScopeItem::Statement(Statement::Expression { expression })
+                    ScopeItem::Statement(Statement::new(StatementKind::Expression { expression }))
                 }
             }
         } else {
-            ScopeItem::Statement(Statement::Null)
+            ScopeItem::Statement(Statement::new(StatementKind::Null))
         };
 
         let condition = if let Some(condition) = self.condition.clone() {
             condition
         } else {
-            Expression::Number(1)
+            Expression::integer_constant(1)
         };
 
         let update = if let Some(update) = self.increment.clone() {
-            Statement::Expression { expression: update }
+            Statement::new(StatementKind::Expression { expression: update })
         } else {
-            Statement::Null
+            Statement::new(StatementKind::Null)
         };
 
         let inner_scope = (*self.block).clone();
@@ -90,21 +93,23 @@ impl Compile for ForStatement {
         Scope {
             items: vec![
                 init,
-                ScopeItem::Statement(Statement::While {
+                ScopeItem::Statement(Statement::new(StatementKind::While {
                     statement: WhileStatement {
                         condition,
-                        block: Arc::new(Statement::Scope {
+                        block: Arc::new(Statement::new(StatementKind::Scope {
                             scope: Scope {
                                 items: vec![
                                     ScopeItem::Statement(update),
                                     ScopeItem::Statement(inner_scope),
                                 ],
+                                span: Span::default(),
                             },
-                        }),
+                        })),
                         id: self.id.clone(),
                     },
-                }),
+                })),
             ],
+            span: Span::default(),
         }
         .compile(state)
     }
@@ -116,8 +121,9 @@ impl Compile for IfStatement {
 
         instructions.extend(self.condition.compile(state));
 
-        let end_of_if_label = unique_identifier(Some("if_end"), None);
-        let start_of_else_label = unique_identifier(Some("else_start"), None);
+        let id = state.next_id("if");
+        let end_of_if_label = state.label(&id, "end");
+        let start_of_else_label = state.label(&id, "else");
 
         instructions.push(Instruction::BeqzP(
             Register::A0,
@@ -156,10 +162,10 @@ impl Compile for JumpStatement {
                 instructions
             }
             JumpStatement::Break { id } => {
-                vec![Instruction::JP(Immediate::Label(id.clone() + "_end"))]
+                vec![Instruction::JP(Immediate::Label(state.label(id, "end")))]
             }
             JumpStatement::Continue { id } => {
-                vec![Instruction::JP(Immediate::Label(id.clone() + "_start"))]
+                vec![Instruction::JP(Immediate::Label(state.label(id, "start")))]
             }
             JumpStatement::__Ebreak => vec![Instruction::EBreak],
         }
@@ -175,26 +181,26 @@ impl Compile for SwitchStatement {
 
         for case in self.cases.iter() {
             if let Case::Case(case) = case {
-                let number = Expression::Number(*case);
+                let number = Expression::integer_constant(*case);
                 instructions.extend(number.compile(state));
                 instructions.push(Instruction::Beq(
                     Register::S1,
                     Register::A0,
-                    Immediate::Label(self.id.clone() + "____case_" + &case.to_string()),
+                    Immediate::Label(state.case_label(&self.id, *case)),
                 ));
             }
         }
         for case in self.cases.iter() {
             if let Case::Default = case {
                 instructions.push(Instruction::JP(Immediate::Label(
-                    self.id.clone() + "____default",
+                    state.label(&self.id, "default"),
                 )));
             }
         }
 
         instructions.extend(self.body.compile(state));
 
-        instructions.push(Instruction::Label(self.id.clone() + "_end"));
+        instructions.push(Instruction::Label(state.label(&self.id, "end")));
 
         instructions
     }
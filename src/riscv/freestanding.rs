@@ -0,0 +1,137 @@
+use super::{
+    instruction::Instruction,
+    values::{Immediate, Register, RegisterWithOffset},
+};
+
+#[derive(Debug, Clone)]
+pub struct FreestandingOptions {
+    pub rom_origin: u32,
+    pub rom_length: u32,
+    pub ram_origin: u32,
+    pub ram_length: u32,
+    // the return value of main is stored here, otherwise the hart spins
+    pub exit_device: Option<u32>,
+}
+
+// splits the 128M of ram of the qemu virt machine, which is where
+// `-bios none -kernel` loads the whole image
+impl Default for FreestandingOptions {
+    fn default() -> Self {
+        FreestandingOptions {
+            rom_origin: 0x8000_0000,
+            rom_length: 0x0040_0000,
+            ram_origin: 0x8040_0000,
+            ram_length: 0x07c0_0000,
+            exit_device: None,
+        }
+    }
+}
+
+fn label(name: &str) -> Immediate {
+    Immediate::Label(name.to_string())
+}
+
+// the symbols used here are defined by the linker script below
+pub fn crt0(options: &FreestandingOptions) -> Vec<Instruction> {
+    let mut instructions = vec![
+        Instruction::Symbol("section .text.init,\"ax\",@progbits".to_string()),
+        Instruction::Symbol("globl _start".to_string()),
+        Instruction::Label("_start".to_string()),
+        // gp must not be relaxed against itself
+        Instruction::Symbol("option push".to_string()),
+        Instruction::Symbol("option norelax".to_string()),
+        Instruction::LaP(Register::Gp, label("__global_pointer$")),
+        Instruction::Symbol("option pop".to_string()),
+        Instruction::LaP(Register::Sp, label("__stack_top")),
+        Instruction::Comment("Zeroing .bss".to_string()),
+        Instruction::LaP(Register::T0, label("__bss_start")),
+        Instruction::LaP(Register::T1, label("__bss_end")),
+        Instruction::Label("_start_clear_bss".to_string()),
+        Instruction::Bgeu(Register::T0, Register::T1, label("_start_copy_data")),
+        Instruction::Sw(Register::Zero, RegisterWithOffset(0.into(), Register::T0)),
+        Instruction::Addi(Register::T0, Register::T0, 4.into()),
+        Instruction::JP(label("_start_clear_bss")),
+        Instruction::Label("_start_copy_data".to_string()),
+        Instruction::Comment("Copying .data from its load address".to_string()),
+        Instruction::LaP(Register::T0, label("__data_load_start")),
+        Instruction::LaP(Register::T1, label("__data_start")),
+        Instruction::LaP(Register::T2, label("__data_end")),
+        Instruction::Label("_start_copy_data_loop".to_string()),
+        Instruction::Bgeu(Register::T1, Register::T2, label("_start_call_main")),
+        Instruction::Lw(Register::T3, RegisterWithOffset(0.into(), Register::T0)),
+        Instruction::Sw(Register::T3, RegisterWithOffset(0.into(), Register::T1)),
+        Instruction::Addi(Register::T0, Register::T0, 4.into()),
+        Instruction::Addi(Register::T1, Register::T1, 4.into()),
+        Instruction::JP(label("_start_copy_data_loop")),
+        Instruction::Label("_start_call_main".to_string()),
+        Instruction::CallP(label("main")),
+    ];
+
+    if let Some(exit_device) = options.exit_device {
+        instructions.push(Instruction::LiP(Register::T0, (exit_device as i32).into()));
+        instructions.push(Instruction::Sw(
+            Register::A0,
+            RegisterWithOffset(0.into(), Register::T0),
+        ));
+    }
+
+    instructions.extend([
+        Instruction::Label("_start_halt".to_string()),
+        Instruction::Wfi,
+        Instruction::JP(label("_start_halt")),
+    ]);
+
+    instructions
+}
+
+pub fn linker_script(options: &FreestandingOptions) -> String {
+    format!(
+        r#"OUTPUT_ARCH("riscv")
+ENTRY(_start)
+
+MEMORY
+{{
+    ROM (rx) : ORIGIN = {rom_origin:#x}, LENGTH = {rom_length:#x}
+    RAM (rwx) : ORIGIN = {ram_origin:#x}, LENGTH = {ram_length:#x}
+}}
+
+SECTIONS
+{{
+    .text : {{
+        KEEP(*(.text.init))
+        *(.text .text.*)
+    }} > ROM
+
+    .rodata : {{
+        *(.rodata .rodata.*)
+    }} > ROM
+
+    .data : ALIGN(4) {{
+        __data_start = .;
+        __global_pointer$ = . + 0x800;
+        *(.sdata .sdata.*)
+        *(.data .data.*)
+        . = ALIGN(4);
+        __data_end = .;
+    }} > RAM AT > ROM
+
+    __data_load_start = LOADADDR(.data);
+
+    .bss (NOLOAD) : ALIGN(4) {{
+        __bss_start = .;
+        *(.sbss .sbss.*)
+        *(.bss .bss.*)
+        *(COMMON)
+        . = ALIGN(4);
+        __bss_end = .;
+    }} > RAM
+
+    __stack_top = ORIGIN(RAM) + LENGTH(RAM);
+}}
+"#,
+        rom_origin = options.rom_origin,
+        rom_length = options.rom_length,
+        ram_origin = options.ram_origin,
+        ram_length = options.ram_length,
+    )
+}
@@ -1,13 +1,14 @@
 use std::fmt::Display;
 
-use super::values::{Immediate, Register, RegisterWithOffset};
+use super::values::{Csr, Immediate, Register, RegisterWithOffset};
 
-#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     Comment(String),
     Label(String),
     Symbol(String),
+    // emitted verbatim, used for inline assembly
+    Raw(String),
 
     // arithmetic
     Add(Register, Register, Register),
@@ -17,6 +18,8 @@ pub enum Instruction {
     Mul(Register, Register, Register),
     Div(Register, Register, Register),
     Rem(Register, Register, Register),
+    Divu(Register, Register, Register),
+    Remu(Register, Register, Register),
 
     // bitwise
     And(Register, Register, Register),
@@ -28,6 +31,7 @@ pub enum Instruction {
     // shift
     Sll(Register, Register, Register),
     Srl(Register, Register, Register),
+    Sra(Register, Register, Register),
 
     // load immediate
     LiP(Register, Immediate),
@@ -45,25 +49,52 @@ pub enum Instruction {
     Jalr(Register, RegisterWithOffset),
     CallP(Immediate),
     RetP,
+    // return from a trap taken into machine, supervisor or user mode
+    Mret,
+    Sret,
+    Uret,
 
     // branch
     Beq(Register, Register, Immediate),
     BeqzP(Register, Immediate),
     Bne(Register, Register, Immediate),
     BnezP(Register, Immediate),
+    Bgeu(Register, Register, Immediate),
 
     // set
+    Slt(Register, Register, Register),
     Sltu(Register, Register, Register),
     Sltiu(Register, Register, Immediate),
     SeqzP(Register, Register),
     SnezP(Register, Register),
     SeqP(Register, Register, Register),
 
+    // csr
+    CsrrP(Register, Csr),
+    CsrwP(Csr, Register),
+    CsrsP(Csr, Register),
+    CsrcP(Csr, Register),
+    Csrrw(Register, Csr, Register),
+
     // misc
     MvP(Register, Register),
     PushP(Register),
     PopP(Register),
     EBreak,
+    Ecall,
+    Wfi,
+    Fence,
+    FenceI,
+}
+
+// how an instruction passes on control, for building control flow graphs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Flow {
+    Next,
+    Jump(String),
+    // to the label or to the next instruction
+    Branch(String),
+    Exit,
 }
 
 // pseudoinstructions list: https://riscv.org/wp-content/uploads/2019/12/riscv-spec-20191213.pdf (page 139, Table 25.2)
@@ -73,17 +104,21 @@ impl Display for Instruction {
             Instruction::Comment(comment) => write!(f, "# {}", comment),
             Instruction::Label(label) => write!(f, "{}:", label),
             Instruction::Symbol(label) => write!(f, ".{}", label),
+            Instruction::Raw(text) => write!(f, "{}", text),
             Instruction::JP(imm) => write!(f, "j {}", imm),
             Instruction::Addi(rd, rs1, imm) => write!(f, "addi {}, {}, {}", rd, rs1, imm),
             Instruction::Add(rd, rs1, rs2) => write!(f, "add {}, {}, {}", rd, rs1, rs2),
             Instruction::Sll(rd, rs1, rs2) => write!(f, "sll {}, {}, {}", rd, rs1, rs2),
             Instruction::Srl(rd, rs1, rs2) => write!(f, "srl {}, {}, {}", rd, rs1, rs2),
+            Instruction::Sra(rd, rs1, rs2) => write!(f, "sra {}, {}, {}", rd, rs1, rs2),
             Instruction::And(rd, rs1, rs2) => write!(f, "and {}, {}, {}", rd, rs1, rs2),
             Instruction::Or(rd, rs1, rs2) => write!(f, "or {}, {}, {}", rd, rs1, rs2),
             Instruction::Sub(rd, rs1, rs2) => write!(f, "sub {}, {}, {}", rd, rs1, rs2),
             Instruction::Mul(rd, rs1, rs2) => write!(f, "mul {}, {}, {}", rd, rs1, rs2),
             Instruction::Div(rd, rs1, rs2) => write!(f, "div {}, {}, {}", rd, rs1, rs2),
             Instruction::Rem(rd, rs1, rs2) => write!(f, "rem {}, {}, {}", rd, rs1, rs2),
+            Instruction::Divu(rd, rs1, rs2) => write!(f, "divu {}, {}, {}", rd, rs1, rs2),
+            Instruction::Remu(rd, rs1, rs2) => write!(f, "remu {}, {}, {}", rd, rs1, rs2),
             Instruction::Sw(rs1, rs2) => write!(f, "sw {}, {}", rs1, rs2),
             Instruction::Sd(rs1, rs2) => write!(f, "sd {}, {}", rs1, rs2),
             Instruction::LiP(rd, imm) => write!(f, "li {}, {}", rd, imm),
@@ -96,39 +131,37 @@ impl Display for Instruction {
             Instruction::Xori(rd, rs1, imm) => write!(f, "xori {}, {}, {}", rd, rs1, imm),
             Instruction::Beq(rs1, rs2, imm) => write!(f, "beq {}, {}, {}", rs1, rs2, imm),
             Instruction::Bne(rs1, rs2, imm) => write!(f, "bne {}, {}, {}", rs1, rs2, imm),
+            Instruction::Bgeu(rs1, rs2, imm) => write!(f, "bgeu {}, {}, {}", rs1, rs2, imm),
+            Instruction::Slt(rd, rs1, rs2) => write!(f, "slt {}, {}, {}", rd, rs1, rs2),
             Instruction::Sltu(rd, rs1, imm) => write!(f, "sltu {}, {}, {}", rd, rs1, imm),
             Instruction::Sltiu(rd, rs1, imm) => write!(f, "sltiu {}, {}, {}", rd, rs1, imm),
             Instruction::CallP(symbol) => write!(f, "call {}", symbol),
             Instruction::RetP => write!(f, "ret"),
+            Instruction::Mret => write!(f, "mret"),
+            Instruction::Sret => write!(f, "sret"),
+            Instruction::Uret => write!(f, "uret"),
             Instruction::NotP(rd, rs1) => write!(f, "not {}, {}", rd, rs1),
             Instruction::BeqzP(rs1, imm) => write!(f, "beqz {}, {}", rs1, imm),
             Instruction::BnezP(rs1, imm) => write!(f, "bnez {}, {}", rs1, imm),
             Instruction::SeqzP(rd, rs1) => write!(f, "seqz {}, {}", rd, rs1),
             Instruction::SnezP(rd, rs1) => write!(f, "snez {}, {}", rd, rs1),
             Instruction::LaP(rd, imm) => write!(f, "la {}, {}", rd, imm),
-            Instruction::SeqP(rd, rs1, rs2) => {
-                Instruction::Xor(rd.clone(), rs1.clone(), rs2.clone()).fmt(f)?;
-                "\n".fmt(f)?;
-                Instruction::SeqzP(rd.clone(), rd.clone()).fmt(f)?;
-
-                Ok(())
-            }
             Instruction::MvP(rd, rs1) => write!(f, "mv {}, {}", rd, rs1),
-            Instruction::PushP(rs1) => {
-                Instruction::Addi(Register::Sp, Register::Sp, (-16).into()).fmt(f)?;
-                "\n".fmt(f)?;
-                Instruction::Sw(rs1.clone(), RegisterWithOffset(0.into(), Register::Sp)).fmt(f)?;
-
-                Ok(())
+            Instruction::SeqP(..) | Instruction::PushP(..) | Instruction::PopP(..) => {
+                let expanded = self.expand().unwrap_or_default();
+                let lines: Vec<String> = expanded.iter().map(ToString::to_string).collect();
+                write!(f, "{}", lines.join("\n"))
             }
-            Instruction::PopP(rd) => {
-                Instruction::Lw(rd.clone(), RegisterWithOffset(0.into(), Register::Sp)).fmt(f)?;
-                "\n".fmt(f)?;
-                Instruction::Addi(Register::Sp, Register::Sp, 16.into()).fmt(f)?;
-
-                Ok(())
-            },
             Instruction::EBreak => write!(f, "ebreak"),
+            Instruction::Ecall => write!(f, "ecall"),
+            Instruction::Wfi => write!(f, "wfi"),
+            Instruction::Fence => write!(f, "fence"),
+            Instruction::FenceI => write!(f, "fence.i"),
+            Instruction::CsrrP(rd, csr) => write!(f, "csrr {}, {}", rd, csr),
+            Instruction::CsrwP(csr, rs1) => write!(f, "csrw {}, {}", csr, rs1),
+            Instruction::CsrsP(csr, rs1) => write!(f, "csrs {}, {}", csr, rs1),
+            Instruction::CsrcP(csr, rs1) => write!(f, "csrc {}, {}", csr, rs1),
+            Instruction::Csrrw(rd, csr, rs1) => write!(f, "csrrw {}, {}, {}", rd, csr, rs1),
         }
     }
 }
@@ -139,16 +172,28 @@ impl Instruction {
             Instruction::Comment(_)
             | Instruction::Label(_)
             | Instruction::Symbol(_)
+            | Instruction::Raw(_)
             | Instruction::Sw(_, _)
             | Instruction::Sd(_, _)
             | Instruction::JP(_)
             | Instruction::CallP(_)
             | Instruction::RetP
+            | Instruction::Mret
+            | Instruction::Sret
+            | Instruction::Uret
             | Instruction::Beq(_, _, _)
             | Instruction::BeqzP(_, _)
             | Instruction::Bne(_, _, _)
             | Instruction::BnezP(_, _)
+            | Instruction::Bgeu(_, _, _)
             | Instruction::EBreak
+            | Instruction::Ecall
+            | Instruction::Wfi
+            | Instruction::Fence
+            | Instruction::FenceI
+            | Instruction::CsrwP(_, _)
+            | Instruction::CsrsP(_, _)
+            | Instruction::CsrcP(_, _)
             | Instruction::PushP(_) => None,
 
             Instruction::Neg(rd, _)
@@ -160,23 +205,29 @@ impl Instruction {
             | Instruction::Mul(rd, _, _)
             | Instruction::Div(rd, _, _)
             | Instruction::Rem(rd, _, _)
+            | Instruction::Divu(rd, _, _)
+            | Instruction::Remu(rd, _, _)
             | Instruction::And(rd, _, _)
             | Instruction::Or(rd, _, _)
             | Instruction::Xor(rd, _, _)
             | Instruction::Xori(rd, _, _)
             | Instruction::Sll(rd, _, _)
             | Instruction::Srl(rd, _, _)
+            | Instruction::Sra(rd, _, _)
             | Instruction::LiP(rd, _)
             | Instruction::Lw(rd, _)
             | Instruction::Ld(rd, _)
             | Instruction::Jal(rd, _)
             | Instruction::Jalr(rd, _)
             | Instruction::LaP(rd, _)
+            | Instruction::Slt(rd, _, _)
             | Instruction::Sltu(rd, _, _)
             | Instruction::Sltiu(rd, _, _)
             | Instruction::SeqzP(rd, _)
             | Instruction::SnezP(rd, _)
             | Instruction::SeqP(rd, _, _)
+            | Instruction::CsrrP(rd, _)
+            | Instruction::Csrrw(rd, _, _)
             | Instruction::PopP(rd) => Some(rd.clone()),
         }
     }
@@ -186,16 +237,28 @@ impl Instruction {
             Instruction::Comment(_)
             | Instruction::Label(_)
             | Instruction::Symbol(_)
+            | Instruction::Raw(_)
             | Instruction::Sw(_, _)
             | Instruction::Sd(_, _)
             | Instruction::JP(_)
             | Instruction::CallP(_)
             | Instruction::RetP
+            | Instruction::Mret
+            | Instruction::Sret
+            | Instruction::Uret
             | Instruction::Beq(_, _, _)
             | Instruction::BeqzP(_, _)
             | Instruction::Bne(_, _, _)
             | Instruction::BnezP(_, _)
+            | Instruction::Bgeu(_, _, _)
             | Instruction::EBreak
+            | Instruction::Ecall
+            | Instruction::Wfi
+            | Instruction::Fence
+            | Instruction::FenceI
+            | Instruction::CsrwP(_, _)
+            | Instruction::CsrsP(_, _)
+            | Instruction::CsrcP(_, _)
             | Instruction::PushP(_) => (),
 
             Instruction::Neg(rd, _)
@@ -207,23 +270,29 @@ impl Instruction {
             | Instruction::Mul(rd, _, _)
             | Instruction::Div(rd, _, _)
             | Instruction::Rem(rd, _, _)
+            | Instruction::Divu(rd, _, _)
+            | Instruction::Remu(rd, _, _)
             | Instruction::And(rd, _, _)
             | Instruction::Or(rd, _, _)
             | Instruction::Xor(rd, _, _)
             | Instruction::Xori(rd, _, _)
             | Instruction::Sll(rd, _, _)
             | Instruction::Srl(rd, _, _)
+            | Instruction::Sra(rd, _, _)
             | Instruction::LiP(rd, _)
             | Instruction::Lw(rd, _)
             | Instruction::Ld(rd, _)
             | Instruction::Jal(rd, _)
             | Instruction::Jalr(rd, _)
             | Instruction::LaP(rd, _)
+            | Instruction::Slt(rd, _, _)
             | Instruction::Sltu(rd, _, _)
             | Instruction::Sltiu(rd, _, _)
             | Instruction::SeqzP(rd, _)
             | Instruction::SnezP(rd, _)
             | Instruction::SeqP(rd, _, _)
+            | Instruction::CsrrP(rd, _)
+            | Instruction::Csrrw(rd, _, _)
             | Instruction::PopP(rd) => {
                 *rd = register;
             }
@@ -231,20 +300,294 @@ impl Instruction {
     }
 
     pub fn does_jump(&self) -> bool {
-        match self {
+        matches!(
+            self,
             Instruction::JP(_)
-            | Instruction::Jal(_, _)
-            | Instruction::Jalr(_, _)
+                | Instruction::Jal(_, _)
+                | Instruction::Jalr(_, _)
+                | Instruction::CallP(_)
+                | Instruction::RetP
+                | Instruction::Mret
+                | Instruction::Sret
+                | Instruction::Uret
+                | Instruction::Beq(_, _, _)
+                | Instruction::BeqzP(_, _)
+                | Instruction::Bne(_, _, _)
+                | Instruction::BnezP(_, _)
+                | Instruction::Bgeu(_, _, _)
+        )
+    }
+
+    pub fn is_call(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Jal(_, _) | Instruction::Jalr(_, _) | Instruction::CallP(_)
+        )
+    }
+
+    // every register the instruction reads or writes
+    pub fn registers(&self) -> Vec<Register> {
+        match self {
+            Instruction::Comment(_)
+            | Instruction::Label(_)
+            | Instruction::Symbol(_)
+            | Instruction::JP(_)
             | Instruction::CallP(_)
             | Instruction::RetP
-            | Instruction::Beq(_, _, _)
-            | Instruction::BeqzP(_, _)
-            | Instruction::Bne(_, _, _)
-            | Instruction::BnezP(_, _) => true,
-            _ => false,
+            | Instruction::Mret
+            | Instruction::Sret
+            | Instruction::Uret
+            | Instruction::EBreak
+            | Instruction::Ecall
+            | Instruction::Wfi
+            | Instruction::Fence
+            | Instruction::FenceI => vec![],
+
+            // inline assembly mentions its registers by name
+            Instruction::Raw(text) => text
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter_map(Register::from_name)
+                .collect(),
+
+            Instruction::LiP(rd, _)
+            | Instruction::LaP(rd, _)
+            | Instruction::Jal(rd, _)
+            | Instruction::BeqzP(rd, _)
+            | Instruction::BnezP(rd, _)
+            | Instruction::CsrrP(rd, _)
+            | Instruction::CsrwP(_, rd)
+            | Instruction::CsrsP(_, rd)
+            | Instruction::CsrcP(_, rd) => vec![rd.clone()],
+
+            Instruction::PushP(rd) | Instruction::PopP(rd) => vec![rd.clone(), Register::Sp],
+
+            Instruction::Neg(rd, rs1)
+            | Instruction::NotP(rd, rs1)
+            | Instruction::MvP(rd, rs1)
+            | Instruction::SeqzP(rd, rs1)
+            | Instruction::SnezP(rd, rs1)
+            | Instruction::Addi(rd, rs1, _)
+            | Instruction::Xori(rd, rs1, _)
+            | Instruction::Sltiu(rd, rs1, _)
+            | Instruction::Beq(rd, rs1, _)
+            | Instruction::Bne(rd, rs1, _)
+            | Instruction::Bgeu(rd, rs1, _)
+            | Instruction::Csrrw(rd, _, rs1)
+            | Instruction::Lw(rd, RegisterWithOffset(_, rs1))
+            | Instruction::Ld(rd, RegisterWithOffset(_, rs1))
+            | Instruction::Sw(rd, RegisterWithOffset(_, rs1))
+            | Instruction::Sd(rd, RegisterWithOffset(_, rs1))
+            | Instruction::Jalr(rd, RegisterWithOffset(_, rs1)) => {
+                vec![rd.clone(), rs1.clone()]
+            }
+
+            Instruction::Add(rd, rs1, rs2)
+            | Instruction::Sub(rd, rs1, rs2)
+            | Instruction::Mul(rd, rs1, rs2)
+            | Instruction::Div(rd, rs1, rs2)
+            | Instruction::Rem(rd, rs1, rs2)
+            | Instruction::Divu(rd, rs1, rs2)
+            | Instruction::Remu(rd, rs1, rs2)
+            | Instruction::And(rd, rs1, rs2)
+            | Instruction::Or(rd, rs1, rs2)
+            | Instruction::Xor(rd, rs1, rs2)
+            | Instruction::Sll(rd, rs1, rs2)
+            | Instruction::Srl(rd, rs1, rs2)
+            | Instruction::Sra(rd, rs1, rs2)
+            | Instruction::Slt(rd, rs1, rs2)
+            | Instruction::Sltu(rd, rs1, rs2)
+            | Instruction::SeqP(rd, rs1, rs2) => vec![rd.clone(), rs1.clone(), rs2.clone()],
+        }
+    }
+
+    // pseudoinstructions keep their own name here, push and pop included,
+    // labels, comments, directives and inline assembly have none
+    pub fn mnemonic(&self) -> Option<&'static str> {
+        let mnemonic = match self {
+            Instruction::Comment(_)
+            | Instruction::Label(_)
+            | Instruction::Symbol(_)
+            | Instruction::Raw(_) => return None,
+            Instruction::Add(..) => "add",
+            Instruction::Addi(..) => "addi",
+            Instruction::Neg(..) => "neg",
+            Instruction::Sub(..) => "sub",
+            Instruction::Mul(..) => "mul",
+            Instruction::Div(..) => "div",
+            Instruction::Rem(..) => "rem",
+            Instruction::Divu(..) => "divu",
+            Instruction::Remu(..) => "remu",
+            Instruction::And(..) => "and",
+            Instruction::NotP(..) => "not",
+            Instruction::Or(..) => "or",
+            Instruction::Xor(..) => "xor",
+            Instruction::Xori(..) => "xori",
+            Instruction::Sll(..) => "sll",
+            Instruction::Srl(..) => "srl",
+            Instruction::Sra(..) => "sra",
+            Instruction::LiP(..) => "li",
+            Instruction::Lw(..) => "lw",
+            Instruction::Ld(..) => "ld",
+            Instruction::LaP(..) => "la",
+            Instruction::Sw(..) => "sw",
+            Instruction::Sd(..) => "sd",
+            Instruction::JP(..) => "j",
+            Instruction::Jal(..) => "jal",
+            Instruction::Jalr(..) => "jalr",
+            Instruction::CallP(..) => "call",
+            Instruction::RetP => "ret",
+            Instruction::Mret => "mret",
+            Instruction::Sret => "sret",
+            Instruction::Uret => "uret",
+            Instruction::Beq(..) => "beq",
+            Instruction::BeqzP(..) => "beqz",
+            Instruction::Bne(..) => "bne",
+            Instruction::BnezP(..) => "bnez",
+            Instruction::Bgeu(..) => "bgeu",
+            Instruction::Slt(..) => "slt",
+            Instruction::Sltu(..) => "sltu",
+            Instruction::Sltiu(..) => "sltiu",
+            Instruction::SeqzP(..) => "seqz",
+            Instruction::SnezP(..) => "snez",
+            Instruction::SeqP(..) => "seq",
+            Instruction::CsrrP(..) => "csrr",
+            Instruction::CsrwP(..) => "csrw",
+            Instruction::CsrsP(..) => "csrs",
+            Instruction::CsrcP(..) => "csrc",
+            Instruction::Csrrw(..) => "csrrw",
+            Instruction::MvP(..) => "mv",
+            Instruction::PushP(..) => "push",
+            Instruction::PopP(..) => "pop",
+            Instruction::EBreak => "ebreak",
+            Instruction::Ecall => "ecall",
+            Instruction::Wfi => "wfi",
+            Instruction::Fence => "fence",
+            Instruction::FenceI => "fence.i",
+        };
+
+        Some(mnemonic)
+    }
+
+    pub fn operands(&self) -> Vec<String> {
+        match self {
+            Instruction::Comment(_)
+            | Instruction::Label(_)
+            | Instruction::Symbol(_)
+            | Instruction::Raw(_)
+            | Instruction::RetP
+            | Instruction::Mret
+            | Instruction::Sret
+            | Instruction::Uret
+            | Instruction::EBreak
+            | Instruction::Ecall
+            | Instruction::Wfi
+            | Instruction::Fence
+            | Instruction::FenceI => vec![],
+
+            Instruction::JP(imm) | Instruction::CallP(imm) => vec![imm.to_string()],
+            Instruction::PushP(rs1) | Instruction::PopP(rs1) => vec![rs1.to_string()],
+
+            Instruction::LiP(rd, imm)
+            | Instruction::LaP(rd, imm)
+            | Instruction::Jal(rd, imm)
+            | Instruction::BeqzP(rd, imm)
+            | Instruction::BnezP(rd, imm) => vec![rd.to_string(), imm.to_string()],
+
+            Instruction::Lw(rd, address)
+            | Instruction::Ld(rd, address)
+            | Instruction::Sw(rd, address)
+            | Instruction::Sd(rd, address)
+            | Instruction::Jalr(rd, address) => vec![rd.to_string(), address.to_string()],
+
+            Instruction::Neg(rd, rs1)
+            | Instruction::NotP(rd, rs1)
+            | Instruction::MvP(rd, rs1)
+            | Instruction::SeqzP(rd, rs1)
+            | Instruction::SnezP(rd, rs1) => vec![rd.to_string(), rs1.to_string()],
+
+            Instruction::CsrrP(rd, csr) => vec![rd.to_string(), csr.to_string()],
+            Instruction::CsrwP(csr, rs1)
+            | Instruction::CsrsP(csr, rs1)
+            | Instruction::CsrcP(csr, rs1) => {
+                vec![csr.to_string(), rs1.to_string()]
+            }
+            Instruction::Csrrw(rd, csr, rs1) => {
+                vec![rd.to_string(), csr.to_string(), rs1.to_string()]
+            }
+
+            Instruction::Addi(rd, rs1, imm)
+            | Instruction::Xori(rd, rs1, imm)
+            | Instruction::Sltiu(rd, rs1, imm)
+            | Instruction::Beq(rd, rs1, imm)
+            | Instruction::Bne(rd, rs1, imm)
+            | Instruction::Bgeu(rd, rs1, imm) => {
+                vec![rd.to_string(), rs1.to_string(), imm.to_string()]
+            }
+
+            Instruction::Add(rd, rs1, rs2)
+            | Instruction::Sub(rd, rs1, rs2)
+            | Instruction::Mul(rd, rs1, rs2)
+            | Instruction::Div(rd, rs1, rs2)
+            | Instruction::Rem(rd, rs1, rs2)
+            | Instruction::Divu(rd, rs1, rs2)
+            | Instruction::Remu(rd, rs1, rs2)
+            | Instruction::And(rd, rs1, rs2)
+            | Instruction::Or(rd, rs1, rs2)
+            | Instruction::Xor(rd, rs1, rs2)
+            | Instruction::Sll(rd, rs1, rs2)
+            | Instruction::Srl(rd, rs1, rs2)
+            | Instruction::Sra(rd, rs1, rs2)
+            | Instruction::Slt(rd, rs1, rs2)
+            | Instruction::Sltu(rd, rs1, rs2)
+            | Instruction::SeqP(rd, rs1, rs2) => {
+                vec![rd.to_string(), rs1.to_string(), rs2.to_string()]
+            }
         }
     }
 
+    // where control can go after the instruction, other than to the next one
+    pub fn flow(&self) -> Flow {
+        match self {
+            Instruction::JP(Immediate::Label(target))
+            | Instruction::Jal(Register::Zero, Immediate::Label(target)) => {
+                Flow::Jump(target.clone())
+            }
+            Instruction::Beq(_, _, Immediate::Label(target))
+            | Instruction::BeqzP(_, Immediate::Label(target))
+            | Instruction::Bne(_, _, Immediate::Label(target))
+            | Instruction::BnezP(_, Immediate::Label(target))
+            | Instruction::Bgeu(_, _, Immediate::Label(target)) => Flow::Branch(target.clone()),
+            Instruction::RetP
+            | Instruction::Mret
+            | Instruction::Sret
+            | Instruction::Uret
+            | Instruction::Jalr(Register::Zero, _) => Flow::Exit,
+            _ => Flow::Next,
+        }
+    }
+
+    // the pseudoinstructions that only this compiler knows, spelled out in
+    // instructions the assembler knows
+    pub fn expand(&self) -> Option<Vec<Instruction>> {
+        let expanded = match self {
+            Instruction::SeqP(rd, rs1, rs2) => vec![
+                Instruction::Xor(rd.clone(), rs1.clone(), rs2.clone()),
+                Instruction::SeqzP(rd.clone(), rd.clone()),
+            ],
+            Instruction::PushP(rs1) => vec![
+                Instruction::Addi(Register::Sp, Register::Sp, (-16).into()),
+                Instruction::Sw(rs1.clone(), RegisterWithOffset(0.into(), Register::Sp)),
+            ],
+            Instruction::PopP(rd) => vec![
+                Instruction::Lw(rd.clone(), RegisterWithOffset(0.into(), Register::Sp)),
+                Instruction::Addi(Register::Sp, Register::Sp, 16.into()),
+            ],
+            _ => return None,
+        };
+
+        Some(expanded)
+    }
+
     pub fn convert_to_equivalent(&self) -> Vec<Instruction> {
         match self {
             Instruction::Addi(rd, rs1, imm) => {
@@ -0,0 +1,643 @@
+// a static linker for freestanding programs, the same layout as the linker
+// script in `freestanding` without needing the gnu toolchain to read it
+use std::collections::{hash_map::Entry, HashMap};
+
+use crate::elf::{
+    Binding, Executable, LoadedSection, Object, Relocation, Section, SectionKind, Symbol,
+    SymbolKind, EF_RISCV_FLOAT_ABI, EF_RISCV_RVC,
+};
+
+use super::freestanding::FreestandingOptions;
+
+mod relax;
+mod relocate;
+
+// an object and the file it came from, for the errors
+pub struct Input {
+    pub name: String,
+    pub object: Object,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Region {
+    Rom,
+    Ram,
+}
+
+// where the sections of the inputs go, in the order they are laid out
+struct OutputSection {
+    name: String,
+    kind: SectionKind,
+    region: Region,
+    // the input sections the linker script puts here, each with the ones
+    // named after it, e.g. .text.main for .text
+    patterns: &'static [&'static str],
+    // input and section indices
+    members: Vec<(usize, usize)>,
+}
+
+// where everything is with the sections as they are now, relaxing moves it
+struct Layout {
+    // by input, then by section
+    addresses: Vec<Vec<u32>>,
+    // address, load address and size of each output section
+    outputs: Vec<(u32, u32, u32)>,
+    symbols: HashMap<&'static str, u32>,
+    rom_end: u32,
+    ram_end: u32,
+}
+
+struct Linker<'m> {
+    inputs: Vec<Input>,
+    memory: &'m FreestandingOptions,
+    outputs: Vec<OutputSection>,
+    // the defining input and symbol of every global name
+    globals: HashMap<String, (usize, usize)>,
+}
+
+const LINKER_SYMBOLS: &[&str] = &[
+    "__data_start",
+    "__global_pointer$",
+    "__data_end",
+    "__data_load_start",
+    "__bss_start",
+    "__bss_end",
+    "__stack_top",
+];
+
+fn output_sections(inputs: &[Input]) -> Vec<OutputSection> {
+    let output = |name: &str, kind, region, patterns| OutputSection {
+        name: name.to_string(),
+        kind,
+        region,
+        patterns,
+        members: Vec::new(),
+    };
+
+    let mut outputs = vec![
+        output(".text", SectionKind::Code, Region::Rom, &[".text"]),
+        output(".rodata", SectionKind::ReadOnly, Region::Rom, &[".rodata"]),
+        output(
+            ".data",
+            SectionKind::Data,
+            Region::Ram,
+            &[".sdata", ".data"],
+        ),
+        output(
+            ".bss",
+            SectionKind::Uninitialized,
+            Region::Ram,
+            &[".sbss", ".bss"],
+        ),
+    ];
+
+    // the startup code goes first, it is where a freestanding program starts
+    let sections = || {
+        inputs.iter().enumerate().flat_map(|(index, input)| {
+            (0..input.object.sections.len()).map(move |section| (index, section))
+        })
+    };
+    let startup = |&(input, section): &(usize, usize)| {
+        inputs[input].object.sections[section].name == ".text.init"
+    };
+
+    for (input, section) in sections()
+        .filter(startup)
+        .chain(sections().filter(|member| !startup(member)))
+    {
+        let Section { name, kind, .. } = &inputs[input].object.sections[section];
+        let matches = |pattern: &&str| {
+            name == pattern
+                || name
+                    .strip_prefix(pattern)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        };
+
+        let placed = outputs
+            .iter()
+            .position(|output| output.patterns.iter().any(matches) || output.name == *name);
+
+        // like ld, a section the script does not mention, e.g. .vectors,
+        // gets an output section of its own after the others of its kind.
+        // the startup code still only sets up .data and .bss
+        let index = placed.unwrap_or_else(|| {
+            let after = outputs
+                .iter()
+                .rposition(|output| output.kind == *kind)
+                .expect("there is an output section of every kind");
+            let region = outputs[after].region;
+            outputs.insert(after + 1, output(name, *kind, region, &[]));
+            after + 1
+        });
+
+        outputs[index].members.push((input, section));
+    }
+
+    outputs
+}
+
+// the float abi has to be the same everywhere, compressed code anywhere
+// makes the whole program compressed
+fn flags(inputs: &[Input]) -> Result<u32, String> {
+    let Some(first) = inputs.first() else {
+        return Ok(0);
+    };
+
+    let abi = first.object.flags & EF_RISCV_FLOAT_ABI;
+    let mut flags = abi;
+
+    for input in inputs.iter() {
+        if input.object.flags & EF_RISCV_FLOAT_ABI != abi {
+            return Err(format!(
+                "{}: Float ABI does not match the one of {}",
+                input.name, first.name
+            ));
+        }
+
+        flags |= input.object.flags & EF_RISCV_RVC;
+    }
+
+    Ok(flags)
+}
+
+// strong definitions win over weak ones, two strong ones are an error
+fn globals(inputs: &[Input]) -> Result<HashMap<String, (usize, usize)>, String> {
+    let mut globals: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for (index, input) in inputs.iter().enumerate() {
+        for (number, symbol) in input.object.symbols.iter().enumerate() {
+            if symbol.binding == Binding::Local || symbol.section.is_none() {
+                continue;
+            }
+
+            match globals.entry(symbol.name.clone()) {
+                Entry::Vacant(entry) => {
+                    entry.insert((index, number));
+                }
+                Entry::Occupied(mut entry) => {
+                    let (other, other_number) = *entry.get();
+                    let existing = &inputs[other].object.symbols[other_number];
+
+                    match (existing.binding, symbol.binding) {
+                        (Binding::Weak, Binding::Global) => {
+                            entry.insert((index, number));
+                        }
+                        (Binding::Global, Binding::Global) => {
+                            return Err(format!(
+                                "Multiple definitions of `{}` in {} and {}",
+                                symbol.name, inputs[other].name, input.name
+                            ));
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+
+    // undefined weak symbols are 0, anything else has to be somewhere
+    for input in inputs.iter() {
+        let undefined = input.object.symbols.iter().find(|symbol| {
+            symbol.section.is_none()
+                && symbol.binding == Binding::Global
+                && !globals.contains_key(&symbol.name)
+                && !LINKER_SYMBOLS.contains(&symbol.name.as_str())
+        });
+
+        if let Some(symbol) = undefined {
+            return Err(format!(
+                "{}: Undefined reference to `{}`",
+                input.name, symbol.name
+            ));
+        }
+    }
+
+    Ok(globals)
+}
+
+impl Linker<'_> {
+    // every input section with code, in address order
+    fn code(&self) -> Vec<(usize, usize)> {
+        self.outputs
+            .iter()
+            .filter(|output| output.kind == SectionKind::Code)
+            .flat_map(|output| output.members.iter().copied())
+            .collect()
+    }
+
+    fn section_length(&self, (input, section): (usize, usize)) -> u32 {
+        self.inputs[input].object.sections[section].data.len() as u32
+    }
+
+    fn section_alignment(&self, (input, section): (usize, usize)) -> u32 {
+        self.inputs[input].object.sections[section].alignment.max(1)
+    }
+
+    // the startup code copies data and zeroes bss a word at a time, so
+    // those start and end on a word
+    fn output_alignment(&self, output: &OutputSection) -> u32 {
+        let word = match output.region {
+            Region::Rom => 1,
+            Region::Ram => 4,
+        };
+
+        output
+            .members
+            .iter()
+            .map(|&member| self.section_alignment(member))
+            .fold(word, u32::max)
+    }
+
+    fn layout(&self) -> Layout {
+        let mut addresses: Vec<Vec<u32>> = self
+            .inputs
+            .iter()
+            .map(|input| vec![0; input.object.sections.len()])
+            .collect();
+        let mut outputs = Vec::new();
+
+        let mut rom = self.memory.rom_origin;
+        let mut ram = self.memory.ram_origin;
+
+        for output in self.outputs.iter() {
+            let alignment = self.output_alignment(output);
+            let cursor = match output.region {
+                Region::Rom => &mut rom,
+                Region::Ram => &mut ram,
+            };
+
+            let start = cursor.next_multiple_of(alignment);
+            let mut address = start;
+
+            for &(input, section) in output.members.iter() {
+                address = address.next_multiple_of(self.section_alignment((input, section)));
+                addresses[input][section] = address;
+                address += self.section_length((input, section));
+            }
+
+            if output.region == Region::Ram {
+                address = address.next_multiple_of(4);
+            }
+            *cursor = address;
+
+            // initialized data is loaded into rom, right after the rest
+            let load_address = match output.kind {
+                SectionKind::Data => {
+                    let load_address = rom.next_multiple_of(alignment);
+                    rom = load_address + (address - start);
+                    load_address
+                }
+                _ => start,
+            };
+
+            outputs.push((start, load_address, address - start));
+        }
+
+        let output = |name: &str| {
+            let index = self.outputs.iter().position(|output| output.name == name);
+            outputs[index.expect("the output sections of the linker script are always there")]
+        };
+        let (data_start, data_load_start, data_size) = output(".data");
+        let (bss_start, _, bss_size) = output(".bss");
+
+        let symbols = HashMap::from([
+            ("__data_start", data_start),
+            ("__global_pointer$", data_start + 0x800),
+            ("__data_end", data_start + data_size),
+            ("__data_load_start", data_load_start),
+            ("__bss_start", bss_start),
+            ("__bss_end", bss_start + bss_size),
+            (
+                "__stack_top",
+                self.memory.ram_origin.wrapping_add(self.memory.ram_length),
+            ),
+        ]);
+
+        Layout {
+            addresses,
+            outputs,
+            symbols,
+            rom_end: rom,
+            ram_end: ram,
+        }
+    }
+
+    fn check_regions(&self, layout: &Layout) -> Result<(), String> {
+        let regions = [
+            (
+                "ROM",
+                self.memory.rom_origin,
+                self.memory.rom_length,
+                layout.rom_end,
+            ),
+            (
+                "RAM",
+                self.memory.ram_origin,
+                self.memory.ram_length,
+                layout.ram_end,
+            ),
+        ];
+
+        for (name, origin, length, end) in regions {
+            let used = end - origin;
+            if used > length {
+                return Err(format!(
+                    "Region {name} overflowed by {} bytes",
+                    used - length
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // where a symbol of an input ends up
+    fn symbol_address(&self, layout: &Layout, input: usize, symbol: usize) -> u32 {
+        let defined = &self.inputs[input].object.symbols[symbol];
+
+        if let Some(section) = defined.section {
+            return layout.addresses[input][section] + defined.value;
+        }
+
+        if let Some(&(input, symbol)) = self.globals.get(&defined.name) {
+            return self.symbol_address(layout, input, symbol);
+        }
+
+        // undefined weak symbols are 0, the rest were checked up front
+        layout
+            .symbols
+            .get(defined.name.as_str())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // S + A in the terms of the psabi
+    fn target(&self, layout: &Layout, input: usize, relocation: &Relocation) -> u32 {
+        let symbol = relocation
+            .symbol
+            .map_or(0, |symbol| self.symbol_address(layout, input, symbol));
+        symbol.wrapping_add(relocation.addend as u32)
+    }
+
+    // what a relocation is against, for the errors
+    fn symbol_name(&self, input: usize, relocation: &Relocation) -> String {
+        let object = &self.inputs[input].object;
+
+        match relocation.symbol.map(|symbol| &object.symbols[symbol]) {
+            Some(Symbol {
+                kind: SymbolKind::Section,
+                section: Some(section),
+                ..
+            }) => object.sections[*section].name.clone(),
+            Some(symbol) => symbol.name.clone(),
+            None => String::new(),
+        }
+    }
+
+    fn executable(self, layout: &Layout) -> Result<Executable, String> {
+        let entry = self
+            .globals
+            .get("_start")
+            .map(|&(input, symbol)| self.symbol_address(layout, input, symbol))
+            .ok_or_else(|| "Undefined entry symbol `_start`".to_string())?;
+
+        let mut sections = Vec::new();
+        // output section to the section of the executable, empty ones are
+        // left out
+        let mut section_index = vec![None; self.outputs.len()];
+
+        for (index, output) in self.outputs.iter().enumerate() {
+            let (address, load_address, size) = layout.outputs[index];
+            if size == 0 {
+                continue;
+            }
+
+            let mut data = Vec::with_capacity(size as usize);
+            for &(input, section) in output.members.iter() {
+                let offset = layout.addresses[input][section] - address;
+                data.resize(offset as usize, 0);
+                data.extend(&self.inputs[input].object.sections[section].data);
+            }
+            data.resize(size as usize, 0);
+
+            section_index[index] = Some(sections.len());
+            sections.push(LoadedSection {
+                name: output.name.clone(),
+                kind: output.kind,
+                address,
+                load_address,
+                alignment: self.output_alignment(output),
+                data,
+            });
+        }
+
+        let output_of = |input: usize, section: usize| {
+            let output = self
+                .outputs
+                .iter()
+                .position(|output| output.members.contains(&(input, section)));
+            output.and_then(|output| section_index[output])
+        };
+
+        // the named symbols, local labels and section symbols are of no use
+        // in an executable
+        let mut symbols = Vec::new();
+        for (index, input) in self.inputs.iter().enumerate() {
+            for (number, symbol) in input.object.symbols.iter().enumerate() {
+                let Some(section) = symbol.section else {
+                    continue;
+                };
+
+                let unnamed = symbol.kind == SymbolKind::Section || symbol.name.starts_with(".L");
+                let overridden = symbol.binding != Binding::Local
+                    && self.globals.get(&symbol.name) != Some(&(index, number));
+                if unnamed || overridden || symbol.name.is_empty() {
+                    continue;
+                }
+
+                symbols.push(Symbol {
+                    section: output_of(index, section),
+                    value: layout.addresses[index][section] + symbol.value,
+                    ..symbol.clone()
+                });
+            }
+        }
+
+        for name in LINKER_SYMBOLS.iter() {
+            if self.globals.contains_key(*name) {
+                continue;
+            }
+
+            symbols.push(Symbol {
+                name: name.to_string(),
+                binding: Binding::Global,
+                kind: SymbolKind::None,
+                section: None,
+                value: layout.symbols[name],
+                size: 0,
+            });
+        }
+
+        Ok(Executable {
+            flags: flags(&self.inputs)?,
+            entry,
+            sections,
+            symbols,
+        })
+    }
+}
+
+// resolves the symbols of the objects against each other, lays them out in
+// the memory map and fills in the relocations. calls are relaxed to a
+// single jal where the target is close enough
+pub fn link(inputs: Vec<Input>, memory: &FreestandingOptions) -> Result<Executable, String> {
+    flags(&inputs)?;
+
+    let mut linker = Linker {
+        outputs: output_sections(&inputs),
+        globals: globals(&inputs)?,
+        inputs,
+        memory,
+    };
+
+    linker.relax_calls();
+    linker.relax_alignment()?;
+
+    let layout = linker.layout();
+    linker.check_regions(&layout)?;
+    linker.relocate(&layout)?;
+
+    linker.executable(&layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assemble,
+        elf::{
+            Binding, Object, Relocation, RelocationKind, Section, SectionKind, Symbol, SymbolKind,
+            EF_RISCV_RVC,
+        },
+        Options,
+    };
+
+    use super::*;
+
+    // the built-in assembler never compresses, objects of the toolchain do
+    #[test]
+    fn compressed_jumps_and_branches() {
+        let relocation = |offset, kind, symbol, addend| Relocation {
+            offset,
+            kind,
+            symbol: Some(symbol),
+            addend,
+        };
+
+        // c.j and c.beqz a0 forward to the end and back to the start
+        let object = Object {
+            flags: EF_RISCV_RVC,
+            sections: vec![Section {
+                relocations: vec![
+                    relocation(0, RelocationKind::RvcJump, 0, 6),
+                    relocation(2, RelocationKind::RvcBranch, 0, 6),
+                    relocation(6, RelocationKind::RvcJump, 1, 0),
+                    relocation(8, RelocationKind::RvcBranch, 1, 0),
+                ],
+                data: vec![0x01, 0xa0, 0x01, 0xc1, 0x01, 0x00, 0x01, 0xa0, 0x01, 0xc1],
+                ..Section::new(".text", SectionKind::Code)
+            }],
+            symbols: vec![
+                Symbol {
+                    name: String::new(),
+                    binding: Binding::Local,
+                    kind: SymbolKind::Section,
+                    section: Some(0),
+                    value: 0,
+                    size: 0,
+                },
+                Symbol {
+                    name: "_start".to_string(),
+                    binding: Binding::Global,
+                    kind: SymbolKind::None,
+                    section: Some(0),
+                    value: 0,
+                    size: 0,
+                },
+            ],
+        };
+
+        let input = Input {
+            name: "start.o".to_string(),
+            object,
+        };
+        let executable = link(vec![input], &FreestandingOptions::default()).unwrap();
+
+        assert_eq!(executable.flags & EF_RISCV_RVC, EF_RISCV_RVC);
+
+        let halves: Vec<u16> = executable.sections[0]
+            .data
+            .chunks(2)
+            .map(|half| u16::from_le_bytes(half.try_into().unwrap()))
+            .collect();
+        assert_eq!(halves, [0xa019, 0xc111, 0x0001, 0xbfed, 0xdd65]);
+    }
+
+    #[test]
+    fn sections_the_script_does_not_mention_are_kept_apart() {
+        let input = |name: &str, source: &str| Input {
+            name: name.to_string(),
+            object: Object::parse(&assemble(source, &Options::default()).unwrap()).unwrap(),
+        };
+
+        let start = input(
+            "start.o",
+            "
+            .section .text.init,\"ax\",@progbits
+            .globl _start
+        _start:
+            call handler
+            .text
+        main:
+            ret
+            .section .data.counter,\"aw\",@progbits
+            .word 1",
+        );
+        let vectors = input(
+            "vectors.o",
+            "
+            .section .vectors,\"ax\",@progbits
+            .globl handler
+        handler:
+            ret
+            .section .rodata
+            .word 2
+            .section .config,\"aw\",@progbits
+            .word 3",
+        );
+
+        let executable = link(vec![start, vectors], &FreestandingOptions::default()).unwrap();
+
+        let sections: Vec<_> = executable
+            .sections
+            .iter()
+            .map(|section| (section.name.as_str(), section.kind))
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                (".text", SectionKind::Code),
+                (".vectors", SectionKind::Code),
+                (".rodata", SectionKind::ReadOnly),
+                (".data", SectionKind::Data),
+                (".config", SectionKind::Data),
+            ]
+        );
+
+        // the vectors come right after the code, and the call to them from
+        // the startup code is still relaxed
+        let text = &executable.sections[0];
+        let handler = &executable.sections[1];
+        assert_eq!(handler.address, text.address + text.data.len() as u32);
+        assert_eq!(text.data.len(), 8);
+    }
+}
@@ -0,0 +1,179 @@
+// shrinking code once the addresses are known. removing bytes moves
+// everything after them, so the labels and relocations of the section move
+// along
+use crate::elf::{Object, RelocationKind, SymbolKind};
+
+use super::{Input, Linker};
+
+const JAL: u32 = 0x6f;
+const NOP: u32 = 0x0000_0013;
+const COMPRESSED_NOP: u16 = 0x0001;
+
+// where an offset in a section goes when `count` bytes at `at` are removed
+fn moved(offset: u32, at: u32, count: u32) -> u32 {
+    if offset >= at + count {
+        offset - count
+    } else {
+        offset.min(at)
+    }
+}
+
+fn delete(object: &mut Object, section: usize, at: u32, count: u32) {
+    let data = &mut object.sections[section].data;
+    data.drain(at as usize..(at + count) as usize);
+
+    for relocation in object.sections[section].relocations.iter_mut() {
+        relocation.offset = moved(relocation.offset, at, count);
+    }
+
+    for symbol in object.symbols.iter_mut() {
+        if symbol.section != Some(section) || symbol.kind == SymbolKind::Section {
+            continue;
+        }
+
+        let end = moved(symbol.value + symbol.size, at, count);
+        symbol.value = moved(symbol.value, at, count);
+        symbol.size = end - symbol.value;
+    }
+
+    // labels the assembler left out are the section plus an addend, from
+    // any section of the object
+    let symbols = &object.symbols;
+    for relocation in object
+        .sections
+        .iter_mut()
+        .flat_map(|section| section.relocations.iter_mut())
+    {
+        let Some(symbol) = relocation.symbol.map(|symbol| &symbols[symbol]) else {
+            continue;
+        };
+
+        if symbol.kind == SymbolKind::Section && symbol.section == Some(section) {
+            relocation.addend = moved(relocation.addend as u32, at, count) as i32;
+        }
+    }
+}
+
+impl Linker<'_> {
+    // the largest alignment of any section, removing bytes before a section
+    // can move it by up to that much the other way
+    fn max_alignment(&self) -> u32 {
+        self.outputs
+            .iter()
+            .flat_map(|output| output.members.iter())
+            .map(|&member| self.section_alignment(member))
+            .max()
+            .unwrap_or(1)
+    }
+
+    // auipc and jalr to a single jal when the target is in reach of one,
+    // until there is nothing left to relax
+    pub(super) fn relax_calls(&mut self) {
+        let reach = (1 << 20) - self.max_alignment() as i64;
+        let code = self.code();
+
+        loop {
+            let layout = self.layout();
+            let mut changed = false;
+
+            for &(input, section) in code.iter() {
+                let mut index = 0;
+
+                while index
+                    < self.inputs[input].object.sections[section]
+                        .relocations
+                        .len()
+                {
+                    let relocations = &self.inputs[input].object.sections[section].relocations;
+                    let relocation = relocations[index].clone();
+
+                    let relaxable = matches!(
+                        relocation.kind,
+                        RelocationKind::Call | RelocationKind::CallPlt
+                    ) && relocations.get(index + 1).is_some_and(|next| {
+                        next.kind == RelocationKind::Relax && next.offset == relocation.offset
+                    });
+
+                    let address = layout.addresses[input][section] + relocation.offset;
+                    let distance = self.target(&layout, input, &relocation) as i64 - address as i64;
+
+                    if !relaxable || distance.abs() >= reach {
+                        index += 1;
+                        continue;
+                    }
+
+                    let object = &mut self.inputs[input].object;
+                    let offset = relocation.offset;
+                    let data = &mut object.sections[section].data;
+
+                    // the jal links to where the jalr did
+                    let at = offset as usize + 4;
+                    let jalr =
+                        u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+                    let jal = (jalr & 0xf80) | JAL;
+                    data[offset as usize..at].copy_from_slice(&jal.to_le_bytes());
+
+                    let relocations = &mut object.sections[section].relocations;
+                    relocations[index].kind = RelocationKind::Jal;
+                    relocations.remove(index + 1);
+
+                    delete(object, section, offset + 4, 4);
+                    changed = true;
+                    index += 1;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    // the assembler pads for the worst case before an aligned label, this
+    // removes what is not needed now that the code before it has shrunk.
+    // the sections are done in address order so that the ones before are
+    // already final
+    pub(super) fn relax_alignment(&mut self) -> Result<(), String> {
+        let code = self.code();
+
+        for (input, section) in code {
+            let base = self.layout().addresses[input][section];
+            let Input { name, object } = &mut self.inputs[input];
+
+            while let Some(index) = object.sections[section]
+                .relocations
+                .iter()
+                .position(|relocation| relocation.kind == RelocationKind::Align)
+            {
+                let relocation = object.sections[section].relocations.remove(index);
+                let offset = relocation.offset;
+                let padding = relocation.addend as u32;
+
+                // the padding is the alignment less the smallest instruction
+                let alignment = (padding + 1).next_power_of_two();
+                let address = base + offset;
+                let needed = address.next_multiple_of(alignment) - address;
+
+                if needed > padding {
+                    return Err(format!(
+                        "{name}: Not enough padding to align to {alignment} bytes"
+                    ));
+                }
+
+                let data = &mut object.sections[section].data;
+                let mut at = offset as usize;
+                for _ in 0..needed / 4 {
+                    data[at..at + 4].copy_from_slice(&NOP.to_le_bytes());
+                    at += 4;
+                }
+                if needed % 4 == 2 {
+                    data[at..at + 2].copy_from_slice(&COMPRESSED_NOP.to_le_bytes());
+                }
+
+                delete(object, section, offset + needed, padding - needed);
+            }
+        }
+
+        Ok(())
+    }
+}
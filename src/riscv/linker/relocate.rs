@@ -0,0 +1,176 @@
+// filling the addresses into the instructions and data that refer to them
+use crate::{
+    elf::{Relocation, RelocationKind},
+    riscv::assembler::encode::{b_type, fits_signed, i_immediate, j_type, s_immediate},
+};
+
+use super::{Layout, Linker};
+
+// the immediates of the compressed formats, put where the format has them
+fn cb_type(value: u32) -> u32 {
+    ((value >> 8 & 0x1) << 12)
+        | ((value >> 3 & 0x3) << 10)
+        | ((value >> 6 & 0x3) << 5)
+        | ((value >> 1 & 0x3) << 3)
+        | ((value >> 5 & 0x1) << 2)
+}
+
+fn cj_type(value: u32) -> u32 {
+    ((value >> 11 & 0x1) << 12)
+        | ((value >> 4 & 0x1) << 11)
+        | ((value >> 8 & 0x3) << 9)
+        | ((value >> 10 & 0x1) << 8)
+        | ((value >> 6 & 0x1) << 7)
+        | ((value >> 7 & 0x1) << 6)
+        | ((value >> 1 & 0x7) << 3)
+        | ((value >> 5 & 0x1) << 2)
+}
+
+// the upper 20 bits, rounded so that the sign extended lower 12 bits add
+// back up to the value
+fn high(value: u32) -> u32 {
+    value.wrapping_add(0x800) & 0xffff_f000
+}
+
+fn low(value: u32) -> u32 {
+    value.wrapping_sub(high(value))
+}
+
+fn patch(data: &mut [u8], offset: u32, mask: u32, bits: u32) {
+    let at = offset as usize;
+    let word = u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+    data[at..at + 4].copy_from_slice(&(word & !mask | bits).to_le_bytes());
+}
+
+fn patch_compressed(data: &mut [u8], offset: u32, mask: u16, bits: u32) {
+    let at = offset as usize;
+    let half = u16::from_le_bytes([data[at], data[at + 1]]);
+    data[at..at + 2].copy_from_slice(&(half & !mask | bits as u16).to_le_bytes());
+}
+
+impl Linker<'_> {
+    // the pc relative offset a pcrel_lo12 stands for, the one of the
+    // pcrel_hi20 at the address its symbol points to
+    fn pcrel_high(
+        &self,
+        layout: &Layout,
+        input: usize,
+        relocations: &[Relocation],
+        base: u32,
+        relocation: &Relocation,
+    ) -> Result<u32, String> {
+        let auipc = self.target(layout, input, relocation);
+
+        relocations
+            .iter()
+            .find(|high| high.kind == RelocationKind::PcrelHi20 && base + high.offset == auipc)
+            .map(|high| self.target(layout, input, high).wrapping_sub(auipc))
+            .ok_or_else(|| {
+                format!(
+                    "{}: No pcrel_hi20 for the pcrel_lo12 at `{}`",
+                    self.inputs[input].name,
+                    self.symbol_name(input, relocation)
+                )
+            })
+    }
+
+    fn apply(
+        &self,
+        layout: &Layout,
+        (input, section): (usize, usize),
+        data: &mut [u8],
+    ) -> Result<(), String> {
+        let base = layout.addresses[input][section];
+        let relocations = &self.inputs[input].object.sections[section].relocations;
+
+        for relocation in relocations.iter() {
+            let offset = relocation.offset;
+            let target = self.target(layout, input, relocation);
+            let relative = target.wrapping_sub(base + offset);
+            // what a pc relative offset has to fit in, as the signed value
+            let distance = relative as i32 as i64;
+
+            let in_range = |fits: bool| {
+                if fits {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "{}: Relocation {:?} against `{}` is out of range",
+                        self.inputs[input].name,
+                        relocation.kind,
+                        self.symbol_name(input, relocation)
+                    ))
+                }
+            };
+
+            match relocation.kind {
+                RelocationKind::Absolute32 => {
+                    let at = offset as usize;
+                    data[at..at + 4].copy_from_slice(&target.to_le_bytes());
+                }
+                RelocationKind::Branch => {
+                    in_range(fits_signed(distance, 13))?;
+                    patch(data, offset, 0xfe00_0f80, b_type(relative));
+                }
+                RelocationKind::Jal => {
+                    in_range(fits_signed(distance, 21))?;
+                    patch(data, offset, 0xffff_f000, j_type(relative));
+                }
+                RelocationKind::Call | RelocationKind::CallPlt => {
+                    patch(data, offset, 0xffff_f000, high(relative));
+                    patch(data, offset + 4, 0xfff0_0000, i_immediate(low(relative)));
+                }
+                RelocationKind::PcrelHi20 => {
+                    patch(data, offset, 0xffff_f000, high(relative));
+                }
+                RelocationKind::PcrelLo12I => {
+                    let relative = self.pcrel_high(layout, input, relocations, base, relocation)?;
+                    patch(data, offset, 0xfff0_0000, i_immediate(low(relative)));
+                }
+                RelocationKind::PcrelLo12S => {
+                    let relative = self.pcrel_high(layout, input, relocations, base, relocation)?;
+                    patch(data, offset, 0xfe00_0f80, s_immediate(low(relative)));
+                }
+                RelocationKind::Hi20 => {
+                    patch(data, offset, 0xffff_f000, high(target));
+                }
+                RelocationKind::Lo12I => {
+                    patch(data, offset, 0xfff0_0000, i_immediate(low(target)));
+                }
+                RelocationKind::Lo12S => {
+                    patch(data, offset, 0xfe00_0f80, s_immediate(low(target)));
+                }
+                RelocationKind::RvcBranch => {
+                    in_range(fits_signed(distance, 9))?;
+                    patch_compressed(data, offset, 0x1c7c, cb_type(relative));
+                }
+                RelocationKind::RvcJump => {
+                    in_range(fits_signed(distance, 12))?;
+                    patch_compressed(data, offset, 0x1ffc, cj_type(relative));
+                }
+                // done with while relaxing
+                RelocationKind::Align | RelocationKind::Relax => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    // once the layout is final
+    pub(super) fn relocate(&mut self, layout: &Layout) -> Result<(), String> {
+        let members: Vec<(usize, usize)> = self
+            .outputs
+            .iter()
+            .flat_map(|output| output.members.iter().copied())
+            .collect();
+
+        for (input, section) in members {
+            let mut data = std::mem::take(&mut self.inputs[input].object.sections[section].data);
+            let applied = self.apply(layout, (input, section), &mut data);
+            self.inputs[input].object.sections[section].data = data;
+            applied?;
+        }
+
+        Ok(())
+    }
+}
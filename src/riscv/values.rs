@@ -2,7 +2,6 @@ use std::fmt::Display;
 
 const USE_ABI_REGISTER_NAMES: bool = true;
 
-#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Register {
     Zero = 0,
@@ -39,6 +38,82 @@ pub enum Register {
     T6 = 31,
 }
 
+impl Register {
+    pub const ALL: [Register; 32] = [
+        Register::Zero,
+        Register::Ra,
+        Register::Sp,
+        Register::Gp,
+        Register::Tp,
+        Register::T0,
+        Register::T1,
+        Register::T2,
+        Register::Fp,
+        Register::S1,
+        Register::A0,
+        Register::A1,
+        Register::A2,
+        Register::A3,
+        Register::A4,
+        Register::A5,
+        Register::A6,
+        Register::A7,
+        Register::S2,
+        Register::S3,
+        Register::S4,
+        Register::S5,
+        Register::S6,
+        Register::S7,
+        Register::S8,
+        Register::S9,
+        Register::S10,
+        Register::S11,
+        Register::T3,
+        Register::T4,
+        Register::T5,
+        Register::T6,
+    ];
+
+    // registers a called function is free to overwrite
+    pub const CALLER_SAVED: [Register; 16] = [
+        Register::Ra,
+        Register::T0,
+        Register::T1,
+        Register::T2,
+        Register::A0,
+        Register::A1,
+        Register::A2,
+        Register::A3,
+        Register::A4,
+        Register::A5,
+        Register::A6,
+        Register::A7,
+        Register::T3,
+        Register::T4,
+        Register::T5,
+        Register::T6,
+    ];
+
+    // accepts abi names, fp and the numeric xN names
+    pub fn from_name(name: &str) -> Option<Register> {
+        if name == "fp" {
+            return Some(Register::Fp);
+        }
+
+        Register::ALL
+            .iter()
+            .enumerate()
+            .find_map(|(number, register)| {
+                let matches = match register {
+                    Register::Fp => name == "s0",
+                    _ => format!("{:?}", register).to_lowercase() == name,
+                } || name == format!("x{number}");
+
+                matches.then(|| register.clone())
+            })
+    }
+}
+
 impl Display for Register {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if USE_ABI_REGISTER_NAMES {
@@ -113,3 +188,74 @@ impl From<i32> for Immediate {
         Immediate::Number(number)
     }
 }
+
+// symbolic names accepted for the csr operand of the csr builtins
+const CSR_NAMES: [(&str, u16); 41] = [
+    ("cycle", 0xC00),
+    ("time", 0xC01),
+    ("instret", 0xC02),
+    ("cycleh", 0xC80),
+    ("timeh", 0xC81),
+    ("instreth", 0xC82),
+    ("sstatus", 0x100),
+    ("sie", 0x104),
+    ("stvec", 0x105),
+    ("scounteren", 0x106),
+    ("sscratch", 0x140),
+    ("sepc", 0x141),
+    ("scause", 0x142),
+    ("stval", 0x143),
+    ("sip", 0x144),
+    ("satp", 0x180),
+    ("mvendorid", 0xF11),
+    ("marchid", 0xF12),
+    ("mimpid", 0xF13),
+    ("mhartid", 0xF14),
+    ("mstatus", 0x300),
+    ("misa", 0x301),
+    ("medeleg", 0x302),
+    ("mideleg", 0x303),
+    ("mie", 0x304),
+    ("mtvec", 0x305),
+    ("mcounteren", 0x306),
+    ("mstatush", 0x310),
+    ("mscratch", 0x340),
+    ("mepc", 0x341),
+    ("mcause", 0x342),
+    ("mtval", 0x343),
+    ("mip", 0x344),
+    ("pmpcfg0", 0x3A0),
+    ("pmpaddr0", 0x3B0),
+    ("mcycle", 0xB00),
+    ("minstret", 0xB02),
+    ("mcycleh", 0xB80),
+    ("minstreth", 0xB82),
+    ("tselect", 0x7A0),
+    ("dcsr", 0x7B0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Csr(pub u16);
+
+impl Csr {
+    pub fn from_name(name: &str) -> Option<Csr> {
+        CSR_NAMES
+            .iter()
+            .find(|(csr_name, _)| *csr_name == name)
+            .map(|(_, number)| Csr(*number))
+    }
+
+    // the top two bits of the csr number are 0b11 for read-only csrs
+    pub fn is_read_only(&self) -> bool {
+        self.0 >> 10 == 0b11
+    }
+}
+
+impl Display for Csr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match CSR_NAMES.iter().find(|(_, number)| *number == self.0) {
+            Some((name, _)) => write!(f, "{}", name),
+            None => write!(f, "{:#x}", self.0),
+        }
+    }
+}
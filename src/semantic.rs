@@ -0,0 +1,149 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    diagnostic::{code, warning::Warning, Diagnostic, Diagnostics, ErrorReported},
+    parser::expression::fold::Fold,
+    types::{
+        datatype::Datatype,
+        function_definition::FunctionKind,
+        program::{Program, ProgramStatement},
+        span::Span,
+    },
+};
+
+pub mod conversion;
+pub mod expression;
+pub mod flow;
+pub mod statement;
+
+// errors are diagnostics without a location yet, the innermost node with
+// a span fills it in on the way out
+pub type SemanticResult<T> = Result<T, Box<Diagnostic>>;
+
+pub fn error<T>(code: &'static str, message: impl Into<String>) -> SemanticResult<T> {
+    Err(Box::new(Diagnostic::error(message).with_code(code)))
+}
+
+// objects can be no bigger than the largest difference of two pointers, and
+// the frame leaves some room below that for the saved registers
+const MAX_OBJECT_SIZE: usize = i32::MAX as usize;
+const MAX_FRAME_SIZE: usize = 0x7fff_0000;
+
+pub fn check_supported(datatype: &Datatype) -> SemanticResult<()> {
+    if datatype.has_long_long() {
+        return error(code::UNSUPPORTED, format!("'{datatype}' is not supported"));
+    }
+
+    check_array_lengths(datatype)
+}
+
+// the length of an array is greater than zero (C11 6.7.6.2), variable ones
+// are rejected on their own
+fn check_array_lengths(datatype: &Datatype) -> SemanticResult<()> {
+    match datatype {
+        Datatype::Integer(_) => Ok(()),
+        Datatype::Pointer { inner } => check_array_lengths(inner),
+        Datatype::Function {
+            return_type,
+            arguments,
+        } => {
+            check_array_lengths(return_type)?;
+            arguments
+                .iter()
+                .try_for_each(|argument| check_array_lengths(&argument.datatype))
+        }
+        Datatype::Array { inner, length } => {
+            check_array_lengths(inner)?;
+
+            match length.fold() {
+                Some(length) if length <= 0 => error(
+                    code::INVALID_SIZE,
+                    format!("Array length must be greater than zero, found {length}"),
+                ),
+                Some(_)
+                    if datatype
+                        .checked_size()
+                        .is_none_or(|size| size > MAX_OBJECT_SIZE) =>
+                {
+                    error(code::INVALID_SIZE, format!("'{datatype}' is too large"))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+}
+
+// the parser already resolved every name, so variables are keyed by their
+// unique name and there is no need for scopes here
+#[derive(Debug, Default)]
+pub struct Analyzer {
+    variables: HashMap<String, Datatype>,
+    functions: HashMap<String, Datatype>,
+    // file-scope objects, keyed by their symbol
+    globals: HashMap<String, Datatype>,
+    // the objects this unit defines, and the ones with an initializer
+    // somewhere in it
+    definitions: HashMap<String, Span>,
+    initialized: HashSet<String>,
+    declarations: HashMap<String, Span>,
+    // case and default labels by switch and value, default has no value
+    case_labels: HashMap<(String, Option<i32>), Span>,
+    noreturn: HashSet<String>,
+    return_type: Option<Datatype>,
+    function_kind: FunctionKind,
+    // whether the current function is declared noreturn
+    returns_never: bool,
+    // the variables of the current function, and which of them are used
+    locals: Vec<Local>,
+    used: HashSet<String>,
+    // bytes of local variables in the current function so far
+    frame_size: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+// a variable or parameter, reported by -Wunused-* if it is never used
+#[derive(Debug)]
+struct Local {
+    unique_name: String,
+    name: String,
+    span: Span,
+    warning: Warning,
+}
+
+// type checks the program and annotates every expression with its type and
+// value category, making implicit conversions explicit on the way
+pub fn analyze_program(
+    program: Program,
+    diagnostics: &mut Diagnostics,
+) -> Result<Program, ErrorReported> {
+    // a definition without an initializer is only tentative, so it has to
+    // be known up front whether another one comes with an initializer
+    let initialized = program
+        .functions
+        .iter()
+        .filter_map(|statement| match statement {
+            ProgramStatement::Declaration(declaration) => Some(&declaration.declarators),
+            ProgramStatement::FunctionDefinition(_) => None,
+        })
+        .flatten()
+        .filter(|declarator| declarator.value.is_some())
+        .map(|declarator| declarator.name.clone())
+        .collect();
+
+    let mut analyzer = Analyzer {
+        initialized,
+        ..Analyzer::default()
+    };
+
+    let functions = program
+        .functions
+        .into_iter()
+        .map(|statement| analyzer.analyze_program_statement(statement))
+        .collect();
+
+    for diagnostic in analyzer.diagnostics {
+        diagnostics.report(diagnostic);
+    }
+
+    diagnostics.check(Program { functions })
+}
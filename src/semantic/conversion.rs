@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use crate::{
+    diagnostic::{code, warning::Warning, Diagnostic},
+    parser::expression::fold::Fold,
+    types::{
+        datatype::{Datatype, IntegerType},
+        expression::{Conversion, ConversionKind, Expression, ExpressionKind, ValueCategory},
+    },
+};
+
+use super::{error, Analyzer, SemanticResult};
+
+fn convert(expression: Expression, kind: ConversionKind, datatype: Datatype) -> Expression {
+    let span = expression.span;
+
+    Expression::typed(
+        ExpressionKind::Conversion(Conversion {
+            kind,
+            expression: Arc::new(expression),
+        }),
+        datatype,
+        ValueCategory::RValue,
+    )
+    .with_span(span)
+}
+
+// arrays and functions used as values become pointers (C11 6.3.2.1)
+pub fn decay(expression: Expression) -> Expression {
+    match expression.datatype().clone() {
+        Datatype::Array { inner, .. } => convert(
+            expression,
+            ConversionKind::ArrayToPointer,
+            Datatype::Pointer { inner },
+        ),
+        function @ Datatype::Function { .. } => convert(
+            expression,
+            ConversionKind::FunctionToPointer,
+            Datatype::Pointer {
+                inner: Arc::new(function),
+            },
+        ),
+        _ => expression,
+    }
+}
+
+pub fn integer_cast(expression: Expression, target: IntegerType) -> Expression {
+    match expression.datatype() {
+        Datatype::Integer(integer) if *integer == target => expression,
+        _ => convert(
+            expression,
+            ConversionKind::Integer,
+            Datatype::Integer(target),
+        ),
+    }
+}
+
+pub fn is_null_pointer_constant(expression: &Expression) -> bool {
+    expression.datatype().is_integer() && expression.fold() == Some(0)
+}
+
+// both operands are converted to their common integer type
+pub fn usual_arithmetic_conversions(
+    lhs: Expression,
+    rhs: Expression,
+) -> (Expression, Expression, IntegerType) {
+    let (Datatype::Integer(a), Datatype::Integer(b)) = (lhs.datatype(), rhs.datatype()) else {
+        unreachable!("arithmetic conversions on non-integer operands");
+    };
+
+    let common = a.common(b);
+
+    (integer_cast(lhs, common), integer_cast(rhs, common), common)
+}
+
+impl Analyzer {
+    // the conversions of simple assignment (C11 6.5.16.1), which also apply
+    // to arguments, return values and initializers
+    pub fn convert_for_assignment(
+        &mut self,
+        expression: Expression,
+        target: &Datatype,
+        context: &str,
+    ) -> SemanticResult<Expression> {
+        let source = expression.datatype().clone();
+
+        // they are allowed, but anything other than a null pointer constant
+        // is most likely a mistake
+        let integer_pointer = match (&source, target) {
+            (Datatype::Integer(_), Datatype::Pointer { .. })
+                if !is_null_pointer_constant(&expression) =>
+            {
+                Some("integer to pointer")
+            }
+            (Datatype::Pointer { .. }, Datatype::Integer(_)) => Some("pointer to integer"),
+            _ => None,
+        };
+
+        if let Some(conversion) = integer_pointer {
+            self.diagnostics.push(
+                Diagnostic::warning(
+                    Warning::IntConversion,
+                    format!(
+                        "Incompatible {conversion} conversion in {context}: '{source}' to '{target}'"
+                    ),
+                )
+                .at(expression.span),
+            );
+        }
+
+        match (&source, target) {
+            (Datatype::Integer(_), Datatype::Integer(target)) => {
+                Ok(integer_cast(expression, *target))
+            }
+            (Datatype::Pointer { .. }, Datatype::Pointer { .. })
+                if source.is_compatible(target) =>
+            {
+                Ok(expression)
+            }
+            (Datatype::Pointer { .. }, Datatype::Pointer { .. }) => error(
+                code::INCOMPATIBLE_TYPES,
+                format!("Incompatible pointer types in {context}: '{source}' and '{target}'"),
+            ),
+            (Datatype::Integer(_), Datatype::Pointer { .. }) => Ok(convert(
+                expression,
+                ConversionKind::IntegerToPointer,
+                target.clone(),
+            )),
+            (Datatype::Pointer { .. }, Datatype::Integer(_)) => Ok(convert(
+                expression,
+                ConversionKind::PointerToInteger,
+                target.clone(),
+            )),
+            _ => error(
+                code::INCOMPATIBLE_TYPES,
+                format!("Incompatible types in {context}: cannot convert '{source}' to '{target}'"),
+            ),
+        }
+    }
+}
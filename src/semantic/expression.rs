@@ -0,0 +1,615 @@
+use std::sync::Arc;
+
+use crate::{
+    diagnostic::{code, warning::Warning, Diagnostic},
+    types::{
+        datatype::{Argument, Datatype, IntegerType},
+        expression::{
+            BinaryOp, BuiltinCall, Call, Expression, ExpressionKind, Subscript, TernaryOp, UnaryOp,
+            ValueCategory,
+        },
+    },
+    utils::unique_name::original_name,
+};
+
+use super::{
+    check_supported,
+    conversion::{decay, is_null_pointer_constant, usual_arithmetic_conversions},
+    error, Analyzer, SemanticResult,
+};
+
+const INT: Datatype = Datatype::Integer(IntegerType::Int);
+
+fn rvalue(kind: ExpressionKind, datatype: Datatype) -> SemanticResult<Expression> {
+    Ok(Expression::typed(kind, datatype, ValueCategory::RValue))
+}
+
+fn unary(op: &UnaryOp, operand: Expression, datatype: Datatype) -> SemanticResult<Expression> {
+    rvalue(
+        ExpressionKind::UnaryOp(op.with_operand(Arc::new(operand))),
+        datatype,
+    )
+}
+
+fn binary(
+    op: &BinaryOp,
+    lhs: Expression,
+    rhs: Expression,
+    datatype: Datatype,
+) -> SemanticResult<Expression> {
+    rvalue(
+        ExpressionKind::BinaryOp(op.with_operands(Arc::new(lhs), Arc::new(rhs))),
+        datatype,
+    )
+}
+
+// arrays are lvalues too, but they can not be assigned to (C11 6.3.2.1)
+fn check_modifiable(expression: &Expression, what: &str) -> SemanticResult<()> {
+    let assignable = expression.category() == ValueCategory::LValue
+        && !matches!(
+            expression.datatype(),
+            Datatype::Array { .. } | Datatype::Function { .. }
+        );
+
+    if assignable {
+        Ok(())
+    } else {
+        error(
+            code::NOT_ASSIGNABLE,
+            format!("{what} must be a modifiable lvalue"),
+        )
+    }
+}
+
+// arithmetic on a pointer needs the size of what it points to
+fn check_object_pointer(datatype: &Datatype, op: &BinaryOp) -> SemanticResult<()> {
+    match datatype.pointee() {
+        Some(Datatype::Function { .. }) => error(
+            code::INVALID_OPERANDS,
+            format!(
+                "Invalid operands to binary {}: arithmetic on a pointer to a function",
+                op.symbol()
+            ),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn invalid_operands<T>(op: &BinaryOp, lhs: &Expression, rhs: &Expression) -> SemanticResult<T> {
+    error(
+        code::INVALID_OPERANDS,
+        format!(
+            "Invalid operands to binary {} ('{}' and '{}')",
+            op.symbol(),
+            lhs.datatype(),
+            rhs.datatype()
+        ),
+    )
+}
+
+impl Analyzer {
+    // an expression used for its value, so arrays and functions decay
+    pub fn analyze_value(&mut self, expression: &Expression) -> SemanticResult<Expression> {
+        Ok(decay(self.analyze_expression(expression)?))
+    }
+
+    // conditions and the operands of logical operators
+    pub fn analyze_scalar(
+        &mut self,
+        expression: &Expression,
+        what: &str,
+    ) -> SemanticResult<Expression> {
+        let expression = self.analyze_value(expression)?;
+
+        if expression.datatype().is_scalar() {
+            Ok(expression)
+        } else {
+            error(
+                code::NOT_SCALAR,
+                format!(
+                    "{what} must have scalar type, not '{}'",
+                    expression.datatype()
+                ),
+            )
+        }
+    }
+
+    // a = b where a == b was meant is a classic, parentheses say it is intended
+    pub fn analyze_condition(
+        &mut self,
+        expression: &Expression,
+        what: &str,
+    ) -> SemanticResult<Expression> {
+        if let ExpressionKind::BinaryOp(BinaryOp::Assignment(lhs, rhs)) = &expression.kind {
+            // parentheses are not kept in the ast, but a parenthesized
+            // expression has a span that covers them
+            if expression.span == lhs.span.to(&rhs.span) {
+                self.diagnostics.push(
+                    Diagnostic::warning(
+                        Warning::Parentheses,
+                        "Using the result of an assignment as a condition without parentheses",
+                    )
+                    .at(expression.span)
+                    .with_note("place parentheses around the assignment to silence this warning")
+                    .with_note("use '==' to turn this assignment into an equality comparison"),
+                );
+            }
+        }
+
+        self.analyze_scalar(expression, what)
+    }
+
+    // analyzed expressions keep the span of the one they were made from
+    pub fn analyze_expression(&mut self, expression: &Expression) -> SemanticResult<Expression> {
+        match self.analyze_expression_kind(expression) {
+            Ok(analyzed) => Ok(analyzed.with_span(expression.span)),
+            Err(error) => Err(Box::new(error.or_at(expression.span))),
+        }
+    }
+
+    fn analyze_expression_kind(&mut self, expression: &Expression) -> SemanticResult<Expression> {
+        match &expression.kind {
+            // the literals that do not fit in 32 bits are long long, so this
+            // also keeps the values of the others to a word
+            ExpressionKind::Number(number) => {
+                let datatype = Datatype::Integer(number.datatype);
+                check_supported(&datatype)?;
+                rvalue(expression.kind.clone(), datatype)
+            }
+            ExpressionKind::Variable(name) => match self.variables.get(name) {
+                Some(datatype) => {
+                    self.used.insert(name.clone());
+                    Ok(Expression::typed(
+                        expression.kind.clone(),
+                        datatype.clone(),
+                        ValueCategory::LValue,
+                    ))
+                }
+                None => error(
+                    code::UNDECLARED,
+                    format!("Variable '{}' not found", original_name(name)),
+                ),
+            },
+            ExpressionKind::Global(name) => match self.globals.get(name) {
+                Some(datatype) => Ok(Expression::typed(
+                    expression.kind.clone(),
+                    datatype.clone(),
+                    ValueCategory::LValue,
+                )),
+                None => error(
+                    code::UNDECLARED,
+                    format!("Variable '{}' not found", original_name(name)),
+                ),
+            },
+            // a function designator is not an object, so it is not an lvalue either
+            ExpressionKind::FunctionSymbol(name) => match self.functions.get(name) {
+                Some(datatype) => rvalue(expression.kind.clone(), datatype.clone()),
+                None => error(code::UNDECLARED, format!("Function '{name}' not found")),
+            },
+            ExpressionKind::UnaryOp(op) => self.analyze_unary(op),
+            ExpressionKind::BinaryOp(op) => self.analyze_binary(op),
+            ExpressionKind::TernaryOp(op) => self.analyze_ternary(op),
+            ExpressionKind::Call(call) => self.analyze_call(call),
+            ExpressionKind::Builtin(call) => self.analyze_builtin(call),
+            ExpressionKind::Subscript(subscript) => self.analyze_subscript(subscript),
+            ExpressionKind::Member(_) => error(
+                code::UNSUPPORTED,
+                "Member access requires a struct or union type",
+            ),
+            // only analysis creates conversions, so this one is already annotated
+            ExpressionKind::Conversion(_) => Ok(expression.clone()),
+        }
+    }
+
+    fn analyze_unary(&mut self, op: &UnaryOp) -> SemanticResult<Expression> {
+        match op {
+            UnaryOp::Plus(operand) | UnaryOp::Negation(operand) | UnaryOp::BitwiseNot(operand) => {
+                let operand = self.analyze_value(operand)?;
+
+                match operand.datatype().clone() {
+                    datatype @ Datatype::Integer(_) => unary(op, operand, datatype),
+                    datatype => error(
+                        code::INVALID_OPERANDS,
+                        format!("Invalid operand to unary {} ('{datatype}')", op.symbol()),
+                    ),
+                }
+            }
+            UnaryOp::LogicalNot(operand) => {
+                let operand = self.analyze_scalar(operand, "Operand of !")?;
+                unary(op, operand, INT)
+            }
+            UnaryOp::PrefixIncrement(operand)
+            | UnaryOp::PrefixDecrement(operand)
+            | UnaryOp::PostfixIncrement(operand)
+            | UnaryOp::PostfixDecrement(operand) => {
+                let operand = self.analyze_expression(operand)?;
+                check_modifiable(&operand, &format!("Operand of {}", op.symbol()))?;
+
+                let datatype = operand.datatype().clone();
+                if !datatype.is_scalar() {
+                    return error(
+                        code::INVALID_OPERANDS,
+                        format!("Invalid operand to {} ('{datatype}')", op.symbol()),
+                    );
+                }
+
+                unary(op, operand, datatype)
+            }
+            UnaryOp::Ref(operand) => {
+                let operand = self.analyze_expression(operand)?;
+
+                let addressable = operand.category() == ValueCategory::LValue
+                    || matches!(operand.datatype(), Datatype::Function { .. });
+                if !addressable {
+                    return error(
+                        code::NOT_ADDRESSABLE,
+                        "Cannot take the address of an rvalue",
+                    );
+                }
+
+                let datatype = Datatype::Pointer {
+                    inner: Arc::new(operand.datatype().clone()),
+                };
+                unary(op, operand, datatype)
+            }
+            UnaryOp::Deref(operand) => {
+                let operand = self.analyze_value(operand)?;
+
+                let Some(pointee) = operand.datatype().pointee().cloned() else {
+                    return error(
+                        code::INVALID_OPERANDS,
+                        format!(
+                            "Cannot dereference a value of type '{}'",
+                            operand.datatype()
+                        ),
+                    );
+                };
+
+                let category = match pointee {
+                    Datatype::Function { .. } => ValueCategory::RValue,
+                    _ => ValueCategory::LValue,
+                };
+
+                Ok(Expression::typed(
+                    ExpressionKind::UnaryOp(op.with_operand(Arc::new(operand))),
+                    pointee,
+                    category,
+                ))
+            }
+        }
+    }
+
+    fn analyze_binary(&mut self, op: &BinaryOp) -> SemanticResult<Expression> {
+        let (lhs, rhs) = op.operands();
+
+        match op {
+            BinaryOp::Assignment(..) => {
+                let lhs = self.analyze_expression(lhs)?;
+                check_modifiable(&lhs, "Left operand of =")?;
+
+                let datatype = lhs.datatype().clone();
+                let rhs = self.analyze_value(rhs)?;
+                let rhs = self.convert_for_assignment(rhs, &datatype, "assignment")?;
+
+                binary(op, lhs, rhs, datatype)
+            }
+            BinaryOp::LogicalAnd(..) | BinaryOp::LogicalOr(..) => {
+                let what = format!("Operand of {}", op.symbol());
+                let lhs = self.analyze_scalar(lhs, &what)?;
+                let rhs = self.analyze_scalar(rhs, &what)?;
+
+                binary(op, lhs, rhs, INT)
+            }
+            BinaryOp::Comma(..) => {
+                let lhs = self.analyze_value(lhs)?;
+                let rhs = self.analyze_value(rhs)?;
+
+                let datatype = rhs.datatype().clone();
+                binary(op, lhs, rhs, datatype)
+            }
+            _ if let Some(operation) = op.compound_operation() => {
+                let lhs = self.analyze_expression(lhs)?;
+                check_modifiable(&lhs, &format!("Left operand of {}", op.symbol()))?;
+
+                let datatype = lhs.datatype().clone();
+                let rhs = self.analyze_value(rhs)?;
+
+                // a op= b has to be valid as a = a op b, but the operands are
+                // kept as they are since the backend expands it the same way
+                let result = self.analyze_operation(&operation, lhs.clone(), rhs.clone())?;
+                self.convert_for_assignment(result, &datatype, "assignment")?;
+
+                binary(op, lhs, rhs, datatype)
+            }
+            _ => {
+                let lhs = self.analyze_value(lhs)?;
+                let rhs = self.analyze_value(rhs)?;
+
+                self.analyze_operation(op, lhs, rhs)
+            }
+        }
+    }
+
+    // arithmetic, bitwise and comparison operators on already analyzed operands
+    fn analyze_operation(
+        &mut self,
+        op: &BinaryOp,
+        lhs: Expression,
+        rhs: Expression,
+    ) -> SemanticResult<Expression> {
+        let lhs_type = lhs.datatype().clone();
+        let rhs_type = rhs.datatype().clone();
+
+        match op {
+            BinaryOp::Multiplication(..)
+            | BinaryOp::Division(..)
+            | BinaryOp::Modulus(..)
+            | BinaryOp::BitwiseAnd(..)
+            | BinaryOp::BitwiseXor(..)
+            | BinaryOp::BitwiseOr(..) => {
+                if !lhs_type.is_integer() || !rhs_type.is_integer() {
+                    return invalid_operands(op, &lhs, &rhs);
+                }
+
+                let (lhs, rhs, common) = usual_arithmetic_conversions(lhs, rhs);
+                binary(op, lhs, rhs, Datatype::Integer(common))
+            }
+            // the result has the type of the left operand, the right one is only a count
+            BinaryOp::LeftShift(..) | BinaryOp::RightShift(..) => {
+                if !lhs_type.is_integer() || !rhs_type.is_integer() {
+                    return invalid_operands(op, &lhs, &rhs);
+                }
+
+                binary(op, lhs, rhs, lhs_type)
+            }
+            BinaryOp::Addition(..) => match (&lhs_type, &rhs_type) {
+                (Datatype::Integer(_), Datatype::Integer(_)) => {
+                    let (lhs, rhs, common) = usual_arithmetic_conversions(lhs, rhs);
+                    binary(op, lhs, rhs, Datatype::Integer(common))
+                }
+                (Datatype::Pointer { .. }, Datatype::Integer(_)) => {
+                    check_object_pointer(&lhs_type, op)?;
+                    binary(op, lhs, rhs, lhs_type)
+                }
+                (Datatype::Integer(_), Datatype::Pointer { .. }) => {
+                    check_object_pointer(&rhs_type, op)?;
+                    binary(op, lhs, rhs, rhs_type)
+                }
+                _ => invalid_operands(op, &lhs, &rhs),
+            },
+            BinaryOp::Subtraction(..) => match (&lhs_type, &rhs_type) {
+                (Datatype::Integer(_), Datatype::Integer(_)) => {
+                    let (lhs, rhs, common) = usual_arithmetic_conversions(lhs, rhs);
+                    binary(op, lhs, rhs, Datatype::Integer(common))
+                }
+                (Datatype::Pointer { .. }, Datatype::Integer(_)) => {
+                    check_object_pointer(&lhs_type, op)?;
+                    binary(op, lhs, rhs, lhs_type)
+                }
+                // ptrdiff_t is int on ilp32
+                (Datatype::Pointer { .. }, Datatype::Pointer { .. })
+                    if lhs_type.is_compatible(&rhs_type) =>
+                {
+                    check_object_pointer(&lhs_type, op)?;
+                    binary(op, lhs, rhs, INT)
+                }
+                _ => invalid_operands(op, &lhs, &rhs),
+            },
+            BinaryOp::LessThan(..)
+            | BinaryOp::GreaterThan(..)
+            | BinaryOp::LessThanEquals(..)
+            | BinaryOp::GreaterThanEquals(..)
+            | BinaryOp::Equals(..)
+            | BinaryOp::NotEquals(..) => {
+                let equality = matches!(op, BinaryOp::Equals(..) | BinaryOp::NotEquals(..));
+
+                match (&lhs_type, &rhs_type) {
+                    (Datatype::Integer(_), Datatype::Integer(_)) => {
+                        let (lhs, rhs, _) = usual_arithmetic_conversions(lhs, rhs);
+                        binary(op, lhs, rhs, INT)
+                    }
+                    (Datatype::Pointer { .. }, Datatype::Pointer { .. })
+                        if lhs_type.is_compatible(&rhs_type) =>
+                    {
+                        binary(op, lhs, rhs, INT)
+                    }
+                    // p == 0
+                    (Datatype::Pointer { .. }, Datatype::Integer(_))
+                        if equality && is_null_pointer_constant(&rhs) =>
+                    {
+                        let rhs = self.convert_for_assignment(rhs, &lhs_type, "comparison")?;
+                        binary(op, lhs, rhs, INT)
+                    }
+                    (Datatype::Integer(_), Datatype::Pointer { .. })
+                        if equality && is_null_pointer_constant(&lhs) =>
+                    {
+                        let lhs = self.convert_for_assignment(lhs, &rhs_type, "comparison")?;
+                        binary(op, lhs, rhs, INT)
+                    }
+                    _ => invalid_operands(op, &lhs, &rhs),
+                }
+            }
+            _ => unreachable!("{} is not an arithmetic operator", op.symbol()),
+        }
+    }
+
+    fn analyze_ternary(&mut self, op: &TernaryOp) -> SemanticResult<Expression> {
+        let condition = self.analyze_condition(&op.condition, "Condition of ?:")?;
+        let then_expr = self.analyze_value(&op.then_expr)?;
+        let else_expr = self.analyze_value(&op.else_expr)?;
+
+        let then_type = then_expr.datatype().clone();
+        let else_type = else_expr.datatype().clone();
+
+        let (then_expr, else_expr, datatype) = match (&then_type, &else_type) {
+            (Datatype::Integer(_), Datatype::Integer(_)) => {
+                let (then_expr, else_expr, common) =
+                    usual_arithmetic_conversions(then_expr, else_expr);
+                (then_expr, else_expr, Datatype::Integer(common))
+            }
+            (Datatype::Pointer { .. }, Datatype::Pointer { .. })
+                if then_type.is_compatible(&else_type) =>
+            {
+                (then_expr, else_expr, then_type)
+            }
+            (Datatype::Pointer { .. }, Datatype::Integer(_))
+                if is_null_pointer_constant(&else_expr) =>
+            {
+                let else_expr = self.convert_for_assignment(else_expr, &then_type, "conditional")?;
+                (then_expr, else_expr, then_type)
+            }
+            (Datatype::Integer(_), Datatype::Pointer { .. })
+                if is_null_pointer_constant(&then_expr) =>
+            {
+                let then_expr = self.convert_for_assignment(then_expr, &else_type, "conditional")?;
+                (then_expr, else_expr, else_type)
+            }
+            _ => {
+                return error(code::INCOMPATIBLE_TYPES, format!(
+                    "Incompatible operand types in conditional expression ('{then_type}' and '{else_type}')"
+                ))
+            }
+        };
+
+        rvalue(
+            ExpressionKind::TernaryOp(TernaryOp {
+                condition: Arc::new(condition),
+                then_expr: Arc::new(then_expr),
+                else_expr: Arc::new(else_expr),
+            }),
+            datatype,
+        )
+    }
+
+    // arguments are converted as if assigned to the parameters
+    fn analyze_arguments(
+        &mut self,
+        arguments: &[Expression],
+        parameters: &[Argument],
+        callee: &str,
+    ) -> SemanticResult<Vec<Expression>> {
+        if arguments.len() != parameters.len() {
+            return error(
+                code::ARGUMENT_COUNT,
+                format!(
+                    "{callee} expects {} arguments, but {} were given",
+                    parameters.len(),
+                    arguments.len()
+                ),
+            );
+        }
+
+        arguments
+            .iter()
+            .zip(parameters.iter())
+            .enumerate()
+            .map(|(index, (argument, parameter))| {
+                let argument = self.analyze_value(argument)?;
+                self.convert_for_assignment(
+                    argument,
+                    &parameter.datatype,
+                    &format!("argument {} of {callee}", index + 1),
+                )
+            })
+            .collect()
+    }
+
+    fn analyze_call(&mut self, call: &Call) -> SemanticResult<Expression> {
+        let callee = self.analyze_value(&call.expression)?;
+
+        let Some(Datatype::Function {
+            return_type,
+            arguments,
+        }) = callee.datatype().pointee().cloned()
+        else {
+            return error(
+                code::NOT_CALLABLE,
+                format!(
+                    "Called object of type '{}' is not a function",
+                    callee.datatype()
+                ),
+            );
+        };
+
+        let (name, declaration) = match &call.expression.kind {
+            ExpressionKind::FunctionSymbol(name) => {
+                (format!("'{name}'"), self.declarations.get(name).copied())
+            }
+            _ => ("function pointer".to_string(), None),
+        };
+
+        let call_arguments = self
+            .analyze_arguments(&call.arguments, &arguments, &name)
+            .map_err(|error| match declaration {
+                Some(span) if error.code == Some(code::ARGUMENT_COUNT) => {
+                    Box::new(error.with_secondary(span, "declared here"))
+                }
+                _ => error,
+            })?;
+
+        rvalue(
+            ExpressionKind::Call(Call {
+                expression: Arc::new(callee),
+                arguments: call_arguments,
+            }),
+            return_type.as_ref().clone(),
+        )
+    }
+
+    fn analyze_builtin(&mut self, call: &BuiltinCall) -> SemanticResult<Expression> {
+        let Datatype::Function {
+            return_type,
+            arguments,
+        } = call.builtin.signature()
+        else {
+            unreachable!("builtin signatures are function types");
+        };
+
+        let call_arguments = self.analyze_arguments(&call.arguments, &arguments, "builtin")?;
+
+        rvalue(
+            ExpressionKind::Builtin(BuiltinCall {
+                builtin: call.builtin,
+                arguments: call_arguments,
+            }),
+            return_type.as_ref().clone(),
+        )
+    }
+
+    // a[i] is *(a + i), and since addition commutes i[a] is allowed as well
+    fn analyze_subscript(&mut self, subscript: &Subscript) -> SemanticResult<Expression> {
+        let expression = self.analyze_value(&subscript.expression)?;
+        let index = self.analyze_value(&subscript.index)?;
+
+        let (pointer, index) = match (expression.datatype(), index.datatype()) {
+            (Datatype::Pointer { .. }, Datatype::Integer(_)) => (expression, index),
+            (Datatype::Integer(_), Datatype::Pointer { .. }) => (index, expression),
+            (Datatype::Pointer { .. }, _) | (_, Datatype::Pointer { .. }) => {
+                return error(code::INVALID_OPERANDS, "Array subscript is not an integer")
+            }
+            _ => {
+                return error(
+                    code::INVALID_OPERANDS,
+                    "Subscripted value is not an array or pointer",
+                )
+            }
+        };
+
+        let element = pointer.datatype().pointee().unwrap().clone();
+        if let Datatype::Function { .. } = element {
+            return error(
+                code::INVALID_OPERANDS,
+                "Subscript of a pointer to a function",
+            );
+        }
+
+        Ok(Expression::typed(
+            ExpressionKind::Subscript(Subscript {
+                expression: Arc::new(pointer),
+                index: Arc::new(index),
+            }),
+            element,
+            ValueCategory::LValue,
+        ))
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    diagnostic::{warning::Warning, Diagnostic},
+    parser::{expression::fold::Fold, Case},
+    types::{
+        expression::{Expression, ExpressionKind},
+        scope::{Scope, ScopeItem},
+        statement::{JumpStatement, Statement, StatementKind},
+    },
+};
+
+use super::Analyzer;
+
+// whether a break in the statement leaves the loop or switch with this id
+fn breaks_out(statement: &Statement, id: &str) -> bool {
+    match &statement.kind {
+        StatementKind::Jump {
+            statement: JumpStatement::Break { id: target },
+        } => target == id,
+        StatementKind::Scope { scope } => scope.items.iter().any(|item| match item {
+            ScopeItem::Statement(statement) => breaks_out(statement, id),
+            _ => false,
+        }),
+        StatementKind::If { statement } => {
+            breaks_out(&statement.then_block, id)
+                || statement
+                    .else_block
+                    .as_ref()
+                    .is_some_and(|block| breaks_out(block, id))
+        }
+        StatementKind::While { statement } => breaks_out(&statement.block, id),
+        StatementKind::For { statement } => breaks_out(&statement.block, id),
+        StatementKind::Switch { statement } => breaks_out(&statement.body, id),
+        _ => false,
+    }
+}
+
+// a loop without a condition or with a constant true one
+fn is_forever(condition: Option<&Expression>) -> bool {
+    condition.is_none_or(|condition| condition.fold().is_some_and(|value| value != 0))
+}
+
+impl Analyzer {
+    // calls to noreturn functions end the path just like a return
+    fn is_noreturn_call(&self, expression: &Expression) -> bool {
+        let ExpressionKind::Call(call) = &expression.kind else {
+            return false;
+        };
+
+        let mut callee = call.expression.as_ref();
+        while let ExpressionKind::Conversion(conversion) = &callee.kind {
+            callee = conversion.expression.as_ref();
+        }
+
+        matches!(&callee.kind, ExpressionKind::FunctionSymbol(name) if self.noreturn.contains(name))
+    }
+
+    // whether control can reach the end of the statement
+    pub fn completes(&self, statement: &Statement) -> bool {
+        match &statement.kind {
+            StatementKind::Jump { statement } => matches!(statement, JumpStatement::__Ebreak),
+            StatementKind::Expression { expression } => !self.is_noreturn_call(expression),
+            StatementKind::Scope { scope } => self.sequence_completes(&scope.items),
+            StatementKind::If { statement } => match &statement.else_block {
+                Some(else_block) => {
+                    self.completes(&statement.then_block) || self.completes(else_block)
+                }
+                None => true,
+            },
+            StatementKind::While { statement } => {
+                !is_forever(Some(&statement.condition))
+                    || breaks_out(&statement.block, &statement.id)
+            }
+            StatementKind::For { statement } => {
+                !is_forever(statement.condition.as_ref())
+                    || breaks_out(&statement.block, &statement.id)
+            }
+            // without a default label a value can skip the whole body
+            StatementKind::Switch { statement } => {
+                !statement
+                    .cases
+                    .iter()
+                    .any(|case| matches!(case, Case::Default))
+                    || self.completes(&statement.body)
+                    || breaks_out(&statement.body, &statement.id)
+            }
+            StatementKind::Asm { .. } | StatementKind::Null | StatementKind::Fallthrough => true,
+        }
+    }
+
+    // labels can be jumped to, so they make the code after them reachable again
+    pub fn sequence_completes(&self, items: &[ScopeItem]) -> bool {
+        items.iter().fold(true, |reachable, item| match item {
+            ScopeItem::Label(_) => true,
+            ScopeItem::Statement(statement) => reachable && self.completes(statement),
+            ScopeItem::Declaration(_) => reachable,
+        })
+    }
+
+    // warns once about the first statement of every unreachable stretch
+    pub fn check_unreachable(&mut self, scope: &Scope) {
+        let mut reachable = true;
+        let mut warned = false;
+
+        for item in scope.items.iter() {
+            match item {
+                ScopeItem::Label(_) => {
+                    reachable = true;
+                    warned = false;
+                }
+                ScopeItem::Statement(statement) => {
+                    let empty = matches!(
+                        statement.kind,
+                        StatementKind::Null | StatementKind::Fallthrough
+                    );
+
+                    if !reachable && !warned && !empty {
+                        self.diagnostics.push(
+                            Diagnostic::warning(
+                                Warning::UnreachableCode,
+                                "Code will never be executed",
+                            )
+                            .at(statement.span),
+                        );
+                        warned = true;
+                    }
+
+                    reachable = reachable && self.completes(statement);
+                }
+                ScopeItem::Declaration(_) => {}
+            }
+        }
+    }
+
+    // a case that control falls into from the one before it, unless the
+    // one before ends in __attribute__((fallthrough));
+    pub fn check_fallthrough(&mut self, body: &Statement) {
+        let StatementKind::Scope { scope } = &body.kind else {
+            return;
+        };
+
+        let mut reachable = true;
+        let mut last: Option<&Statement> = None;
+
+        for item in scope.items.iter() {
+            match item {
+                ScopeItem::Label(label) => {
+                    if let Some(statement) = last.filter(|_| reachable) {
+                        if !matches!(statement.kind, StatementKind::Fallthrough) {
+                            self.diagnostics.push(
+                                Diagnostic::warning(
+                                    Warning::ImplicitFallthrough,
+                                    "This statement may fall through",
+                                )
+                                .at(statement.span)
+                                .with_secondary(label.span, "to this label"),
+                            );
+                        }
+                    }
+
+                    reachable = true;
+                    last = None;
+                }
+                ScopeItem::Statement(statement) => {
+                    reachable = reachable && self.completes(statement);
+
+                    if !matches!(statement.kind, StatementKind::Null) {
+                        last = Some(statement);
+                    }
+                }
+                ScopeItem::Declaration(_) => {}
+            }
+        }
+    }
+}
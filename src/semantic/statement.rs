@@ -0,0 +1,704 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
+
+use crate::{
+    diagnostic::{code, warning::Warning, Diagnostic},
+    parser::expression::fold::Fold,
+    types::{
+        attribute::AttributeList,
+        datatype::{Argument, Datatype},
+        declaration::{Declaration, InitDeclarator},
+        expression::{Expression, ValueCategory},
+        function_definition::{FunctionDefinition, FunctionKind},
+        program::ProgramStatement,
+        scope::{Label, LabelKind, Scope, ScopeItem},
+        span::Span,
+        statement::{
+            AsmOperand, AsmStatement, ForInit, ForStatement, IfStatement, JumpStatement, Statement,
+            StatementKind, SwitchStatement, WhileStatement,
+        },
+    },
+    utils::unique_name::original_name,
+};
+
+use super::{check_supported, error, Analyzer, Local, SemanticResult, MAX_FRAME_SIZE};
+
+impl Analyzer {
+    pub fn analyze_program_statement(&mut self, statement: ProgramStatement) -> ProgramStatement {
+        match statement {
+            ProgramStatement::FunctionDefinition(function) => {
+                ProgramStatement::FunctionDefinition(self.analyze_function(function))
+            }
+            ProgramStatement::Declaration(declaration) => {
+                match self.analyze_global_declaration(&declaration) {
+                    Ok(declaration) => ProgramStatement::Declaration(declaration),
+                    Err(error) => {
+                        self.diagnostics.push(*error);
+                        ProgramStatement::Declaration(declaration)
+                    }
+                }
+            }
+        }
+    }
+
+    fn analyze_function(&mut self, function: FunctionDefinition) -> FunctionDefinition {
+        // declared before the body is analyzed, so that it can recurse
+        self.functions.insert(
+            function.name.clone(),
+            Datatype::Function {
+                return_type: Arc::new(function.return_type.clone()),
+                arguments: function
+                    .arguments
+                    .iter()
+                    .map(|argument| Argument {
+                        name: argument.name.clone(),
+                        datatype: Arc::new(argument.datatype.clone()),
+                    })
+                    .collect(),
+            },
+        );
+
+        self.declarations
+            .insert(function.name.clone(), function.span);
+
+        let noreturn = function.attributes.has_attribute("noreturn");
+        if noreturn {
+            self.noreturn.insert(function.name.clone());
+        }
+
+        if let Err(error) = check_supported(&function.return_type) {
+            self.diagnostics.push(error.or_at(function.span));
+        }
+
+        for argument in function.arguments.iter() {
+            if let Err(error) = check_supported(&argument.datatype) {
+                self.diagnostics.push(error.or_at(argument.span));
+            }
+
+            if argument.datatype.is_variably_modified() {
+                self.diagnostics.push(
+                    Diagnostic::error("Variable length arrays are not supported")
+                        .with_code(code::UNSUPPORTED)
+                        .at(argument.span),
+                );
+            }
+
+            self.variables
+                .insert(argument.unique_name.clone(), argument.datatype.clone());
+
+            // naked functions can only get at their arguments from assembly
+            if function.kind != FunctionKind::Naked {
+                self.locals.push(Local {
+                    unique_name: argument.unique_name.clone(),
+                    name: argument.name.clone(),
+                    span: argument.span,
+                    warning: Warning::UnusedParameter,
+                });
+            }
+        }
+
+        self.return_type = Some(function.return_type.clone());
+        self.function_kind = function.kind;
+        self.returns_never = noreturn;
+        self.frame_size = 0;
+        // switches are numbered per function, so the labels start over
+        self.case_labels.clear();
+        // the parameters are in the same scope as the outermost block
+        let parameters = function
+            .arguments
+            .iter()
+            .filter(|argument| !argument.name.is_empty())
+            .map(|argument| (argument.name.clone(), argument.span))
+            .collect();
+        let body = self.analyze_scope(&function.body, parameters);
+        self.function_kind = FunctionKind::Normal;
+        self.returns_never = false;
+        self.return_type = None;
+
+        self.check_unused();
+
+        // the backend returns 0 from a function that runs off its end, which
+        // is only what the standard asks for in main
+        let returns = function.name != "main" && function.kind != FunctionKind::Naked && !noreturn;
+        if returns && self.sequence_completes(&body.items) {
+            self.diagnostics.push(
+                Diagnostic::warning(
+                    Warning::ReturnType,
+                    format!(
+                        "Control reaches end of non-void function '{}'",
+                        function.name
+                    ),
+                )
+                .at(function.span),
+            );
+        }
+
+        // the caller of a noreturn function does not expect to get control back
+        let falls_off = function.kind != FunctionKind::Naked && noreturn;
+        if falls_off && self.sequence_completes(&body.items) {
+            self.diagnostics.push(
+                Diagnostic::warning(
+                    Warning::InvalidNoreturn,
+                    format!(
+                        "Function '{}' is declared noreturn but can return",
+                        function.name
+                    ),
+                )
+                .at(function.span),
+            );
+        }
+
+        FunctionDefinition { body, ..function }
+    }
+
+    // unused variables are only known once the whole function has been seen
+    fn check_unused(&mut self) {
+        for local in std::mem::take(&mut self.locals) {
+            if self.used.contains(&local.unique_name) {
+                continue;
+            }
+
+            let what = match local.warning {
+                Warning::UnusedParameter => "parameter",
+                _ => "variable",
+            };
+
+            self.diagnostics.push(
+                Diagnostic::warning(local.warning, format!("Unused {what} '{}'", local.name))
+                    .at(local.span),
+            );
+        }
+
+        self.used.clear();
+    }
+
+    // errors are collected per item, so one bad statement does not hide the rest.
+    // `declared` has the names that are already declared in the scope
+    fn analyze_scope(&mut self, scope: &Scope, mut declared: HashMap<String, Span>) -> Scope {
+        let items = scope
+            .items
+            .iter()
+            .map(|item| {
+                let analyzed = match item {
+                    ScopeItem::Statement(statement) => {
+                        self.analyze_statement(statement).map(ScopeItem::Statement)
+                    }
+                    ScopeItem::Declaration(declaration) => {
+                        self.check_redefinitions(declaration, &mut declared);
+                        self.analyze_declaration(declaration)
+                            .map(ScopeItem::Declaration)
+                    }
+                    ScopeItem::Label(label) => self.analyze_label(label).map(|()| item.clone()),
+                };
+
+                analyzed.unwrap_or_else(|error| {
+                    self.diagnostics.push(*error);
+                    item.clone()
+                })
+            })
+            .collect();
+
+        let scope = Scope {
+            items,
+            span: scope.span,
+        };
+
+        self.check_unreachable(&scope);
+
+        scope
+    }
+
+    // the parser gave every declaration a name of its own, so a second
+    // object of the same name in a scope is only noticed here. functions and
+    // extern objects may be declared as often as needed
+    fn check_redefinitions(
+        &mut self,
+        declaration: &Declaration,
+        declared: &mut HashMap<String, Span>,
+    ) {
+        for declarator in declaration.declarators.iter() {
+            if declarator.external || matches!(declarator.datatype, Datatype::Function { .. }) {
+                continue;
+            }
+
+            let name = original_name(&declarator.name);
+            match declared.entry(name.to_string()) {
+                Entry::Occupied(previous) => self.diagnostics.push(
+                    Diagnostic::error(format!("Redefinition of '{name}'"))
+                        .with_code(code::REDEFINITION)
+                        .at(declarator.span)
+                        .with_secondary(*previous.get(), "previous definition is here"),
+                ),
+                Entry::Vacant(entry) => {
+                    entry.insert(declarator.span);
+                }
+            }
+        }
+    }
+
+    fn analyze_label(&mut self, label: &Label) -> SemanticResult<()> {
+        let (id, value) = match &label.kind {
+            LabelKind::Case { id, value } => (id, Some(*value)),
+            LabelKind::Default { id } => (id, None),
+            LabelKind::Named(_) => return Ok(()),
+        };
+
+        // the parser already reported labels outside of a switch
+        if id.is_empty() {
+            return Ok(());
+        }
+
+        let Some(previous) = self.case_labels.insert((id.clone(), value), label.span) else {
+            return Ok(());
+        };
+
+        let message = match value {
+            Some(value) => format!("Duplicate case value '{value}'"),
+            None => "Multiple default labels in one switch".to_string(),
+        };
+
+        Err(Box::new(
+            Diagnostic::error(message)
+                .with_code(code::DUPLICATE_CASE)
+                .at(label.span)
+                .with_secondary(previous, "previously used here"),
+        ))
+    }
+
+    fn analyze_declaration(&mut self, declaration: &Declaration) -> SemanticResult<Declaration> {
+        self.analyze_declarators(declaration, Self::analyze_init_declarator)
+    }
+
+    fn analyze_global_declaration(
+        &mut self,
+        declaration: &Declaration,
+    ) -> SemanticResult<Declaration> {
+        self.analyze_declarators(declaration, Self::analyze_global)
+    }
+
+    // every declarator is analyzed, so that one that is rejected does not
+    // leave the ones after it undeclared. the last error is returned, the
+    // ones before it are reported right away to keep them in order
+    fn analyze_declarators(
+        &mut self,
+        declaration: &Declaration,
+        analyze: fn(&mut Self, &InitDeclarator) -> SemanticResult<InitDeclarator>,
+    ) -> SemanticResult<Declaration> {
+        let mut declarators = Vec::new();
+        let mut last_error = None;
+
+        for declarator in declaration.declarators.iter() {
+            match analyze(self, declarator) {
+                Ok(declarator) => declarators.push(declarator),
+                Err(error) => {
+                    let error = Box::new(error.or_at(declarator.span));
+                    if let Some(previous) = last_error.replace(error) {
+                        self.diagnostics.push(*previous);
+                    }
+                }
+            }
+        }
+
+        match last_error {
+            Some(error) => Err(error),
+            None => Ok(Declaration {
+                declarators,
+                ..declaration.clone()
+            }),
+        }
+    }
+
+    // a file-scope object is defined by the one declaration with an
+    // initializer, or else by the first one without. the others only
+    // declare it, like extern does
+    fn analyze_global(&mut self, declarator: &InitDeclarator) -> SemanticResult<InitDeclarator> {
+        let is_function = matches!(declarator.datatype, Datatype::Function { .. });
+        if is_function || declarator.external {
+            return self.analyze_init_declarator(declarator);
+        }
+
+        // a rejected declaration still declares the name, so its uses do not
+        // pile up errors of their own
+        self.globals
+            .insert(declarator.name.clone(), declarator.datatype.clone());
+
+        check_supported(&declarator.datatype)?;
+
+        if declarator.datatype.is_variably_modified() {
+            return error(
+                code::UNSUPPORTED,
+                "Variable length arrays are not supported",
+            );
+        }
+
+        let Some(value) = &declarator.value else {
+            let defined = self.initialized.contains(&declarator.name)
+                || self.definitions.contains_key(&declarator.name);
+            if !defined {
+                self.definitions
+                    .insert(declarator.name.clone(), declarator.span);
+            }
+
+            return Ok(InitDeclarator {
+                external: defined,
+                ..declarator.clone()
+            });
+        };
+
+        if let Some(previous) = self.definitions.get(&declarator.name) {
+            return Err(Box::new(
+                Diagnostic::error(format!("Redefinition of '{}'", declarator.name))
+                    .with_code(code::REDEFINITION)
+                    .at(declarator.span)
+                    .with_secondary(*previous, "previous definition is here"),
+            ));
+        }
+
+        self.definitions
+            .insert(declarator.name.clone(), declarator.span);
+
+        if let Datatype::Array { .. } = declarator.datatype {
+            return error(code::UNSUPPORTED, "Array initializers are not supported");
+        }
+
+        let value = self.analyze_value(value)?;
+        let value = self.convert_for_assignment(value, &declarator.datatype, "initialization")?;
+
+        // the value is written into the object file, there is no code that
+        // could compute it. the linker fills in addresses
+        if value.fold().is_none() && value.address_constant().is_none() {
+            return Err(Box::new(
+                Diagnostic::error("Initializer of a file-scope variable must be a constant")
+                    .with_code(code::NOT_CONSTANT)
+                    .at(value.span),
+            ));
+        }
+
+        Ok(InitDeclarator {
+            value: Some(value),
+            ..declarator.clone()
+        })
+    }
+
+    fn analyze_init_declarator(
+        &mut self,
+        declarator: &InitDeclarator,
+    ) -> SemanticResult<InitDeclarator> {
+        if let Datatype::Function { .. } = declarator.datatype {
+            if declarator.attributes.has_attribute("noreturn") {
+                self.noreturn.insert(declarator.name.clone());
+            }
+
+            self.functions
+                .insert(declarator.name.clone(), declarator.datatype.clone());
+            self.declarations
+                .entry(declarator.name.clone())
+                .or_insert(declarator.span);
+
+            check_supported(&declarator.datatype)?;
+            return Ok(declarator.clone());
+        }
+
+        // the storage is in another unit, or somewhere else in this one
+        if declarator.external {
+            self.globals
+                .insert(declarator.name.clone(), declarator.datatype.clone());
+
+            check_supported(&declarator.datatype)?;
+            return Ok(declarator.clone());
+        }
+
+        // in scope even when it is rejected, like globals are
+        self.variables
+            .insert(declarator.name.clone(), declarator.datatype.clone());
+
+        check_supported(&declarator.datatype)?;
+
+        if declarator.datatype.is_variably_modified() {
+            return error(
+                code::UNSUPPORTED,
+                "Variable length arrays are not supported",
+            );
+        }
+
+        // naked functions have no frame, so there is nowhere to keep variables
+        if self.function_kind == FunctionKind::Naked {
+            return error(
+                code::NAKED_FUNCTION,
+                "Naked functions can not have local variables",
+            );
+        }
+
+        // the frame is addressed with offsets from the frame pointer, which
+        // have to reach all of it
+        self.frame_size = self.frame_size.saturating_add(declarator.datatype.size());
+        if self.frame_size > MAX_FRAME_SIZE {
+            return error(
+                code::INVALID_SIZE,
+                "The local variables of this function do not fit in a stack frame",
+            );
+        }
+
+        self.locals.push(Local {
+            unique_name: declarator.name.clone(),
+            name: original_name(&declarator.name).to_string(),
+            span: declarator.span,
+            warning: Warning::UnusedVariable,
+        });
+
+        let value = match &declarator.value {
+            Some(_) if matches!(declarator.datatype, Datatype::Array { .. }) => {
+                return error(code::UNSUPPORTED, "Array initializers are not supported");
+            }
+            Some(value) => {
+                let value = self.analyze_value(value)?;
+                Some(self.convert_for_assignment(value, &declarator.datatype, "initialization")?)
+            }
+            None => None,
+        };
+
+        Ok(InitDeclarator {
+            value,
+            ..declarator.clone()
+        })
+    }
+
+    fn analyze_block(&mut self, statement: &Statement) -> SemanticResult<Arc<Statement>> {
+        Ok(Arc::new(self.analyze_statement(statement)?))
+    }
+
+    fn analyze_statement(&mut self, statement: &Statement) -> SemanticResult<Statement> {
+        let returns = matches!(
+            statement.kind,
+            StatementKind::Jump {
+                statement: JumpStatement::Return { .. }
+            }
+        );
+        if returns && self.returns_never {
+            self.diagnostics.push(
+                Diagnostic::warning(
+                    Warning::InvalidNoreturn,
+                    "Function declared noreturn has a return statement",
+                )
+                .at(statement.span),
+            );
+        }
+
+        let kind = self
+            .analyze_statement_kind(&statement.kind)
+            .map_err(|error| Box::new(error.or_at(statement.span)))?;
+
+        Ok(Statement {
+            kind,
+            span: statement.span,
+        })
+    }
+
+    fn analyze_statement_kind(
+        &mut self,
+        statement: &StatementKind,
+    ) -> SemanticResult<StatementKind> {
+        Ok(match statement {
+            StatementKind::Jump { statement } => StatementKind::Jump {
+                statement: self.analyze_jump(statement)?,
+            },
+            StatementKind::Expression { expression } => StatementKind::Expression {
+                expression: self.analyze_value(expression)?,
+            },
+            StatementKind::Scope { scope } => StatementKind::Scope {
+                scope: self.analyze_scope(scope, HashMap::new()),
+            },
+            StatementKind::If { statement } => StatementKind::If {
+                statement: IfStatement {
+                    condition: self.analyze_condition(&statement.condition, "Condition of if")?,
+                    then_block: self.analyze_block(&statement.then_block)?,
+                    else_block: match &statement.else_block {
+                        Some(block) => Some(self.analyze_block(block)?),
+                        None => None,
+                    },
+                },
+            },
+            StatementKind::While { statement } => StatementKind::While {
+                statement: WhileStatement {
+                    condition: self
+                        .analyze_condition(&statement.condition, "Condition of while")?,
+                    block: self.analyze_block(&statement.block)?,
+                    id: statement.id.clone(),
+                },
+            },
+            StatementKind::For { statement } => StatementKind::For {
+                statement: Arc::new(self.analyze_for(statement)?),
+            },
+            StatementKind::Switch { statement } => StatementKind::Switch {
+                statement: self.analyze_switch(statement)?,
+            },
+            StatementKind::Asm { statement } => StatementKind::Asm {
+                statement: self.analyze_asm(statement)?,
+            },
+            StatementKind::Null => StatementKind::Null,
+            StatementKind::Fallthrough => StatementKind::Fallthrough,
+        })
+    }
+
+    fn analyze_jump(&mut self, statement: &JumpStatement) -> SemanticResult<JumpStatement> {
+        // there is no epilogue to return through
+        if let (JumpStatement::Return { .. }, FunctionKind::Naked) = (statement, self.function_kind)
+        {
+            return Err(Box::new(
+                Diagnostic::error("Naked functions can not use return")
+                    .with_code(code::NAKED_FUNCTION)
+                    .with_note("the body of a naked function has to return with inline assembly"),
+            ));
+        }
+
+        match statement {
+            JumpStatement::Return {
+                expression: Some(expression),
+            } => {
+                let return_type = self
+                    .return_type
+                    .clone()
+                    .expect("return statement outside of a function");
+
+                let expression = self.analyze_value(expression)?;
+
+                Ok(JumpStatement::Return {
+                    expression: Some(self.convert_for_assignment(
+                        expression,
+                        &return_type,
+                        "return",
+                    )?),
+                })
+            }
+            _ => Ok(statement.clone()),
+        }
+    }
+
+    fn analyze_for(&mut self, statement: &ForStatement) -> SemanticResult<ForStatement> {
+        let init = match &statement.init {
+            Some(ForInit::Declaration(declaration)) => {
+                self.check_redefinitions(declaration, &mut HashMap::new());
+                Some(ForInit::Declaration(self.analyze_declaration(declaration)?))
+            }
+            Some(ForInit::Expression(expression)) => {
+                Some(ForInit::Expression(self.analyze_value(expression)?))
+            }
+            None => None,
+        };
+
+        let condition = match &statement.condition {
+            Some(condition) => Some(self.analyze_condition(condition, "Condition of for")?),
+            None => None,
+        };
+
+        let increment = match &statement.increment {
+            Some(increment) => Some(self.analyze_value(increment)?),
+            None => None,
+        };
+
+        Ok(ForStatement {
+            init,
+            condition,
+            increment,
+            block: self.analyze_block(&statement.block)?,
+            id: statement.id.clone(),
+        })
+    }
+
+    fn analyze_switch(&mut self, statement: &SwitchStatement) -> SemanticResult<SwitchStatement> {
+        let expression = self.analyze_value(&statement.expression)?;
+
+        if !expression.datatype().is_integer() {
+            return error(
+                code::INVALID_OPERANDS,
+                format!(
+                    "Switch quantity must have integer type, not '{}'",
+                    expression.datatype()
+                ),
+            );
+        }
+
+        let body = self.analyze_block(&statement.body)?;
+        self.check_fallthrough(&body);
+
+        Ok(SwitchStatement {
+            expression,
+            body,
+            ..statement.clone()
+        })
+    }
+
+    fn analyze_asm_operand(
+        &mut self,
+        operand: &AsmOperand,
+        output: bool,
+    ) -> SemanticResult<AsmOperand> {
+        self.analyze_asm_operand_expression(operand, output)
+            .map(|expression| AsmOperand {
+                expression,
+                ..operand.clone()
+            })
+            .map_err(|error| Box::new(error.or_at(operand.span)))
+    }
+
+    fn analyze_asm_operand_expression(
+        &mut self,
+        operand: &AsmOperand,
+        output: bool,
+    ) -> SemanticResult<Expression> {
+        let memory = operand.constraint.ends_with('m');
+
+        // outputs and memory operands need an address, the rest a value
+        let expression = if output || memory {
+            let expression = self.analyze_expression(&operand.expression)?;
+
+            if expression.category() != ValueCategory::LValue {
+                return error(
+                    code::INVALID_ASM,
+                    format!(
+                        "Asm operand with constraint \"{}\" must be an lvalue",
+                        operand.constraint
+                    ),
+                );
+            }
+
+            expression
+        } else {
+            self.analyze_value(&operand.expression)?
+        };
+
+        if !memory && !expression.datatype().is_scalar() {
+            return error(
+                code::INVALID_ASM,
+                format!(
+                    "Asm operand with constraint \"{}\" must have scalar type",
+                    operand.constraint
+                ),
+            );
+        }
+
+        Ok(expression)
+    }
+
+    fn analyze_asm(&mut self, statement: &AsmStatement) -> SemanticResult<AsmStatement> {
+        let outputs = statement
+            .outputs
+            .iter()
+            .map(|operand| self.analyze_asm_operand(operand, true))
+            .collect::<SemanticResult<_>>()?;
+
+        let inputs = statement
+            .inputs
+            .iter()
+            .map(|operand| self.analyze_asm_operand(operand, false))
+            .collect::<SemanticResult<_>>()?;
+
+        Ok(AsmStatement {
+            outputs,
+            inputs,
+            ..statement.clone()
+        })
+    }
+}
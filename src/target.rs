@@ -0,0 +1,91 @@
+use std::fmt::Display;
+
+use crate::elf;
+
+// the machine the code is generated for. the code generator only knows
+// rv32 with the m extension, so anything else is refused up front
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    march: String,
+    mabi: String,
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target {
+            march: "rv32imafdc".to_string(),
+            mabi: "ilp32".to_string(),
+        }
+    }
+}
+
+impl Target {
+    pub fn new(march: impl Into<String>, mabi: impl Into<String>) -> Result<Target, String> {
+        let target = Target {
+            march: march.into(),
+            mabi: mabi.into(),
+        };
+
+        target.check_march()?;
+        target.check_mabi()?;
+
+        Ok(target)
+    }
+
+    fn check_march(&self) -> Result<(), String> {
+        let march = &self.march;
+
+        let Some(extensions) = march.strip_prefix("rv32") else {
+            return Err(format!(
+                "Unsupported architecture '{march}', only rv32 targets are supported"
+            ));
+        };
+
+        let base = extensions.split('_').next().unwrap_or_default();
+        if !base.starts_with(['i', 'g']) {
+            return Err(format!("Unsupported architecture '{march}'"));
+        }
+
+        if !base.contains(['m', 'g']) {
+            return Err(format!(
+                "Architecture '{march}' has no m extension, which the generated code needs"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn check_mabi(&self) -> Result<(), String> {
+        match self.mabi.as_str() {
+            "ilp32" | "ilp32f" | "ilp32d" => Ok(()),
+            mabi => Err(format!(
+                "Unsupported abi '{mabi}', only ilp32 abis are supported"
+            )),
+        }
+    }
+
+    pub fn march(&self) -> &str {
+        &self.march
+    }
+
+    pub fn mabi(&self) -> &str {
+        &self.mabi
+    }
+
+    // what objects for the target say about themselves, the linker refuses
+    // to mix float abis. whether there is compressed code is up to what the
+    // assembler emits, not to the target
+    pub fn elf_flags(&self) -> u32 {
+        match self.mabi.as_str() {
+            "ilp32f" => elf::EF_RISCV_FLOAT_ABI_SINGLE,
+            "ilp32d" => elf::EF_RISCV_FLOAT_ABI_DOUBLE,
+            _ => 0,
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.march, self.mabi)
+    }
+}
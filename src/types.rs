@@ -1,7 +1,9 @@
+pub mod attribute;
+pub mod datatype;
+pub mod declaration;
 pub mod expression;
 pub mod function_definition;
+pub mod program;
 pub mod scope;
+pub mod span;
 pub mod statement;
-pub mod declaration;
-pub mod datatype;
-pub mod program;
\ No newline at end of file
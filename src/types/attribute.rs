@@ -0,0 +1,64 @@
+use crate::parser::expression::fold::Fold;
+
+use super::{expression::Expression, span::Span};
+
+#[derive(Debug, Clone)]
+pub enum AttributeArgument {
+    Identifier(String),
+    String(String),
+    Expression(Expression),
+}
+
+// a single entry of an __attribute__((...)) list
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub arguments: Vec<AttributeArgument>,
+    pub span: Span,
+}
+
+impl Attribute {
+    pub fn string_argument(&self) -> Option<&str> {
+        match self.arguments.as_slice() {
+            [AttributeArgument::String(value)] => Some(value),
+            _ => None,
+        }
+    }
+}
+
+pub trait AttributeList {
+    fn find_attribute(&self, name: &str) -> Option<&Attribute>;
+
+    fn has_attribute(&self, name: &str) -> bool {
+        self.find_attribute(name).is_some()
+    }
+
+    // what aligned asks for, without an argument the largest alignment of
+    // the target. the parser checked that it is a power of two
+    fn alignment(&self) -> Option<u32> {
+        let aligned = self.find_attribute("aligned")?;
+
+        match aligned.arguments.as_slice() {
+            [AttributeArgument::Expression(alignment)] => {
+                alignment.fold().map(|value| value as u32)
+            }
+            _ => Some(16),
+        }
+    }
+
+    // adds the attributes, replacing the ones that are already present
+    fn merge_attributes(&mut self, attributes: &[Attribute]);
+}
+
+impl AttributeList for Vec<Attribute> {
+    fn find_attribute(&self, name: &str) -> Option<&Attribute> {
+        self.iter().find(|attribute| attribute.name == name)
+    }
+
+    fn merge_attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.retain(|a| a.name != attribute.name);
+            self.push(attribute.clone());
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{fmt::Display, sync::Arc};
 
 use crate::parser::expression::fold::Fold;
 
@@ -10,9 +10,98 @@ pub struct Argument {
     pub datatype: Arc<Datatype>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntegerType {
+    Int,
+    UnsignedInt,
+    Long,
+    UnsignedLong,
+    LongLong,
+    UnsignedLongLong,
+}
+
+impl IntegerType {
+    // ilp32: int and long are 32 bits wide, long long is 64 bits wide
+    pub fn size(&self) -> usize {
+        match self {
+            IntegerType::Int
+            | IntegerType::UnsignedInt
+            | IntegerType::Long
+            | IntegerType::UnsignedLong => 4,
+            IntegerType::LongLong | IntegerType::UnsignedLongLong => 8,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            IntegerType::Int | IntegerType::Long | IntegerType::LongLong
+        )
+    }
+
+    pub fn max_value(&self) -> u64 {
+        let bits = self.size() as u32 * 8;
+
+        if self.is_signed() {
+            (1 << (bits - 1)) - 1
+        } else {
+            u64::MAX >> (64 - bits)
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            IntegerType::Int | IntegerType::UnsignedInt => 0,
+            IntegerType::Long | IntegerType::UnsignedLong => 1,
+            IntegerType::LongLong | IntegerType::UnsignedLongLong => 2,
+        }
+    }
+
+    // usual arithmetic conversions between two integer types
+    pub fn common(&self, other: &IntegerType) -> IntegerType {
+        let (higher, lower) = if self.rank() >= other.rank() {
+            (*self, *other)
+        } else {
+            (*other, *self)
+        };
+
+        if higher.is_signed() == lower.is_signed() || !higher.is_signed() {
+            higher
+        } else if higher.rank() == lower.rank() || higher.size() == lower.size() {
+            // the signed type can not represent every value of the unsigned one
+            higher.to_unsigned()
+        } else {
+            higher
+        }
+    }
+
+    pub fn to_unsigned(self) -> IntegerType {
+        match self {
+            IntegerType::Int | IntegerType::UnsignedInt => IntegerType::UnsignedInt,
+            IntegerType::Long | IntegerType::UnsignedLong => IntegerType::UnsignedLong,
+            IntegerType::LongLong | IntegerType::UnsignedLongLong => IntegerType::UnsignedLongLong,
+        }
+    }
+}
+
+impl Display for IntegerType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            IntegerType::Int => "int",
+            IntegerType::UnsignedInt => "unsigned int",
+            IntegerType::Long => "long",
+            IntegerType::UnsignedLong => "unsigned long",
+            IntegerType::LongLong => "long long",
+            IntegerType::UnsignedLongLong => "unsigned long long",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Datatype {
-    Int,
+    Integer(IntegerType),
     Function {
         return_type: Arc<Datatype>,
         arguments: Vec<Argument>,
@@ -29,18 +118,154 @@ pub enum Datatype {
 impl Datatype {
     pub fn size(&self) -> usize {
         match self {
-            Datatype::Int => 4,
+            Datatype::Integer(integer) => integer.size(),
             Datatype::Function { .. } => 0, // Functions don't have a size
             Datatype::Pointer { .. } => 4,
+            Datatype::Array { .. } => self
+                .checked_size()
+                .expect("array lengths are checked during semantic analysis"),
+        }
+    }
+
+    // the size of an array, or None when its length is not a constant, is
+    // negative or the size overflows
+    pub fn checked_size(&self) -> Option<usize> {
+        match self {
+            Datatype::Array { inner, length } => {
+                let length = usize::try_from(length.fold()?).ok()?;
+                inner.checked_size()?.checked_mul(length)
+            }
+            _ => Some(self.size()),
+        }
+    }
+
+    // the type an expression of this type has when used as a value: arrays
+    // become pointers to their first element and functions pointers to themselves
+    pub fn decay(&self) -> Datatype {
+        match self {
+            Datatype::Array { inner, .. } => Datatype::Pointer {
+                inner: inner.clone(),
+            },
+            Datatype::Function { .. } => Datatype::Pointer {
+                inner: Arc::new(self.clone()),
+            },
+            _ => self.clone(),
+        }
+    }
+
+    // variably modified types (C11 6.7.6) only know their size at run time
+    pub fn is_variably_modified(&self) -> bool {
+        match self {
             Datatype::Array { inner, length } => {
-                let inner_size = inner.size();
-                let length_fold = length.fold();
-
-                if let Some(length) = length_fold {
-                    inner_size * length as usize
-                } else {
-                    todo!("vla")
-                }
+                length.fold().is_none() || inner.is_variably_modified()
+            }
+            Datatype::Pointer { inner } => inner.is_variably_modified(),
+            _ => false,
+        }
+    }
+
+    // long long is two words wide, which the code generator does not handle
+    pub fn has_long_long(&self) -> bool {
+        match self {
+            Datatype::Integer(integer) => integer.size() > 4,
+            Datatype::Pointer { inner } | Datatype::Array { inner, .. } => inner.has_long_long(),
+            Datatype::Function {
+                return_type,
+                arguments,
+            } => {
+                return_type.has_long_long()
+                    || arguments
+                        .iter()
+                        .any(|argument| argument.datatype.has_long_long())
+            }
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Datatype::Integer(_))
+    }
+
+    pub fn is_pointer(&self) -> bool {
+        matches!(self, Datatype::Pointer { .. })
+    }
+
+    // scalars are what conditions and logical operators accept
+    pub fn is_scalar(&self) -> bool {
+        self.is_integer() || self.is_pointer()
+    }
+
+    pub fn pointee(&self) -> Option<&Datatype> {
+        match self {
+            Datatype::Pointer { inner } => Some(inner),
+            _ => None,
+        }
+    }
+
+    // structural type compatibility, parameter names are not part of a type
+    pub fn is_compatible(&self, other: &Datatype) -> bool {
+        match (self, other) {
+            (Datatype::Integer(a), Datatype::Integer(b)) => a == b,
+            (Datatype::Pointer { inner: a }, Datatype::Pointer { inner: b }) => a.is_compatible(b),
+            (
+                Datatype::Array {
+                    inner: a,
+                    length: a_length,
+                },
+                Datatype::Array {
+                    inner: b,
+                    length: b_length,
+                },
+            ) => {
+                let lengths_match = match (a_length.fold(), b_length.fold()) {
+                    (Some(a_length), Some(b_length)) => a_length == b_length,
+                    _ => true,
+                };
+
+                lengths_match && a.is_compatible(b)
+            }
+            (
+                Datatype::Function {
+                    return_type: a_return,
+                    arguments: a_arguments,
+                },
+                Datatype::Function {
+                    return_type: b_return,
+                    arguments: b_arguments,
+                },
+            ) => {
+                a_return.is_compatible(b_return)
+                    && a_arguments.len() == b_arguments.len()
+                    && a_arguments
+                        .iter()
+                        .zip(b_arguments.iter())
+                        .all(|(a, b)| a.datatype.is_compatible(&b.datatype))
+            }
+            _ => false,
+        }
+    }
+}
+
+// spelled the way a C compiler would in a diagnostic, without declarator syntax
+impl Display for Datatype {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Datatype::Integer(integer) => write!(f, "{}", integer),
+            Datatype::Pointer { inner } if inner.is_pointer() => write!(f, "{}*", inner),
+            Datatype::Pointer { inner } => write!(f, "{} *", inner),
+            Datatype::Array { inner, length } => match length.fold() {
+                Some(length) => write!(f, "{}[{}]", inner, length),
+                None => write!(f, "{}[]", inner),
+            },
+            Datatype::Function {
+                return_type,
+                arguments,
+            } => {
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .map(|argument| argument.datatype.to_string())
+                    .collect();
+
+                write!(f, "{} ({})", return_type, arguments.join(", "))
             }
         }
     }
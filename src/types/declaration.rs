@@ -1,20 +1,31 @@
-use super::{datatype::Datatype, expression::Expression};
+use super::{attribute::Attribute, datatype::Datatype, expression::Expression, span::Span};
 
-#[allow(dead_code)]
 #[derive(Debug, Clone)]
-pub struct Declaration {
+pub struct InitDeclarator {
     pub datatype: Datatype,
     pub name: String,
     pub value: Option<Expression>,
+    pub attributes: Vec<Attribute>,
+    // declared extern without an initializer, the object lives in another
+    // translation unit
+    pub external: bool,
+    pub span: Span,
 }
 
-#[derive(Debug)]
-pub struct Argument {
-    pub name: String,
-    pub datatype: Datatype,
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub base_type: Datatype,
+    pub declarators: Vec<InitDeclarator>,
+    pub attributes: Vec<Attribute>,
+    pub span: Span,
 }
+
 #[derive(Debug)]
 pub struct Declarator {
     pub name: String,
     pub datatype: Datatype,
+    // of the identifier, abstract declarators have none
+    pub span: Span,
+    // the declarators of the parameters, if this declares a function
+    pub parameters: Option<Vec<Declarator>>,
 }
@@ -1,14 +1,142 @@
 use std::sync::Arc;
 
+use crate::riscv::values::Csr;
+
+use super::{
+    datatype::{Argument, Datatype, IntegerType},
+    span::Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueCategory {
+    LValue,
+    RValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    // filled in by semantic analysis, the parser leaves them empty
+    pub datatype: Option<Arc<Datatype>>,
+    pub category: Option<ValueCategory>,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind) -> Self {
+        Expression {
+            kind,
+            datatype: None,
+            category: None,
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Expression { span, ..self }
+    }
+
+    pub fn typed(kind: ExpressionKind, datatype: Datatype, category: ValueCategory) -> Self {
+        Expression {
+            kind,
+            datatype: Some(Arc::new(datatype)),
+            category: Some(category),
+            span: Span::default(),
+        }
+    }
+
+    // an already analyzed int constant, for code the backend generates itself
+    pub fn integer_constant(value: i32) -> Self {
+        Expression::typed(
+            ExpressionKind::Number(value.into()),
+            Datatype::Integer(IntegerType::Int),
+            ValueCategory::RValue,
+        )
+    }
+
+    pub fn datatype(&self) -> &Datatype {
+        self.datatype
+            .as_deref()
+            .expect("Expression has not been analyzed")
+    }
+
+    pub fn category(&self) -> ValueCategory {
+        self.category.expect("Expression has not been analyzed")
+    }
+
+    // the address of a file-scope object or of a function, which the linker
+    // can write into the object file for an initializer
+    pub fn address_constant(&self) -> Option<&str> {
+        let operand = match &self.kind {
+            ExpressionKind::UnaryOp(UnaryOp::Ref(operand)) => operand,
+            ExpressionKind::Conversion(Conversion {
+                kind: ConversionKind::ArrayToPointer | ConversionKind::FunctionToPointer,
+                expression,
+            }) => expression,
+            _ => return None,
+        };
+
+        match &operand.kind {
+            ExpressionKind::Global(name) | ExpressionKind::FunctionSymbol(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl From<ExpressionKind> for Expression {
+    fn from(kind: ExpressionKind) -> Self {
+        Expression::new(kind)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expression {
-    Number(i32),
+pub enum ExpressionKind {
+    Number(IntegerLiteral),
     UnaryOp(UnaryOp),
     BinaryOp(BinaryOp),
     TernaryOp(TernaryOp),
     Variable(String),
+    // an object with static storage, referred to by its symbol
+    Global(String),
     FunctionSymbol(String),
     Call(Call),
+    Builtin(BuiltinCall),
+    Subscript(Subscript),
+    Member(Member),
+    Conversion(Conversion),
+}
+
+// conversions the language performs implicitly, made explicit by semantic analysis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConversionKind {
+    // between integer types of a different width or signedness
+    Integer,
+    ArrayToPointer,
+    FunctionToPointer,
+    IntegerToPointer,
+    PointerToInteger,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    pub kind: ConversionKind,
+    pub expression: Arc<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegerLiteral {
+    pub value: u64,
+    pub datatype: IntegerType,
+}
+
+// a literal is never negative, so it keeps the bits of the word
+impl From<i32> for IntegerLiteral {
+    fn from(value: i32) -> Self {
+        IntegerLiteral {
+            value: value as u32 as u64,
+            datatype: IntegerType::Int,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -17,9 +145,105 @@ pub struct Call {
     pub arguments: Vec<Expression>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Builtin {
+    Csrr(Csr),
+    Csrw(Csr),
+    Csrs(Csr),
+    Csrc(Csr),
+    Csrrw(Csr),
+    Wfi,
+    Fence,
+    FenceI,
+    Ecall,
+}
+
+impl Builtin {
+    pub fn takes_csr(name: &str) -> Option<fn(Csr) -> Builtin> {
+        match name {
+            "__builtin_riscv_csrr" => Some(Builtin::Csrr),
+            "__builtin_riscv_csrw" => Some(Builtin::Csrw),
+            "__builtin_riscv_csrs" => Some(Builtin::Csrs),
+            "__builtin_riscv_csrc" => Some(Builtin::Csrc),
+            "__builtin_riscv_csrrw" => Some(Builtin::Csrrw),
+            _ => None,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        match name {
+            "__builtin_riscv_wfi" => Some(Builtin::Wfi),
+            "__builtin_riscv_fence" => Some(Builtin::Fence),
+            "__builtin_riscv_fence_i" => Some(Builtin::FenceI),
+            "__builtin_riscv_ecall" => Some(Builtin::Ecall),
+            _ => None,
+        }
+    }
+
+    pub fn is_builtin(name: &str) -> bool {
+        Builtin::takes_csr(name).is_some() || Builtin::from_name(name).is_some()
+    }
+
+    pub fn writes_csr(&self) -> bool {
+        matches!(
+            self,
+            Builtin::Csrw(_) | Builtin::Csrs(_) | Builtin::Csrc(_) | Builtin::Csrrw(_)
+        )
+    }
+
+    pub fn has_result(&self) -> bool {
+        matches!(self, Builtin::Csrr(_) | Builtin::Csrrw(_) | Builtin::Ecall)
+    }
+
+    // builtins are type checked like calls to a function with this type,
+    // there is no void yet so the ones without a result return int
+    pub fn signature(&self) -> Datatype {
+        let xlen = Arc::new(Datatype::Integer(IntegerType::UnsignedLong));
+        let int = Arc::new(Datatype::Integer(IntegerType::Int));
+
+        let value = Argument {
+            name: "value".to_string(),
+            datatype: xlen.clone(),
+        };
+
+        let (return_type, arguments) = match self {
+            Builtin::Csrr(_) => (xlen, vec![]),
+            Builtin::Csrw(_) | Builtin::Csrs(_) | Builtin::Csrc(_) => (int, vec![value]),
+            Builtin::Csrrw(_) => (xlen, vec![value]),
+            Builtin::Wfi | Builtin::Fence | Builtin::FenceI => (int, vec![]),
+            // whatever the execution environment leaves in a0
+            Builtin::Ecall => (xlen, vec![]),
+        };
+
+        Datatype::Function {
+            return_type,
+            arguments,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuiltinCall {
+    pub builtin: Builtin,
+    pub arguments: Vec<Expression>,
+}
+
+// after semantic analysis the pointer is always the expression, even for i[a]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscript {
+    pub expression: Arc<Expression>,
+    pub index: Arc<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub expression: Arc<Expression>,
+    pub member: String,
+    pub dereference: bool,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum UnaryOp {
-    Nothing(Arc<Expression>),
     Plus(Arc<Expression>),
     Negation(Arc<Expression>),
     BitwiseNot(Arc<Expression>),
@@ -72,3 +296,164 @@ pub struct TernaryOp {
     pub then_expr: Arc<Expression>,
     pub else_expr: Arc<Expression>,
 }
+
+impl UnaryOp {
+    // the same operator applied to another operand
+    pub fn with_operand(&self, operand: Arc<Expression>) -> UnaryOp {
+        match self {
+            UnaryOp::Plus(_) => UnaryOp::Plus(operand),
+            UnaryOp::Negation(_) => UnaryOp::Negation(operand),
+            UnaryOp::BitwiseNot(_) => UnaryOp::BitwiseNot(operand),
+            UnaryOp::LogicalNot(_) => UnaryOp::LogicalNot(operand),
+            UnaryOp::PostfixIncrement(_) => UnaryOp::PostfixIncrement(operand),
+            UnaryOp::PostfixDecrement(_) => UnaryOp::PostfixDecrement(operand),
+            UnaryOp::PrefixIncrement(_) => UnaryOp::PrefixIncrement(operand),
+            UnaryOp::PrefixDecrement(_) => UnaryOp::PrefixDecrement(operand),
+            UnaryOp::Ref(_) => UnaryOp::Ref(operand),
+            UnaryOp::Deref(_) => UnaryOp::Deref(operand),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Plus(_) => "+",
+            UnaryOp::Negation(_) => "-",
+            UnaryOp::BitwiseNot(_) => "~",
+            UnaryOp::LogicalNot(_) => "!",
+            UnaryOp::PostfixIncrement(_) | UnaryOp::PrefixIncrement(_) => "++",
+            UnaryOp::PostfixDecrement(_) | UnaryOp::PrefixDecrement(_) => "--",
+            UnaryOp::Ref(_) => "&",
+            UnaryOp::Deref(_) => "*",
+        }
+    }
+}
+
+impl BinaryOp {
+    pub fn operands(&self) -> (&Arc<Expression>, &Arc<Expression>) {
+        match self {
+            BinaryOp::Addition(l, r)
+            | BinaryOp::Subtraction(l, r)
+            | BinaryOp::Multiplication(l, r)
+            | BinaryOp::Division(l, r)
+            | BinaryOp::Modulus(l, r)
+            | BinaryOp::BitwiseAnd(l, r)
+            | BinaryOp::BitwiseXor(l, r)
+            | BinaryOp::BitwiseOr(l, r)
+            | BinaryOp::LeftShift(l, r)
+            | BinaryOp::RightShift(l, r)
+            | BinaryOp::LogicalAnd(l, r)
+            | BinaryOp::LogicalOr(l, r)
+            | BinaryOp::LessThan(l, r)
+            | BinaryOp::GreaterThan(l, r)
+            | BinaryOp::LessThanEquals(l, r)
+            | BinaryOp::GreaterThanEquals(l, r)
+            | BinaryOp::Equals(l, r)
+            | BinaryOp::NotEquals(l, r)
+            | BinaryOp::Assignment(l, r)
+            | BinaryOp::AssignmentAddition(l, r)
+            | BinaryOp::AssignmentSubtraction(l, r)
+            | BinaryOp::AssignmentMultiplication(l, r)
+            | BinaryOp::AssignmentDivision(l, r)
+            | BinaryOp::AssignmentModulus(l, r)
+            | BinaryOp::AssignmentShiftLeft(l, r)
+            | BinaryOp::AssignmentShiftRight(l, r)
+            | BinaryOp::AssignmentBitwiseAnd(l, r)
+            | BinaryOp::AssignmentBitwiseXor(l, r)
+            | BinaryOp::AssignmentBitwiseOr(l, r)
+            | BinaryOp::Comma(l, r) => (l, r),
+        }
+    }
+
+    // the same operator applied to other operands
+    pub fn with_operands(&self, lhs: Arc<Expression>, rhs: Arc<Expression>) -> BinaryOp {
+        let constructor = match self {
+            BinaryOp::Addition(..) => BinaryOp::Addition,
+            BinaryOp::Subtraction(..) => BinaryOp::Subtraction,
+            BinaryOp::Multiplication(..) => BinaryOp::Multiplication,
+            BinaryOp::Division(..) => BinaryOp::Division,
+            BinaryOp::Modulus(..) => BinaryOp::Modulus,
+            BinaryOp::BitwiseAnd(..) => BinaryOp::BitwiseAnd,
+            BinaryOp::BitwiseXor(..) => BinaryOp::BitwiseXor,
+            BinaryOp::BitwiseOr(..) => BinaryOp::BitwiseOr,
+            BinaryOp::LeftShift(..) => BinaryOp::LeftShift,
+            BinaryOp::RightShift(..) => BinaryOp::RightShift,
+            BinaryOp::LogicalAnd(..) => BinaryOp::LogicalAnd,
+            BinaryOp::LogicalOr(..) => BinaryOp::LogicalOr,
+            BinaryOp::LessThan(..) => BinaryOp::LessThan,
+            BinaryOp::GreaterThan(..) => BinaryOp::GreaterThan,
+            BinaryOp::LessThanEquals(..) => BinaryOp::LessThanEquals,
+            BinaryOp::GreaterThanEquals(..) => BinaryOp::GreaterThanEquals,
+            BinaryOp::Equals(..) => BinaryOp::Equals,
+            BinaryOp::NotEquals(..) => BinaryOp::NotEquals,
+            BinaryOp::Assignment(..) => BinaryOp::Assignment,
+            BinaryOp::AssignmentAddition(..) => BinaryOp::AssignmentAddition,
+            BinaryOp::AssignmentSubtraction(..) => BinaryOp::AssignmentSubtraction,
+            BinaryOp::AssignmentMultiplication(..) => BinaryOp::AssignmentMultiplication,
+            BinaryOp::AssignmentDivision(..) => BinaryOp::AssignmentDivision,
+            BinaryOp::AssignmentModulus(..) => BinaryOp::AssignmentModulus,
+            BinaryOp::AssignmentShiftLeft(..) => BinaryOp::AssignmentShiftLeft,
+            BinaryOp::AssignmentShiftRight(..) => BinaryOp::AssignmentShiftRight,
+            BinaryOp::AssignmentBitwiseAnd(..) => BinaryOp::AssignmentBitwiseAnd,
+            BinaryOp::AssignmentBitwiseXor(..) => BinaryOp::AssignmentBitwiseXor,
+            BinaryOp::AssignmentBitwiseOr(..) => BinaryOp::AssignmentBitwiseOr,
+            BinaryOp::Comma(..) => BinaryOp::Comma,
+        };
+
+        constructor(lhs, rhs)
+    }
+
+    // `a op= b` behaves like `a = a op b`, this is the `a op b`
+    pub fn compound_operation(&self) -> Option<BinaryOp> {
+        let (lhs, rhs) = self.operands();
+        let (lhs, rhs) = (lhs.clone(), rhs.clone());
+
+        match self {
+            BinaryOp::AssignmentAddition(..) => Some(BinaryOp::Addition(lhs, rhs)),
+            BinaryOp::AssignmentSubtraction(..) => Some(BinaryOp::Subtraction(lhs, rhs)),
+            BinaryOp::AssignmentMultiplication(..) => Some(BinaryOp::Multiplication(lhs, rhs)),
+            BinaryOp::AssignmentDivision(..) => Some(BinaryOp::Division(lhs, rhs)),
+            BinaryOp::AssignmentModulus(..) => Some(BinaryOp::Modulus(lhs, rhs)),
+            BinaryOp::AssignmentShiftLeft(..) => Some(BinaryOp::LeftShift(lhs, rhs)),
+            BinaryOp::AssignmentShiftRight(..) => Some(BinaryOp::RightShift(lhs, rhs)),
+            BinaryOp::AssignmentBitwiseAnd(..) => Some(BinaryOp::BitwiseAnd(lhs, rhs)),
+            BinaryOp::AssignmentBitwiseXor(..) => Some(BinaryOp::BitwiseXor(lhs, rhs)),
+            BinaryOp::AssignmentBitwiseOr(..) => Some(BinaryOp::BitwiseOr(lhs, rhs)),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Addition(..) => "+",
+            BinaryOp::Subtraction(..) => "-",
+            BinaryOp::Multiplication(..) => "*",
+            BinaryOp::Division(..) => "/",
+            BinaryOp::Modulus(..) => "%",
+            BinaryOp::BitwiseAnd(..) => "&",
+            BinaryOp::BitwiseXor(..) => "^",
+            BinaryOp::BitwiseOr(..) => "|",
+            BinaryOp::LeftShift(..) => "<<",
+            BinaryOp::RightShift(..) => ">>",
+            BinaryOp::LogicalAnd(..) => "&&",
+            BinaryOp::LogicalOr(..) => "||",
+            BinaryOp::LessThan(..) => "<",
+            BinaryOp::GreaterThan(..) => ">",
+            BinaryOp::LessThanEquals(..) => "<=",
+            BinaryOp::GreaterThanEquals(..) => ">=",
+            BinaryOp::Equals(..) => "==",
+            BinaryOp::NotEquals(..) => "!=",
+            BinaryOp::Assignment(..) => "=",
+            BinaryOp::AssignmentAddition(..) => "+=",
+            BinaryOp::AssignmentSubtraction(..) => "-=",
+            BinaryOp::AssignmentMultiplication(..) => "*=",
+            BinaryOp::AssignmentDivision(..) => "/=",
+            BinaryOp::AssignmentModulus(..) => "%=",
+            BinaryOp::AssignmentShiftLeft(..) => "<<=",
+            BinaryOp::AssignmentShiftRight(..) => ">>=",
+            BinaryOp::AssignmentBitwiseAnd(..) => "&=",
+            BinaryOp::AssignmentBitwiseXor(..) => "^=",
+            BinaryOp::AssignmentBitwiseOr(..) => "|=",
+            BinaryOp::Comma(..) => ",",
+        }
+    }
+}
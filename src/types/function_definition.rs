@@ -1,21 +1,32 @@
 use crate::parser::ParserScopeState;
 
-use super::{datatype::Datatype, scope::Scope};
+use super::{attribute::Attribute, datatype::Datatype, scope::Scope, span::Span};
 
 #[derive(Debug)]
 pub struct FunctionArgument {
     pub name: String,
     pub unique_name: String,
     pub datatype: Datatype,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone)]
-pub struct FunctionArgumentOptionalName {
-    pub name: Option<String>,
-    pub datatype: Datatype,
+// the privilege mode a trap handler is entered in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterruptMode {
+    Machine,
+    Supervisor,
+    User,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FunctionKind {
+    #[default]
+    Normal,
+    Interrupt(InterruptMode),
+    // no prologue or epilogue, the body is expected to be inline assembly
+    Naked,
 }
 
-#[allow(dead_code)]
 #[derive(Debug)]
 pub struct FunctionDefinition {
     pub name: String,
@@ -23,4 +34,7 @@ pub struct FunctionDefinition {
     pub return_type: Datatype,
     pub body: Scope,
     pub scope_state: ParserScopeState,
+    pub attributes: Vec<Attribute>,
+    pub kind: FunctionKind,
+    pub span: Span,
 }
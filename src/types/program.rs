@@ -1,8 +1,9 @@
-use super::function_definition::FunctionDefinition;
+use super::{declaration::Declaration, function_definition::FunctionDefinition};
 
 #[derive(Debug)]
 pub enum ProgramStatement {
     FunctionDefinition(FunctionDefinition),
+    Declaration(Declaration),
 }
 
 #[derive(Debug)]
@@ -1,4 +1,4 @@
-use super::{declaration::Declaration, statement::Statement};
+use super::{declaration::Declaration, span::Span, statement::Statement};
 
 #[derive(Debug, Clone)]
 pub enum ScopeItem {
@@ -7,9 +7,14 @@ pub enum ScopeItem {
     Label(Label),
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Clone)]
-pub enum Label {
+pub struct Label {
+    pub kind: LabelKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum LabelKind {
     Named(String),
     Case { id: String, value: i32 },
     Default { id: String },
@@ -18,4 +23,5 @@ pub enum Label {
 #[derive(Debug, Clone)]
 pub struct Scope {
     pub items: Vec<ScopeItem>,
+    pub span: Span,
 }
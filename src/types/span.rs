@@ -0,0 +1,50 @@
+use std::ops::Range;
+
+// where a node came from in the source: a byte range, plus the 1-based line
+// and column of its first character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    // from the start of this span to the end of the other one
+    pub fn to(&self, other: &Span) -> Span {
+        Span {
+            end: other.end,
+            ..*self
+        }
+    }
+}
+
+// turns byte offsets into line and column numbers
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        LineIndex { line_starts }
+    }
+
+    pub fn span(&self, range: Range<usize>) -> Span {
+        let line = self
+            .line_starts
+            .partition_point(|&start| start <= range.start);
+
+        Span {
+            start: range.start,
+            end: range.end,
+            line,
+            column: range.start - self.line_starts[line - 1] + 1,
+        }
+    }
+}
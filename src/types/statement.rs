@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use crate::parser::Case;
 
-use super::{declaration::Declaration, expression::Expression, scope::Scope};
+use super::{declaration::Declaration, expression::Expression, scope::Scope, span::Span};
 
 #[derive(Debug, Clone)]
 pub enum JumpStatement {
@@ -50,13 +50,54 @@ pub struct SwitchStatement {
 }
 
 #[derive(Debug, Clone)]
-pub enum Statement {
+pub struct AsmOperand {
+    pub name: Option<String>,
+    pub constraint: String,
+    pub expression: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmStatement {
+    pub template: String,
+    // where the template is, for errors about the operands it refers to
+    pub template_span: Span,
+    pub outputs: Vec<AsmOperand>,
+    pub inputs: Vec<AsmOperand>,
+    pub clobbers: Vec<String>,
+    pub volatile: bool,
+    // basic asm has no operand list and its template is emitted verbatim
+    pub basic: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+impl Statement {
+    // for statements the compiler makes up itself
+    pub fn new(kind: StatementKind) -> Self {
+        Statement {
+            kind,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum StatementKind {
     Jump { statement: JumpStatement },
     Expression { expression: Expression },
     Scope { scope: Scope },
     If { statement: IfStatement },
     While { statement: WhileStatement },
-    For { statement: ForStatement },
+    // behind a pointer, it is much larger than the other statements
+    For { statement: Arc<ForStatement> },
     Switch { statement: SwitchStatement },
+    Asm { statement: AsmStatement },
     Null,
+    // __attribute__((fallthrough)); marks falling into the next case as intended
+    Fallthrough,
 }
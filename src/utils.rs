@@ -1,2 +1,2 @@
-pub mod random_name;
-pub mod nearest_multiple;
\ No newline at end of file
+pub mod nearest_multiple;
+pub mod unique_name;
@@ -1,7 +1,5 @@
 pub fn nearest_multiple(value: u32, multiple: u32) -> u32 {
-    if value == 0 {
-        0
-    } else {
-        (1 + (value - 1) / multiple) * multiple
-    }
+    value
+        .checked_next_multiple_of(multiple)
+        .expect("sizes are checked during semantic analysis")
 }
@@ -0,0 +1,4 @@
+// the name a unique identifier was made from, for showing it to the user
+pub fn original_name(unique: &str) -> &str {
+    unique.rsplit_once('.').map_or(unique, |(name, _)| name)
+}
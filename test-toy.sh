@@ -1,6 +1,6 @@
 echo "Compiling file $1 with toy compiler"
 
-cargo r $1
+cargo r -- -S tests/$1.c -o output/$1.s
 
 if [ $? -ne 0 ]; then
     echo "Error compiling file $1"
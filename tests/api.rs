@@ -0,0 +1,157 @@
+// the library, used the way an embedding program would
+mod common;
+
+use c_riscv::{
+    analyze, assembly,
+    ast::{
+        ConversionKind, Datatype, Expression, ExpressionKind, IntegerType, JumpStatement,
+        ProgramStatement, ScopeItem, StatementKind,
+    },
+    compile_to_assembly, generate, optimize, parse, tokenize, Diagnostics, Immediate, Instruction,
+    Options, Register, RegisterWithOffset, Target,
+};
+
+const SOURCE: &str = "int square(int x) { return x * x; }\nint main() { return square(7); }\n";
+
+#[test]
+fn compile_to_assembly_returns_the_assembly() {
+    let assembly = compile_to_assembly(SOURCE, &Options::default()).unwrap();
+
+    assert!(assembly.contains(".globl square\nsquare:\n"));
+    assert!(assembly.contains(".globl main\nmain:\n"));
+    assert!(assembly.contains("mul a0, a1, a0\n"));
+}
+
+#[test]
+fn compile_to_assembly_returns_the_errors() {
+    let errors = compile_to_assembly("int main() { return x; }", &Options::default()).unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Use of undeclared identifier 'x'");
+}
+
+// the stages one at a time give the same result as all of them at once
+#[test]
+fn stages_one_at_a_time() {
+    let options = Options::default();
+    let mut diagnostics = Diagnostics::new(options.warnings.clone());
+
+    let tokens = tokenize(SOURCE, &mut diagnostics).unwrap();
+    assert_eq!(tokens.first().unwrap().kind.text(), "int");
+
+    let ast = parse(&tokens, &mut diagnostics).unwrap();
+    assert_eq!(ast.functions.len(), 2);
+
+    let typed_ast = analyze(ast, &mut diagnostics).unwrap();
+    let instructions = generate(typed_ast, &options, &mut diagnostics).unwrap();
+    let optimized = optimize(instructions.clone(), &options);
+
+    assert_eq!(
+        assembly(&optimized),
+        compile_to_assembly(SOURCE, &options).unwrap()
+    );
+    assert_eq!(
+        optimize(
+            instructions.clone(),
+            &Options {
+                optimization: 0,
+                ..options
+            }
+        ),
+        instructions
+    );
+    assert_eq!(diagnostics.iter().count(), 0);
+}
+
+#[test]
+fn targets() {
+    let target = Target::new("rv32imac", "ilp32").unwrap();
+    assert_eq!(target.to_string(), "rv32imac-ilp32");
+    assert_eq!(Target::default().to_string(), "rv32imafdc-ilp32");
+
+    assert_eq!(
+        Target::new("rv64gc", "lp64").unwrap_err(),
+        "Unsupported architecture 'rv64gc', only rv32 targets are supported"
+    );
+    assert_eq!(
+        Target::new("rv32i", "ilp32").unwrap_err(),
+        "Architecture 'rv32i' has no m extension, which the generated code needs"
+    );
+    assert_eq!(
+        Target::new("rv32im", "lp64").unwrap_err(),
+        "Unsupported abi 'lp64', only ilp32 abis are supported"
+    );
+}
+
+// objects from the library link and run without any other tool
+#[test]
+fn objects_link_into_executables() {
+    assert_eq!(common::run(&[SOURCE]), 49);
+}
+
+// what a function returns, found by walking the typed ast
+fn returned<'p>(program: &'p c_riscv::Program, name: &str) -> &'p Expression {
+    let function = program
+        .functions
+        .iter()
+        .find_map(|statement| match statement {
+            ProgramStatement::FunctionDefinition(function) if function.name == name => {
+                Some(function)
+            }
+            _ => None,
+        })
+        .unwrap();
+
+    function
+        .body
+        .items
+        .iter()
+        .find_map(|item| match item {
+            ScopeItem::Statement(statement) => match &statement.kind {
+                StatementKind::Jump {
+                    statement:
+                        JumpStatement::Return {
+                            expression: Some(expression),
+                        },
+                } => Some(expression),
+                _ => None,
+            },
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn the_ast_and_the_instructions_can_be_walked() {
+    let options = Options::default();
+    let mut diagnostics = Diagnostics::new(options.warnings.clone());
+    let tokens = tokenize(SOURCE, &mut diagnostics).unwrap();
+    let program = analyze(parse(&tokens, &mut diagnostics).unwrap(), &mut diagnostics).unwrap();
+
+    let ExpressionKind::Call(call) = &returned(&program, "main").kind else {
+        panic!("main does not return a call");
+    };
+    let ExpressionKind::Conversion(conversion) = &call.expression.kind else {
+        panic!("the function is not converted to a pointer");
+    };
+    assert_eq!(conversion.kind, ConversionKind::FunctionToPointer);
+    assert_eq!(
+        conversion.expression.kind,
+        ExpressionKind::FunctionSymbol("square".to_string())
+    );
+    assert_eq!(
+        call.arguments[0].datatype.as_deref(),
+        Some(&Datatype::Integer(IntegerType::Int))
+    );
+
+    let instructions = generate(program, &options, &mut diagnostics).unwrap();
+    assert!(instructions.contains(&Instruction::CallP(Immediate::Label("square".to_string()))));
+    assert!(instructions.contains(&Instruction::Mul(Register::A0, Register::A1, Register::A0)));
+    assert!(instructions.iter().any(|instruction| matches!(
+        instruction,
+        Instruction::Sw(
+            Register::A0,
+            RegisterWithOffset(Immediate::Number(_), Register::Fp)
+        )
+    )));
+}
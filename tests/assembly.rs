@@ -0,0 +1,263 @@
+// programs that cannot run on a host or a simulator without a privileged
+// mode, checked by the instructions they compile to instead, and the
+// names codegen gives to labels
+mod common;
+
+use c_riscv::Options;
+use common::assembly;
+
+// the lines of the assembly, without comments and indentation
+fn lines(source: &str) -> Vec<String> {
+    assembly(source, &Options::default())
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn assert_contains_in_order(lines: &[String], expected: &[&str]) {
+    let mut rest = lines.iter();
+
+    for wanted in expected {
+        assert!(
+            rest.any(|line| line == wanted),
+            "`{wanted}` is missing or out of order in:\n{}",
+            lines.join("\n")
+        );
+    }
+}
+
+#[test]
+fn csr_builtins() {
+    let source = "
+        int main() {
+          unsigned long start = __builtin_riscv_csrr(mcycle);
+
+          __builtin_riscv_csrw(mscratch, 5);
+          __builtin_riscv_csrs(mstatus, 8);
+          __builtin_riscv_csrc(mie, 128);
+          unsigned long previous = __builtin_riscv_csrrw(0x340, start);
+
+          __builtin_riscv_fence();
+          __builtin_riscv_fence_i();
+
+          return __builtin_riscv_wfi();
+        }
+    ";
+
+    assert_contains_in_order(
+        &lines(source),
+        &[
+            "csrr a0, mcycle",
+            "csrw mscratch, a0",
+            "csrs mstatus, a0",
+            "csrc mie, a0",
+            // 0x340 is mscratch
+            "csrrw a0, mscratch, a0",
+            "fence",
+            "fence.i",
+            "wfi",
+        ],
+    );
+}
+
+// the lines from the label of the function up to the next function
+fn function<'a>(lines: &'a [String], name: &str) -> &'a [String] {
+    let start = lines
+        .iter()
+        .position(|line| *line == format!("{name}:"))
+        .unwrap_or_else(|| panic!("no function {name} in:\n{}", lines.join("\n")));
+    let length = lines[start..]
+        .iter()
+        .position(|line| line.starts_with(".text") || line.starts_with(".section"))
+        .unwrap_or(lines.len() - start);
+
+    &lines[start + 1..start + length]
+}
+
+#[test]
+fn interrupt_handlers_save_what_they_use_and_return_with_xret() {
+    let source = r#"
+        int helper(int x) { return x * 2; }
+
+        __attribute__((interrupt("machine"))) int machine_handler() {
+          return helper(3);
+        }
+
+        int __attribute__((interrupt("supervisor"))) supervisor_handler() {
+          asm volatile("li t4, 1");
+          return 0;
+        }
+    "#;
+    let lines = lines(source);
+
+    // a call can change any caller-saved register, so all of them are saved
+    let caller_saved = [
+        "t0", "t1", "t2", "a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7", "t3", "t4", "t5", "t6",
+    ];
+    let machine = function(&lines, "machine_handler");
+    assert_eq!(machine[0], "addi sp, sp, -64");
+    for (index, register) in caller_saved.iter().enumerate() {
+        let offset = index * 4;
+        assert_eq!(machine[index + 1], format!("sw {register}, {offset}(sp)"));
+        assert!(machine.contains(&format!("lw {register}, {offset}(sp)")));
+    }
+    assert_eq!(machine[machine.len() - 2], "addi sp, sp, 64");
+    assert_eq!(machine[machine.len() - 1], "mret");
+
+    // without a call only the registers the handler writes are saved
+    let supervisor = function(&lines, "supervisor_handler");
+    assert_contains_in_order(
+        supervisor,
+        &[
+            "addi sp, sp, -16",
+            "sw a0, 0(sp)",
+            "sw t4, 4(sp)",
+            "li t4, 1",
+            "lw a0, 0(sp)",
+            "lw t4, 4(sp)",
+            "addi sp, sp, 16",
+            "sret",
+        ],
+    );
+    assert!(!supervisor.iter().any(|line| line.contains("t3")));
+    assert!(!supervisor
+        .iter()
+        .any(|line| line == "ret" || line == "mret"));
+
+    // the other functions return as usual
+    assert_eq!(function(&lines, "helper").last().unwrap(), "ret");
+}
+
+#[test]
+fn function_attributes() {
+    let source = r#"
+        int helper(void) __attribute__((weak));
+
+        __attribute__((section(".text.boot"), aligned(64))) int boot() { return 20; }
+
+        int __attribute__((naked)) naked_add() {
+          asm("add a0, a0, a1\n"
+              "ret");
+        }
+
+        __attribute__((weak, __used__)) int helper() { return 2; }
+    "#;
+    let lines = lines(source);
+
+    assert_contains_in_order(
+        &lines,
+        &[
+            ".weak helper",
+            ".section .text.boot,\"ax\",@progbits",
+            ".balign 64",
+            ".globl boot",
+            "boot:",
+        ],
+    );
+
+    // a naked function is only its asm, no prologue and no epilogue
+    assert_eq!(function(&lines, "naked_add"), ["add a0, a0, a1", "ret"]);
+
+    let helper = lines.iter().position(|line| line == "helper:").unwrap();
+    assert_eq!(lines[helper - 1], ".weak helper");
+    assert!(!lines.contains(&".globl helper".to_string()));
+}
+
+const CLASSIFY: &str = "
+    int classify(int x) {
+      switch (x) {
+        case 1: return 2;
+        default: return 3;
+      }
+    }
+";
+
+const COUNT: &str = "
+    int count(int n) {
+      int i = 0;
+      while (i < n) {
+        if (i == 1 || n == 5) break;
+        i = i + 1;
+      }
+      return i && n;
+    }
+";
+
+#[test]
+fn labels_are_named_after_their_function() {
+    let source = format!("{CLASSIFY}{COUNT}");
+
+    // compiling twice gives the same assembly byte for byte
+    assert_eq!(
+        assembly(&source, &Options::default()),
+        assembly(&source, &Options::default())
+    );
+
+    let lines = lines(&source);
+    let labels: Vec<&str> = function(&lines, "count")
+        .iter()
+        .filter_map(|line| line.strip_suffix(':'))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            ".Lcount_while_2_start",
+            ".Lcount_or_0_true",
+            ".Lcount_or_0_end",
+            ".Lcount_or_1_true",
+            ".Lcount_or_1_end",
+            ".Lcount_if_2_else",
+            ".Lcount_if_2_end",
+            ".Lcount_while_2_end",
+            ".Lcount_and_3_false",
+        ]
+    );
+    assert!(function(&lines, "classify").contains(&".Lclassify_switch_1_case_1:".to_string()));
+}
+
+#[test]
+fn functions_compile_the_same_in_any_order() {
+    let forward = lines(&format!("{CLASSIFY}{COUNT}"));
+    let backward = lines(&format!("{COUNT}{CLASSIFY}"));
+
+    for name in ["classify", "count"] {
+        assert_eq!(function(&forward, name), function(&backward, name));
+    }
+}
+
+#[test]
+fn attribute_arguments_are_constant_expressions() {
+    let source = "__attribute__((aligned(1 << 4))) int f() { return 0; }";
+
+    assert_contains_in_order(&lines(source), &[".balign 16", "f:"]);
+}
+
+#[test]
+fn variable_attributes() {
+    let source = r#"
+        extern int missing __attribute__((weak));
+        __attribute__((section(".sdata"), aligned(16))) int tuned = 1;
+        int fallback __attribute__((weak));
+        int plain;
+    "#;
+
+    assert_contains_in_order(
+        &lines(source),
+        &[
+            ".weak missing",
+            ".section .sdata,\"aw\",@progbits",
+            ".balign 16",
+            ".globl tuned",
+            "tuned:",
+            ".bss",
+            ".balign 4",
+            ".weak fallback",
+            "fallback:",
+            ".bss",
+            ".balign 4",
+            ".globl plain",
+        ],
+    );
+}
@@ -0,0 +1,264 @@
+// just enough of rv32im to run what the compiler generates. segments are
+// loaded at their physical address and a word stored to the exit device
+// ends the program. the executable is read on its own, without the reader
+// of the crate, and tests look at where things went the same way
+use std::collections::HashMap;
+
+const PAGE: u32 = 0x1000;
+const STEPS: usize = 50_000_000;
+
+struct Memory {
+    pages: HashMap<u32, Vec<u8>>,
+}
+
+impl Memory {
+    fn byte(&mut self, address: u32) -> &mut u8 {
+        let page = self
+            .pages
+            .entry(address / PAGE)
+            .or_insert_with(|| vec![0; PAGE as usize]);
+        &mut page[(address % PAGE) as usize]
+    }
+
+    fn load(&mut self, address: u32, size: u32) -> u32 {
+        (0..size).fold(0, |value, index| {
+            value | (*self.byte(address.wrapping_add(index)) as u32) << (8 * index)
+        })
+    }
+
+    fn store(&mut self, address: u32, size: u32, value: u32) {
+        for index in 0..size {
+            *self.byte(address.wrapping_add(index)) = (value >> (8 * index)) as u8;
+        }
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+pub fn sign_extend(value: u32, bits: u32) -> u32 {
+    (((value << (32 - bits)) as i32) >> (32 - bits)) as u32
+}
+
+// where the jal at pc goes
+pub fn jal_target(pc: u32, instruction: u32) -> u32 {
+    let offset = (instruction >> 31) << 20
+        | (instruction >> 12 & 0xff) << 12
+        | (instruction >> 20 & 1) << 11
+        | (instruction >> 21 & 0x3ff) << 1;
+    pc.wrapping_add(sign_extend(offset, 21))
+}
+
+fn load_segments(executable: &[u8], memory: &mut Memory) -> Result<u32, String> {
+    if !executable.starts_with(b"\x7fELF") || u16_at(executable, 16) != 2 {
+        return Err("not an ELF executable".to_string());
+    }
+
+    let entry = u32_at(executable, 24);
+    let headers = u32_at(executable, 28) as usize;
+    let count = u16_at(executable, 44) as usize;
+
+    for index in 0..count {
+        let header = headers + index * 32;
+        // PT_LOAD
+        if u32_at(executable, header) != 1 {
+            continue;
+        }
+
+        let offset = u32_at(executable, header + 4) as usize;
+        let load_address = u32_at(executable, header + 12);
+        let file_size = u32_at(executable, header + 16) as usize;
+
+        for (index, &byte) in executable[offset..offset + file_size].iter().enumerate() {
+            *memory.byte(load_address + index as u32) = byte;
+        }
+    }
+
+    Ok(entry)
+}
+
+pub fn entry(executable: &[u8]) -> u32 {
+    u32_at(executable, 24)
+}
+
+// from the symbol table, the strings are in the section it links to
+pub fn address(executable: &[u8], name: &str) -> u32 {
+    let headers = u32_at(executable, 32) as usize;
+    let count = u16_at(executable, 48) as usize;
+    let header = |index: usize| headers + index * 40;
+
+    for index in 0..count {
+        // SHT_SYMTAB
+        if u32_at(executable, header(index) + 4) != 2 {
+            continue;
+        }
+
+        let offset = u32_at(executable, header(index) + 16) as usize;
+        let size = u32_at(executable, header(index) + 20) as usize;
+        let strings = header(u32_at(executable, header(index) + 24) as usize);
+        let strings = u32_at(executable, strings + 16) as usize;
+
+        for symbol in (offset..offset + size).step_by(16) {
+            let start = strings + u32_at(executable, symbol) as usize;
+            let length = executable[start..].iter().position(|&byte| byte == 0);
+
+            if length.is_some_and(|length| &executable[start..start + length] == name.as_bytes()) {
+                return u32_at(executable, symbol + 4);
+            }
+        }
+    }
+
+    panic!("no symbol {name}")
+}
+
+// a word of the program as it is loaded
+pub fn word(executable: &[u8], address: u32) -> u32 {
+    let mut memory = Memory {
+        pages: HashMap::new(),
+    };
+    load_segments(executable, &mut memory).unwrap_or_else(|error| panic!("{error}"));
+    memory.load(address, 4)
+}
+
+fn multiply(funct3: u32, a: u32, b: u32) -> u32 {
+    let (signed_a, signed_b) = (a as i32, b as i32);
+
+    match funct3 {
+        0 => a.wrapping_mul(b),
+        1 => ((signed_a as i64 * signed_b as i64) >> 32) as u32,
+        2 => ((signed_a as i64 * b as i64) >> 32) as u32,
+        3 => ((a as u64 * b as u64) >> 32) as u32,
+        4 if b == 0 => u32::MAX,
+        4 => signed_a.wrapping_div(signed_b) as u32,
+        5 if b == 0 => u32::MAX,
+        5 => a / b,
+        6 if b == 0 => a,
+        6 => signed_a.wrapping_rem(signed_b) as u32,
+        _ if b == 0 => a,
+        _ => a % b,
+    }
+}
+
+fn operation(funct3: u32, alternate: bool, a: u32, b: u32) -> u32 {
+    match funct3 {
+        0 if alternate => a.wrapping_sub(b),
+        0 => a.wrapping_add(b),
+        1 => a << (b & 31),
+        2 => ((a as i32) < (b as i32)) as u32,
+        3 => (a < b) as u32,
+        4 => a ^ b,
+        5 if alternate => ((a as i32) >> (b & 31)) as u32,
+        5 => a >> (b & 31),
+        6 => a | b,
+        _ => a & b,
+    }
+}
+
+// the value main returned, as the low byte like an exit status
+pub fn run_executable(executable: &[u8], exit_device: u32) -> Result<u8, String> {
+    let mut memory = Memory {
+        pages: HashMap::new(),
+    };
+    let mut pc = load_segments(executable, &mut memory)?;
+    let mut x = [0u32; 32];
+
+    for _ in 0..STEPS {
+        let instruction = memory.load(pc, 4);
+        if instruction & 3 != 3 {
+            return Err(format!("compressed instruction at {pc:#x}"));
+        }
+
+        let opcode = instruction & 0x7f;
+        let rd = (instruction >> 7 & 31) as usize;
+        let funct3 = instruction >> 12 & 7;
+        let rs1 = x[(instruction >> 15 & 31) as usize];
+        let rs2 = x[(instruction >> 20 & 31) as usize];
+        let funct7 = instruction >> 25;
+        let immediate = sign_extend(instruction >> 20, 12);
+
+        let mut next = pc.wrapping_add(4);
+        let mut result = None;
+
+        match opcode {
+            0x37 => result = Some(instruction & 0xffff_f000),
+            0x17 => result = Some(pc.wrapping_add(instruction & 0xffff_f000)),
+            0x6f => {
+                result = Some(next);
+                next = jal_target(pc, instruction);
+            }
+            0x67 => {
+                result = Some(next);
+                next = rs1.wrapping_add(immediate) & !1;
+            }
+            0x63 => {
+                let offset = (instruction >> 31) << 12
+                    | (instruction >> 7 & 1) << 11
+                    | (instruction >> 25 & 0x3f) << 5
+                    | (instruction >> 8 & 0xf) << 1;
+                let taken = match funct3 {
+                    0 => rs1 == rs2,
+                    1 => rs1 != rs2,
+                    4 => (rs1 as i32) < (rs2 as i32),
+                    5 => (rs1 as i32) >= (rs2 as i32),
+                    6 => rs1 < rs2,
+                    7 => rs1 >= rs2,
+                    _ => return Err(format!("invalid branch at {pc:#x}")),
+                };
+                if taken {
+                    next = pc.wrapping_add(sign_extend(offset, 13));
+                }
+            }
+            0x03 => {
+                let address = rs1.wrapping_add(immediate);
+                let size = 1 << (funct3 & 3);
+                let value = memory.load(address, size);
+                result = Some(match funct3 {
+                    0 | 1 => sign_extend(value, size * 8),
+                    _ => value,
+                });
+            }
+            0x23 => {
+                let offset = sign_extend(funct7 << 5 | (instruction >> 7 & 31), 12);
+                let address = rs1.wrapping_add(offset);
+                if address == exit_device {
+                    return Ok(rs2 as u8);
+                }
+                memory.store(address, 1 << funct3, rs2);
+            }
+            0x13 => {
+                let alternate = funct3 == 5 && funct7 == 0x20;
+                let b = if funct3 == 1 || funct3 == 5 {
+                    instruction >> 20 & 31
+                } else {
+                    immediate
+                };
+                result = Some(operation(funct3, alternate, rs1, b));
+            }
+            0x33 if funct7 == 1 => result = Some(multiply(funct3, rs1, rs2)),
+            0x33 => result = Some(operation(funct3, funct7 == 0x20, rs1, rs2)),
+            0x0f => (),
+            // csr reads see zeros, wfi with nothing to wake up for is a hang
+            0x73 if funct3 != 0 => result = Some(0),
+            0x73 if instruction == 0x1050_0073 => {
+                return Err(format!("wfi without an exit at {pc:#x}"))
+            }
+            _ => {
+                return Err(format!(
+                    "unsupported instruction {instruction:#010x} at {pc:#x}"
+                ))
+            }
+        }
+
+        if let (Some(value), true) = (result, rd != 0) {
+            x[rd] = value;
+        }
+        pc = next;
+    }
+
+    Err("the program did not exit".to_string())
+}
@@ -0,0 +1,95 @@
+// compiling, linking and running programs for the integration tests. the
+// programs are linked with the built-in linker and run on a small rv32im
+// emulator, so no toolchain or simulator is needed
+#![allow(dead_code, unused_imports)]
+
+use c_riscv::{compile, link, object, Diagnostic, Diagnostics, FreestandingOptions, Options};
+
+mod emulator;
+
+pub use emulator::{address, entry, jal_target, run_executable, sign_extend, word};
+
+// where the startup code stores the return value of main
+pub const EXIT_DEVICE: u32 = 0x0010_0000;
+
+// the default memory map, with the exit device the emulator watches
+pub fn memory() -> FreestandingOptions {
+    FreestandingOptions {
+        exit_device: Some(EXIT_DEVICE),
+        ..FreestandingOptions::default()
+    }
+}
+
+pub fn freestanding() -> Options {
+    Options {
+        freestanding: Some(memory()),
+        ..Options::default()
+    }
+}
+
+// the error diagnostics of a source that does not compile
+pub fn errors(source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Diagnostics::new(Options::default().warnings);
+    let compiled = compile(source, &Options::default(), &mut diagnostics);
+    assert!(compiled.is_err(), "expected errors compiling:\n{source}");
+    diagnostics.iter().cloned().collect()
+}
+
+// the source code the diagnostic points at
+pub fn primary<'s>(source: &'s str, diagnostic: &Diagnostic) -> &'s str {
+    let span = diagnostic
+        .primary
+        .as_ref()
+        .unwrap_or_else(|| panic!("no span on {diagnostic:?}"))
+        .span;
+    &source[span.start..span.end]
+}
+
+// the assembly of a source that compiles
+pub fn assembly(source: &str, options: &Options) -> String {
+    let mut diagnostics = Diagnostics::new(options.warnings.clone());
+    let instructions = compile(source, options, &mut diagnostics).unwrap_or_else(|_| {
+        panic!(
+            "could not compile:\n{source}\n{:?}",
+            diagnostics.iter().collect::<Vec<_>>()
+        )
+    });
+    c_riscv::assembly(&instructions)
+}
+
+// an object for every unit, the way -c makes them
+pub fn objects(units: &[&str], options: &Options) -> Vec<(String, Vec<u8>)> {
+    units
+        .iter()
+        .enumerate()
+        .map(|(index, source)| {
+            let mut diagnostics = Diagnostics::new(options.warnings.clone());
+            let instructions = compile(source, options, &mut diagnostics)
+                .unwrap_or_else(|_| panic!("could not compile unit {index}:\n{source}"));
+            let object = object(&instructions, options).expect("the unit assembles");
+            (format!("unit{index}.c"), object)
+        })
+        .collect()
+}
+
+pub fn executable(units: &[&str]) -> Result<Vec<u8>, String> {
+    let options = freestanding();
+    let memory = options.freestanding.clone().expect("freestanding");
+    link(&objects(units, &options), &memory)
+}
+
+// an executable laid out in the memory map, to look at where things went
+pub fn linked(units: &[&str], memory: &FreestandingOptions) -> Vec<u8> {
+    let options = Options {
+        freestanding: Some(memory.clone()),
+        ..Options::default()
+    };
+    link(&objects(units, &options), memory)
+        .unwrap_or_else(|error| panic!("could not link: {error}"))
+}
+
+// the exit code of a program made of the units
+pub fn run(units: &[&str]) -> u8 {
+    let executable = executable(units).unwrap_or_else(|error| panic!("could not link: {error}"));
+    run_executable(&executable, EXIT_DEVICE).unwrap_or_else(|error| panic!("{error}"))
+}
@@ -0,0 +1,555 @@
+// errors and warnings, checked for their message and where they point
+mod common;
+
+use c_riscv::{
+    compile,
+    diagnostic::{code, json, render::Renderer, sarif, Report},
+    Diagnostics, Options,
+};
+use common::{errors, primary};
+
+// what used to be a panic or a todo! in the compiler is an error now
+#[test]
+fn invalid_programs_are_errors() {
+    let cases = [
+        ("int main() { break; }", code::JUMP_OUTSIDE_LOOP, "break"),
+        (
+            "int main() { continue; }",
+            code::JUMP_OUTSIDE_LOOP,
+            "continue",
+        ),
+        ("int main() { return y; }", code::UNDECLARED, "y"),
+        ("int main() { return f(); }", code::UNDECLARED, "f"),
+        (
+            "int main() { int x; return x(); }",
+            code::NOT_CALLABLE,
+            "x()",
+        ),
+        (
+            "int f(int a) { return a; } int main() { return f(1, 2); }",
+            code::ARGUMENT_COUNT,
+            "f(1, 2)",
+        ),
+        (
+            "int main() { 1 = 2; return 0; }",
+            code::NOT_ASSIGNABLE,
+            "1 = 2",
+        ),
+    ];
+
+    for (source, code, at) in cases {
+        let errors = errors(source);
+
+        assert_eq!(errors.len(), 1, "{source}: {errors:?}");
+        assert_eq!(errors[0].code, Some(code), "{source}");
+        assert_eq!(primary(source, &errors[0]), at, "{source}");
+    }
+}
+
+#[test]
+fn errors_are_rendered_with_the_source() {
+    let source = "int main() {\n  int x;\n  int x;\n  return 0;\n}\n";
+    let errors = errors(source);
+    let rendered = Renderer::new("main.c", source).render(&errors[0]);
+
+    assert_eq!(
+        rendered,
+        "error[E0108]: Redefinition of 'x'\n\
+         \x20--> main.c:3:7\n\
+         \x20 |\n\
+         2 |   int x;\n\
+         \x20 |       - previous definition is here\n\
+         3 |   int x;\n\
+         \x20 |       ^\n"
+    );
+}
+
+#[test]
+fn literals_wider_than_a_word_are_rejected() {
+    let source = "int main() { int x = 0x100000000; return 0; }";
+    let errors = errors(source);
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "'long long' is not supported");
+    assert_eq!(primary(source, &errors[0]), "0x100000000");
+}
+
+#[test]
+fn long_long_is_rejected() {
+    let source = "
+        long long global;
+        int f(unsigned long long x);
+        int main() { long long *p = 0; return 1LL; }
+    ";
+    let errors = errors(source);
+    let rejected: Vec<&str> = errors
+        .iter()
+        .map(|error| {
+            assert_eq!(error.code, Some(code::UNSUPPORTED));
+            primary(source, error)
+        })
+        .collect();
+
+    assert_eq!(
+        rejected,
+        ["global", "f(unsigned long long x)", "*p = 0", "1LL"]
+    );
+}
+
+#[test]
+fn array_lengths_must_be_positive() {
+    for (length, found) in [("0", "0"), ("-1", "-1"), ("2 - 5", "-3")] {
+        for source in [
+            format!("int a[{length}]; int main() {{ return 0; }}"),
+            format!("int main() {{ int a[{length}]; return 0; }}"),
+            format!("int main() {{ int (*p)[{length}]; return 0; }}"),
+        ] {
+            let errors = errors(&source);
+
+            assert_eq!(errors.len(), 1, "{source}");
+            assert_eq!(errors[0].code, Some(code::INVALID_SIZE));
+            assert_eq!(
+                errors[0].message,
+                format!("Array length must be greater than zero, found {found}")
+            );
+        }
+    }
+}
+
+#[test]
+fn arrays_too_large_for_the_address_space() {
+    for source in [
+        "int a[2147483647]; int main() { return 0; }",
+        "int main() { int a[2147483647]; return 0; }",
+        "int main() { int a[65536][65536]; return 0; }",
+    ] {
+        let errors = errors(source);
+
+        assert_eq!(errors.len(), 1, "{source}");
+        assert_eq!(errors[0].code, Some(code::INVALID_SIZE));
+        assert!(errors[0].message.ends_with("is too large"), "{source}");
+        assert_eq!(primary(source, &errors[0]).split('[').next(), Some("a"));
+    }
+}
+
+#[test]
+fn locals_too_large_for_a_frame() {
+    let source = "int main() { int a[0x10000000]; int b[0x10000000]; return 0; }";
+    let errors = errors(source);
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].code, Some(code::INVALID_SIZE));
+    assert_eq!(
+        errors[0].message,
+        "The local variables of this function do not fit in a stack frame"
+    );
+    assert_eq!(primary(source, &errors[0]), "b[0x10000000]");
+}
+
+#[test]
+fn literals_of_a_word_are_accepted() {
+    let source = "int main() { unsigned x = 4294967295u; return x == 0xffffffffu; }";
+
+    assert_eq!(common::run(&[source]), 1);
+}
+
+#[test]
+fn octal_literals_take_digit_separators() {
+    let source = "int main() { return 0'17 + 0'1'0 + 0; }";
+
+    assert_eq!(common::run(&[source]), 23);
+}
+
+#[test]
+fn asm_operand_numbers_out_of_range() {
+    for reference in ["%1", "%99999999999999999999999"] {
+        let source =
+            format!("int main() {{ int x; asm(\"li {reference}, 1\" : \"=r\"(x)); return x; }}");
+        let errors = errors(&source);
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("out of range"));
+        assert_eq!(
+            primary(&source, &errors[0]),
+            format!("\"li {reference}, 1\"")
+        );
+    }
+}
+
+#[test]
+fn asm_clobbers_must_name_registers() {
+    let source = r#"int main() { asm volatile("nop" ::: "memory", "foo"); return 0; }"#;
+    let errors = errors(source);
+
+    assert_eq!(errors.len(), 1, "{errors:?}");
+    assert_eq!(errors[0].message, "Unknown register name \"foo\" in asm");
+    assert_eq!(errors[0].code, Some(code::INVALID_ASM));
+    assert_eq!(
+        primary(source, &errors[0]),
+        r#"asm volatile("nop" ::: "memory", "foo");"#
+    );
+}
+
+// the primary and the first secondary span of the only error
+fn spans(source: &str) -> (String, &str, &str) {
+    let errors = errors(source);
+    assert_eq!(errors.len(), 1, "{errors:?}");
+
+    let secondary = errors[0].secondary[0].span;
+    (
+        errors[0].message.clone(),
+        primary(source, &errors[0]),
+        &source[secondary.start..secondary.end],
+    )
+}
+
+#[test]
+fn duplicate_case_labels() {
+    let source = "
+        int main() {
+          int a = 1;
+          switch (a) {
+            case 1: return 1;
+            case 2: { case 1: return 2; }
+          }
+          return 0;
+        }
+    ";
+
+    assert_eq!(
+        spans(source),
+        ("Duplicate case value '1'".to_string(), "case 1:", "case 1:")
+    );
+
+    // the second one is the error, the first one is where it was used before
+    let errors = errors(source);
+    let primary = errors[0].primary.as_ref().unwrap().span;
+    assert_eq!((primary.line, errors[0].secondary[0].span.line), (6, 5));
+}
+
+#[test]
+fn duplicate_default_labels() {
+    let source = "
+        int main() {
+          switch (1) { default: break; default: break; }
+          return 0;
+        }
+    ";
+
+    let (message, _, _) = spans(source);
+    assert_eq!(message, "Multiple default labels in one switch");
+}
+
+#[test]
+fn separate_switches_can_repeat_case_values() {
+    let source = "
+        int main() {
+          switch (1) {
+            case 1:
+              switch (2) { case 1: return 1; case 2: return 2; }
+          }
+          return 0;
+        }
+
+        // switches in different functions are unrelated too
+        int f(int x) { switch (x) { case 1: return 3; default: return 4; } }
+    ";
+
+    assert_eq!(common::run(&[source]), 2);
+}
+
+#[test]
+fn redefinitions_in_the_same_block() {
+    let (message, primary, previous) = spans("int main() { int a; int b, a; return 0; }");
+    assert_eq!(
+        (message.as_str(), primary, previous),
+        ("Redefinition of 'a'", "a", "a")
+    );
+
+    // parameters share the outermost block of the function
+    let (message, _, _) = spans("int f(int x) { int x = 1; return x; }");
+    assert_eq!(message, "Redefinition of 'x'");
+}
+
+#[test]
+fn declarations_in_nested_blocks_and_repeated_declarations_are_fine() {
+    let source = "
+        int twice(int x);
+
+        int main() {
+          int twice(int x);
+          int twice(int y);
+          int a = 1;
+          { int a = 2; }
+          return a;
+        }
+
+        int twice(int x) { return x * 2; }
+    ";
+
+    assert_eq!(common::run(&[source]), 1);
+}
+
+// the parser picks up again after a statement or declaration it could not
+// parse, so one run finds every syntax error
+#[test]
+fn parser_recovers_at_statements_and_declarations() {
+    let source = "\
+int main() {
+  int x = 1
+  return x +;
+}
+int f( { }
+int g() { return 3 }
+";
+    let errors = errors(source);
+    let found: Vec<_> = errors
+        .iter()
+        .map(|error| {
+            let span = error.primary.as_ref().unwrap().span;
+            (error.message.as_str(), span.line)
+        })
+        .collect();
+
+    assert_eq!(
+        found,
+        [
+            ("Expected `;` after declaration", 2),
+            ("Expected an expression", 3),
+            ("Expected a declaration or a function definition", 5),
+            ("Expected `;` after return statement", 6),
+        ]
+    );
+    assert!(errors.iter().all(|error| error.code == Some(code::SYNTAX)));
+
+    // a missing semicolon comes with the fix
+    let suggestion = &errors[0].suggestions[0];
+    assert_eq!(suggestion.replacement, ";");
+    assert_eq!(primary(source, &errors[0]), "");
+    assert_eq!(suggestion.span, errors[0].primary.as_ref().unwrap().span);
+}
+
+#[test]
+fn expected_tokens_are_named() {
+    let source = "int main() { if (1 { return 0; } return 1; }";
+    let errors = errors(source);
+
+    assert_eq!(errors[0].message, "Expected `)`");
+    assert_eq!(primary(source, &errors[0]), "{");
+}
+
+fn diagnostics(source: &str, flags: &[&str]) -> Diagnostics {
+    let mut options = Options::default();
+    for flag in flags {
+        options.warnings.apply(flag).unwrap();
+    }
+
+    let mut diagnostics = Diagnostics::new(options.warnings.clone());
+    let _ = compile(source, &options, &mut diagnostics);
+    diagnostics
+}
+
+fn report<'a>(filename: &'a str, source: &'a str, diagnostics: &'a Diagnostics) -> Report<'a> {
+    Report {
+        filename,
+        source,
+        diagnostics,
+    }
+}
+
+const UNDECLARED: &str = "int main() {\n  return y;\n}\n";
+
+#[test]
+fn json_diagnostics() {
+    let diagnostics = diagnostics(UNDECLARED, &[]);
+    let json = json::render(&[report("main.c", UNDECLARED, &diagnostics)]);
+
+    assert_eq!(
+        json.to_string(),
+        r#"[{"file":"main.c","severity":"error","code":"E0100","option":null,"#.to_string()
+            + r#""message":"Use of undeclared identifier 'y'","#
+            + r#""range":{"start":{"line":2,"column":10,"offset":22},"#
+            + r#""end":{"line":2,"column":11,"offset":23}},"#
+            + r#""labels":[{"range":{"start":{"line":2,"column":10,"offset":22},"#
+            + r#""end":{"line":2,"column":11,"offset":23}},"message":null,"primary":true}],"#
+            + r#""notes":[],"suggestions":[]}]"#
+    );
+}
+
+#[test]
+fn sarif_diagnostics() {
+    let diagnostics = diagnostics(UNDECLARED, &[]);
+    let sarif = sarif::render(&[report("main.c", UNDECLARED, &diagnostics)]).to_string();
+
+    assert!(sarif.starts_with(
+        r#"{"$schema":"https://json.schemastore.org/sarif-2.1.0.json","version":"2.1.0","runs":[{"#
+    ));
+    assert!(sarif.contains(r#""rules":[{"id":"E0100"}]"#));
+    assert!(sarif.contains(
+        r#""results":[{"ruleId":"E0100","level":"error","message":{"text":"Use of undeclared identifier 'y'"},"#
+    ));
+    assert!(
+        sarif.contains(r#"{"physicalLocation":{"artifactLocation":{"uri":"main.c","index":0},"#)
+    );
+    assert!(sarif.contains(
+        r#""region":{"startLine":2,"startColumn":10,"endLine":2,"endColumn":11,"charOffset":22,"charLength":1}"#
+    ));
+}
+
+// every input goes into the same document, each record knows its file
+#[test]
+fn structured_diagnostics_of_several_files() {
+    let warning = "int main() { int x = 0; if (x = 1) return 1; return 0; }";
+    let (first, second) = (
+        diagnostics(UNDECLARED, &[]),
+        diagnostics(warning, &["parentheses"]),
+    );
+    let reports = [
+        report("a.c", UNDECLARED, &first),
+        report("b.c", warning, &second),
+    ];
+
+    let json = json::render(&reports).to_string();
+    assert!(json.starts_with(r#"[{"file":"a.c","severity":"error""#));
+    assert!(json.contains(r#"},{"file":"b.c","severity":"warning""#));
+    assert_eq!(json.matches(r#""file":"#).count(), 2);
+
+    let sarif = sarif::render(&reports).to_string();
+    assert_eq!(sarif.matches(r#""tool":"#).count(), 1);
+    assert!(sarif.contains(r#""rules":[{"id":"-Wparentheses"},{"id":"E0100"}]"#));
+    assert!(
+        sarif.contains(r#""artifacts":[{"location":{"uri":"a.c"}},{"location":{"uri":"b.c"}}]"#)
+    );
+    assert!(sarif.contains(r#""artifactLocation":{"uri":"b.c","index":1}"#));
+}
+
+// warnings are identified by the flag that turns them off
+#[test]
+fn structured_warnings_name_their_flag() {
+    let source = "int main() { int x = 0; if (x = 1) return 1; return 0; }";
+    let diagnostics = diagnostics(source, &["parentheses"]);
+
+    let report = [report("main.c", source, &diagnostics)];
+    let json = json::render(&report).to_string();
+    assert!(json.contains(r#""severity":"warning","code":null,"option":"-Wparentheses""#));
+    assert!(json.contains(r#""notes":["place parentheses around the assignment"#));
+
+    let sarif = sarif::render(&report).to_string();
+    assert!(sarif.contains(r#""rules":[{"id":"-Wparentheses"}]"#));
+    assert!(sarif.contains(r#""ruleId":"-Wparentheses","level":"warning""#));
+}
+
+// the lexer reports every bad character and carries on after it
+#[test]
+fn lexer_recovers_after_bad_characters() {
+    let source = "int main() {\n  int a = 1 @ 2;\n  int b = $;\n  return a;\n}\n";
+    let errors = errors(source);
+
+    let found: Vec<_> = errors
+        .iter()
+        .map(|error| (error.message.as_str(), primary(source, error)))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("Unexpected character '@'", "@"),
+            ("Unexpected character '$'", "$")
+        ]
+    );
+}
+
+#[test]
+fn unterminated_comments_and_strings() {
+    let source = "int main() { /* open\n";
+    let comment = errors(source);
+    assert_eq!(comment[0].message, "Unterminated comment");
+    assert_eq!(primary(source, &comment[0]), "/*");
+
+    let source = "int main() { char *s = \"abc\n; }";
+    let string = errors(source);
+    assert_eq!(string[0].message, "Unterminated string literal");
+    assert_eq!(primary(source, &string[0]), "\"");
+}
+
+#[test]
+fn keywords_are_not_names() {
+    let source = "int main() { int return = 1; return 0; }";
+    let errors = errors(source);
+
+    assert_eq!(
+        errors[0].message,
+        "Expected an identifier, found keyword `return`"
+    );
+    assert_eq!(primary(source, &errors[0]), "return");
+}
+
+#[test]
+fn file_scope_definitions() {
+    let (message, redefined, previous) = spans("int a = 1;\nint a = 2;\nint main() { return a; }");
+    assert_eq!(
+        (message.as_str(), redefined, previous),
+        ("Redefinition of 'a'", "a = 2", "a = 1")
+    );
+
+    let source = "int b;\nint a = b + 1;\nint main() { return a; }";
+    let errors = errors(source);
+    assert_eq!(
+        errors[0].message,
+        "Initializer of a file-scope variable must be a constant"
+    );
+    assert_eq!(errors[0].code, Some(code::NOT_CONSTANT));
+    assert_eq!(primary(source, &errors[0]), "b + 1");
+}
+
+#[test]
+fn constant_expressions_that_overflow_are_not_constant() {
+    let cases = [
+        "int x = 1 / 0;",
+        "int x = 5 % (2 - 2);",
+        "int x = 2147483647 + 1;",
+        "int x = -2147483647 - 2;",
+        "int x = 1 << 32;",
+        "int x = 3 << 31;",
+        "int x = 1 >> -1;",
+    ];
+
+    for source in cases {
+        let errors = errors(source);
+
+        assert_eq!(errors.len(), 1, "{source}");
+        assert_eq!(errors[0].code, Some(code::NOT_CONSTANT), "{source}");
+    }
+
+    // case labels are folded by the parser, before there are any types
+    let source = "int main() { switch (0) { case 1 << 31 << 1: break; } return 0; }";
+    let errors = errors(source);
+    assert_eq!(
+        errors[0].message,
+        "Case label must be a constant expression"
+    );
+    assert_eq!(primary(source, &errors[0]), "1 << 31 << 1");
+}
+
+#[test]
+fn rejected_declarations_stay_in_scope() {
+    let source = "
+        long long wide;
+
+        int main() {
+          int n = 2;
+          int v[n], w = 1;
+          return v[0] + w + wide;
+        }
+    ";
+    let errors = errors(source);
+    let messages: Vec<&str> = errors.iter().map(|error| error.message.as_str()).collect();
+
+    // the uses of wide, v and w are not errors of their own
+    assert_eq!(
+        messages,
+        [
+            "'long long' is not supported",
+            "Variable length arrays are not supported",
+        ]
+    );
+}
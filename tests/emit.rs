@@ -0,0 +1,208 @@
+// --emit and where the diagnostics go, through the compiler binary
+use std::{
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+const SOURCE: &str = "int main() { return 1 + 2; }\n";
+
+// a directory of its own for every test, with the source in it
+fn directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("c-riscv-emit-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("main.c"), SOURCE).unwrap();
+    directory
+}
+
+fn compiler(directory: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_c-riscv"))
+        .current_dir(directory)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn every_stage_goes_to_a_file_of_its_own() {
+    let directory = directory("files");
+    let output = compiler(
+        &directory,
+        &["--emit=tokens,ast,typed-ast,ir,cfg,asm", "main.c"],
+    );
+    assert_eq!(stdout(&output), "");
+
+    let read = |name: &str| std::fs::read_to_string(directory.join(name)).unwrap();
+
+    assert!(read("main.tokens")
+        .starts_with("keyword text=\"int\" <1:1>\nidentifier text=\"main\" <1:5>\n"));
+    assert_eq!(
+        read("main.ast"),
+        "Program
+  FunctionDefinition name=\"main\" return_type=\"int\" <1:1>
+    body: Scope <1:12>
+      Return <1:14>
+        value: Binary operator=\"+\" <1:21>
+          Number value=\"1\" literal_type=\"int\" <1:21>
+          Number value=\"2\" literal_type=\"int\" <1:25>
+"
+    );
+    assert!(read("main.typed-ast")
+        .contains("value: Binary operator=\"+\" type=\"int\" category=\"rvalue\" <1:21>\n"));
+    assert!(read("main.cfg").starts_with("function main\n  block main"));
+    assert!(read("main.ir").contains("    .globl main\nmain:\n    # Function Prologue\n"));
+    assert!(read("main.s").contains("\nmain:\n"));
+
+    // --emit only writes the stages, there is no executable
+    assert!(!directory.join("a.out").exists());
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn stages_can_go_to_stdout() {
+    let directory = directory("stdout");
+
+    let explicit = stdout(&compiler(&directory, &["--emit=ast=-", "main.c"]));
+    let output = stdout(&compiler(&directory, &["--emit=ast", "-o", "-", "main.c"]));
+
+    assert!(explicit.starts_with("Program\n"));
+    assert_eq!(explicit, output);
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn stages_as_json() {
+    let directory = directory("json");
+    let output = compiler(
+        &directory,
+        &["--emit=tokens", "--emit-format=json", "main.c"],
+    );
+    assert_eq!(stdout(&output), "");
+
+    let tokens = std::fs::read_to_string(directory.join("main.tokens.json")).unwrap();
+    assert!(tokens.starts_with(
+        r#"[{"kind":"keyword","text":"int","span":{"line":1,"column":1,"start":0,"end":3}},"#
+    ));
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn unknown_stages_are_errors() {
+    let directory = directory("unknown");
+    let output = compiler(&directory, &["--emit=bytecode", "main.c"]);
+
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "error: Unknown stage 'bytecode' for --emit\n"
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+// the assembly on stdout stays clean, the diagnostics of every input are a
+// single array on stderr
+#[test]
+fn structured_diagnostics_go_to_stderr() {
+    let directory = directory("diagnostics");
+    std::fs::write(
+        directory.join("warning.c"),
+        "int main() { int x = 0; if (x = 1) return 1; return 0; }\n",
+    )
+    .unwrap();
+
+    let output = compiler(
+        &directory,
+        &[
+            "--diagnostics-format=json",
+            "-Wparentheses",
+            "-S",
+            "-o",
+            "-",
+            "warning.c",
+        ],
+    );
+    assert!(stdout(&output).contains("\nmain:\n"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with(r#"[{"file":"warning.c","severity":"warning""#));
+
+    std::fs::write(directory.join("a.c"), "int f() { return x; }\n").unwrap();
+    std::fs::write(directory.join("b.c"), "int g() { return y; }\n").unwrap();
+    let output = compiler(
+        &directory,
+        &["--diagnostics-format=json", "-c", "a.c", "b.c"],
+    );
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with(r#"[{"file":"a.c""#));
+    assert!(stderr.contains(r#"},{"file":"b.c""#));
+    assert_eq!(stderr.lines().count(), 1);
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+// the errors of the driver are in the same document as the ones of the
+// sources, and belong to no file
+#[test]
+fn driver_errors_are_structured_too() {
+    let directory = directory("driver");
+
+    let output = compiler(
+        &directory,
+        &["--diagnostics-format=json", "--bogus", "main.c"],
+    );
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "[{\"file\":null,\"severity\":\"error\",\"code\":null,\"option\":null,\
+         \"message\":\"Unknown option '--bogus'\",\"range\":null,\"labels\":[],\
+         \"notes\":[],\"suggestions\":[]}]\n"
+    );
+
+    std::fs::write(directory.join("warning.c"), "int f() { return 0, 1; }\n").unwrap();
+    let output = compiler(
+        &directory,
+        &[
+            "--diagnostics-format=sarif",
+            "-Wall",
+            "-c",
+            "warning.c",
+            "missing.c",
+        ],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().count(), 1, "{stderr}");
+    assert!(stderr.contains(r#""artifacts":[{"location":{"uri":"warning.c"}}]"#));
+    assert!(stderr.contains(r#"{"level":"error","message":{"text":"Could not read missing.c: "#));
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+// anything the built-in assembler does not know goes to the gnu toolchain,
+// which may not be installed
+#[test]
+fn unknown_instructions_are_left_to_the_toolchain() {
+    let directory = directory("fallback");
+    std::fs::write(
+        directory.join("float.s"),
+        "f:\n    fadd.s fa0, fa0, fa1\n    ret\n",
+    )
+    .unwrap();
+
+    let output = compiler(&directory, &["-c", "float.s"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    if output.status.success() {
+        assert!(directory.join("float.o").exists());
+    } else {
+        assert!(stderr.contains("Unknown instruction `fadd.s`"), "{stderr}");
+        assert!(stderr.contains("riscv64-unknown-elf-gcc"), "{stderr}");
+    }
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
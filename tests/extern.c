@@ -0,0 +1,10 @@
+extern int twice(int);
+int extern half(int x);
+
+int main() {
+  return twice(20) + half(5);
+}
+
+int twice(int x) { return 2 * x; }
+
+int half(int x) { return x / 2; }
@@ -0,0 +1,136 @@
+// the startup code and the memory map of programs without an operating
+// system
+mod common;
+
+use c_riscv::{
+    analyze, assembly, generate, linker_script, parse, tokenize, Diagnostics, FreestandingOptions,
+    Options,
+};
+use common::{address, entry, linked, run_executable};
+
+// away from the default memory map, so that nothing only works by chance
+fn memory() -> FreestandingOptions {
+    FreestandingOptions {
+        rom_origin: 0x2000_0000,
+        rom_length: 0x1_0000,
+        ram_origin: 0x3000_0000,
+        ram_length: 0x2_0000,
+        ..common::memory()
+    }
+}
+
+// the startup code is put in front of the unit that defines main, the
+// code of main starts in .text. comments are left out
+fn crt0(memory: &FreestandingOptions) -> String {
+    let options = Options {
+        freestanding: Some(memory.clone()),
+        ..Options::default()
+    };
+    let mut diagnostics = Diagnostics::new(options.warnings.clone());
+
+    let tokens = tokenize("int main() { return 0; }", &mut diagnostics).unwrap();
+    let program = analyze(parse(&tokens, &mut diagnostics).unwrap(), &mut diagnostics).unwrap();
+    let assembly = assembly(&generate(program, &options, &mut diagnostics).unwrap());
+
+    let (crt0, _) = assembly.split_once("\n.text\n").expect("main is in .text");
+    crt0.lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.to_string() + "\n")
+        .collect()
+}
+
+#[test]
+fn crt0_sets_up_the_registers_before_main() {
+    let crt0 = crt0(&memory());
+    let lines: Vec<_> = crt0.lines().collect();
+
+    assert_eq!(lines[0], ".section .text.init,\"ax\",@progbits");
+    assert_eq!(lines[1], ".globl _start");
+    assert_eq!(lines[2], "_start:");
+
+    let position = |wanted: &str| {
+        lines
+            .iter()
+            .position(|line| *line == wanted)
+            .unwrap_or_else(|| panic!("`{wanted}` is missing from:\n{crt0}"))
+    };
+
+    // gp is set up without relaxing, relaxing would make it relative to itself
+    assert!(position(".option norelax") < position("la gp, __global_pointer$"));
+    assert!(position("la gp, __global_pointer$") < position(".option pop"));
+
+    let order = [
+        "la sp, __stack_top",
+        "la t0, __bss_start",
+        "la t0, __data_load_start",
+        "call main",
+        "li t0, 1048576",
+        "sw a0, 0(t0)",
+        "wfi",
+    ];
+    for pair in order.windows(2) {
+        assert!(position(pair[0]) < position(pair[1]), "{pair:?}");
+    }
+}
+
+#[test]
+fn crt0_spins_without_an_exit_device() {
+    let memory = FreestandingOptions {
+        exit_device: None,
+        ..memory()
+    };
+    let crt0 = crt0(&memory);
+
+    assert!(!crt0.contains("sw a0"));
+    assert!(crt0.ends_with("_start_halt:\nwfi\nj _start_halt\n"));
+}
+
+#[test]
+fn linker_script_has_the_memory_map() {
+    let script = linker_script(&memory());
+
+    assert!(script.contains("ENTRY(_start)"));
+    assert!(script.contains("ROM (rx) : ORIGIN = 0x20000000, LENGTH = 0x10000"));
+    assert!(script.contains("RAM (rwx) : ORIGIN = 0x30000000, LENGTH = 0x20000"));
+    assert!(script.contains("KEEP(*(.text.init))"));
+    assert!(script.contains("} > RAM AT > ROM"));
+
+    for symbol in [
+        "__data_start",
+        "__global_pointer$",
+        "__data_end",
+        "__data_load_start",
+        "__bss_start",
+        "__bss_end",
+        "__stack_top",
+    ] {
+        assert!(script.contains(&format!("{symbol} = ")), "{symbol}");
+    }
+}
+
+// the built-in linker lays the program out like the script does
+#[test]
+fn executables_follow_the_memory_map() {
+    let memory = memory();
+    let executable = linked(&["int main() { return 42; }"], &memory);
+
+    assert_eq!(entry(&executable), memory.rom_origin);
+    assert_eq!(address(&executable, "_start"), memory.rom_origin);
+    assert_eq!(
+        address(&executable, "__stack_top"),
+        memory.ram_origin + memory.ram_length
+    );
+    assert_eq!(address(&executable, "__data_start"), memory.ram_origin);
+    assert_eq!(
+        address(&executable, "__global_pointer$"),
+        address(&executable, "__data_start") + 0x800
+    );
+
+    let main = address(&executable, "main");
+    assert!((memory.rom_origin..memory.rom_origin + memory.rom_length).contains(&main));
+
+    assert_eq!(
+        run_executable(&executable, memory.exit_device.unwrap()),
+        Ok(42)
+    );
+}
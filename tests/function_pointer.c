@@ -0,0 +1,26 @@
+int add(int a, int b) { return a + b; }
+
+int sub(int a, int b) { return a - b; }
+
+int apply(int (*op)(int, int), int a, int b) { return op(a, b); }
+
+int apply_adjusted(int op(int, int), int a, int b) { return (*op)(a, b); }
+
+int (*select(int which))(int, int) { return which ? &sub : add; }
+
+int main() {
+  int (*table[2])(int x, int y);
+  table[0] = add;
+  table[1] = *sub;
+
+  int result = apply(add, 1, 2);
+  result += apply(&sub, 10, 4);
+  result += apply(*add, 2, 2);
+  result += apply(table[1], 7, 5);
+  result += apply_adjusted(**table, 3, 3);
+  result += (*table[0])(1, 1);
+  result += select(1)(9, 3);
+  result += (**select(0))(1, 2);
+
+  return result;
+}
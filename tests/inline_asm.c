@@ -0,0 +1,28 @@
+int main() {
+    int a = 12;
+    int b = 30;
+    int sum;
+
+    asm volatile("add %0, %1, %2" : "=r"(sum) : "r"(a), "r"(b));
+
+    int shifted;
+    __asm__("slli %[out], %[in], %[amount]"
+            : [out] "=r"(shifted)
+            : [in] "r"(a), [amount] "i"(2));
+
+    int counter = 5;
+    asm("addi %0, %0, 10" : "+r"(counter));
+
+    int memory = 0;
+    asm volatile("li t0, 3\n"
+                 "sw t0, %0"
+                 : "=m"(memory)
+                 :
+                 : "t0");
+
+    asm volatile("fence");
+    asm volatile("nop" ::: "memory");
+
+    // 42 + 48 + 15 + 3
+    return sum + shifted + counter + memory - 57;
+}
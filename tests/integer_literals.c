@@ -0,0 +1,11 @@
+int main() {
+  unsigned int big = 4294967295u;
+  long wide = 0x7fffffffL;
+  unsigned long huge = 4294967295ul;
+  unsigned long mask = 0xFFu | 0b1000'0000'0000ul;
+  int octal = 017 + 0'17;
+  int million = 1'000'000;
+  long unsigned int mixed = 10LU > 5 ? 3 : 4u;
+
+  return (octal + million / 100000 + (mask >> 8) + mixed + (big == 4294967295u) + (huge != 0) + (wide > 0)) & 0xff;
+}
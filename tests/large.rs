@@ -0,0 +1,166 @@
+// translation units far bigger than anyone writes by hand, the parser has
+// to get through them without backtracking itself into the ground
+mod common;
+#[path = "../benches/generated/mod.rs"]
+mod generated;
+
+use std::{
+    fmt::Write,
+    process::{Command, Output},
+};
+
+use c_riscv::{parse, tokenize, Diagnostics, Options, WarningOptions};
+
+// every stage recurses into nested expressions, the compiler runs on a main
+// thread with 8M of stack but test threads only get 2M
+fn run_with_stack(source: String) -> u8 {
+    std::thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(move || common::run(&[&source]))
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn long_operator_chains() {
+    // 1 - 1 + 1 - 1 ..., only left associativity makes that 1
+    let mut expression = String::from("1");
+    for index in 1..=200 {
+        expression += if index % 2 == 1 { " - 1" } else { " + 1" };
+    }
+
+    let source = format!("int main() {{ return {expression}; }}");
+    assert_eq!(run_with_stack(source), 1);
+}
+
+#[test]
+fn deeply_nested_expressions_and_blocks() {
+    // the nesting C11 5.2.4.1 asks every compiler to handle
+    let (parentheses, blocks) = (63, 127);
+    let expression = "(".repeat(parentheses) + "7" + &")".repeat(parentheses);
+    let blocks = "{ ".repeat(blocks) + &format!("x = {expression};") + &" }".repeat(blocks);
+
+    let source = format!("int main() {{ int x = 0; {blocks} return x; }}");
+    assert_eq!(run_with_stack(source), 7);
+}
+
+// the compiler binary, on the stack of its own main thread
+fn compile(name: &str, source: &str, args: &[&str]) -> Output {
+    let directory =
+        std::env::temp_dir().join(format!("c-riscv-large-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("main.c"), source).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_c-riscv"))
+        .current_dir(&directory)
+        .args(args)
+        .arg("main.c")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(directory).unwrap();
+    output
+}
+
+#[test]
+fn nesting_is_limited_instead_of_overflowing_the_stack() {
+    let nested = |depth: usize| {
+        [
+            (
+                "parentheses",
+                format!("return {}1{};", "(".repeat(depth), ")".repeat(depth)),
+            ),
+            ("negations", format!("return {}1;", "- ".repeat(depth))),
+            (
+                "ifs",
+                format!("{}x = 2; return x;", "if (x) ".repeat(depth)),
+            ),
+            (
+                "blocks",
+                format!("{}{}", "{ ".repeat(depth), " }".repeat(depth)),
+            ),
+            ("sums", format!("return {};", vec!["x"; depth].join(" + "))),
+        ]
+        .map(|(name, body)| (name, format!("int main() {{ int x = 1; {body} }}\n")))
+    };
+
+    // right below the limit, each of the stages still has stack to spare
+    for (name, source) in nested(250) {
+        for args in [&["-S", "-o", "main.s"][..], &["--emit=ast"]] {
+            let output = compile(name, &source, args);
+            assert!(output.status.success(), "{name} {args:?}: {output:?}");
+        }
+    }
+
+    for depth in [300, 20000] {
+        for (name, source) in nested(depth) {
+            for args in [&["-S", "-o", "main.s"][..], &["--emit=ast"]] {
+                let output = compile(name, &source, args);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+
+                assert_eq!(output.status.code(), Some(1), "{name} {depth}: {stderr}");
+                assert_eq!(
+                    stderr.matches("error").count(),
+                    1,
+                    "{name} {depth}: {stderr}"
+                );
+                assert!(
+                    stderr.contains("Nesting deeper than 256 levels is not supported"),
+                    "{name} {depth}: {stderr}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn many_functions() {
+    let functions = 2000;
+    let mut source = String::new();
+    for index in 0..functions {
+        let _ = writeln!(
+            source,
+            "int f{index}(int a, int b) {{\n  int x = a * {index} + b, *p = &x;\n  if (x > 3 && b != 0 || a == 1) {{ *p += a ? a : b; }}\n  return x;\n}}"
+        );
+    }
+
+    let mut diagnostics = Diagnostics::new(WarningOptions::default());
+    let tokens = tokenize(&source, &mut diagnostics).unwrap();
+    let program = parse(&tokens, &mut diagnostics).unwrap();
+
+    assert_eq!(program.functions.len(), functions);
+}
+
+#[test]
+fn the_benchmark_source_compiles() {
+    // `cargo bench` only finds out at the end of a long build otherwise
+    common::assembly(&generated::translation_unit(3), &Options::default());
+}
+
+#[test]
+fn bodies_longer_than_a_branch_reaches() {
+    // a few instructions per statement, so the loop and the if around them
+    // span well over the 4K a branch reaches
+    let body = "x = x * 3 + 1; x = x % 1000;\n".repeat(400);
+    let source = format!(
+        "int main() {{
+           int x = 0, i;
+           for (i = 0; i < 3; i++) {{
+             if (i != 1) {{ {body} }}
+           }}
+           return x % 256;
+         }}"
+    );
+
+    let mut expected = 0;
+    for i in 0..3 {
+        if i != 1 {
+            for _ in 0..400 {
+                expected = (expected * 3 + 1) % 1000;
+            }
+        }
+    }
+
+    assert_eq!(run_with_stack(source), (expected % 256) as u8);
+}
@@ -0,0 +1,204 @@
+// what the built-in linker makes out of the objects the assembler writes,
+// looked at the way a loader sees it
+mod common;
+
+use c_riscv::{assemble, Options};
+use common::{address, jal_target, linked, memory, run_executable, sign_extend, word, EXIT_DEVICE};
+
+fn link(sources: &[&str]) -> Vec<u8> {
+    let objects: Vec<_> = sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| {
+            let bytes = assemble(source, &Options::default()).unwrap();
+            (format!("unit{index}.s"), bytes)
+        })
+        .collect();
+
+    c_riscv::link(&objects, &memory()).unwrap_or_else(|error| panic!("could not link: {error}"))
+}
+
+#[test]
+fn relocations_are_applied_across_objects() {
+    let start = "
+        .text
+        .globl _start
+    _start:
+        call compute
+        li t0, 0x100000
+        sw a0, 0(t0)
+    1:
+        j 1b
+
+        .globl finish
+    finish:
+        addi a0, a0, 18
+        ret";
+
+    let compute = "
+        .text
+        .globl compute
+    compute:
+        lui a0, %hi(table)
+        lw a1, %lo(table)(a0)
+        addi a0, a0, %lo(table)
+        lw a2, 4(a0)
+    .Ltable:
+        auipc a3, %pcrel_hi(table)
+        lw a3, %pcrel_lo(.Ltable)(a3)
+        lui a4, %hi(pointer)
+        lw a4, %lo(pointer)(a4)
+        lw a4, 0(a4)
+        bne a1, a3, fail
+
+        lui t0, %hi(slot)
+        sw a4, %lo(slot)(t0)
+    .Lslot:
+        auipc t1, %pcrel_hi(slot)
+        lw a5, %pcrel_lo(.Lslot)(t1)
+        bne a5, a4, fail
+        sw a1, %pcrel_lo(.Lslot)(t1)
+        lw a6, %lo(slot)(t0)
+        bne a6, a1, fail
+
+        add a0, a1, a2
+        add a0, a0, a3
+        .option push
+        .option norelax
+        tail finish
+        .option pop
+    fail:
+        li a0, 1
+        ret
+
+        .section .rodata
+        .globl table
+    table:
+        .word 5, 7
+    pointer:
+        .word table+4
+
+        .bss
+    slot:
+        .zero 4";
+
+    let executable = link(&[start, compute]);
+
+    let start = address(&executable, "_start");
+    let compute = address(&executable, "compute");
+    let table = address(&executable, "table");
+
+    // the call is in reach of a jal, which takes the place of the auipc and
+    // the jalr
+    let jal = word(&executable, start);
+    assert_eq!(jal & 0xfff, 0x0ef);
+    assert_eq!(jal_target(start, jal), compute);
+    assert_eq!(word(&executable, start + 4) & 0x7f, 0x37);
+
+    // %hi and %lo add up to the address of the table
+    let lui = word(&executable, compute);
+    let lw = word(&executable, compute + 4);
+    assert_eq!(
+        (lui & 0xffff_f000).wrapping_add(sign_extend(lw >> 20, 12)),
+        table
+    );
+
+    // the tail call is left an auipc and a jalr through t1, it was not to
+    // be relaxed
+    let fail = address(&executable, "fail");
+    assert_eq!(word(&executable, fail - 8) & 0xfff, 0x317);
+    assert_eq!(word(&executable, fail - 4) & 0xf_ffff, 0x3_0067);
+
+    // 5 + 7 + 5 + 18 from finish
+    assert_eq!(run_executable(&executable, EXIT_DEVICE), Ok(35));
+}
+
+#[test]
+fn calls_between_c_functions_are_relaxed() {
+    let source = "
+        int twice(int x);
+
+        int main() { return twice(21); }
+
+        int twice(int x) { return x * 2; }
+    ";
+
+    let executable = linked(&[source], &memory());
+    let main = address(&executable, "main");
+    let twice = address(&executable, "twice");
+
+    // the call is a jal to twice, no auipc of ra is left in main
+    let words: Vec<(u32, u32)> = (main..twice)
+        .step_by(4)
+        .map(|at| (at, word(&executable, at)))
+        .collect();
+    assert!(words.iter().all(|(_, word)| word & 0xfff != 0x097));
+
+    let (at, jal) = *words
+        .iter()
+        .find(|(_, word)| word & 0xfff == 0x0ef)
+        .expect("a jal in main");
+    assert_eq!(jal_target(at, jal), twice);
+
+    assert_eq!(run_executable(&executable, EXIT_DEVICE), Ok(42));
+}
+
+#[test]
+fn calls_in_loops_and_conditionals_are_relaxed() {
+    let source = "
+        int step(int x);
+
+        int main() {
+            int total = 0;
+            for (int i = 0; i < 3; i++) {
+                total = step(total);
+            }
+            if (total > 0) {
+                total = step(total);
+            }
+            return total;
+        }
+
+        int step(int x) { return x + 5; }
+    ";
+
+    let executable = linked(&[source], &memory());
+    let main = address(&executable, "main");
+    let step = address(&executable, "step");
+
+    // both calls sit in between a branch and its label and are still jals
+    let words: Vec<(u32, u32)> = (main..step)
+        .step_by(4)
+        .map(|at| (at, word(&executable, at)))
+        .collect();
+    assert!(words.iter().all(|(_, word)| word & 0xfff != 0x097));
+
+    let calls: Vec<u32> = words
+        .iter()
+        .filter(|(_, word)| word & 0xfff == 0x0ef)
+        .map(|&(at, jal)| jal_target(at, jal))
+        .collect();
+    assert_eq!(calls, [step, step]);
+
+    assert_eq!(run_executable(&executable, EXIT_DEVICE), Ok(20));
+}
+
+#[test]
+fn functions_in_sections_of_their_own_are_linked_apart() {
+    let source = r#"
+        __attribute__((section(".vectors"))) int handler(int x) { return x + 2; }
+
+        int twice(int x);
+
+        int main() { return twice(handler(19)); }
+    "#;
+    let other = "int twice(int x) { return x * 2; }";
+
+    let executable = linked(&[source, other], &memory());
+    let twice = address(&executable, "twice");
+    let handler = address(&executable, "handler");
+
+    // .vectors is laid out after the .text of every unit, not in between
+    assert!(handler > twice);
+    assert_eq!(run_executable(&executable, EXIT_DEVICE), Ok(42));
+}
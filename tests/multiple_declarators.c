@@ -0,0 +1,14 @@
+int square(int x), cube(int x);
+
+int main() {
+  int a, b = 2, *p;
+  p = &b;
+  a = *p + 1;
+
+  int sum = 0, limit = 10;
+  for (int i = 0, j = limit; i < j; i++) {
+    sum += 1;
+  }
+
+  return a + sum;
+}
@@ -0,0 +1,25 @@
+int sum(int *values, int count) {
+  int total = 0;
+  int *end = values + count;
+  while (values != end) {
+    total += *values++;
+  }
+  return total;
+}
+
+int main() {
+  int numbers[5];
+  int *p = numbers;
+  int i = 0;
+  while (i < 5) {
+    *(p + i) = i + 1;
+    i = i + 1;
+  }
+
+  int *last = &numbers[4];
+  int distance = last - p;
+  last -= 2;
+  p++;
+
+  return sum(numbers, 5) + distance * 10 + *last * 100 + 2[numbers] + *p;
+}
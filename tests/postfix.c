@@ -0,0 +1,27 @@
+int add_one(int x) { return x + 1; }
+
+int twice(int x) { return x * 2; }
+
+int (*pick(int which))(int) {
+  if (which)
+    return twice;
+  return add_one;
+}
+
+int main() {
+  int grid[2][3];
+  int (*table[2])(int);
+  int counter = 0;
+  int *p = &counter;
+
+  table[0] = add_one;
+  table[1] = twice;
+  grid[1][2] = 5;
+  grid[0][1] = 7;
+
+  p[0]++;
+  int old = grid[1][2]--;
+
+  int i = 1;
+  return table[i](grid[1][2]) + table[0](counter) + pick(1)(old) + grid[0][1];
+}
@@ -0,0 +1,216 @@
+// whole programs, compiled, linked and run. the exit code is what main
+// returned
+mod common;
+
+use common::run;
+
+#[test]
+fn declarators_with_several_suffixes() {
+    let source = "
+        int add_one(int x) { return x + 1; }
+
+        int (*pick(void))(int) { return add_one; }
+
+        int main() {
+          int grid[2][3];
+          int (*table[1])(int);
+
+          table[0] = pick();
+          grid[1][0] = 4;
+
+          // grid[0] is three ints long, so grid[1][0] is the fourth one
+          int *cells = grid[0];
+          return table[0](cells[3]);
+        }
+    ";
+
+    assert_eq!(run(&[source]), 5);
+}
+
+#[test]
+fn assignment_has_the_stored_value() {
+    let source = "
+        int main() {
+          int a;
+          int b;
+          int c = (a = b = 3) + 1;
+          return a * 100 + b * 10 + c;
+        }
+    ";
+
+    assert_eq!(run(&[source]), (334 % 256) as u8);
+}
+
+#[test]
+fn arrays_decay_into_pointers() {
+    let source = "
+        int second(int *p) { return p[1]; }
+
+        int main() {
+          int a[3];
+          int *p = a;
+          p[1] = 4;
+          return second(a) + a[1];
+        }
+    ";
+
+    assert_eq!(run(&[source]), 8);
+}
+
+#[test]
+fn parameters_can_be_shadowed_in_blocks() {
+    let source = "
+        int f(int x) {
+          {
+            int x = 5;
+            x = x + 1;
+          }
+          return x;
+        }
+
+        int main() { return f(2); }
+    ";
+
+    assert_eq!(run(&[source]), 2);
+}
+
+#[test]
+fn file_scope_variables() {
+    let source = "
+        int count = -2;
+        long wide = -1;
+        unsigned mask = ~0x0f;
+        int zeroed[3];
+        int later;
+        int later = 7;
+
+        int bump(int *p) { return *p = *p + 1; }
+
+        int main() {
+          bump(&count);
+          bump(&zeroed[1]);
+          return count + wide + (mask & 0xff) + zeroed[0] + zeroed[1] + later;
+        }
+    ";
+
+    // -1 - 1 + 240 + 0 + 1 + 7
+    assert_eq!(run(&[source]), 246);
+}
+
+#[test]
+fn unsigned_arithmetic_above_int_max() {
+    let source = "
+        int main() {
+          unsigned big = 0xfffffff0u;
+          unsigned half = big / 2u;
+          unsigned rest = big % 7u;
+          unsigned shifted = big >> 28;
+          int bits = 0;
+
+          bits = bits | (half == 0x7ffffff8u);
+          bits = bits | (rest == 2u) << 1;
+          bits = bits | (shifted == 15u) << 2;
+          bits = bits | (big > 1u) << 3;
+          bits = bits | (1u < big) << 4;
+          // the int is converted to unsigned, so -1 is the largest value
+          bits = bits | (big <= -1) << 5;
+
+          big /= 16u;
+          bits = bits | (big == 0x0fffffffu) << 6;
+          return bits;
+        }
+    ";
+
+    assert_eq!(run(&[source]), 0x7f);
+}
+
+#[test]
+fn signed_arithmetic_on_negative_values() {
+    let source = "
+        int main() {
+          int negative = -17;
+          int bits = 0;
+
+          bits = bits | (negative / 4 == -4);
+          bits = bits | (negative % 4 == -1) << 1;
+          bits = bits | (negative >> 1 == -9) << 2;
+          bits = bits | (negative < 1) << 3;
+          bits = bits | (3 > negative) << 4;
+          return bits;
+        }
+    ";
+
+    assert_eq!(run(&[source]), 0x1f);
+}
+
+#[test]
+fn constant_expressions() {
+    let source = "
+        int shifted = 1 << 2;
+        unsigned top = (1u << 31) >> 28;
+        int mixed = (-1 < 1u) + (7 / 2 == 3) * 2 + (0 && 1 / 0);
+        int wrapped = 0u - 1 > 0;
+
+        int classify(int x) {
+          switch (x) {
+            case 2 * 3: return 1;
+            case (1 << 3) | 1: return 2;
+            case 100 % 7 - 3: return 3;
+            default: return 0;
+          }
+        }
+
+        int main() {
+          return shifted + top * 10 + mixed * 100 + wrapped * 200
+            + classify(6) * 1000 + classify(9) * 2000 + classify(-1) * 3000;
+        }
+    ";
+
+    // 4 + 8 * 10 + 2 * 100 + 1 * 200 + 1000 + 4000 + 9000, as a byte
+    assert_eq!(run(&[source]), (14484 % 256) as u8);
+}
+
+#[test]
+fn address_constants_initialize_file_scope_variables() {
+    let source = "
+        int twice(int x) { return x * 2; }
+
+        int value = 7;
+        int table[2];
+        int *pointer = &value;
+        int *first = table;
+        int (*function)(int) = twice;
+        int (*reference)(int) = &twice;
+
+        int main() {
+          first[0] = 3;
+          return function(*pointer) + reference(table[0]);
+        }
+    ";
+
+    assert_eq!(run(&[source]), 20);
+}
+
+#[test]
+fn asm_clobbers_of_callee_saved_registers_are_restored() {
+    let source = r#"
+        int clobber(int x) {
+          int local = x;
+          asm volatile("li s2, 7\n"
+                       "li s11, 8\n"
+                       "li s0, 9" ::: "s2", "x27", "fp");
+          return local + 1;
+        }
+
+        int main() {
+          int kept;
+          asm volatile("li s2, 30\n"
+                       "li s11, 4" ::: "s2", "s11");
+          int result = clobber(7);
+          asm volatile("add %0, s2, s11" : "=r"(kept));
+          return kept + result;
+        }
+    "#;
+
+    assert_eq!(run(&[source]), 42);
+}
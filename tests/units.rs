@@ -0,0 +1,73 @@
+// programs made of several translation units, compiled one at a time with
+// -c and linked by the compiler binary
+mod common;
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use common::{run_executable, EXIT_DEVICE};
+
+fn directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("c-riscv-units-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn compiler(directory: &Path, args: &[&str]) {
+    let exit_device = format!("--exit-device={EXIT_DEVICE:#x}");
+    let output = Command::new(env!("CARGO_BIN_EXE_c-riscv"))
+        .current_dir(directory)
+        .arg("--freestanding")
+        .arg(exit_device)
+        .args(args)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn variables_defined_in_another_unit() {
+    let directory = directory("extern");
+
+    std::fs::write(
+        directory.join("main.c"),
+        "extern int counter;
+         extern int total;
+         int step(void);
+
+         int main() {
+           step();
+           step();
+           return counter * 10 + total;
+         }
+        ",
+    )
+    .unwrap();
+    std::fs::write(
+        directory.join("counter.c"),
+        "int counter = 3;
+         int total;
+
+         int step(void) {
+           counter = counter + 1;
+           total = total + counter;
+           return counter;
+         }
+        ",
+    )
+    .unwrap();
+
+    compiler(&directory, &["-c", "main.c"]);
+    compiler(&directory, &["-c", "counter.c"]);
+    compiler(&directory, &["main.o", "counter.o", "-o", "program"]);
+
+    let executable = std::fs::read(directory.join("program")).unwrap();
+    // counter went from 3 to 5, total is 4 + 5
+    assert_eq!(run_executable(&executable, EXIT_DEVICE), Ok(59));
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,154 @@
+// which warnings the -W flags turn on, off and into errors
+use c_riscv::{compile, diagnostic::Severity, Diagnostics, Options, WarningOptions};
+
+const SOURCE: &str = "
+int f(int unused_parameter) {
+  int unused_variable;
+  int x = 0;
+  if (x = 1) {
+    return 1;
+  }
+}
+
+int main() {
+  int a = 1;
+  {
+    int a = 2;
+  }
+  return f(a);
+  a = 3;
+}
+";
+
+fn options(flags: &[&str]) -> WarningOptions {
+    let mut options = WarningOptions::default();
+    for flag in flags {
+        options.apply(flag).unwrap();
+    }
+    options
+}
+
+// the names of the warnings, and whether any of them stopped compilation
+fn warnings(flags: &[&str]) -> (Vec<String>, bool) {
+    let mut diagnostics = Diagnostics::new(options(flags));
+    let compiled = compile(SOURCE, &Options::default(), &mut diagnostics);
+
+    let names = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.warning.expect("only warnings").to_string())
+        .collect();
+    (names, compiled.is_err())
+}
+
+#[test]
+fn default_warnings() {
+    assert_eq!(warnings(&[]), (vec![], false));
+}
+
+#[test]
+fn wall_and_wextra() {
+    let (mut found, failed) = warnings(&["all"]);
+    found.sort();
+    assert_eq!(
+        (found, failed),
+        (
+            vec![
+                "parentheses".to_string(),
+                "return-type".to_string(),
+                "unreachable-code".to_string(),
+                // unused_variable and the inner a
+                "unused-variable".to_string(),
+                "unused-variable".to_string(),
+            ],
+            false
+        )
+    );
+
+    let (found, _) = warnings(&["all", "extra"]);
+    assert!(found.contains(&"unused-parameter".to_string()));
+    assert!(!found.contains(&"shadow".to_string()));
+}
+
+#[test]
+fn single_warnings_win_over_groups() {
+    let (found, _) = warnings(&["no-unused-variable", "all", "shadow"]);
+
+    assert!(!found.contains(&"unused-variable".to_string()));
+    assert!(found.contains(&"shadow".to_string()));
+    assert!(found.contains(&"parentheses".to_string()));
+}
+
+#[test]
+fn wunused_toggles_every_unused_warning() {
+    let unused = |found: Vec<String>| -> Vec<String> {
+        let mut found: Vec<String> = found
+            .into_iter()
+            .filter(|name| name.starts_with("unused-"))
+            .collect();
+        found.dedup();
+        found
+    };
+
+    let (found, _) = warnings(&["unused"]);
+    assert_eq!(unused(found), ["unused-parameter", "unused-variable"]);
+
+    let (found, _) = warnings(&["all", "extra", "no-unused"]);
+    assert_eq!(unused(found.clone()), Vec::<String>::new());
+    assert!(found.contains(&"parentheses".to_string()));
+
+    // a single warning still wins over the group
+    let (found, _) = warnings(&["unused-variable", "no-unused"]);
+    assert_eq!(unused(found), ["unused-variable"]);
+}
+
+#[test]
+fn werror_makes_warnings_errors() {
+    let mut diagnostics = Diagnostics::new(options(&["parentheses", "error"]));
+    let compiled = compile(SOURCE, &Options::default(), &mut diagnostics);
+
+    assert!(compiled.is_err());
+    let diagnostic = diagnostics.iter().next().unwrap();
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.warning.unwrap().name(), "parentheses");
+
+    let (_, failed) = warnings(&["parentheses", "error", "no-error"]);
+    assert!(!failed);
+}
+
+#[test]
+fn unknown_warnings_are_rejected() {
+    assert_eq!(
+        WarningOptions::default().apply("no-such-thing"),
+        Err("Unknown warning option '-Wno-such-thing'".to_string())
+    );
+}
+
+#[test]
+fn noreturn_functions_that_return() {
+    let source = "
+        __attribute__((noreturn)) int halt(void) { for (;;) {} }
+        __attribute__((noreturn)) int stop(void) { halt(); }
+        __attribute__((noreturn)) int leave(int x) { if (x) return; halt(); }
+        __attribute__((noreturn)) int fall(int x) { if (x) halt(); }
+
+        int main() { return 0; }
+    ";
+
+    let mut diagnostics = Diagnostics::new(WarningOptions::default());
+    compile(source, &Options::default(), &mut diagnostics).unwrap();
+
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            assert_eq!(diagnostic.warning.unwrap().name(), "invalid-noreturn");
+            diagnostic.message.as_str()
+        })
+        .collect();
+    assert_eq!(
+        found,
+        [
+            "Function declared noreturn has a return statement",
+            "Function 'fall' is declared noreturn but can return",
+        ]
+    );
+}